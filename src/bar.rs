@@ -1,4 +1,7 @@
 use crate::config::{BarPosition, MarginConfig, ModuleConfig};
+#[cfg(feature = "ipc")]
+use crate::ipc::{send_event, Event};
+use crate::module_tasks::ModuleTasks;
 use crate::modules::{
     create_module, set_widget_identifiers, wrap_widget, ModuleInfo, ModuleLocation,
 };
@@ -8,10 +11,48 @@ use crate::{Config, GlobalState};
 use color_eyre::Result;
 use gtk::gdk::Monitor;
 use gtk::prelude::*;
-use gtk::{Application, ApplicationWindow, IconTheme, Orientation};
+use gtk::{Application, ApplicationWindow, EventBox, IconTheme, Orientation};
 use std::cell::RefCell;
 use std::rc::Rc;
-use tracing::{debug, info};
+use tracing::{debug, error, info};
+
+/// A single bar window, and the state required
+/// to update it in place when the config changes.
+#[derive(Debug)]
+pub struct Bar {
+    name: String,
+    monitor_name: String,
+    /// Position of the bar's config in the list of bars for its monitor.
+    index: usize,
+    monitor: Monitor,
+    window: ApplicationWindow,
+    config: Config,
+    icon_theme: IconTheme,
+    popup: Rc<RefCell<Popup>>,
+
+    start: gtk::Box,
+    center: gtk::Box,
+    end: gtk::Box,
+
+    start_modules: Vec<LoadedModule>,
+    center_modules: Vec<LoadedModule>,
+    end_modules: Vec<LoadedModule>,
+}
+
+/// A module instance which has been added to a bar.
+#[derive(Debug)]
+struct LoadedModule {
+    /// The config the module was created from,
+    /// used to check for changes on reload.
+    config: ModuleConfig,
+    /// The module's unique ID, also used to key its popup content.
+    id: usize,
+    /// The event box wrapping the module widget.
+    container: EventBox,
+    /// The module's background tasks,
+    /// which are stopped when it is removed.
+    tasks: ModuleTasks,
+}
 
 /// Creates a new window for a bar,
 /// sets it up and adds its widgets.
@@ -19,9 +60,10 @@ pub fn create_bar(
     app: &Application,
     monitor: &Monitor,
     monitor_name: &str,
+    index: usize,
     config: Config,
     global_state: &Rc<RefCell<GlobalState>>,
-) -> Result<()> {
+) -> Result<Bar> {
     let win = ApplicationWindow::builder().application(app).build();
     let bar_name = config
         .name
@@ -64,14 +106,29 @@ pub fn create_bar(
     // content.set_center_widget(Some(&center));
     content.pack_end(&end, false, false, 0);
 
-    let load_result = load_modules(&start, &center, &end, app, config, monitor, monitor_name)?;
+    let icon_theme = create_icon_theme(&config);
+
+    let load_result = load_modules(
+        &start,
+        &center,
+        &end,
+        app,
+        config.clone(),
+        &icon_theme,
+        monitor,
+        monitor_name,
+        &bar_name,
+    )?;
     global_state
         .borrow_mut()
         .popups_mut()
-        .insert(bar_name.into(), load_result.popup);
+        .insert(bar_name.clone().into(), load_result.popup.clone());
 
     win.add(&content);
 
+    #[cfg(feature = "ipc")]
+    send_visibility_events(&win, &bar_name);
+
     win.connect_destroy_event(|_, _| {
         info!("Shutting down");
         gtk::main_quit();
@@ -88,7 +145,118 @@ pub fn create_bar(
     content.show();
     win.show();
 
-    Ok(())
+    Ok(Bar {
+        name: bar_name,
+        monitor_name: monitor_name.to_string(),
+        index,
+        monitor: monitor.clone(),
+        window: win,
+        config,
+        icon_theme,
+        popup: load_result.popup,
+        start,
+        center,
+        end,
+        start_modules: load_result.start,
+        center_modules: load_result.center,
+        end_modules: load_result.end,
+    })
+}
+
+impl Bar {
+    /// Gets the name of the output the bar is on.
+    pub fn monitor_name(&self) -> &str {
+        &self.monitor_name
+    }
+
+    /// Gets the position of the bar's config
+    /// in the list of bars for its monitor.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Updates the bar in place to match the new config.
+    ///
+    /// Modules whose config is unchanged are kept as-is,
+    /// changed modules are recreated and removed modules are destroyed.
+    ///
+    /// Modules which fail to be created are logged and skipped.
+    ///
+    /// Returns `false` without making any changes
+    /// if a bar-level option has changed,
+    /// in which case the bar must be recreated instead.
+    pub fn update(&mut self, app: &Application, config: Config) -> bool {
+        if !self.config.bar_options_eq(&config) {
+            return false;
+        }
+
+        debug!("Updating modules on bar {}", self.name);
+
+        macro_rules! info {
+            ($location:expr) => {
+                ModuleInfo {
+                    app,
+                    bar_position: config.position,
+                    monitor: &self.monitor,
+                    output_name: &self.monitor_name,
+                    location: $location,
+                    icon_theme: &self.icon_theme,
+                }
+            };
+        }
+
+        update_modules(
+            &self.start,
+            &mut self.start_modules,
+            config.start.clone(),
+            &info!(ModuleLocation::Left),
+            &self.popup,
+        );
+
+        update_modules(
+            &self.center,
+            &mut self.center_modules,
+            config.center.clone(),
+            &info!(ModuleLocation::Center),
+            &self.popup,
+        );
+
+        update_modules(
+            &self.end,
+            &mut self.end_modules,
+            config.end.clone(),
+            &info!(ModuleLocation::Right),
+            &self.popup,
+        );
+
+        self.config = config;
+
+        true
+    }
+
+    /// Closes the bar and its popup window,
+    /// stopping its modules and removing the popup from the global state.
+    pub fn close(self, global_state: &Rc<RefCell<GlobalState>>) {
+        info!("Closing bar {}", self.name);
+
+        global_state
+            .borrow_mut()
+            .popups_mut()
+            .remove(self.name.as_str());
+
+        let modules = self
+            .start_modules
+            .iter()
+            .chain(&self.center_modules)
+            .chain(&self.end_modules);
+
+        for module in modules {
+            module.tasks.abort();
+        }
+
+        self.popup.borrow().window.close();
+        self.window.close();
+    }
 }
 
 /// Sets up GTK layer shell for a provided application window.
@@ -150,26 +318,52 @@ fn create_container(name: &str, orientation: Orientation) -> gtk::Box {
     container
 }
 
+/// Creates the icon theme for a bar,
+/// using the custom theme if one is set.
+fn create_icon_theme(config: &Config) -> IconTheme {
+    let icon_theme = IconTheme::new();
+    if let Some(ref theme) = config.icon_theme {
+        icon_theme.set_custom_theme(Some(theme));
+    }
+
+    icon_theme
+}
+
 #[derive(Debug)]
 struct BarLoadResult {
     popup: Rc<RefCell<Popup>>,
+    start: Vec<LoadedModule>,
+    center: Vec<LoadedModule>,
+    end: Vec<LoadedModule>,
+}
+
+/// Sends IPC events whenever the bar is shown or hidden.
+///
+/// Popup events are sent by the popup itself.
+#[cfg(feature = "ipc")]
+fn send_visibility_events(win: &ApplicationWindow, bar_name: &str) {
+    let bar_name = bar_name.to_string();
+    win.connect_visible_notify(move |win| {
+        send_event(Event::BarVisibility {
+            bar_name: bar_name.clone(),
+            visible: win.is_visible(),
+        });
+    });
 }
 
 /// Loads the configured modules onto a bar.
+#[allow(clippy::too_many_arguments)]
 fn load_modules(
     left: &gtk::Box,
     center: &gtk::Box,
     right: &gtk::Box,
     app: &Application,
     config: Config,
+    icon_theme: &IconTheme,
     monitor: &Monitor,
     output_name: &str,
+    bar_name: &str,
 ) -> Result<BarLoadResult> {
-    let icon_theme = IconTheme::new();
-    if let Some(ref theme) = config.icon_theme {
-        icon_theme.set_custom_theme(Some(theme));
-    }
-
     macro_rules! info {
         ($location:expr) => {
             ModuleInfo {
@@ -178,31 +372,45 @@ fn load_modules(
                 monitor,
                 output_name,
                 location: $location,
-                icon_theme: &icon_theme,
+                icon_theme,
             }
         };
     }
 
     // popup ignores module location so can bodge this for now
-    let popup = Popup::new(&info!(ModuleLocation::Left), config.popup_gap);
+    let popup = Popup::new(&info!(ModuleLocation::Left), bar_name, config.popup_gap);
     let popup = Rc::new(RefCell::new(popup));
 
-    if let Some(modules) = config.start {
-        let info = info!(ModuleLocation::Left);
-        add_modules(left, modules, &info, &popup)?;
-    }
+    let start = match config.start {
+        Some(modules) => {
+            let info = info!(ModuleLocation::Left);
+            add_modules(left, modules, &info, &popup)?
+        }
+        None => vec![],
+    };
 
-    if let Some(modules) = config.center {
-        let info = info!(ModuleLocation::Center);
-        add_modules(center, modules, &info, &popup)?;
-    }
+    let center = match config.center {
+        Some(modules) => {
+            let info = info!(ModuleLocation::Center);
+            add_modules(center, modules, &info, &popup)?
+        }
+        None => vec![],
+    };
 
-    if let Some(modules) = config.end {
-        let info = info!(ModuleLocation::Right);
-        add_modules(right, modules, &info, &popup)?;
-    }
+    let end = match config.end {
+        Some(modules) => {
+            let info = info!(ModuleLocation::Right);
+            add_modules(right, modules, &info, &popup)?
+        }
+        None => vec![],
+    };
 
-    let result = BarLoadResult { popup };
+    let result = BarLoadResult {
+        popup,
+        start,
+        center,
+        end,
+    };
 
     Ok(result)
 }
@@ -214,50 +422,132 @@ fn add_modules(
     modules: Vec<ModuleConfig>,
     info: &ModuleInfo,
     popup: &Rc<RefCell<Popup>>,
-) -> Result<()> {
+) -> Result<Vec<LoadedModule>> {
+    modules
+        .into_iter()
+        .map(|config| {
+            let module = load_module(config, info, popup)?;
+            content.add(&module.container);
+            Ok(module)
+        })
+        .collect()
+}
+
+/// Diffs the currently loaded modules in a container against the new config.
+///
+/// Unchanged modules are re-used and moved into their new position.
+/// New or changed modules are created, and any left over are destroyed.
+/// Modules which fail to be created are logged and skipped.
+fn update_modules(
+    content: &gtk::Box,
+    loaded: &mut Vec<LoadedModule>,
+    modules: Option<Vec<ModuleConfig>>,
+    info: &ModuleInfo,
+    popup: &Rc<RefCell<Popup>>,
+) {
+    let mut previous = std::mem::take(loaded);
+
+    for config in modules.unwrap_or_default() {
+        let module = match previous.iter().position(|module| module.config == config) {
+            Some(index) => previous.remove(index),
+            None => match load_module(config, info, popup) {
+                Ok(module) => {
+                    content.add(&module.container);
+                    module
+                }
+                Err(err) => {
+                    error!("{:?}", err.wrap_err("Failed to create module"));
+                    continue;
+                }
+            },
+        };
+
+        content.reorder_child(&module.container, loaded.len() as i32);
+        loaded.push(module);
+    }
+
+    for module in previous {
+        debug!("Removing module #{}", module.id);
+        module.tasks.abort();
+        popup.borrow_mut().unregister_content(module.id);
+        content.remove(&module.container);
+    }
+}
+
+/// Creates a single module from its config,
+/// wrapping it in its container.
+fn load_module(
+    config: ModuleConfig,
+    info: &ModuleInfo,
+    popup: &Rc<RefCell<Popup>>,
+) -> Result<LoadedModule> {
     let orientation = info.bar_position.get_orientation();
+    let id = get_unique_usize();
+    let module_config = config.clone();
 
-    macro_rules! add_module {
-        ($module:expr, $id:expr) => {{
+    macro_rules! create {
+        ($module:expr) => {{
             let common = $module.common.take().expect("common config to exist");
-            let widget_parts = create_module(
-                *$module,
-                $id,
-                common.name.clone(),
-                &info,
-                &Rc::clone(&popup),
-            )?;
+            let widget_parts =
+                create_module(*$module, id, common.name.clone(), &info, &Rc::clone(&popup))?;
             set_widget_identifiers(&widget_parts, &common);
 
-            let container = wrap_widget(&widget_parts.widget, common, orientation);
-            content.add(&container);
+            wrap_widget(&widget_parts.widget, common, orientation)
         }};
     }
 
-    for config in modules {
-        let id = get_unique_usize();
-        match config {
+    // any tasks spawned while creating the module belong to it
+    let tasks = ModuleTasks::default();
+    let container = tasks.enter(|| -> Result<EventBox> {
+        Ok(match config {
+            #[cfg(feature = "bluetooth")]
+            ModuleConfig::Bluetooth(mut module) => create!(module),
+            #[cfg(feature = "brightness")]
+            ModuleConfig::Brightness(mut module) => create!(module),
             #[cfg(feature = "clipboard")]
-            ModuleConfig::Clipboard(mut module) => add_module!(module, id),
+            ModuleConfig::Clipboard(mut module) => create!(module),
             #[cfg(feature = "clock")]
-            ModuleConfig::Clock(mut module) => add_module!(module, id),
-            ModuleConfig::Custom(mut module) => add_module!(module, id),
-            ModuleConfig::Focused(mut module) => add_module!(module, id),
-            ModuleConfig::Label(mut module) => add_module!(module, id),
-            ModuleConfig::Launcher(mut module) => add_module!(module, id),
+            ModuleConfig::Clock(mut module) => create!(module),
+            ModuleConfig::Custom(mut module) => create!(module),
+            ModuleConfig::Focused(mut module) => create!(module),
+            #[cfg(feature = "idle_inhibitor")]
+            ModuleConfig::IdleInhibitor(mut module) => create!(module),
+            #[cfg(feature = "keyboard")]
+            ModuleConfig::Keyboard(mut module) => create!(module),
+            ModuleConfig::Label(mut module) => create!(module),
+            ModuleConfig::Launcher(mut module) => create!(module),
             #[cfg(feature = "music")]
-            ModuleConfig::Music(mut module) => add_module!(module, id),
-            ModuleConfig::Script(mut module) => add_module!(module, id),
+            ModuleConfig::Music(mut module) => create!(module),
+            #[cfg(feature = "network")]
+            ModuleConfig::Network(mut module) => create!(module),
+            #[cfg(feature = "notifications")]
+            ModuleConfig::Notifications(mut module) => create!(module),
+            ModuleConfig::Script(mut module) => create!(module),
             #[cfg(feature = "sys_info")]
-            ModuleConfig::SysInfo(mut module) => add_module!(module, id),
+            ModuleConfig::SysInfo(mut module) => create!(module),
             #[cfg(feature = "tray")]
-            ModuleConfig::Tray(mut module) => add_module!(module, id),
+            ModuleConfig::Tray(mut module) => create!(module),
             #[cfg(feature = "upower")]
-            ModuleConfig::Upower(mut module) => add_module!(module, id),
+            ModuleConfig::Upower(mut module) => create!(module),
+            #[cfg(feature = "volume")]
+            ModuleConfig::Volume(mut module) => create!(module),
             #[cfg(feature = "workspaces")]
-            ModuleConfig::Workspaces(mut module) => add_module!(module, id),
-        }
-    }
+            ModuleConfig::Workspaces(mut module) => create!(module),
+        })
+    });
 
-    Ok(())
+    let container = match container {
+        Ok(container) => container,
+        Err(err) => {
+            tasks.abort();
+            return Err(err);
+        }
+    };
+
+    Ok(LoadedModule {
+        config: module_config,
+        id,
+        container,
+        tasks,
+    })
 }
@@ -1,5 +1,5 @@
+use crate::module_tasks::spawn;
 use crate::send;
-use tokio::spawn;
 use tokio::sync::mpsc;
 
 /// MPSC async -> GTK sync channel.
@@ -1,5 +1,6 @@
 use crate::ipc::commands::Command;
 use crate::ipc::responses::Response;
+use crate::ipc::Event;
 use clap::Parser;
 use serde::{Deserialize, Serialize};
 
@@ -17,3 +18,11 @@ pub fn handle_response(response: Response) {
         Response::Err { message } => eprintln!("error\n{}", message.unwrap_or_default()),
     }
 }
+
+/// Writes an event to stdout as a single line of JSON.
+pub fn handle_event(event: &Event) {
+    match serde_json::to_string(event) {
+        Ok(json) => println!("{json}"),
+        Err(err) => eprintln!("error\n{err}"),
+    }
+}
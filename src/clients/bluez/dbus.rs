@@ -0,0 +1,33 @@
+//! D-Bus proxies for the subset of the BlueZ API used by Ironbar.
+//!
+//! See <https://github.com/bluez/bluez/tree/master/doc>.
+
+use zbus::dbus_proxy;
+
+#[dbus_proxy(interface = "org.bluez.Adapter1", default_service = "org.bluez")]
+trait Adapter {
+    fn start_discovery(&self) -> zbus::Result<()>;
+
+    fn stop_discovery(&self) -> zbus::Result<()>;
+
+    #[dbus_proxy(property)]
+    fn set_powered(&self, value: bool) -> zbus::Result<()>;
+
+    #[dbus_proxy(property)]
+    fn set_discoverable(&self, value: bool) -> zbus::Result<()>;
+
+    #[dbus_proxy(property)]
+    fn set_pairable(&self, value: bool) -> zbus::Result<()>;
+}
+
+#[dbus_proxy(interface = "org.bluez.Device1", default_service = "org.bluez")]
+trait Device {
+    fn connect(&self) -> zbus::Result<()>;
+
+    fn disconnect(&self) -> zbus::Result<()>;
+
+    fn pair(&self) -> zbus::Result<()>;
+
+    #[dbus_proxy(property)]
+    fn set_trusted(&self, value: bool) -> zbus::Result<()>;
+}
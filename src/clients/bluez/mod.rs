@@ -0,0 +1,309 @@
+mod dbus;
+
+use self::dbus::{AdapterProxy, DeviceProxy};
+use crate::clients::signals::next_burst;
+use crate::{arc_mut, lock, send};
+use async_once::AsyncOnce;
+use color_eyre::{Report, Result};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::spawn;
+use tokio::sync::broadcast;
+use tracing::{debug, error, trace};
+use zbus::fdo::ObjectManagerProxy;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::{CacheProperties, MatchRule, MessageStream, MessageType, ProxyBuilder, ProxyDefault};
+
+const SERVICE_NAME: &str = "org.bluez";
+const ADAPTER_INTERFACE: &str = "org.bluez.Adapter1";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
+
+lazy_static! {
+    static ref CLIENT: AsyncOnce<Client> =
+        AsyncOnce::new(async { Client::new().await.expect("Failed to connect to BlueZ") });
+}
+
+type Properties = HashMap<String, OwnedValue>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Adapter {
+    pub path: String,
+    pub name: String,
+    pub powered: bool,
+    pub discoverable: bool,
+    /// Whether the adapter is scanning for new devices.
+    pub discovering: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+    pub path: String,
+    pub name: String,
+    pub address: String,
+    pub icon_name: Option<String>,
+    pub paired: bool,
+    pub connected: bool,
+    /// Battery percentage, if the device reports it.
+    pub battery: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BluetoothState {
+    /// The first available adapter, if any.
+    pub adapter: Option<Adapter>,
+    /// Devices known to the adapter, connected first.
+    pub devices: Vec<Device>,
+}
+
+pub struct Client {
+    connection: zbus::Connection,
+    state: Arc<Mutex<BluetoothState>>,
+    tx: broadcast::Sender<BluetoothState>,
+    _rx: broadcast::Receiver<BluetoothState>,
+}
+
+impl Client {
+    async fn new() -> Result<Self> {
+        // respects `DBUS_SYSTEM_BUS_ADDRESS`,
+        // allowing a different bus to be used for testing.
+        let connection = zbus::Connection::system().await?;
+
+        let (tx, rx) = broadcast::channel(8);
+        let state = arc_mut!(get_state(&connection).await?);
+
+        {
+            let connection = connection.clone();
+            let state = state.clone();
+            let tx = tx.clone();
+
+            spawn(async move {
+                // covers property changes as well as devices being added and removed
+                let rule = MatchRule::builder()
+                    .msg_type(MessageType::Signal)
+                    .sender(SERVICE_NAME)?
+                    .build();
+
+                let mut stream = MessageStream::for_match_rule(rule, &connection, None).await?;
+
+                while next_burst(&mut stream).await.is_some() {
+                    match get_state(&connection).await {
+                        Ok(new_state) => {
+                            let mut state = lock!(state);
+                            if *state != new_state {
+                                trace!("Bluetooth state changed: {new_state:?}");
+                                *state = new_state.clone();
+                                send!(tx, new_state);
+                            }
+                        }
+                        Err(err) => {
+                            error!("{:?}", err.wrap_err("Failed to refresh bluetooth state"));
+                        }
+                    }
+                }
+
+                Ok::<(), zbus::Error>(())
+            });
+        }
+
+        Ok(Self {
+            connection,
+            state,
+            tx,
+            _rx: rx,
+        })
+    }
+
+    /// Subscribes to bluetooth state changes.
+    ///
+    /// The current state is returned alongside the receiver
+    /// to bring the subscriber up to date.
+    pub fn subscribe(&self) -> (BluetoothState, broadcast::Receiver<BluetoothState>) {
+        let state = lock!(self.state);
+
+        // subscribe while locked so no changes are missed or duplicated
+        let rx = self.tx.subscribe();
+
+        (state.clone(), rx)
+    }
+
+    /// Powers the adapter on or off.
+    pub async fn set_powered(&self, powered: bool) -> Result<()> {
+        debug!("Setting adapter powered: {powered}");
+        self.adapter().await?.set_powered(powered).await?;
+        Ok(())
+    }
+
+    /// Toggles pairing mode,
+    /// which makes the adapter visible and pairable.
+    pub async fn set_discoverable(&self, discoverable: bool) -> Result<()> {
+        debug!("Setting adapter discoverable: {discoverable}");
+
+        let adapter = self.adapter().await?;
+        adapter.set_pairable(discoverable).await?;
+        adapter.set_discoverable(discoverable).await?;
+        Ok(())
+    }
+
+    /// Starts or stops scanning for new devices.
+    pub async fn set_discovering(&self, discovering: bool) -> Result<()> {
+        debug!("Setting adapter discovering: {discovering}");
+
+        let adapter = self.adapter().await?;
+        if discovering {
+            adapter.start_discovery().await?;
+        } else {
+            adapter.stop_discovery().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn connect(&self, path: &str) -> Result<()> {
+        debug!("Connecting to device '{path}'");
+        self.device(path).await?.connect().await?;
+        Ok(())
+    }
+
+    pub async fn disconnect(&self, path: &str) -> Result<()> {
+        debug!("Disconnecting from device '{path}'");
+        self.device(path).await?.disconnect().await?;
+        Ok(())
+    }
+
+    /// Pairs with the device, then trusts and connects to it.
+    pub async fn pair(&self, path: &str) -> Result<()> {
+        debug!("Pairing with device '{path}'");
+
+        let device = self.device(path).await?;
+        device.pair().await?;
+        device.set_trusted(true).await?;
+        device.connect().await?;
+        Ok(())
+    }
+
+    async fn adapter(&self) -> Result<AdapterProxy<'static>> {
+        let path = lock!(self.state)
+            .adapter
+            .as_ref()
+            .map(|adapter| adapter.path.clone())
+            .ok_or_else(|| Report::msg("No bluetooth adapter available"))?;
+
+        build_proxy(&self.connection, &path).await
+    }
+
+    async fn device(&self, path: &str) -> Result<DeviceProxy<'static>> {
+        build_proxy(&self.connection, path).await
+    }
+}
+
+pub async fn get_client() -> &'static Client {
+    CLIENT.get().await
+}
+
+/// Builds a proxy for the object at the given path,
+/// without caching properties as the state is tracked separately.
+async fn build_proxy<'a, T>(connection: &zbus::Connection, path: &str) -> Result<T>
+where
+    T: From<zbus::Proxy<'a>> + ProxyDefault,
+{
+    let proxy = ProxyBuilder::new(connection)
+        .path(OwnedObjectPath::try_from(path)?)?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+
+    Ok(proxy)
+}
+
+/// Gets the complete current bluetooth state
+/// from the BlueZ object tree.
+async fn get_state(connection: &zbus::Connection) -> Result<BluetoothState> {
+    let object_manager = ObjectManagerProxy::builder(connection)
+        .destination(SERVICE_NAME)?
+        .path("/")?
+        .build()
+        .await?;
+
+    let objects = object_manager.get_managed_objects().await?;
+
+    let mut state = BluetoothState::default();
+
+    let mut adapters = objects
+        .iter()
+        .filter_map(|(path, interfaces)| {
+            interfaces
+                .get(ADAPTER_INTERFACE)
+                .map(|props| (path.to_string(), props))
+        })
+        .collect::<Vec<_>>();
+    adapters.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    if let Some((path, props)) = adapters.first() {
+        state.adapter = Some(Adapter {
+            path: path.clone(),
+            name: get_string(props, "Alias").unwrap_or_default(),
+            powered: get_bool(props, "Powered"),
+            discoverable: get_bool(props, "Discoverable"),
+            discovering: get_bool(props, "Discovering"),
+        });
+
+        let adapter_path = path.clone();
+
+        state.devices = objects
+            .iter()
+            .filter_map(|(path, interfaces)| {
+                let props = interfaces.get(DEVICE_INTERFACE)?;
+
+                if get_string(props, "Adapter").as_ref() != Some(&adapter_path) {
+                    return None;
+                }
+
+                let address = get_string(props, "Address").unwrap_or_default();
+
+                Some(Device {
+                    path: path.to_string(),
+                    name: get_string(props, "Alias").unwrap_or_else(|| address.clone()),
+                    address,
+                    icon_name: get_string(props, "Icon"),
+                    paired: get_bool(props, "Paired"),
+                    connected: get_bool(props, "Connected"),
+                    battery: interfaces
+                        .get(BATTERY_INTERFACE)
+                        .and_then(|props| props.get("Percentage"))
+                        .and_then(|value| value.downcast_ref::<u8>().copied()),
+                })
+            })
+            .collect();
+
+        state.devices.sort_by(|a, b| {
+            b.connected
+                .cmp(&a.connected)
+                .then(b.paired.cmp(&a.paired))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+    }
+
+    Ok(state)
+}
+
+fn get_bool(props: &Properties, key: &str) -> bool {
+    props
+        .get(key)
+        .and_then(|value| value.downcast_ref::<bool>().copied())
+        .unwrap_or_default()
+}
+
+/// Gets a string or object path property.
+fn get_string(props: &Properties, key: &str) -> Option<String> {
+    props.get(key).and_then(|value| {
+        value
+            .downcast_ref::<str>()
+            .map(ToString::to_string)
+            .or_else(|| {
+                value
+                    .downcast_ref::<zbus::zvariant::ObjectPath>()
+                    .map(ToString::to_string)
+            })
+    })
+}
@@ -0,0 +1,14 @@
+//! D-Bus proxy for the subset of the logind session API used by Ironbar.
+//!
+//! See <https://www.freedesktop.org/software/systemd/man/org.freedesktop.login1.html>.
+
+use zbus::dbus_proxy;
+
+#[dbus_proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+trait Session {
+    fn set_brightness(&self, subsystem: &str, name: &str, brightness: u32) -> zbus::Result<()>;
+}
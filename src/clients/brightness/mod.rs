@@ -0,0 +1,404 @@
+mod dbus;
+
+use self::dbus::SessionProxy;
+use color_eyre::{Help, Report, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, OnceCell};
+use tokio::time::interval;
+use tracing::{debug, error};
+
+/// Default location of backlight devices in sysfs.
+pub const BACKLIGHT_ROOT: &str = "/sys/class/backlight";
+
+const SUBSYSTEM: &str = "backlight";
+
+/// How often to check the brightness for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The current brightness of a backlight device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Brightness {
+    pub value: u32,
+    pub max: u32,
+}
+
+impl Brightness {
+    pub fn percentage(self) -> f64 {
+        if self.max == 0 {
+            0.0
+        } else {
+            f64::from(self.value) / f64::from(self.max) * 100.0
+        }
+    }
+
+    /// Gets the raw value for the given percentage.
+    pub fn value_for(self, percentage: f64) -> u32 {
+        (percentage.clamp(0.0, 100.0) / 100.0 * f64::from(self.max)).round() as u32
+    }
+}
+
+/// A backlight device under a sysfs root.
+///
+/// Brightness is read directly from sysfs,
+/// and set using logind so that no extra permissions are required.
+#[derive(Debug, Clone)]
+pub struct Client {
+    name: String,
+    path: PathBuf,
+    /// System bus connection, created on first use.
+    connection: Arc<OnceCell<zbus::Connection>>,
+}
+
+impl Client {
+    /// Creates a client for the named device under `root`,
+    /// or the first device if no name is given.
+    pub fn new(root: impl AsRef<Path>, name: Option<&str>) -> Result<Self> {
+        let root = root.as_ref();
+
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => {
+                let mut names = fs::read_dir(root)?
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect::<Vec<_>>();
+
+                names.sort();
+                names.into_iter().next().ok_or_else(|| {
+                    Report::msg(format!(
+                        "No backlight devices found in '{}'",
+                        root.display()
+                    ))
+                })?
+            }
+        };
+
+        let path = root.join(&name);
+        if !path.is_dir() {
+            return Err(Report::msg(format!(
+                "Backlight device '{name}' does not exist"
+            )))
+            .suggestion(format!("Check the contents of '{}'", root.display()));
+        }
+
+        debug!("Using backlight device '{name}'");
+        Ok(Self {
+            name,
+            path,
+            connection: Arc::default(),
+        })
+    }
+
+    /// Reads the current brightness.
+    ///
+    /// This is the brightness reported by the hardware where available,
+    /// falling back to the last requested brightness.
+    pub fn get(&self) -> Result<Brightness> {
+        let read = |file: &str| -> Result<u32> {
+            let value = fs::read_to_string(self.path.join(file))?;
+            Ok(value.trim().parse()?)
+        };
+
+        let value_file = if self.path.join("actual_brightness").exists() {
+            "actual_brightness"
+        } else {
+            "brightness"
+        };
+
+        Ok(Brightness {
+            value: read(value_file)?,
+            max: read("max_brightness")?,
+        })
+    }
+
+    /// Sets the brightness to a raw value using logind.
+    pub async fn set(&self, value: u32) -> Result<()> {
+        debug!("Setting brightness of '{}' to {value}", self.name);
+
+        // respects `DBUS_SYSTEM_BUS_ADDRESS`,
+        // allowing a different bus to be used for testing.
+        let connection = self
+            .connection
+            .get_or_try_init(zbus::Connection::system)
+            .await?;
+
+        let session = SessionProxy::new(connection).await?;
+
+        session.set_brightness(SUBSYSTEM, &self.name, value).await?;
+        Ok(())
+    }
+
+    /// Watches the brightness,
+    /// sending the current value on `tx` and again whenever it changes.
+    ///
+    /// Sysfs attributes do not produce inotify events
+    /// when changed by the hardware or firmware, such as from brightness keys,
+    /// so the brightness is polled instead.
+    ///
+    /// Runs until the receiver is dropped.
+    pub async fn watch(&self, tx: mpsc::Sender<Brightness>) {
+        let mut interval = interval(POLL_INTERVAL);
+        let mut last = None;
+        let mut failed = false;
+
+        while !tx.is_closed() {
+            interval.tick().await;
+
+            match self.get() {
+                Ok(brightness) => {
+                    failed = false;
+
+                    if last != Some(brightness) {
+                        last = Some(brightness);
+
+                        if tx.send(brightness).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+                // only log the first failure, rather than on every poll
+                Err(err) if !failed => {
+                    failed = true;
+                    error!("{:?}", err.wrap_err("Failed to read brightness"));
+                }
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unique_id::get_unique_usize;
+    use std::env;
+    use std::os::unix::net::UnixStream;
+    use std::sync::Mutex;
+    use tokio::time::timeout;
+    use zbus::{dbus_interface, ConnectionBuilder, Guid};
+
+    /// A backlight root in a temporary directory,
+    /// removed when dropped.
+    struct FakeSysfs {
+        root: PathBuf,
+    }
+
+    impl FakeSysfs {
+        fn new(devices: &[(&str, u32)]) -> Self {
+            let root = env::temp_dir().join(format!(
+                "ironbar-backlight-test-{}-{}",
+                std::process::id(),
+                get_unique_usize()
+            ));
+
+            for (name, value) in devices {
+                let device = root.join(name);
+                fs::create_dir_all(&device).expect("device dir should be created");
+                fs::write(device.join("max_brightness"), "100\n").expect("write max_brightness");
+                write_brightness(&device, *value);
+            }
+
+            Self { root }
+        }
+
+        /// Simulates the hardware changing the brightness,
+        /// which only updates `actual_brightness`.
+        fn set_actual(&self, name: &str, value: u32) {
+            fs::write(
+                self.root.join(name).join("actual_brightness"),
+                format!("{value}\n"),
+            )
+            .expect("write actual_brightness");
+        }
+    }
+
+    impl Drop for FakeSysfs {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn write_brightness(device: &Path, value: u32) {
+        for file in ["brightness", "actual_brightness"] {
+            fs::write(device.join(file), format!("{value}\n")).expect("write brightness");
+        }
+    }
+
+    /// A logind session which applies brightness changes to a fake sysfs root.
+    struct MockSession {
+        root: PathBuf,
+        calls: Arc<Mutex<Vec<(String, String, u32)>>>,
+    }
+
+    #[dbus_interface(name = "org.freedesktop.login1.Session")]
+    impl MockSession {
+        fn set_brightness(&self, subsystem: &str, name: &str, brightness: u32) {
+            crate::lock!(self.calls).push((subsystem.to_string(), name.to_string(), brightness));
+            write_brightness(&self.root.join(name), brightness);
+        }
+    }
+
+    /// Connects the client to a mock logind session over a private socket,
+    /// returning the calls made to it.
+    ///
+    /// The server connection must be kept alive for the duration of the test.
+    async fn mock_logind(
+        client: &Client,
+        sysfs: &FakeSysfs,
+    ) -> (zbus::Connection, Arc<Mutex<Vec<(String, String, u32)>>>) {
+        let calls = Arc::default();
+        let session = MockSession {
+            root: sysfs.root.clone(),
+            calls: Arc::clone(&calls),
+        };
+
+        let (server_stream, client_stream) = UnixStream::pair().expect("socket pair");
+        let guid = Guid::generate();
+
+        // zbus takes Tokio sockets when its `tokio` feature is enabled by another dependency,
+        // and std sockets otherwise, so convert to whichever it expects.
+        let into_socket = |stream: UnixStream| {
+            stream
+                .set_nonblocking(true)
+                .expect("socket should be non-blocking");
+            stream.try_into().expect("socket should convert")
+        };
+
+        let server = ConnectionBuilder::unix_stream(into_socket(server_stream))
+            .server(&guid)
+            .p2p()
+            .serve_at("/org/freedesktop/login1/session/auto", session)
+            .expect("serve mock session")
+            .build();
+
+        let connection = ConnectionBuilder::unix_stream(into_socket(client_stream))
+            .p2p()
+            .build();
+
+        let (server, connection) =
+            tokio::try_join!(server, connection).expect("connections should be established");
+
+        client
+            .connection
+            .set(connection)
+            .expect("connection should not be set yet");
+
+        (server, calls)
+    }
+
+    #[test]
+    fn test_percentage() {
+        let brightness = Brightness {
+            value: 120,
+            max: 480,
+        };
+
+        assert!((brightness.percentage() - 25.0).abs() < f64::EPSILON);
+        assert_eq!(brightness.value_for(50.0), 240);
+        assert_eq!(brightness.value_for(150.0), 480);
+        assert_eq!(Brightness { value: 0, max: 0 }.percentage(), 0.0);
+    }
+
+    #[test]
+    fn test_new_picks_first_device() {
+        let sysfs = FakeSysfs::new(&[("intel_backlight", 10), ("acpi_video0", 20)]);
+
+        let client = Client::new(&sysfs.root, None).expect("client should be created");
+        assert_eq!(client.name, "acpi_video0");
+
+        let client =
+            Client::new(&sysfs.root, Some("intel_backlight")).expect("client should be created");
+        assert_eq!(client.name, "intel_backlight");
+
+        assert!(Client::new(&sysfs.root, Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_new_without_devices() {
+        let sysfs = FakeSysfs::new(&[]);
+        fs::create_dir_all(&sysfs.root).expect("root should be created");
+
+        assert!(Client::new(&sysfs.root, None).is_err());
+    }
+
+    #[test]
+    fn test_get_reads_actual_brightness() {
+        let sysfs = FakeSysfs::new(&[("intel_backlight", 40)]);
+        let client = Client::new(&sysfs.root, None).expect("client should be created");
+
+        sysfs.set_actual("intel_backlight", 30);
+
+        let brightness = client.get().expect("brightness should be read");
+        assert_eq!(
+            brightness,
+            Brightness {
+                value: 30,
+                max: 100
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_falls_back_to_brightness() {
+        let sysfs = FakeSysfs::new(&[("intel_backlight", 40)]);
+        fs::remove_file(sysfs.root.join("intel_backlight").join("actual_brightness"))
+            .expect("actual_brightness should be removed");
+
+        let client = Client::new(&sysfs.root, None).expect("client should be created");
+
+        let brightness = client.get().expect("brightness should be read");
+        assert_eq!(
+            brightness,
+            Brightness {
+                value: 40,
+                max: 100
+            }
+        );
+    }
+
+    async fn recv(rx: &mut mpsc::Receiver<Brightness>) -> Brightness {
+        timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .expect("watch should send before timing out")
+            .expect("watch should be running")
+    }
+
+    #[tokio::test]
+    async fn test_watch_detects_hardware_changes() {
+        let sysfs = FakeSysfs::new(&[("intel_backlight", 40)]);
+        let client = Client::new(&sysfs.root, None).expect("client should be created");
+
+        let (tx, mut rx) = mpsc::channel(1);
+        let watch = tokio::spawn(async move { client.watch(tx).await });
+
+        assert_eq!(recv(&mut rx).await.value, 40);
+
+        sysfs.set_actual("intel_backlight", 75);
+        assert_eq!(recv(&mut rx).await.value, 75);
+
+        drop(rx);
+        timeout(Duration::from_secs(2), watch)
+            .await
+            .expect("watch should stop once the receiver is dropped")
+            .expect("watch should not panic");
+    }
+
+    #[tokio::test]
+    async fn test_set_uses_logind() {
+        let sysfs = FakeSysfs::new(&[("intel_backlight", 40)]);
+        let client = Client::new(&sysfs.root, None).expect("client should be created");
+
+        let (_server, calls) = mock_logind(&client, &sysfs).await;
+
+        client.set(60).await.expect("brightness should be set");
+
+        assert_eq!(
+            *crate::lock!(calls),
+            [("backlight".to_string(), "intel_backlight".to_string(), 60)]
+        );
+        assert_eq!(client.get().expect("brightness should be read").value, 60);
+    }
+}
@@ -0,0 +1,51 @@
+use super::{WorkspaceClient, WorkspaceId, WorkspaceUpdate};
+use crate::clients::wayland;
+use crate::lock;
+use color_eyre::{Report, Result};
+use tokio::sync::broadcast::Receiver;
+
+/// Workspace client backed by the `ext-workspace-v1` Wayland protocol,
+/// for compositors without their own IPC.
+pub struct ExtWorkspaceClient;
+
+impl WorkspaceClient for ExtWorkspaceClient {
+    /// Names are not unique across outputs,
+    /// so this focuses the first workspace with the name.
+    fn focus(&self, name: String) -> Result<()> {
+        let wl = wayland::get_client();
+        let workspace = lock!(wl)
+            .get_workspaces()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|workspace| workspace.name == name)
+            .ok_or_else(|| Report::msg(format!("Workspace '{name}' not found")))?;
+
+        self.focus_by_id(&workspace.id, name)
+    }
+
+    fn focus_by_id(&self, id: &WorkspaceId, _name: String) -> Result<()> {
+        let wl = wayland::get_client();
+        lock!(wl).focus_workspace(id.clone());
+        Ok(())
+    }
+
+    fn subscribe_workspace_change(&self) -> Receiver<WorkspaceUpdate> {
+        let wl = wayland::get_client();
+        let rx = lock!(wl).subscribe_workspaces();
+        rx
+    }
+}
+
+static CLIENT: ExtWorkspaceClient = ExtWorkspaceClient;
+
+/// Gets the `ext-workspace-v1` workspace client.
+pub fn get_client() -> &'static ExtWorkspaceClient {
+    &CLIENT
+}
+
+/// Checks whether the compositor advertises the `ext-workspace-v1` global.
+pub fn is_supported() -> bool {
+    let wl = wayland::get_client();
+    let supported = lock!(wl).get_workspaces().is_some();
+    supported
+}
@@ -1,197 +1,325 @@
-use super::{Visibility, Workspace, WorkspaceClient, WorkspaceId, WorkspaceUpdate};
-use crate::{arc_mut, lock, send};
+use crate::send;
+use cfg_if::cfg_if;
 use color_eyre::Result;
-use hyprland::data::{Workspace as HWorkspace, Workspaces};
-use hyprland::dispatch::{Dispatch, DispatchType, WorkspaceIdentifierWithSpecial};
 use hyprland::event_listener::EventListener;
 use hyprland::prelude::*;
-use hyprland::shared::WorkspaceType;
 use lazy_static::lazy_static;
 use tokio::sync::broadcast::{channel, Receiver, Sender};
 use tokio::task::spawn_blocking;
-use tracing::{debug, error, info};
+use tracing::{debug, info};
+
+cfg_if! {
+    if #[cfg(feature = "workspaces+hyprland")] {
+        use super::{Visibility, Workspace, WorkspaceClient, WorkspaceId, WorkspaceUpdate};
+        use crate::{arc_mut, lock};
+        use super::{ScratchpadWindow, WorkspaceWindow};
+        use hyprland::data::{Client, Clients, Workspace as HWorkspace, Workspaces};
+        use hyprland::dispatch::{
+            Dispatch, DispatchType, WindowIdentifier, WorkspaceIdentifierWithSpecial,
+        };
+        use std::collections::HashSet;
+        use hyprland::shared::{Address, WorkspaceType};
+        use tracing::error;
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "keyboard+hyprland")] {
+        use super::{KeyboardLayoutClient, KeyboardLayoutUpdate};
+        use hyprland::ctl::switch_xkb_layout::{self, SwitchXKBLayoutCmdTypes};
+        use hyprland::data::Devices;
+    }
+}
 
 pub struct EventClient {
+    #[cfg(feature = "workspaces+hyprland")]
     workspace_tx: Sender<WorkspaceUpdate>,
+    #[cfg(feature = "workspaces+hyprland")]
     _workspace_rx: Receiver<WorkspaceUpdate>,
+    #[cfg(feature = "keyboard+hyprland")]
+    keyboard_layout_tx: Sender<KeyboardLayoutUpdate>,
+    #[cfg(feature = "keyboard+hyprland")]
+    _keyboard_layout_rx: Receiver<KeyboardLayoutUpdate>,
 }
 
 impl EventClient {
     fn new() -> Self {
+        #[cfg(feature = "workspaces+hyprland")]
         let (workspace_tx, workspace_rx) = channel(16);
+        #[cfg(feature = "keyboard+hyprland")]
+        let (keyboard_layout_tx, keyboard_layout_rx) = channel(4);
 
         Self {
+            #[cfg(feature = "workspaces+hyprland")]
             workspace_tx,
+            #[cfg(feature = "workspaces+hyprland")]
             _workspace_rx: workspace_rx,
+            #[cfg(feature = "keyboard+hyprland")]
+            keyboard_layout_tx,
+            #[cfg(feature = "keyboard+hyprland")]
+            _keyboard_layout_rx: keyboard_layout_rx,
         }
     }
 
-    fn listen_workspace_events(&self) {
+    fn listen_events(&self) {
         info!("Starting Hyprland event listener");
 
-        let tx = self.workspace_tx.clone();
+        #[cfg(feature = "workspaces+hyprland")]
+        let workspace_tx = self.workspace_tx.clone();
+        #[cfg(feature = "keyboard+hyprland")]
+        let keyboard_layout_tx = self.keyboard_layout_tx.clone();
 
         spawn_blocking(move || {
             let mut event_listener = EventListener::new();
 
-            // we need a lock to ensure events don't run at the same time
-            let lock = arc_mut!(());
+            #[cfg(feature = "workspaces+hyprland")]
+            Self::add_workspace_handlers(&mut event_listener, workspace_tx);
+            #[cfg(feature = "keyboard+hyprland")]
+            Self::add_keyboard_layout_handler(&mut event_listener, keyboard_layout_tx);
 
-            // cache the active workspace since Hyprland doesn't give us the prev active
-            let active = Self::get_active_workspace().expect("Failed to get active workspace");
-            let active = arc_mut!(Some(active));
+            event_listener
+                .start_listener()
+                .expect("Failed to start listener");
+        });
+    }
 
-            {
-                let tx = tx.clone();
-                let lock = lock.clone();
-                let active = active.clone();
+    #[cfg(feature = "workspaces+hyprland")]
+    fn add_workspace_handlers(event_listener: &mut EventListener, tx: Sender<WorkspaceUpdate>) {
+        // we need a lock to ensure events don't run at the same time
+        let lock = arc_mut!(());
 
-                event_listener.add_workspace_added_handler(move |workspace_type| {
-                    let _lock = lock!(lock);
-                    debug!("Added workspace: {workspace_type:?}");
+        // cache the active workspace since Hyprland doesn't give us the prev active
+        let active = Self::get_active_workspace().expect("Failed to get active workspace");
+        let active = arc_mut!(Some(active));
 
-                    let workspace_name = get_workspace_id(workspace_type);
-                    let prev_workspace = lock!(active);
+        // Hyprland only reports urgency per window, so track which workspaces are urgent
+        // until they are next focused.
+        let urgent = arc_mut!(HashSet::<WorkspaceId>::new());
 
-                    let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
+        {
+            let tx = tx.clone();
+            let lock = lock.clone();
+            let active = active.clone();
 
-                    if let Some(workspace) = workspace {
-                        send!(tx, WorkspaceUpdate::Add(workspace));
-                    }
-                });
-            }
+            event_listener.add_workspace_added_handler(move |workspace_type| {
+                let _lock = lock!(lock);
+                debug!("Added workspace: {workspace_type:?}");
 
-            {
-                let tx = tx.clone();
-                let lock = lock.clone();
-                let active = active.clone();
+                let workspace_name = get_workspace_id(workspace_type);
+                let prev_workspace = lock!(active);
 
-                event_listener.add_workspace_change_handler(move |workspace_type| {
-                    let _lock = lock!(lock);
-
-                    let mut prev_workspace = lock!(active);
-
-                    debug!(
-                        "Received workspace change: {:?} -> {workspace_type:?}",
-                        prev_workspace.as_ref().map(|w| &w.id)
-                    );
-
-                    let workspace_name = get_workspace_id(workspace_type);
-                    let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
-
-                    workspace.map_or_else(
-                        || {
-                            error!("Unable to locate workspace {workspace_name:?}");
-                        },
-                        |workspace| {
-                            // there may be another type of update so dispatch that regardless of focus change
-                            send!(tx, WorkspaceUpdate::Update(workspace.clone()));
-                            if !workspace.visibility.is_focused() {
-                                Self::send_focus_change(&mut prev_workspace, workspace, &tx);
-                            }
-                        },
-                    );
-                });
-            }
-
-            macro_rules! workspace_batch_event {
-                ($event:ident) => {
-                    let tx = tx.clone();
-                    let active = active.clone();
-
-                    // Just update all the workspaces
-                    event_listener.$event(move |_state| {
-                        Workspaces::get().unwrap().into_iter().for_each(|ws| {
-                            let prev_workspace = lock!(active);
-                            let focused = prev_workspace
-                                .as_ref()
-                                .map_or(Visibility::Visible(false), |w| {
-                                    Visibility::Visible(w.id == WorkspaceId(format!("{}", ws.id)))
-                                });
-                            send!(tx, WorkspaceUpdate::Update(Workspace::from((focused, ws))));
-                        });
-                    })
-                };
-            }
+                let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
+
+                if let Some(workspace) = workspace {
+                    send!(tx, WorkspaceUpdate::Add(workspace));
+                }
+            });
+        }
 
-            workspace_batch_event!(add_window_open_handler);
-            workspace_batch_event!(add_window_close_handler);
-            workspace_batch_event!(add_window_moved_handler);
+        {
+            let tx = tx.clone();
+            let lock = lock.clone();
+            let active = active.clone();
+            let urgent = urgent.clone();
+
+            event_listener.add_workspace_change_handler(move |workspace_type| {
+                let _lock = lock!(lock);
+
+                let mut prev_workspace = lock!(active);
+
+                debug!(
+                    "Received workspace change: {:?} -> {workspace_type:?}",
+                    prev_workspace.as_ref().map(|w| &w.id)
+                );
+
+                let workspace_name = get_workspace_id(workspace_type);
+                let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
+
+                workspace.map_or_else(
+                    || {
+                        error!("Unable to locate workspace {workspace_name:?}");
+                    },
+                    |workspace| {
+                        // urgency is tracked by the numeric ID, not the name from the event
+                        lock!(urgent).remove(&workspace.id);
+
+                        // there may be another type of update so dispatch that regardless of focus change
+                        send!(tx, WorkspaceUpdate::Update(workspace.clone()));
+                        if !workspace.visibility.is_focused() {
+                            Self::send_focus_change(&mut prev_workspace, workspace, &tx);
+                        }
+                    },
+                );
+            });
+        }
 
-            {
+        macro_rules! workspace_batch_event {
+            ($event:ident) => {
                 let tx = tx.clone();
-                let lock = lock.clone();
                 let active = active.clone();
+                let urgent = urgent.clone();
+
+                // Just update all the workspaces
+                event_listener.$event(move |_state| {
+                    Workspaces::get().unwrap().into_iter().for_each(|ws| {
+                        let prev_workspace = lock!(active);
+                        let focused = prev_workspace
+                            .as_ref()
+                            .map_or(Visibility::Visible(false), |w| {
+                                Visibility::Visible(w.id == WorkspaceId(format!("{}", ws.id)))
+                            });
+
+                        let mut workspace = Workspace::from((focused, ws));
+                        workspace.urgent = lock!(urgent).contains(&workspace.id);
+
+                        send!(tx, WorkspaceUpdate::Update(workspace));
+                    });
+                })
+            };
+        }
 
-                event_listener.add_active_monitor_change_handler(move |event_data| {
-                    let _lock = lock!(lock);
-                    let workspace_type = event_data.workspace;
+        workspace_batch_event!(add_window_open_handler);
+        workspace_batch_event!(add_window_close_handler);
+        workspace_batch_event!(add_window_moved_handler);
 
-                    let mut prev_workspace = lock!(active);
+        {
+            let tx = tx.clone();
+            let lock = lock.clone();
+            let active = active.clone();
+
+            event_listener.add_urgent_state_handler(move |address| {
+                let _lock = lock!(lock);
+                debug!("Received urgent state: {address}");
+
+                // event addresses are not prefixed with `0x`, unlike those from `clients`
+                let address = address.to_string();
+                let address = address.trim_start_matches("0x");
+
+                let workspace_id = Clients::get().ok().and_then(|clients| {
+                    clients
+                        .into_iter()
+                        .find(|client| {
+                            client.address.to_string().trim_start_matches("0x") == address
+                        })
+                        .map(|client| WorkspaceId(client.workspace.id.to_string()))
+                });
 
-                    debug!(
-                        "Received active monitor change: {:?} -> {workspace_type:?}",
-                        prev_workspace.as_ref().map(|w| &w.name)
-                    );
+                let Some(workspace_id) = workspace_id else {
+                    error!("Unable to locate workspace for urgent window {address}");
+                    return;
+                };
 
-                    let workspace_name = get_workspace_id(workspace_type);
-                    let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
+                let prev_workspace = lock!(active);
+                let workspace = Self::get_workspace(&workspace_id, prev_workspace.as_ref());
 
-                    if let Some((false, workspace)) =
-                        workspace.map(|w| (w.visibility.is_focused(), w))
-                    {
-                        Self::send_focus_change(&mut prev_workspace, workspace, &tx);
-                    } else {
-                        error!("Unable to locate workspace");
+                if let Some(mut workspace) = workspace {
+                    if !workspace.visibility.is_focused() {
+                        lock!(urgent).insert(workspace.id.clone());
+                        workspace.urgent = true;
+                        send!(tx, WorkspaceUpdate::Update(workspace));
                     }
-                });
-            }
+                }
+            });
+        }
 
-            {
-                let tx = tx.clone();
-                let lock = lock.clone();
+        {
+            let tx = tx.clone();
+            let lock = lock.clone();
+            let active = active.clone();
 
-                event_listener.add_workspace_moved_handler(move |event_data| {
-                    let _lock = lock!(lock);
-                    let workspace_type = event_data.workspace;
-                    debug!("Received workspace move: {workspace_type:?}");
+            event_listener.add_active_monitor_change_handler(move |event_data| {
+                let _lock = lock!(lock);
+                let workspace_type = event_data.workspace;
 
-                    let mut prev_workspace = lock!(active);
+                let mut prev_workspace = lock!(active);
 
-                    let workspace_name = get_workspace_id(workspace_type);
-                    let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
+                debug!(
+                    "Received active monitor change: {:?} -> {workspace_type:?}",
+                    prev_workspace.as_ref().map(|w| &w.name)
+                );
 
-                    if let Some(workspace) = workspace {
-                        send!(tx, WorkspaceUpdate::Move(workspace.clone()));
+                let workspace_name = get_workspace_id(workspace_type);
+                let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
 
-                        if !workspace.visibility.is_focused() {
-                            Self::send_focus_change(&mut prev_workspace, workspace, &tx);
-                        }
-                    }
-                });
-            }
+                if let Some((false, workspace)) = workspace.map(|w| (w.visibility.is_focused(), w))
+                {
+                    Self::send_focus_change(&mut prev_workspace, workspace, &tx);
+                } else {
+                    error!("Unable to locate workspace");
+                }
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            let lock = lock.clone();
 
-            {
-                event_listener.add_workspace_destroy_handler(move |workspace_type| {
-                    let _lock = lock!(lock);
-                    debug!("Received workspace destroy: {workspace_type:?}");
+            event_listener.add_workspace_moved_handler(move |event_data| {
+                let _lock = lock!(lock);
+                let workspace_type = event_data.workspace;
+                debug!("Received workspace move: {workspace_type:?}");
 
-                    let name = get_workspace_id(workspace_type);
-                    debug!("Received workspace destroy: {name:?}");
+                let mut prev_workspace = lock!(active);
 
-                    // TODO: Horrible hack, see other todo in remove handler
-                    send!(tx, WorkspaceUpdate::Remove { name: name.0 });
-                });
-            }
+                let workspace_name = get_workspace_id(workspace_type);
+                let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
 
-            event_listener
-                .start_listener()
-                .expect("Failed to start listener");
+                if let Some(workspace) = workspace {
+                    send!(tx, WorkspaceUpdate::Move(workspace.clone()));
+
+                    if !workspace.visibility.is_focused() {
+                        Self::send_focus_change(&mut prev_workspace, workspace, &tx);
+                    }
+                }
+            });
+        }
+
+        {
+            event_listener.add_workspace_destroy_handler(move |workspace_type| {
+                let _lock = lock!(lock);
+                debug!("Received workspace destroy: {workspace_type:?}");
+
+                let name = get_workspace_id(workspace_type);
+                debug!("Received workspace destroy: {name:?}");
+
+                // TODO: Horrible hack, see other todo in remove handler
+                send!(
+                    tx,
+                    WorkspaceUpdate::Remove {
+                        name: name.0.clone(),
+                        id: name,
+                    }
+                );
+            });
+        }
+    }
+
+    #[cfg(feature = "keyboard+hyprland")]
+    fn add_keyboard_layout_handler(
+        event_listener: &mut EventListener,
+        tx: Sender<KeyboardLayoutUpdate>,
+    ) {
+        event_listener.add_keyboard_layout_change_handler(move |event| {
+            debug!("Received layout change: {event:?}");
+
+            // the event regex in `hyprland-rs` captures everything into the keyboard name,
+            // so the layout must be split out manually.
+            let layout = if event.layout_name.is_empty() {
+                event
+                    .keyboard_name
+                    .split_once(',')
+                    .map_or_else(String::new, |(_, layout)| layout.to_string())
+            } else {
+                event.layout_name
+            };
+
+            send!(tx, KeyboardLayoutUpdate(layout));
         });
     }
 
     /// Sends a `WorkspaceUpdate::Focus` event
     /// and updates the active workspace cache.
+    #[cfg(feature = "workspaces+hyprland")]
     fn send_focus_change(
         prev_workspace: &mut Option<Workspace>,
         workspace: Workspace,
@@ -212,6 +340,7 @@ impl EventClient {
     }
 
     /// Gets a workspace by name from the server, given the active workspace if known.
+    #[cfg(feature = "workspaces+hyprland")]
     fn get_workspace(id: &WorkspaceId, active: Option<&Workspace>) -> Option<Workspace> {
         Workspaces::get()
             .expect("Failed to get workspaces")
@@ -229,12 +358,14 @@ impl EventClient {
     }
 
     /// Gets the active workspace from the server.
+    #[cfg(feature = "workspaces+hyprland")]
     fn get_active_workspace() -> Result<Workspace> {
         let w = HWorkspace::get_active().map(|w| Workspace::from((Visibility::focused(), w)))?;
         Ok(w)
     }
 }
 
+#[cfg(feature = "workspaces+hyprland")]
 impl WorkspaceClient for EventClient {
     fn focus(&self, id: String) -> Result<()> {
         let identifier = match id.parse::<i32>() {
@@ -269,12 +400,91 @@ impl WorkspaceClient for EventClient {
 
         rx
     }
+
+    fn scratchpad_windows(&self) -> Result<Vec<ScratchpadWindow>> {
+        // special workspaces have negative IDs
+        let windows = Clients::get()?
+            .filter(|client| client.workspace.id < 0)
+            .map(|client| ScratchpadWindow {
+                id: client.workspace.name,
+                title: client.title,
+                app_id: client.class,
+            })
+            .collect();
+
+        Ok(windows)
+    }
+
+    fn show_scratchpad_window(&self, id: String) -> Result<()> {
+        // windows cannot be shown individually,
+        // so toggle the special workspace containing the window instead.
+        let name = id.strip_prefix("special:").unwrap_or(&id).to_string();
+        Dispatch::call(DispatchType::ToggleSpecialWorkspace(Some(name)))?;
+        Ok(())
+    }
+
+    fn workspace_windows(&self, id: &WorkspaceId) -> Result<Vec<WorkspaceWindow>> {
+        let active = Client::get_active()?.map(|client| client.address.to_string());
+
+        let windows = Clients::get()?
+            .filter(|client| client.workspace.id.to_string() == id.0)
+            .map(|client| {
+                let address = client.address.to_string();
+
+                WorkspaceWindow {
+                    focused: active.as_ref() == Some(&address),
+                    id: address,
+                    title: client.title,
+                    app_id: client.class,
+                }
+            })
+            .collect();
+
+        Ok(windows)
+    }
+
+    fn focus_window(&self, id: String) -> Result<()> {
+        // the dispatcher adds its own `0x` prefix
+        let address = Address::new(id.trim_start_matches("0x"));
+        Dispatch::call(DispatchType::FocusWindow(WindowIdentifier::Address(
+            address,
+        )))?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "keyboard+hyprland")]
+impl KeyboardLayoutClient for EventClient {
+    fn set_next_active(&self) -> Result<()> {
+        let devices = Devices::get()?;
+
+        for keyboard in devices.keyboards {
+            switch_xkb_layout::call(&keyboard.name, SwitchXKBLayoutCmdTypes::Next)?;
+        }
+
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Receiver<KeyboardLayoutUpdate> {
+        let rx = self.keyboard_layout_tx.subscribe();
+
+        let layout = Devices::get()
+            .ok()
+            .and_then(|devices| devices.keyboards.into_iter().next())
+            .map(|keyboard| keyboard.active_keymap);
+
+        if let Some(layout) = layout {
+            send!(self.keyboard_layout_tx, KeyboardLayoutUpdate(layout));
+        }
+
+        rx
+    }
 }
 
 lazy_static! {
     static ref CLIENT: EventClient = {
         let client = EventClient::new();
-        client.listen_workspace_events();
+        client.listen_events();
         client
     };
 }
@@ -283,6 +493,7 @@ pub fn get_client() -> &'static EventClient {
     &CLIENT
 }
 
+#[cfg(feature = "workspaces+hyprland")]
 fn get_workspace_id(name: WorkspaceType) -> WorkspaceId {
     match name {
         WorkspaceType::Regular(name) => WorkspaceId(name),
@@ -291,12 +502,14 @@ fn get_workspace_id(name: WorkspaceType) -> WorkspaceId {
 }
 
 /// Creates a function which determines if a workspace is visible. This function makes a Hyprland call that allocates so it should be cached when possible, but it is only valid so long as workspaces do not change so it should not be stored long term
+#[cfg(feature = "workspaces+hyprland")]
 fn create_is_visible() -> impl Fn(&HWorkspace) -> bool {
     let monitors = hyprland::data::Monitors::get().map_or(Vec::new(), |ms| ms.to_vec());
 
     move |w| monitors.iter().any(|m| m.active_workspace.id == w.id)
 }
 
+#[cfg(feature = "workspaces+hyprland")]
 impl From<(Visibility, HWorkspace)> for Workspace {
     fn from((visibility, workspace): (Visibility, HWorkspace)) -> Self {
         Self {
@@ -304,10 +517,13 @@ impl From<(Visibility, HWorkspace)> for Workspace {
             name: workspace.name,
             monitor: workspace.monitor,
             visibility,
+            urgent: false,
+            windows: Some(u32::from(workspace.windows)),
         }
     }
 }
 
+#[cfg(feature = "workspaces+hyprland")]
 impl<'a, 'f, F> From<(&'a HWorkspace, Option<&str>, F)> for Visibility
 where
     F: FnOnce(&'f HWorkspace) -> bool,
@@ -4,16 +4,20 @@ use std::fmt::{Display, Formatter};
 use tokio::sync::broadcast;
 use tracing::debug;
 
-#[cfg(feature = "workspaces+hyprland")]
+#[cfg(feature = "workspaces+ext")]
+pub mod ext;
+#[cfg(any(feature = "keyboard+hyprland", feature = "workspaces+hyprland"))]
 pub mod hyprland;
-#[cfg(feature = "workspaces+sway")]
+#[cfg(any(feature = "keyboard+sway", feature = "workspaces+sway"))]
 pub mod sway;
 
 pub enum Compositor {
-    #[cfg(feature = "workspaces+sway")]
+    #[cfg(any(feature = "keyboard+sway", feature = "workspaces+sway"))]
     Sway,
-    #[cfg(feature = "workspaces+hyprland")]
+    #[cfg(any(feature = "keyboard+hyprland", feature = "workspaces+hyprland"))]
     Hyprland,
+    #[cfg(feature = "workspaces+ext")]
+    Ext,
     Unsupported,
 }
 
@@ -23,10 +27,12 @@ impl Display for Compositor {
             f,
             "{}",
             match self {
-                #[cfg(feature = "workspaces+sway")]
+                #[cfg(any(feature = "keyboard+sway", feature = "workspaces+sway"))]
                 Self::Sway => "Sway",
-                #[cfg(feature = "workspaces+hyprland")]
+                #[cfg(any(feature = "keyboard+hyprland", feature = "workspaces+hyprland"))]
                 Self::Hyprland => "Hyprland",
+                #[cfg(feature = "workspaces+ext")]
+                Self::Ext => "ext-workspace-v1",
                 Self::Unsupported => "Unsupported",
             }
         )
@@ -35,24 +41,31 @@ impl Display for Compositor {
 
 impl Compositor {
     /// Attempts to get the current compositor.
-    /// This is done by checking system env vars.
+    /// This is done by checking system env vars,
+    /// falling back to the `ext-workspace-v1` protocol if neither IPC socket is present.
     fn get_current() -> Self {
         if std::env::var("SWAYSOCK").is_ok() {
             cfg_if! {
-                if #[cfg(feature = "workspaces+sway")] { Self::Sway }
+                if #[cfg(any(feature = "keyboard+sway", feature = "workspaces+sway"))] { Self::Sway }
                 else { tracing::error!("Not compiled with Sway support"); Self::Unsupported }
             }
         } else if std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok() {
             cfg_if! {
-                if #[cfg(feature = "workspaces+hyprland")] { Self::Hyprland}
+                if #[cfg(any(feature = "keyboard+hyprland", feature = "workspaces+hyprland"))] { Self::Hyprland }
                 else { tracing::error!("Not compiled with Hyprland support"); Self::Unsupported }
             }
         } else {
-            Self::Unsupported
+            cfg_if! {
+                if #[cfg(feature = "workspaces+ext")] {
+                    if ext::is_supported() { Self::Ext } else { Self::Unsupported }
+                }
+                else { Self::Unsupported }
+            }
         }
     }
 
     /// Gets the workspace client for the current compositor
+    #[cfg(feature = "workspaces")]
     pub fn get_workspace_client() -> Result<&'static (dyn WorkspaceClient + Send)> {
         let current = Self::get_current();
         debug!("Getting workspace client for: {current}");
@@ -61,15 +74,37 @@ impl Compositor {
             Self::Sway => Ok(sway::get_sub_client()),
             #[cfg(feature = "workspaces+hyprland")]
             Self::Hyprland => Ok(hyprland::get_client()),
-            Self::Unsupported => Err(Report::msg("Unsupported compositor")
-                .note("Currently workspaces are only supported by Sway and Hyprland")),
+            #[cfg(feature = "workspaces+ext")]
+            Self::Ext => Ok(ext::get_client()),
+            #[allow(unreachable_patterns)]
+            _ => Err(Report::msg("Unsupported compositor").note(
+                "Currently workspaces are only supported by Sway, Hyprland, and compositors implementing ext-workspace-v1",
+            )),
+        }
+    }
+
+    /// Gets the keyboard layout client for the current compositor
+    #[cfg(feature = "keyboard")]
+    pub fn get_keyboard_layout_client() -> Result<&'static (dyn KeyboardLayoutClient + Send)> {
+        let current = Self::get_current();
+        debug!("Getting keyboard layout client for: {current}");
+        match current {
+            #[cfg(feature = "keyboard+sway")]
+            Self::Sway => Ok(sway::get_sub_client()),
+            #[cfg(feature = "keyboard+hyprland")]
+            Self::Hyprland => Ok(hyprland::get_client()),
+            #[allow(unreachable_patterns)]
+            _ => Err(Report::msg("Unsupported compositor")
+                .note("Currently keyboard layouts are only supported by Sway and Hyprland")),
         }
     }
 }
 
+#[cfg(feature = "workspaces")]
 #[derive(Hash, Debug, Clone, PartialEq, Eq)]
 pub struct WorkspaceId(pub String);
 
+#[cfg(feature = "workspaces")]
 #[derive(Debug, Clone)]
 pub struct Workspace {
     /// Unique identifier
@@ -80,16 +115,48 @@ pub struct Workspace {
     pub monitor: String,
     /// How visible the workspace is
     pub visibility: Visibility,
+    /// Whether a window on the workspace is requesting attention
+    pub urgent: bool,
+    /// Number of windows on the workspace, if known
+    pub windows: Option<u32>,
+}
+
+/// A window currently hidden in the scratchpad.
+#[cfg(feature = "workspaces")]
+#[derive(Debug, Clone)]
+pub struct ScratchpadWindow {
+    /// Compositor-specific identifier, used to show the window.
+    pub id: String,
+    /// Window title
+    pub title: String,
+    /// Window app ID or class
+    pub app_id: String,
+}
+
+/// A window open on a workspace.
+#[cfg(feature = "workspaces")]
+#[derive(Debug, Clone)]
+pub struct WorkspaceWindow {
+    /// Compositor-specific identifier, used to focus the window.
+    pub id: String,
+    /// Window title
+    pub title: String,
+    /// Window app ID or class
+    pub app_id: String,
+    /// Whether the window currently has focus
+    pub focused: bool,
 }
 
 /// Indicates workspace visibility. Visible workspaces have a boolean flag to indicate if they are also focused.
 /// Yes, this is the same signature as Option<bool>, but it's impl is a lot more suited for our case.
+#[cfg(feature = "workspaces")]
 #[derive(Debug, Copy, Clone)]
 pub enum Visibility {
     Visible(bool),
     Hidden,
 }
 
+#[cfg(feature = "workspaces")]
 impl Visibility {
     pub fn visible() -> Self {
         Self::Visible(false)
@@ -112,13 +179,17 @@ impl Visibility {
     }
 }
 
+#[cfg(feature = "workspaces")]
 #[derive(Debug, Clone)]
 pub enum WorkspaceUpdate {
     /// Provides an initial list of workspaces.
     /// This is re-sent to all subscribers when a new subscription is created.
     Init(Vec<Workspace>),
     Add(Workspace),
-    Remove{name: String},
+    Remove {
+        id: WorkspaceId,
+        name: String,
+    },
     Update(Workspace),
     Move(Workspace),
     /// Declares focus moved from the old workspace to the new.
@@ -128,10 +199,61 @@ pub enum WorkspaceUpdate {
     },
 }
 
+#[cfg(feature = "workspaces")]
 pub trait WorkspaceClient {
     /// Requests the workspace with this name is focused.
     fn focus(&self, name: String) -> Result<()>;
 
+    /// Requests the workspace with this ID is focused.
+    ///
+    /// Clients which can only focus workspaces by name use `name` instead.
+    fn focus_by_id(&self, _id: &WorkspaceId, name: String) -> Result<()> {
+        self.focus(name)
+    }
+
     /// Creates a new to workspace event receiver.
     fn subscribe_workspace_change(&self) -> broadcast::Receiver<WorkspaceUpdate>;
+
+    /// Gets the windows currently in the scratchpad.
+    fn scratchpad_windows(&self) -> Result<Vec<ScratchpadWindow>> {
+        Err(Report::msg(
+            "Scratchpad is not supported by this compositor",
+        ))
+    }
+
+    /// Requests the scratchpad window with this ID is shown.
+    fn show_scratchpad_window(&self, _id: String) -> Result<()> {
+        Err(Report::msg(
+            "Scratchpad is not supported by this compositor",
+        ))
+    }
+
+    /// Gets the windows open on the workspace with this ID.
+    fn workspace_windows(&self, _id: &WorkspaceId) -> Result<Vec<WorkspaceWindow>> {
+        Err(Report::msg(
+            "Listing windows is not supported by this compositor",
+        ))
+    }
+
+    /// Requests the window with this ID is focused.
+    fn focus_window(&self, _id: String) -> Result<()> {
+        Err(Report::msg(
+            "Focusing windows is not supported by this compositor",
+        ))
+    }
+}
+
+/// The active keyboard layout.
+#[cfg(feature = "keyboard")]
+#[derive(Debug, Clone)]
+pub struct KeyboardLayoutUpdate(pub String);
+
+#[cfg(feature = "keyboard")]
+pub trait KeyboardLayoutClient {
+    /// Switches to the next configured layout.
+    fn set_next_active(&self) -> Result<()>;
+
+    /// Creates a new keyboard layout event receiver.
+    /// The current layout is sent immediately.
+    fn subscribe(&self) -> broadcast::Receiver<KeyboardLayoutUpdate>;
 }
@@ -1,40 +1,118 @@
-use super::{Visibility, Workspace, WorkspaceClient, WorkspaceUpdate, WorkspaceId};
 use crate::{await_sync, send};
 use async_once::AsyncOnce;
+use cfg_if::cfg_if;
 use color_eyre::Report;
 use futures_util::StreamExt;
 use lazy_static::lazy_static;
 use std::sync::Arc;
-use swayipc_async::{Connection, Event, EventType, Node, WorkspaceChange, WorkspaceEvent};
+use swayipc_async::{Connection, Event, EventType};
 use tokio::spawn;
 use tokio::sync::broadcast::{channel, Receiver, Sender};
 use tokio::sync::Mutex;
 use tracing::{info, trace};
 
+cfg_if! {
+    if #[cfg(feature = "workspaces+sway")] {
+        use super::{ScratchpadWindow, Visibility, Workspace, WorkspaceClient, WorkspaceUpdate, WorkspaceId, WorkspaceWindow};
+        use std::collections::HashMap;
+        use swayipc_async::{Node, NodeType, WindowChange, WorkspaceChange, WorkspaceEvent};
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "keyboard+sway")] {
+        use super::{KeyboardLayoutClient, KeyboardLayoutUpdate};
+        use swayipc_async::{Input, InputChange, InputEvent};
+    }
+}
+
 pub struct SwayEventClient {
+    #[cfg(feature = "workspaces+sway")]
     workspace_tx: Sender<WorkspaceUpdate>,
+    #[cfg(feature = "workspaces+sway")]
     _workspace_rx: Receiver<WorkspaceUpdate>,
+    #[cfg(feature = "keyboard+sway")]
+    keyboard_layout_tx: Sender<KeyboardLayoutUpdate>,
+    #[cfg(feature = "keyboard+sway")]
+    _keyboard_layout_rx: Receiver<KeyboardLayoutUpdate>,
 }
 
 impl SwayEventClient {
     fn new() -> Self {
+        #[cfg(feature = "workspaces+sway")]
         let (workspace_tx, workspace_rx) = channel(16);
+        #[cfg(feature = "keyboard+sway")]
+        let (keyboard_layout_tx, keyboard_layout_rx) = channel(4);
 
         {
+            #[cfg(feature = "workspaces+sway")]
             let workspace_tx = workspace_tx.clone();
+            #[cfg(feature = "keyboard+sway")]
+            let keyboard_layout_tx = keyboard_layout_tx.clone();
+
             spawn(async move {
                 let client = Connection::new().await?;
                 info!("Sway IPC subscription client connected");
 
-                let event_types = [EventType::Workspace];
+                let event_types = [
+                    #[cfg(feature = "workspaces+sway")]
+                    EventType::Workspace,
+                    #[cfg(feature = "workspaces+sway")]
+                    EventType::Window,
+                    #[cfg(feature = "keyboard+sway")]
+                    EventType::Input,
+                ];
 
                 let mut events = client.subscribe(event_types).await?;
 
+                // window counts last sent for each workspace,
+                // so that only workspaces whose count changes are updated.
+                #[cfg(feature = "workspaces+sway")]
+                let mut sent_counts = {
+                    let client = get_client().await;
+                    let mut client = client.lock().await;
+
+                    window_counts(&get_workspaces(&mut client).await?)
+                };
+
                 while let Some(event) = events.next().await {
                     trace!("event: {:?}", event);
-                    if let Event::Workspace(ev) = event? {
-                        workspace_tx.send(WorkspaceUpdate::from(*ev))?;
-                    };
+                    match event? {
+                        #[cfg(feature = "workspaces+sway")]
+                        Event::Workspace(ev) => {
+                            workspace_tx.send(WorkspaceUpdate::from(*ev))?;
+                        }
+                        // window counts are only available from the tree,
+                        // so refresh them whenever windows come and go.
+                        #[cfg(feature = "workspaces+sway")]
+                        Event::Window(ev)
+                            if matches!(
+                                ev.change,
+                                WindowChange::New | WindowChange::Close | WindowChange::Move
+                            ) =>
+                        {
+                            let client = get_client().await;
+                            let mut client = client.lock().await;
+
+                            let workspaces = get_workspaces(&mut client).await?;
+                            let counts = window_counts(&workspaces);
+
+                            for workspace in workspaces {
+                                if sent_counts.get(&workspace.id) != Some(&workspace.windows) {
+                                    workspace_tx.send(WorkspaceUpdate::Update(workspace))?;
+                                }
+                            }
+
+                            sent_counts = counts;
+                        }
+                        #[cfg(feature = "keyboard+sway")]
+                        Event::Input(ev) => {
+                            if let Some(update) = KeyboardLayoutUpdate::from_event(*ev) {
+                                keyboard_layout_tx.send(update)?;
+                            }
+                        }
+                        _ => {}
+                    }
                 }
 
                 Ok::<(), Report>(())
@@ -42,12 +120,19 @@ impl SwayEventClient {
         }
 
         Self {
+            #[cfg(feature = "workspaces+sway")]
             workspace_tx,
+            #[cfg(feature = "workspaces+sway")]
             _workspace_rx: workspace_rx,
+            #[cfg(feature = "keyboard+sway")]
+            keyboard_layout_tx,
+            #[cfg(feature = "keyboard+sway")]
+            _keyboard_layout_rx: keyboard_layout_rx,
         }
     }
 }
 
+#[cfg(feature = "workspaces+sway")]
 impl WorkspaceClient for SwayEventClient {
     fn focus(&self, id: String) -> color_eyre::Result<()> {
         await_sync(async move {
@@ -67,14 +152,196 @@ impl WorkspaceClient for SwayEventClient {
                 let client = get_client().await;
                 let mut client = client.lock().await;
 
-                let workspaces = client
-                    .get_workspaces()
+                let workspaces = get_workspaces(&mut client)
                     .await
                     .expect("Failed to get workspaces");
-                let event =
-                    WorkspaceUpdate::Init(workspaces.into_iter().map(Workspace::from).collect());
 
-                send!(tx, event);
+                send!(tx, WorkspaceUpdate::Init(workspaces));
+            });
+        }
+
+        rx
+    }
+
+    fn scratchpad_windows(&self) -> color_eyre::Result<Vec<ScratchpadWindow>> {
+        let tree = await_sync(async {
+            let client = get_client().await;
+            let mut client = client.lock().await;
+            client.get_tree().await
+        })?;
+
+        let windows = find_node(&tree, &|node| node.name.as_deref() == Some("__i3_scratch"))
+            .map(|scratchpad| {
+                scratchpad
+                    .floating_nodes
+                    .iter()
+                    .flat_map(leaf_nodes)
+                    .map(ScratchpadWindow::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(windows)
+    }
+
+    fn show_scratchpad_window(&self, id: String) -> color_eyre::Result<()> {
+        await_sync(async move {
+            let client = get_client().await;
+            let mut client = client.lock().await;
+            client
+                .run_command(format!("[con_id={id}] scratchpad show"))
+                .await
+        })?;
+        Ok(())
+    }
+
+    fn workspace_windows(&self, id: &WorkspaceId) -> color_eyre::Result<Vec<WorkspaceWindow>> {
+        let tree = await_sync(async {
+            let client = get_client().await;
+            let mut client = client.lock().await;
+            client.get_tree().await
+        })?;
+
+        let windows = find_node(&tree, &|node| {
+            node.node_type == NodeType::Workspace && node.id.to_string() == id.0
+        })
+        .map(|workspace| {
+            workspace
+                .nodes
+                .iter()
+                .chain(&workspace.floating_nodes)
+                .flat_map(leaf_nodes)
+                .map(WorkspaceWindow::from)
+                .collect()
+        })
+        .unwrap_or_default();
+
+        Ok(windows)
+    }
+
+    fn focus_window(&self, id: String) -> color_eyre::Result<()> {
+        await_sync(async move {
+            let client = get_client().await;
+            let mut client = client.lock().await;
+            client.run_command(format!("[con_id={id}] focus")).await
+        })?;
+        Ok(())
+    }
+}
+
+/// Gets all workspaces, including their window counts.
+#[cfg(feature = "workspaces+sway")]
+async fn get_workspaces(client: &mut Connection) -> Result<Vec<Workspace>, swayipc_async::Error> {
+    let workspaces = client.get_workspaces().await?;
+    let tree = client.get_tree().await?;
+
+    let mut counts = HashMap::new();
+    collect_window_counts(&tree, &mut counts);
+
+    let workspaces = workspaces
+        .into_iter()
+        .map(|workspace| {
+            let windows = counts.get(&workspace.id).copied();
+            Workspace {
+                windows,
+                ..Workspace::from(workspace)
+            }
+        })
+        .collect();
+
+    Ok(workspaces)
+}
+
+/// Gets the window count of each workspace against its ID.
+#[cfg(feature = "workspaces+sway")]
+fn window_counts(workspaces: &[Workspace]) -> HashMap<WorkspaceId, Option<u32>> {
+    workspaces
+        .iter()
+        .map(|workspace| (workspace.id.clone(), workspace.windows))
+        .collect()
+}
+
+/// Recursively finds workspace nodes in the tree,
+/// storing the number of windows in each against its ID.
+#[cfg(feature = "workspaces+sway")]
+fn collect_window_counts(node: &Node, counts: &mut HashMap<i64, u32>) {
+    if node.node_type == NodeType::Workspace {
+        counts.insert(node.id, count_windows(node));
+    } else {
+        for child in &node.nodes {
+            collect_window_counts(child, counts);
+        }
+    }
+}
+
+/// Counts the number of windows below this node.
+#[cfg(feature = "workspaces+sway")]
+fn count_windows(node: &Node) -> u32 {
+    node.nodes
+        .iter()
+        .chain(&node.floating_nodes)
+        .map(|child| leaf_nodes(child).len() as u32)
+        .sum()
+}
+
+/// Gets all nodes without children below, and including, this node.
+/// These are the windows.
+#[cfg(feature = "workspaces+sway")]
+fn leaf_nodes(node: &Node) -> Vec<&Node> {
+    if node.nodes.is_empty() && node.floating_nodes.is_empty() {
+        vec![node]
+    } else {
+        node.nodes
+            .iter()
+            .chain(&node.floating_nodes)
+            .flat_map(leaf_nodes)
+            .collect()
+    }
+}
+
+/// Recursively finds the first node matching the predicate.
+#[cfg(feature = "workspaces+sway")]
+fn find_node<'a>(node: &'a Node, predicate: &dyn Fn(&Node) -> bool) -> Option<&'a Node> {
+    if predicate(node) {
+        Some(node)
+    } else {
+        node.nodes
+            .iter()
+            .chain(&node.floating_nodes)
+            .find_map(|child| find_node(child, predicate))
+    }
+}
+
+#[cfg(feature = "keyboard+sway")]
+impl KeyboardLayoutClient for SwayEventClient {
+    fn set_next_active(&self) -> color_eyre::Result<()> {
+        await_sync(async {
+            let client = get_client().await;
+            let mut client = client.lock().await;
+            client
+                .run_command("input type:keyboard xkb_switch_layout next")
+                .await
+        })?;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Receiver<KeyboardLayoutUpdate> {
+        let rx = self.keyboard_layout_tx.subscribe();
+
+        {
+            let tx = self.keyboard_layout_tx.clone();
+            await_sync(async {
+                let client = get_client().await;
+                let mut client = client.lock().await;
+
+                let inputs = client.get_inputs().await.expect("Failed to get inputs");
+
+                if let Some(update) = inputs
+                    .into_iter()
+                    .find_map(KeyboardLayoutUpdate::from_input)
+                {
+                    send!(tx, update);
+                }
             });
         }
 
@@ -103,19 +370,24 @@ pub fn get_sub_client() -> &'static SwayEventClient {
     &SUB_CLIENT
 }
 
+#[cfg(feature = "workspaces+sway")]
 impl From<Node> for Workspace {
     fn from(node: Node) -> Self {
         let visibility = Visibility::from(&node);
+        let windows = count_windows(&node);
 
         Self {
             id: WorkspaceId(node.id.to_string()),
             name: node.name.unwrap_or_default(),
             monitor: node.output.unwrap_or_default(),
             visibility,
+            urgent: node.urgent,
+            windows: Some(windows),
         }
     }
 }
 
+#[cfg(feature = "workspaces+sway")]
 impl From<swayipc_async::Workspace> for Workspace {
     fn from(workspace: swayipc_async::Workspace) -> Self {
         let visibility = Visibility::from(&workspace);
@@ -125,10 +397,50 @@ impl From<swayipc_async::Workspace> for Workspace {
             name: workspace.name,
             monitor: workspace.output,
             visibility,
+            urgent: workspace.urgent,
+            windows: None,
+        }
+    }
+}
+
+/// Gets the app ID of a window node,
+/// falling back to the X11 class for Xwayland windows.
+#[cfg(feature = "workspaces+sway")]
+fn get_app_id(node: &Node) -> String {
+    node.app_id
+        .clone()
+        .or_else(|| {
+            node.window_properties
+                .as_ref()
+                .and_then(|props| props.class.clone())
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "workspaces+sway")]
+impl From<&Node> for ScratchpadWindow {
+    fn from(node: &Node) -> Self {
+        Self {
+            id: node.id.to_string(),
+            title: node.name.clone().unwrap_or_default(),
+            app_id: get_app_id(node),
+        }
+    }
+}
+
+#[cfg(feature = "workspaces+sway")]
+impl From<&Node> for WorkspaceWindow {
+    fn from(node: &Node) -> Self {
+        Self {
+            id: node.id.to_string(),
+            title: node.name.clone().unwrap_or_default(),
+            app_id: get_app_id(node),
+            focused: node.focused,
         }
     }
 }
 
+#[cfg(feature = "workspaces+sway")]
 impl From<&Node> for Visibility {
     fn from(node: &Node) -> Self {
         if node.focused {
@@ -141,6 +453,7 @@ impl From<&Node> for Visibility {
     }
 }
 
+#[cfg(feature = "workspaces+sway")]
 impl From<&swayipc_async::Workspace> for Visibility {
     fn from(workspace: &swayipc_async::Workspace) -> Self {
         if workspace.focused {
@@ -153,21 +466,21 @@ impl From<&swayipc_async::Workspace> for Visibility {
     }
 }
 
+#[cfg(feature = "workspaces+sway")]
 impl From<WorkspaceEvent> for WorkspaceUpdate {
     fn from(event: WorkspaceEvent) -> Self {
         match event.change {
             WorkspaceChange::Init => {
                 Self::Add(event.current.expect("Missing current workspace").into())
             }
-            WorkspaceChange::Empty => todo!("Re-add support for sway empty"),/*Self::Remove(
-                event
-                    .current
-                    .expect("Missing current workspace")
-                    .name
-                    .unwrap_or_default(),
+            WorkspaceChange::Empty => todo!("Re-add support for sway empty"), /*Self::Remove(
+            event
+            .current
+            .expect("Missing current workspace")
+            .name
+            .unwrap_or_default(),
             ),*/
-            WorkspaceChange::Focus => 
-                todo!("Re-add support for focus on sway"),
+            WorkspaceChange::Focus => todo!("Re-add support for focus on sway"),
             WorkspaceChange::Move => {
                 Self::Move(event.current.expect("Missing current workspace").into())
             }
@@ -175,3 +488,25 @@ impl From<WorkspaceEvent> for WorkspaceUpdate {
         }
     }
 }
+
+#[cfg(feature = "keyboard+sway")]
+impl KeyboardLayoutUpdate {
+    /// Gets the active layout of a keyboard input.
+    /// Returns `None` for non-keyboard inputs.
+    fn from_input(input: Input) -> Option<Self> {
+        if input.input_type == "keyboard" {
+            input.xkb_active_layout_name.map(Self)
+        } else {
+            None
+        }
+    }
+
+    /// Gets the new layout from an input event,
+    /// if the event is a layout change.
+    fn from_event(event: InputEvent) -> Option<Self> {
+        match event.change {
+            InputChange::XkbLayout | InputChange::XkbKeymap => Self::from_input(event.input),
+            _ => None,
+        }
+    }
+}
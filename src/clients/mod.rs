@@ -1,11 +1,23 @@
+#[cfg(feature = "bluetooth")]
+pub mod bluez;
+#[cfg(feature = "brightness")]
+pub mod brightness;
 #[cfg(feature = "clipboard")]
 pub mod clipboard;
-#[cfg(feature = "workspaces")]
+#[cfg(any(feature = "keyboard", feature = "workspaces"))]
 pub mod compositor;
 #[cfg(feature = "music")]
 pub mod music;
+#[cfg(feature = "network")]
+pub mod networkmanager;
+#[cfg(feature = "notifications")]
+pub mod notifications;
+#[cfg(any(feature = "bluetooth", feature = "network", feature = "upower"))]
+pub mod signals;
 #[cfg(feature = "tray")]
 pub mod system_tray;
 #[cfg(feature = "upower")]
 pub mod upower;
+#[cfg(feature = "volume")]
+pub mod volume;
 pub mod wayland;
@@ -0,0 +1,121 @@
+use color_eyre::Result;
+use std::path::Path;
+use std::time::Duration;
+use tokio::fs;
+
+/// Synced lyrics, parsed from an `.lrc` file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Lyrics {
+    /// Lines and the time they start at, in order.
+    lines: Vec<(Duration, String)>,
+}
+
+impl Lyrics {
+    pub async fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).await?;
+        Ok(Self::parse(&text))
+    }
+
+    /// Parses the contents of an `.lrc` file.
+    ///
+    /// Lines can have multiple timestamps, such as `[00:12.00][01:30.50]Chorus`.
+    /// The `offset` tag is respected, and other tags are ignored,
+    /// as are lines without timestamps.
+    pub fn parse(text: &str) -> Self {
+        let mut offset_ms: i64 = 0;
+        let mut lines = vec![];
+
+        for line in text.lines() {
+            let mut rest = line.trim();
+            let mut times = vec![];
+
+            while let Some((tag, remaining)) =
+                rest.strip_prefix('[').and_then(|rest| rest.split_once(']'))
+            {
+                if let Some(offset) = tag.strip_prefix("offset:") {
+                    offset_ms = offset.trim().parse().unwrap_or_default();
+                } else if let Some(time) = parse_timestamp(tag) {
+                    times.push(time);
+                }
+
+                rest = remaining;
+            }
+
+            for time in times {
+                lines.push((time, rest.trim().to_string()));
+            }
+        }
+
+        // a positive offset shows lyrics sooner
+        for (time, _) in &mut lines {
+            *time = if offset_ms >= 0 {
+                time.saturating_sub(Duration::from_millis(offset_ms.unsigned_abs()))
+            } else {
+                *time + Duration::from_millis(offset_ms.unsigned_abs())
+            };
+        }
+
+        lines.sort_by_key(|(time, _)| *time);
+
+        Self { lines }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Gets the line being sung at the given point in the track,
+    /// if the first line has started.
+    pub fn line_at(&self, elapsed: Duration) -> Option<&str> {
+        let index = self.lines.partition_point(|(time, _)| *time <= elapsed);
+        index
+            .checked_sub(1)
+            .map(|index| self.lines[index].1.as_str())
+    }
+}
+
+/// Parses an `mm:ss.xx` timestamp.
+fn parse_timestamp(tag: &str) -> Option<Duration> {
+    let (minutes, seconds) = tag.split_once(':')?;
+
+    let minutes = minutes.parse::<u64>().ok()?;
+    let seconds = seconds.parse::<f64>().ok()?;
+
+    if !seconds.is_finite() || seconds < 0.0 {
+        return None;
+    }
+
+    Some(Duration::from_secs(minutes * 60) + Duration::from_secs_f64(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        const INPUT: &str =
+            "[ar:Artist]\n[00:01.00]First\n[00:05.50][00:20.00]Chorus\nNo timestamp\n[00:10]Second";
+        let lyrics = Lyrics::parse(INPUT);
+
+        assert_eq!(lyrics.line_at(Duration::from_millis(500)), None);
+        assert_eq!(lyrics.line_at(Duration::from_secs(1)), Some("First"));
+        assert_eq!(lyrics.line_at(Duration::from_secs(6)), Some("Chorus"));
+        assert_eq!(lyrics.line_at(Duration::from_secs(12)), Some("Second"));
+        assert_eq!(lyrics.line_at(Duration::from_secs(30)), Some("Chorus"));
+    }
+
+    #[test]
+    fn test_offset() {
+        let lyrics = Lyrics::parse("[offset:+500]\n[00:01.00]First");
+        assert_eq!(lyrics.line_at(Duration::from_millis(500)), Some("First"));
+
+        let lyrics = Lyrics::parse("[offset:-500]\n[00:01.00]First");
+        assert_eq!(lyrics.line_at(Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn test_empty() {
+        assert!(Lyrics::parse("[ti:Title]\nplain text").is_empty());
+    }
+}
@@ -0,0 +1,424 @@
+use super::{
+    MusicClient, PlayerState, PlayerUpdate, ProgressTick, QueueItem, RepeatMode, Status, Track,
+    TICK_INTERVAL_MS,
+};
+use crate::script::{OutputStream, Script};
+use crate::{arc_mut, lock, send};
+use color_eyre::{Report, Result};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::spawn;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+use tracing::{debug, error};
+
+lazy_static! {
+    static ref CLIENTS: Mutex<HashMap<MockSource, Arc<Client>>> = Mutex::new(HashMap::new());
+}
+
+/// Status sent before the first update event.
+const EMPTY_STATUS: Status = Status {
+    state: PlayerState::Stopped,
+    volume_percent: None,
+    playlist_position: 0,
+    playlist_length: 0,
+    shuffle: None,
+    repeat: None,
+};
+
+/// Where mock player events are read from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MockSource {
+    /// JSON file containing a list of events.
+    File(PathBuf),
+    /// Long-running script which writes one JSON event per line to `stdout`.
+    Script(String),
+}
+
+/// An event played back by the mock player.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MockEvent {
+    /// Sets the current track and player status.
+    Update {
+        track: Option<Track>,
+        status: Status,
+    },
+    /// Sets the progress through the current track, in seconds.
+    Progress {
+        elapsed: Option<f64>,
+        duration: Option<f64>,
+    },
+    /// Sets the queue.
+    Queue { items: Vec<QueueItem> },
+    /// Waits before playing the next event.
+    Wait { ms: u64 },
+    /// Simulates the player disconnecting.
+    Disconnect,
+}
+
+#[derive(Debug, Deserialize)]
+struct MockFile {
+    /// Whether to start again from the first event after the last.
+    #[serde(default)]
+    repeat: bool,
+    events: Vec<MockEvent>,
+}
+
+/// The player state built up from the events so far,
+/// which commands modify to mimic a real player.
+#[derive(Debug, Default)]
+struct State {
+    track: Option<Track>,
+    status: Option<Status>,
+    progress: Option<ProgressTick>,
+    queue: Vec<QueueItem>,
+}
+
+impl State {
+    fn send_update(&self, tx: &broadcast::Sender<PlayerUpdate>) {
+        let update = PlayerUpdate::Update(
+            Box::new(self.track.clone()),
+            self.status.unwrap_or(EMPTY_STATUS),
+        );
+
+        send!(tx, update);
+    }
+
+    fn status_mut(&mut self) -> &mut Status {
+        self.status.get_or_insert(EMPTY_STATUS)
+    }
+
+    /// Makes the queue item at `index` the current track,
+    /// if it exists.
+    fn select_queue_item(&mut self, index: usize, tx: &broadcast::Sender<PlayerUpdate>) {
+        let Some(item) = self.queue.get(index).cloned() else {
+            return;
+        };
+
+        for (i, item) in self.queue.iter_mut().enumerate() {
+            item.current = i == index;
+        }
+
+        self.track = Some(Track {
+            title: item.title,
+            artist: item.artist,
+            ..Track::default()
+        });
+
+        let queue_length = self.queue.len() as u32;
+        let status = self.status_mut();
+        status.playlist_position = index as u32;
+        status.playlist_length = queue_length;
+
+        self.progress = None;
+
+        send!(tx, PlayerUpdate::Queue(self.queue.clone()));
+    }
+
+    fn current_queue_index(&self) -> Option<usize> {
+        self.queue.iter().position(|item| item.current)
+    }
+}
+
+/// A fake player which plays back a list of events,
+/// allowing the music module to be developed and themed without a real player.
+///
+/// Commands change the player state as a real player would.
+pub struct Client {
+    state: Arc<Mutex<State>>,
+    tx: broadcast::Sender<PlayerUpdate>,
+    _rx: broadcast::Receiver<PlayerUpdate>,
+}
+
+impl Client {
+    fn new(source: MockSource) -> Self {
+        let (tx, rx) = broadcast::channel(32);
+        let state = arc_mut!(State::default());
+
+        {
+            let state = state.clone();
+            let tx = tx.clone();
+
+            spawn(async move {
+                let res = match source {
+                    MockSource::File(path) => play_file(&path, &state, &tx).await,
+                    MockSource::Script(cmd) => play_script(&cmd, &state, &tx).await,
+                };
+
+                if let Err(err) = res {
+                    error!("{:?}", err.wrap_err("Failed to run mock music player"));
+                }
+            });
+        }
+
+        Self { state, tx, _rx: rx }
+    }
+
+    /// Runs `f` against the player state,
+    /// then sends an update with the new state.
+    fn modify(&self, command: &str, f: impl FnOnce(&mut State, &broadcast::Sender<PlayerUpdate>)) {
+        debug!("Mock player received command: {command}");
+
+        let mut state = lock!(self.state);
+        f(&mut state, &self.tx);
+        state.send_update(&self.tx);
+    }
+}
+
+async fn play_file(
+    path: &Path,
+    state: &Mutex<State>,
+    tx: &broadcast::Sender<PlayerUpdate>,
+) -> Result<()> {
+    let file: MockFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+    loop {
+        for event in &file.events {
+            handle_event(event.clone(), state, tx).await;
+        }
+
+        if !file.repeat {
+            break Ok(());
+        }
+
+        // avoid spinning if there are no wait events
+        sleep(Duration::from_millis(TICK_INTERVAL_MS)).await;
+    }
+}
+
+async fn play_script(
+    cmd: &str,
+    state: &Mutex<State>,
+    tx: &broadcast::Sender<PlayerUpdate>,
+) -> Result<()> {
+    let mut rx = Script::from(cmd).spawn().await?;
+
+    while let Some(output) = rx.recv().await {
+        match output {
+            OutputStream::Stdout(line) => match serde_json::from_str(&line) {
+                Ok(event) => handle_event(event, state, tx).await,
+                Err(err) => error!(
+                    "{:?}",
+                    Report::new(err).wrap_err(format!("Invalid mock event: '{line}'"))
+                ),
+            },
+            OutputStream::Stderr(line) => error!("{line}"),
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_event(
+    event: MockEvent,
+    state: &Mutex<State>,
+    tx: &broadcast::Sender<PlayerUpdate>,
+) {
+    debug!("Playing mock event: {event:?}");
+
+    match event {
+        MockEvent::Update { track, status } => {
+            let mut state = lock!(state);
+            state.track = track;
+            state.status = Some(status);
+            state.send_update(tx);
+        }
+        MockEvent::Progress { elapsed, duration } => {
+            let to_duration =
+                |secs: Option<f64>| secs.and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+
+            let progress = ProgressTick {
+                elapsed: to_duration(elapsed),
+                duration: to_duration(duration),
+            };
+
+            lock!(state).progress = Some(progress);
+            send!(tx, PlayerUpdate::ProgressTick(progress));
+        }
+        MockEvent::Queue { items } => {
+            lock!(state).queue = items.clone();
+            send!(tx, PlayerUpdate::Queue(items));
+        }
+        MockEvent::Wait { ms } => sleep(Duration::from_millis(ms)).await,
+        MockEvent::Disconnect => {
+            send!(tx, PlayerUpdate::Disconnect);
+        }
+    }
+}
+
+impl MusicClient for Client {
+    fn play(&self) -> Result<()> {
+        self.modify("play", |state, _| {
+            state.status_mut().state = PlayerState::Playing;
+        });
+        Ok(())
+    }
+
+    fn pause(&self) -> Result<()> {
+        self.modify("pause", |state, _| {
+            state.status_mut().state = PlayerState::Paused;
+        });
+        Ok(())
+    }
+
+    fn next(&self) -> Result<()> {
+        self.modify("next", |state, tx| {
+            let index = state.current_queue_index().map_or(0, |index| index + 1);
+            state.select_queue_item(index, tx);
+        });
+        Ok(())
+    }
+
+    fn prev(&self) -> Result<()> {
+        self.modify("prev", |state, tx| {
+            let index = state.current_queue_index().unwrap_or(0).saturating_sub(1);
+            state.select_queue_item(index, tx);
+        });
+        Ok(())
+    }
+
+    fn set_volume_percent(&self, vol: u8) -> Result<()> {
+        self.modify("volume", |state, _| {
+            state.status_mut().volume_percent = Some(vol);
+        });
+        Ok(())
+    }
+
+    fn seek(&self, duration: Duration) -> Result<()> {
+        self.modify("seek", |state, tx| {
+            let progress = ProgressTick {
+                elapsed: Some(duration),
+                duration: state.progress.and_then(|progress| progress.duration),
+            };
+
+            state.progress = Some(progress);
+            send!(tx, PlayerUpdate::ProgressTick(progress));
+        });
+        Ok(())
+    }
+
+    fn set_shuffle(&self, shuffle: bool) -> Result<()> {
+        self.modify("shuffle", |state, _| {
+            state.status_mut().shuffle = Some(shuffle);
+        });
+        Ok(())
+    }
+
+    fn set_repeat(&self, mode: RepeatMode) -> Result<()> {
+        self.modify("repeat", |state, _| {
+            state.status_mut().repeat = Some(mode);
+        });
+        Ok(())
+    }
+
+    fn play_queue_item(&self, id: &str) -> Result<()> {
+        self.modify("play queue item", |state, tx| {
+            if let Some(index) = state.queue.iter().position(|item| item.id == id) {
+                state.select_queue_item(index, tx);
+            }
+        });
+        Ok(())
+    }
+
+    fn subscribe_change(&self) -> broadcast::Receiver<PlayerUpdate> {
+        let rx = self.tx.subscribe();
+
+        let state = lock!(self.state);
+        state.send_update(&self.tx);
+        send!(self.tx, PlayerUpdate::Queue(state.queue.clone()));
+
+        if let Some(progress) = state.progress {
+            send!(self.tx, PlayerUpdate::ProgressTick(progress));
+        }
+
+        rx
+    }
+}
+
+pub fn get_client(source: MockSource) -> Arc<Client> {
+    lock!(CLIENTS)
+        .entry(source.clone())
+        .or_insert_with(|| Arc::new(Client::new(source)))
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file() {
+        const INPUT: &str = r#"{
+            "repeat": true,
+            "events": [
+                {
+                    "type": "update",
+                    "track": { "title": "Song", "artist": "Artist" },
+                    "status": { "state": "playing", "volume_percent": 50 }
+                },
+                { "type": "progress", "elapsed": 12.5, "duration": 180 },
+                { "type": "queue", "items": [{ "id": "1", "title": "Song", "current": true }] },
+                { "type": "wait", "ms": 1000 },
+                { "type": "disconnect" }
+            ]
+        }"#;
+
+        let file: MockFile = serde_json::from_str(INPUT).expect("valid mock file");
+
+        assert!(file.repeat);
+        assert_eq!(file.events.len(), 5);
+
+        assert!(matches!(
+            &file.events[0],
+            MockEvent::Update { track: Some(track), status }
+                if track.title.as_deref() == Some("Song")
+                    && track.album.is_none()
+                    && status.state == PlayerState::Playing
+                    && status.volume_percent == Some(50)
+                    && status.shuffle.is_none()
+        ));
+        assert!(matches!(
+            &file.events[2],
+            MockEvent::Queue { items } if items.len() == 1 && items[0].current
+        ));
+        assert!(matches!(file.events[3], MockEvent::Wait { ms: 1000 }));
+        assert!(matches!(file.events[4], MockEvent::Disconnect));
+    }
+
+    #[test]
+    fn test_select_queue_item() {
+        let (tx, _rx) = broadcast::channel(8);
+
+        let mut state = State {
+            queue: (1..=3)
+                .map(|i| QueueItem {
+                    id: i.to_string(),
+                    title: Some(format!("Track {i}")),
+                    artist: None,
+                    current: i == 1,
+                })
+                .collect(),
+            ..State::default()
+        };
+
+        state.select_queue_item(2, &tx);
+
+        assert_eq!(state.current_queue_index(), Some(2));
+        assert_eq!(
+            state.track.and_then(|track| track.title).as_deref(),
+            Some("Track 3")
+        );
+        assert_eq!(state.status.map(|status| status.playlist_position), Some(2));
+
+        // out of range items are ignored
+        let mut state = State::default();
+        state.select_queue_item(0, &tx);
+        assert!(state.track.is_none());
+    }
+}
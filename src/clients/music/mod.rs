@@ -1,9 +1,13 @@
 use color_eyre::Result;
+use serde::Deserialize;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::broadcast;
 
+pub mod lyrics;
+#[cfg(feature = "music+mock")]
+pub mod mock;
 #[cfg(feature = "music+mpd")]
 pub mod mpd;
 #[cfg(feature = "music+mpris")]
@@ -19,11 +23,78 @@ pub enum PlayerUpdate {
     /// Triggered at regular intervals while a track is playing.
     /// Used to keep track of the progress through the current track.
     ProgressTick(ProgressTick),
+    /// Triggered when the queue changes, or the current track within it changes.
+    /// Empty if the player does not expose its queue.
+    Queue(Vec<QueueItem>),
+    /// Triggered when a player is added or removed,
+    /// or the active player changes.
+    /// Only sent by clients which support multiple players.
+    PlayerList {
+        players: Vec<PlayerInfo>,
+        /// Bus name of the active player.
+        active: Option<String>,
+    },
     /// Triggered when the client disconnects from the player.
     Disconnect,
 }
 
-#[derive(Clone, Debug)]
+/// A player available to a client which supports multiple players.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlayerInfo {
+    /// Unique name used to select the player.
+    pub bus_name: String,
+    /// Human-readable player name.
+    pub name: String,
+    pub state: PlayerState,
+}
+
+/// Rules for choosing which players a client follows.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PlayerFilter {
+    /// Players to prefer over all others, highest priority first.
+    pub pinned: Vec<String>,
+    /// Players to ignore entirely.
+    pub blacklist: Vec<String>,
+}
+
+impl PlayerFilter {
+    pub fn allows(&self, bus_name: &str) -> bool {
+        !self
+            .blacklist
+            .iter()
+            .any(|pattern| matches_player(pattern, bus_name))
+    }
+
+    /// Gets the priority of the player.
+    /// Lower values are preferred, with unpinned players having the lowest priority.
+    pub fn rank(&self, bus_name: &str) -> usize {
+        self.pinned
+            .iter()
+            .position(|pattern| matches_player(pattern, bus_name))
+            .unwrap_or(self.pinned.len())
+    }
+}
+
+/// Checks whether a player's bus name matches a pattern.
+///
+/// The pattern can be the full bus name, such as `org.mpris.MediaPlayer2.spotify`,
+/// or just the player name, such as `spotify`.
+/// Player names also match any instances of the player,
+/// so `firefox` matches `org.mpris.MediaPlayer2.firefox.instance_1_23`.
+fn matches_player(pattern: &str, bus_name: &str) -> bool {
+    const PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+    let name = bus_name.strip_prefix(PREFIX).unwrap_or(bus_name);
+
+    bus_name == pattern
+        || name == pattern
+        || name
+            .strip_prefix(pattern)
+            .map_or(false, |instance| instance.starts_with('.'))
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
 pub struct Track {
     pub title: Option<String>,
     pub album: Option<String>,
@@ -33,21 +104,64 @@ pub struct Track {
     pub genre: Option<String>,
     pub track: Option<u64>,
     pub cover_path: Option<String>,
+    /// Path to a synced `.lrc` lyrics file for the track.
+    /// The file is not guaranteed to exist.
+    pub lyrics_path: Option<PathBuf>,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PlayerState {
     Playing,
     Paused,
     Stopped,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepeatMode {
+    Off,
+    /// Repeat the current track.
+    Track,
+    /// Repeat the whole queue.
+    Playlist,
+}
+
+impl RepeatMode {
+    /// Gets the mode to switch to when toggling repeat.
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Playlist,
+            Self::Playlist => Self::Track,
+            Self::Track => Self::Off,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
 pub struct Status {
     pub state: PlayerState,
     pub volume_percent: Option<u8>,
+    #[serde(default)]
     pub playlist_position: u32,
+    #[serde(default)]
     pub playlist_length: u32,
+    /// `None` if the player does not support shuffling.
+    pub shuffle: Option<bool>,
+    /// `None` if the player does not support repeating.
+    pub repeat: Option<RepeatMode>,
+}
+
+/// A track in the player's queue.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct QueueItem {
+    /// Player-specific ID used to jump to the track.
+    pub id: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// Whether this is the current track.
+    #[serde(default)]
+    pub current: bool,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -56,7 +170,7 @@ pub struct ProgressTick {
     pub elapsed: Option<Duration>,
 }
 
-pub trait MusicClient {
+pub trait MusicClient: Send + Sync {
     fn play(&self) -> Result<()>;
     fn pause(&self) -> Result<()>;
     fn next(&self) -> Result<()>;
@@ -65,12 +179,29 @@ pub trait MusicClient {
     fn set_volume_percent(&self, vol: u8) -> Result<()>;
     fn seek(&self, duration: Duration) -> Result<()>;
 
+    fn set_shuffle(&self, shuffle: bool) -> Result<()>;
+    fn set_repeat(&self, mode: RepeatMode) -> Result<()>;
+
+    /// Jumps to the queue item with the given ID.
+    fn play_queue_item(&self, id: &str) -> Result<()>;
+
+    /// Switches the active player.
+    /// Clients which only support a single player ignore this.
+    fn set_active_player(&self, _bus_name: &str) -> Result<()> {
+        Ok(())
+    }
+
     fn subscribe_change(&self) -> broadcast::Receiver<PlayerUpdate>;
 }
 
 pub enum ClientType<'a> {
-    Mpd { host: &'a str, music_dir: PathBuf },
-    Mpris,
+    Mpd {
+        host: &'a str,
+        music_dir: PathBuf,
+    },
+    Mpris(PlayerFilter),
+    #[cfg(feature = "music+mock")]
+    Mock(mock::MockSource),
 }
 
 pub async fn get_client(client_type: ClientType<'_>) -> Box<Arc<dyn MusicClient>> {
@@ -80,6 +211,8 @@ pub async fn get_client(client_type: ClientType<'_>) -> Box<Arc<dyn MusicClient>
                 .await
                 .expect("Failed to connect to MPD client"),
         ),
-        ClientType::Mpris => Box::new(mpris::get_client()),
+        ClientType::Mpris(filter) => Box::new(mpris::get_client(filter)),
+        #[cfg(feature = "music+mock")]
+        ClientType::Mock(source) => Box::new(mock::get_client(source)),
     }
 }
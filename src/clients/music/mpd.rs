@@ -1,13 +1,14 @@
 use super::{
-    MusicClient, PlayerState, PlayerUpdate, ProgressTick, Status, Track, TICK_INTERVAL_MS,
+    MusicClient, PlayerState, PlayerUpdate, ProgressTick, QueueItem, RepeatMode, Status, Track,
+    TICK_INTERVAL_MS,
 };
 use crate::{await_sync, send};
-use color_eyre::Result;
+use color_eyre::{Report, Result};
 use lazy_static::lazy_static;
 use mpd_client::client::{Connection, ConnectionEvent, Subsystem};
-use mpd_client::commands::SeekMode;
+use mpd_client::commands::{SeekMode, SingleMode, SongId};
 use mpd_client::protocol::MpdProtocolError;
-use mpd_client::responses::{PlayState, Song};
+use mpd_client::responses::{PlayState, Song, SongInQueue};
 use mpd_client::tag::Tag;
 use mpd_client::{commands, Client};
 use std::collections::HashMap;
@@ -31,6 +32,9 @@ lazy_static! {
 pub struct MpdClient {
     client: Client,
     music_dir: PathBuf,
+    /// The queue as of the last playlist change,
+    /// so that it does not need fetching again when only the current song changes.
+    queue: Arc<Mutex<Vec<QueueItem>>>,
     tx: broadcast::Sender<PlayerUpdate>,
     _rx: broadcast::Receiver<PlayerUpdate>,
 }
@@ -61,22 +65,41 @@ impl MpdClient {
 
         let (tx, rx) = broadcast::channel(16);
 
+        let queue = Arc::new(Mutex::new(vec![]));
+        Self::refresh_queue(&client, &queue).await;
+
         {
             let music_dir = music_dir.clone();
             let tx = tx.clone();
             let client = client.clone();
+            let queue = queue.clone();
 
             spawn(async move {
                 while let Some(change) = state_changes.next().await {
                     debug!("Received state change: {:?}", change);
 
-                    if let ConnectionEvent::SubsystemChange(
-                        Subsystem::Player | Subsystem::Queue | Subsystem::Mixer,
-                    ) = change
-                    {
-                        Self::send_update(&client, &tx, &music_dir)
-                            .await
-                            .expect("Failed to send update");
+                    if let ConnectionEvent::SubsystemChange(subsystem) = change {
+                        if matches!(
+                            subsystem,
+                            Subsystem::Player
+                                | Subsystem::Queue
+                                | Subsystem::Mixer
+                                | Subsystem::Options
+                        ) {
+                            Self::send_update(&client, &tx, &music_dir)
+                                .await
+                                .expect("Failed to send update");
+                        }
+
+                        // `Queue` is the playlist changing,
+                        // whereas the current song changing is a player change
+                        if matches!(subsystem, Subsystem::Queue) {
+                            Self::refresh_queue(&client, &queue).await;
+                        }
+
+                        if matches!(subsystem, Subsystem::Player | Subsystem::Queue) {
+                            Self::send_queue_update(&client, &tx, &queue).await;
+                        }
                     }
                 }
 
@@ -99,6 +122,7 @@ impl MpdClient {
         Ok(Self {
             client,
             music_dir,
+            queue,
             tx,
             _rx: rx,
         })
@@ -123,6 +147,42 @@ impl MpdClient {
         Ok(())
     }
 
+    /// Fetches the queue.
+    async fn refresh_queue(client: &Client, queue: &Mutex<Vec<QueueItem>>) {
+        match client.command(commands::Queue).await {
+            Ok(songs) => {
+                *queue.lock().await = songs.iter().map(Self::convert_queue_item).collect();
+            }
+            Err(err) => error!("Failed to get queue: {err:?}"),
+        }
+    }
+
+    /// Sends the last fetched queue, marking the current song.
+    async fn send_queue_update(
+        client: &Client,
+        tx: &broadcast::Sender<PlayerUpdate>,
+        queue: &Mutex<Vec<QueueItem>>,
+    ) {
+        match client.command(commands::Status).await {
+            Ok(status) => {
+                let current_id = status.current_song.map(|(_, id)| id.0.to_string());
+
+                let queue = queue
+                    .lock()
+                    .await
+                    .iter()
+                    .map(|item| QueueItem {
+                        current: current_id.as_ref() == Some(&item.id),
+                        ..item.clone()
+                    })
+                    .collect();
+
+                send!(tx, PlayerUpdate::Queue(queue));
+            }
+            Err(err) => error!("Failed to get status: {err:?}"),
+        }
+    }
+
     async fn send_tick_update(client: &Client, tx: &broadcast::Sender<PlayerUpdate>) {
         let status = client.command(commands::Status).await;
 
@@ -148,6 +208,25 @@ impl MpdClient {
         Ok(())
     }
 
+    fn convert_queue_item(item: &SongInQueue) -> QueueItem {
+        let song = &item.song;
+
+        // fall back to the file name for untagged songs
+        let title = song.title().map(ToString::to_string).or_else(|| {
+            let stem = song.file_path().file_stem()?;
+            Some(stem.to_string_lossy().to_string())
+        });
+
+        let artists = song.artists();
+
+        QueueItem {
+            id: item.id.0.to_string(),
+            title,
+            artist: (!artists.is_empty()).then(|| artists.join(", ")),
+            current: false,
+        }
+    }
+
     fn convert_song(song: &Song, music_dir: &Path) -> Track {
         let (track, disc) = song.number();
 
@@ -162,6 +241,8 @@ impl MpdClient {
             .into_string()
             .ok();
 
+        let lyrics_path = Some(music_dir.join(song.file_path().with_extension("lrc")));
+
         Track {
             title: song.title().map(std::string::ToString::to_string),
             album: song.album().map(std::string::ToString::to_string),
@@ -171,6 +252,7 @@ impl MpdClient {
             disc: Some(disc),
             track: Some(track),
             cover_path,
+            lyrics_path,
         }
     }
 }
@@ -217,12 +299,39 @@ impl MusicClient for MpdClient {
         Ok(())
     }
 
+    fn set_shuffle(&self, shuffle: bool) -> Result<()> {
+        async_command!(self.client, commands::SetRandom(shuffle));
+        Ok(())
+    }
+
+    fn set_repeat(&self, mode: RepeatMode) -> Result<()> {
+        let (repeat, single) = match mode {
+            RepeatMode::Off => (false, SingleMode::Disabled),
+            RepeatMode::Track => (true, SingleMode::Enabled),
+            RepeatMode::Playlist => (true, SingleMode::Disabled),
+        };
+
+        async_command!(self.client, commands::SetRepeat(repeat));
+        async_command!(self.client, commands::SetSingle(single));
+        Ok(())
+    }
+
+    fn play_queue_item(&self, id: &str) -> Result<()> {
+        let id = id
+            .parse()
+            .map_err(|_| Report::msg(format!("Invalid MPD song id '{id}'")))?;
+
+        async_command!(self.client, commands::Play::song(SongId(id)));
+        Ok(())
+    }
+
     fn subscribe_change(&self) -> broadcast::Receiver<PlayerUpdate> {
         let rx = self.tx.subscribe();
         await_sync(async {
             Self::send_update(&self.client, &self.tx, &self.music_dir)
                 .await
                 .expect("Failed to send player update");
+            Self::send_queue_update(&self.client, &self.tx, &self.queue).await;
         });
         rx
     }
@@ -329,6 +438,12 @@ impl From<mpd_client::responses::Status> for Status {
             volume_percent: Some(status.volume),
             playlist_position: status.current_song.map_or(0, |(pos, _)| pos.0 as u32),
             playlist_length: status.playlist_length as u32,
+            shuffle: Some(status.random),
+            repeat: Some(match (status.repeat, status.single) {
+                (false, _) => RepeatMode::Off,
+                (true, SingleMode::Enabled) => RepeatMode::Track,
+                (true, _) => RepeatMode::Playlist,
+            }),
         }
     }
 }
@@ -1,83 +1,281 @@
-use super::{MusicClient, PlayerState, PlayerUpdate, Status, Track, TICK_INTERVAL_MS};
+use super::{
+    MusicClient, PlayerFilter, PlayerInfo, PlayerState, PlayerUpdate, QueueItem, RepeatMode,
+    Status, Track, TICK_INTERVAL_MS,
+};
 use crate::clients::music::ProgressTick;
 use crate::{arc_mut, lock, send};
-use color_eyre::Result;
+use color_eyre::{Report, Result};
+use dirs::data_dir;
 use lazy_static::lazy_static;
-use mpris::{DBusError, Event, Metadata, PlaybackStatus, Player, PlayerFinder};
-use std::collections::HashSet;
-use std::sync::{Arc, Mutex};
+use mpris::{
+    DBusError, Event, LoopStatus, Metadata, PlaybackStatus, Player, PlayerFinder, TrackID,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
 use std::thread::sleep;
 use std::time::Duration;
 use std::{cmp, string};
+use std::{env, fs};
 use tokio::sync::broadcast;
 use tokio::task::spawn_blocking;
 use tracing::{debug, error, trace};
 
+/// Status sent when there is no active player.
+const EMPTY_STATUS: Status = Status {
+    playlist_position: 0,
+    playlist_length: 0,
+    state: PlayerState::Stopped,
+    volume_percent: None,
+    shuffle: None,
+    repeat: None,
+};
+
 lazy_static! {
-    static ref CLIENT: Arc<Client> = Arc::new(Client::new());
+    static ref CLIENTS: Mutex<HashMap<PlayerFilter, Weak<Client>>> = Mutex::new(HashMap::new());
+    /// Held while reading or writing the saved players file,
+    /// as each client writes to it independently.
+    static ref SAVE_LOCK: Mutex<()> = Mutex::new(());
 }
 
 pub struct Client {
-    current_player: Arc<Mutex<Option<String>>>,
+    state: Arc<Mutex<State>>,
     tx: broadcast::Sender<PlayerUpdate>,
     _rx: broadcast::Receiver<PlayerUpdate>,
+    /// Cleared when the client is dropped,
+    /// stopping its background threads.
+    running: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    filter: PlayerFilter,
+    /// Known players, in the order they were found.
+    players: Vec<PlayerInfo>,
+    /// Bus name of the active player.
+    current: Option<String>,
+    /// Whether the active player was selected by the user,
+    /// in which case it stays active until it disappears.
+    selected: bool,
+    /// The active player from the previous run,
+    /// which becomes active again if it is running at startup.
+    restored: Option<SavedPlayer>,
+    /// Bus names of previously active players, most recent last.
+    history: Vec<String>,
+    /// The last fetched queue of each player,
+    /// or `None` if the player does not support track lists.
+    queues: HashMap<String, Option<Vec<QueueItem>>>,
+}
+
+impl State {
+    fn contains(&self, bus_name: &str) -> bool {
+        self.players
+            .iter()
+            .any(|player| player.bus_name == bus_name)
+    }
+
+    fn set_current(&mut self, bus_name: &str, selected: bool) {
+        debug!("Setting active player to '{bus_name}'");
+
+        self.current = Some(bus_name.to_string());
+        self.selected = selected;
+        self.history.retain(|name| name != bus_name);
+        self.history.push(bus_name.to_string());
+    }
+
+    /// Makes a newly found player active if it should be.
+    ///
+    /// Returns whether the active player changed.
+    fn add(&mut self, player: PlayerInfo) -> bool {
+        debug!("Adding MPRIS player '{}'", player.bus_name);
+
+        let bus_name = player.bus_name.clone();
+        let playing = player.state == PlayerState::Playing;
+
+        self.players.push(player);
+
+        let restored = self
+            .restored
+            .as_ref()
+            .filter(|saved| saved.bus_name == bus_name)
+            .map(|saved| saved.selected);
+
+        match restored {
+            Some(selected) if !self.selected => {
+                self.set_current(&bus_name, selected);
+                true
+            }
+            _ if self.can_take_over(&bus_name, playing) => {
+                self.set_current(&bus_name, false);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn set_player_state(&mut self, bus_name: &str, state: PlayerState) {
+        if let Some(player) = self
+            .players
+            .iter_mut()
+            .find(|player| player.bus_name == bus_name)
+        {
+            player.state = state;
+        }
+    }
+
+    /// Checks whether the player is allowed to take over from the current player.
+    ///
+    /// A player takes over if it is pinned more highly,
+    /// or if it is playing and pinned equally highly.
+    /// A player selected by the user is never taken over from.
+    fn can_take_over(&self, bus_name: &str, playing: bool) -> bool {
+        if self.selected {
+            return false;
+        }
+
+        self.current.as_ref().map_or(true, |current| {
+            let rank = self.filter.rank(bus_name);
+            let current_rank = self.filter.rank(current);
+
+            rank < current_rank || (playing && rank == current_rank)
+        })
+    }
+
+    /// Removes the player.
+    /// If it was active, the most preferred remaining player becomes active,
+    /// choosing the most recently active if several are preferred equally.
+    ///
+    /// Returns whether the active player changed.
+    fn remove(&mut self, bus_name: &str) -> bool {
+        debug!("Removing MPRIS player '{bus_name}'");
+
+        self.players.retain(|player| player.bus_name != bus_name);
+        self.history.retain(|name| name != bus_name);
+        self.queues.remove(bus_name);
+
+        if self.current.as_deref() != Some(bus_name) {
+            return false;
+        }
+
+        self.selected = false;
+
+        let recency = |bus_name: &str| self.history.iter().position(|name| name == bus_name);
+
+        self.current = self
+            .players
+            .iter()
+            .map(|player| player.bus_name.as_str())
+            .min_by_key(|bus_name| (self.filter.rank(bus_name), cmp::Reverse(recency(bus_name))))
+            .map(ToString::to_string);
+
+        debug!("Active player is now {:?}", self.current);
+        true
+    }
+
+    /// Gets the active player to persist, if there is one.
+    fn saved_player(&self) -> Option<SavedPlayer> {
+        self.current.as_ref().map(|bus_name| SavedPlayer {
+            bus_name: bus_name.clone(),
+            selected: self.selected,
+        })
+    }
+
+    fn queue(&self, bus_name: &str) -> Option<Vec<QueueItem>> {
+        self.queues.get(bus_name).cloned().flatten()
+    }
+
+    fn player_list(&self) -> PlayerUpdate {
+        PlayerUpdate::PlayerList {
+            players: self.players.clone(),
+            active: self.current.clone(),
+        }
+    }
 }
 
 impl Client {
-    fn new() -> Self {
+    fn new(filter: PlayerFilter) -> Self {
         let (tx, rx) = broadcast::channel(32);
 
-        let current_player = arc_mut!(None);
+        let restored = SavedPlayer::load(&filter).filter(|saved| filter.allows(&saved.bus_name));
+
+        let state = arc_mut!(State {
+            filter: filter.clone(),
+            restored,
+            ..State::default()
+        });
+
+        let running = Arc::new(AtomicBool::new(true));
 
         {
-            let players_list = arc_mut!(HashSet::new());
-            let current_player = current_player.clone();
+            let state = state.clone();
             let tx = tx.clone();
+            let running = running.clone();
 
             spawn_blocking(move || {
                 let player_finder = PlayerFinder::new().expect("Failed to connect to D-Bus");
 
                 // D-Bus gives no event for new players,
                 // so we have to keep polling the player list
-                loop {
+                while running.load(Ordering::Relaxed) {
                     let players = player_finder
                         .find_all()
                         .expect("Failed to connect to D-Bus");
 
-                    let mut players_list_val = lock!(players_list);
                     for player in players {
-                        let identity = player.identity();
+                        let bus_name = player.bus_name().to_string();
+
+                        {
+                            let state = lock!(state);
+                            if state.contains(&bus_name) || !state.filter.allows(&bus_name) {
+                                continue;
+                            }
+                        }
 
-                        if !players_list_val.contains(identity) {
-                            debug!("Adding MPRIS player '{identity}'");
-                            players_list_val.insert(identity.to_string());
+                        // the player may have closed since it was found
+                        let status = match player.get_playback_status() {
+                            Ok(status) => status,
+                            Err(err) => {
+                                error!("{err:?}");
+                                continue;
+                            }
+                        };
 
-                            let status = player
-                                .get_playback_status()
-                                .expect("Failed to connect to D-Bus");
+                        let queue = get_queue(&player);
 
-                            {
-                                let mut current_player = lock!(current_player);
+                        let (take_over, saved_player, player_list) = {
+                            let mut state = lock!(state);
 
-                                if status == PlaybackStatus::Playing || current_player.is_none() {
-                                    debug!("Setting active player to '{identity}'");
+                            state.queues.insert(bus_name.clone(), queue.clone());
+                            let take_over = state.add(PlayerInfo {
+                                bus_name: bus_name.clone(),
+                                name: player.identity().to_string(),
+                                state: PlayerState::from(status),
+                            });
 
-                                    current_player.replace(identity.to_string());
-                                    if let Err(err) = Self::send_update(&player, &tx) {
-                                        error!("{err:?}");
-                                    }
-                                }
-                            }
+                            (take_over, state.saved_player(), state.player_list())
+                        };
 
-                            Self::listen_player_events(
-                                identity.to_string(),
-                                players_list.clone(),
-                                current_player.clone(),
-                                tx.clone(),
-                            );
+                        if take_over {
+                            SavedPlayer::save(&filter, saved_player);
+                            Self::send_update(&player, queue.as_deref(), &tx)
+                                .unwrap_or_else(|err| error!("{err:?}"));
                         }
+
+                        send!(tx, player_list);
+
+                        Self::listen_player_events(
+                            bus_name,
+                            state.clone(),
+                            tx.clone(),
+                            running.clone(),
+                        );
                     }
 
+                    // the previous player is only restored if it is already running
+                    lock!(state).restored = None;
+
                     // wait 1 second before re-checking players
                     sleep(Duration::from_secs(1));
                 }
@@ -85,62 +283,136 @@ impl Client {
         }
 
         {
-            let current_player = current_player.clone();
+            let state = state.clone();
             let tx = tx.clone();
+            let running = running.clone();
 
             spawn_blocking(move || {
                 let player_finder = PlayerFinder::new().expect("to get new player finder");
 
-                loop {
-                    Self::send_tick_update(&player_finder, &current_player, &tx);
+                while running.load(Ordering::Relaxed) {
+                    Self::send_tick_update(&player_finder, &state, &tx);
                     sleep(Duration::from_millis(TICK_INTERVAL_MS));
                 }
             });
         }
 
         Self {
-            current_player,
+            state,
             tx,
             _rx: rx,
+            running,
         }
     }
 
     fn listen_player_events(
-        player_id: String,
-        players: Arc<Mutex<HashSet<String>>>,
-        current_player: Arc<Mutex<Option<String>>>,
+        bus_name: String,
+        state: Arc<Mutex<State>>,
         tx: broadcast::Sender<PlayerUpdate>,
+        running: Arc<AtomicBool>,
     ) {
         spawn_blocking(move || {
             let player_finder = PlayerFinder::new()?;
+            let filter = lock!(state).filter.clone();
 
-            if let Ok(player) = player_finder.find_by_name(&player_id) {
-                let identity = player.identity();
-
+            if let Some(player) = find_player(&player_finder, &bus_name) {
                 for event in player.events()? {
-                    trace!("Received player event from '{identity}': {event:?}");
+                    // events block until the player sends one,
+                    // so the thread can only stop after the next event
+                    if !running.load(Ordering::Relaxed) {
+                        break;
+                    }
+
+                    trace!("Received player event from '{bus_name}': {event:?}");
+
+                    // the state lock is only held while updating the state,
+                    // and not while talking to the player
                     match event {
                         Ok(Event::PlayerShutDown) => {
-                            lock!(current_player).take();
-                            lock!(players).remove(identity);
+                            let (current_changed, saved_player, player_list) = {
+                                let mut state = lock!(state);
+                                let current_changed = state.remove(&bus_name);
+
+                                (current_changed, state.saved_player(), state.player_list())
+                            };
+
+                            if current_changed {
+                                SavedPlayer::save(&filter, saved_player);
+                                Self::send_current_update(&player_finder, &state, &tx);
+                            }
+
+                            send!(tx, player_list);
                             break;
                         }
                         Ok(Event::Playing) => {
-                            lock!(current_player).replace(identity.to_string());
+                            let (is_current, take_over, saved_player, player_list, queue) = {
+                                let mut state = lock!(state);
+                                state.set_player_state(&bus_name, PlayerState::Playing);
+
+                                let is_current = state.current.as_ref() == Some(&bus_name);
+                                let take_over = !is_current && state.can_take_over(&bus_name, true);
+                                if take_over {
+                                    state.set_current(&bus_name, false);
+                                }
 
-                            if let Err(err) = Self::send_update(&player, &tx) {
-                                error!("{err:?}");
+                                (
+                                    is_current,
+                                    take_over,
+                                    state.saved_player(),
+                                    state.player_list(),
+                                    state.queue(&bus_name),
+                                )
+                            };
+
+                            if take_over {
+                                SavedPlayer::save(&filter, saved_player);
                             }
+
+                            if is_current || take_over {
+                                Self::send_update(&player, queue.as_deref(), &tx)
+                                    .unwrap_or_else(|err| error!("{err:?}"));
+                            }
+
+                            send!(tx, player_list);
                         }
-                        Ok(_) => {
-                            let current_player = lock!(current_player);
-                            let current_player = current_player.as_ref();
-                            if let Some(current_player) = current_player {
-                                if current_player == identity {
-                                    if let Err(err) = Self::send_update(&player, &tx) {
-                                        error!("{err:?}");
-                                    }
+                        Ok(event) => {
+                            let track_list_changed = matches!(
+                                event,
+                                Event::TrackAdded(_)
+                                    | Event::TrackRemoved(_)
+                                    | Event::TrackMetadataChanged { .. }
+                                    | Event::TrackListReplaced
+                            );
+
+                            let new_queue = track_list_changed.then(|| get_queue(&player));
+
+                            let player_state = match event {
+                                Event::Paused => Some(PlayerState::Paused),
+                                Event::Stopped => Some(PlayerState::Stopped),
+                                _ => None,
+                            };
+
+                            let (is_current, queue) = {
+                                let mut state = lock!(state);
+
+                                if let Some(queue) = new_queue {
+                                    state.queues.insert(bus_name.clone(), queue);
+                                }
+
+                                if let Some(player_state) = player_state {
+                                    state.set_player_state(&bus_name, player_state);
+                                    send!(tx, state.player_list());
                                 }
+
+                                (
+                                    state.current.as_ref() == Some(&bus_name),
+                                    state.queue(&bus_name),
+                                )
+                            };
+
+                            if is_current {
+                                Self::send_update(&player, queue.as_deref(), &tx)
+                                    .unwrap_or_else(|err| error!("{err:?}"));
                             }
                         }
                         Err(err) => error!("{err:?}"),
@@ -152,7 +424,39 @@ impl Client {
         });
     }
 
-    fn send_update(player: &Player, tx: &broadcast::Sender<PlayerUpdate>) -> Result<()> {
+    /// Sends an update for the active player,
+    /// or an empty update if there is no active player.
+    fn send_current_update(
+        player_finder: &PlayerFinder,
+        state: &Mutex<State>,
+        tx: &broadcast::Sender<PlayerUpdate>,
+    ) {
+        let (current, queue) = {
+            let state = lock!(state);
+            let current = state.current.clone();
+            let queue = current.as_ref().and_then(|bus_name| state.queue(bus_name));
+
+            (current, queue)
+        };
+
+        match current.and_then(|bus_name| find_player(player_finder, &bus_name)) {
+            Some(player) => {
+                Self::send_update(&player, queue.as_deref(), tx)
+                    .unwrap_or_else(|err| error!("{err:?}"));
+            }
+            None => {
+                send!(tx, PlayerUpdate::Update(Box::new(None), EMPTY_STATUS));
+            }
+        }
+    }
+
+    /// Sends the player's track and status,
+    /// along with its last fetched queue.
+    fn send_update(
+        player: &Player,
+        queue: Option<&[QueueItem]>,
+        tx: &broadcast::Sender<PlayerUpdate>,
+    ) -> Result<()> {
         debug!("Sending update using '{}'", player.identity());
 
         let metadata = player.get_metadata()?;
@@ -160,46 +464,60 @@ impl Client {
             .get_playback_status()
             .unwrap_or(PlaybackStatus::Stopped);
 
-        let track_list = player.get_track_list();
-
         let volume_percent = player.get_volume().map(|vol| (vol * 100.0) as u8).ok();
 
         let status = Status {
             // MRPIS doesn't seem to provide playlist info reliably,
             // so we can just assume next/prev will work by bodging the numbers
             playlist_position: 1,
-            playlist_length: track_list.map(|list| list.len() as u32).unwrap_or(u32::MAX),
+            playlist_length: queue.map_or(u32::MAX, |queue| queue.len() as u32),
             state: PlayerState::from(playback_status),
             volume_percent,
+            shuffle: player.checked_get_shuffle().ok().flatten(),
+            repeat: player
+                .checked_get_loop_status()
+                .ok()
+                .flatten()
+                .map(RepeatMode::from),
         };
 
+        let current_id = metadata.track_id().map(|id| id.to_string());
         let track = Track::from(metadata);
 
         let player_update = PlayerUpdate::Update(Box::new(Some(track)), status);
         send!(tx, player_update);
 
+        let queue = queue
+            .unwrap_or_default()
+            .iter()
+            .map(|item| QueueItem {
+                current: current_id.as_ref() == Some(&item.id),
+                ..item.clone()
+            })
+            .collect();
+
+        send!(tx, PlayerUpdate::Queue(queue));
+
         Ok(())
     }
 
     fn get_player(&self) -> Option<Player> {
-        let player_name = lock!(self.current_player);
-        let player_name = player_name.as_ref();
+        let current = lock!(self.state).current.clone();
 
-        player_name.and_then(|player_name| {
+        current.and_then(|bus_name| {
             let player_finder = PlayerFinder::new().expect("Failed to connect to D-Bus");
-            player_finder.find_by_name(player_name).ok()
+            find_player(&player_finder, &bus_name)
         })
     }
 
     fn send_tick_update(
         player_finder: &PlayerFinder,
-        current_player: &Mutex<Option<String>>,
+        state: &Mutex<State>,
         tx: &broadcast::Sender<PlayerUpdate>,
     ) {
-        if let Some(player) = lock!(current_player)
-            .as_ref()
-            .and_then(|name| player_finder.find_by_name(name).ok())
-        {
+        let current = lock!(state).current.clone();
+
+        if let Some(player) = current.and_then(|bus_name| find_player(player_finder, &bus_name)) {
             if let Ok(metadata) = player.get_metadata() {
                 let update = PlayerUpdate::ProgressTick(ProgressTick {
                     elapsed: player.get_position().ok(),
@@ -212,6 +530,133 @@ impl Client {
     }
 }
 
+/// Gets the player's track list,
+/// or `None` if the player does not support track lists.
+///
+/// No item is marked as current.
+fn get_queue(player: &Player) -> Option<Vec<QueueItem>> {
+    if !player.supports_track_lists() {
+        return None;
+    }
+
+    let ids = match player.get_track_list() {
+        Ok(track_list) => track_list.ids().to_vec(),
+        Err(err) => {
+            error!("Failed to get track list: {err:?}");
+            return None;
+        }
+    };
+
+    match player.get_tracks_metadata(&ids) {
+        Ok(metadata) => Some(
+            metadata
+                .into_iter()
+                .filter_map(|metadata| {
+                    Some(QueueItem {
+                        id: metadata.track_id()?.to_string(),
+                        title: metadata.title().map(ToString::to_string),
+                        artist: metadata.artists().map(|artists| artists.join(", ")),
+                        current: false,
+                    })
+                })
+                .collect(),
+        ),
+        Err(err) => {
+            error!("Failed to get track list metadata: {err:?}");
+            None
+        }
+    }
+}
+
+/// The active player, persisted between runs.
+///
+/// A player is saved for each set of filters,
+/// as each has its own active player.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SavedPlayer {
+    bus_name: String,
+    /// Whether the player was selected by the user.
+    selected: bool,
+}
+
+impl SavedPlayer {
+    /// Gets the path to the state file,
+    /// alongside the log file in the user's data directory.
+    fn path() -> Result<PathBuf> {
+        Ok(data_dir()
+            .unwrap_or(env::current_dir()?)
+            .join("ironbar")
+            .join("mpris.json"))
+    }
+
+    /// Gets the key the player for `filter` is saved under.
+    fn key(filter: &PlayerFilter) -> String {
+        format!(
+            "pinned={};blacklist={}",
+            filter.pinned.join(","),
+            filter.blacklist.join(",")
+        )
+    }
+
+    /// Reads the saved players for every set of filters.
+    fn read_all() -> Result<HashMap<String, Self>> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let contents = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Loads the player saved for `filter` by the previous run, if there is one.
+    fn load(filter: &PlayerFilter) -> Option<Self> {
+        let _guard = lock!(SAVE_LOCK);
+
+        Self::read_all()
+            .map_err(|err| error!("{:?}", err.wrap_err("Failed to load saved MPRIS player")))
+            .ok()
+            .and_then(|mut players| players.remove(&Self::key(filter)))
+    }
+
+    /// Saves the active player for `filter`.
+    /// The last saved player is kept when there is no active player.
+    fn save(filter: &PlayerFilter, player: Option<Self>) {
+        let Some(player) = player else {
+            return;
+        };
+
+        let _guard = lock!(SAVE_LOCK);
+
+        let save = || -> Result<()> {
+            // players saved in an older format are discarded
+            let mut players = Self::read_all().unwrap_or_default();
+            players.insert(Self::key(filter), player);
+
+            let path = Self::path()?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            fs::write(path, serde_json::to_string_pretty(&players)?)?;
+            Ok(())
+        };
+
+        if let Err(err) = save() {
+            error!("{:?}", err.wrap_err("Failed to save active MPRIS player"));
+        }
+    }
+}
+
+/// Finds a player by its bus name.
+fn find_player(player_finder: &PlayerFinder, bus_name: &str) -> Option<Player> {
+    player_finder
+        .find_all()
+        .ok()?
+        .into_iter()
+        .find(|player| player.bus_name() == bus_name)
+}
+
 macro_rules! command {
     ($self:ident, $func:ident) => {
         if let Some(player) = Self::get_player($self) {
@@ -268,30 +713,98 @@ impl MusicClient for Client {
         Ok(())
     }
 
+    fn set_shuffle(&self, shuffle: bool) -> Result<()> {
+        if let Some(player) = Self::get_player(self) {
+            player.set_shuffle(shuffle)?;
+        } else {
+            error!("Could not find player");
+        }
+        Ok(())
+    }
+
+    fn set_repeat(&self, mode: RepeatMode) -> Result<()> {
+        if let Some(player) = Self::get_player(self) {
+            player.set_loop_status(LoopStatus::from(mode))?;
+        } else {
+            error!("Could not find player");
+        }
+        Ok(())
+    }
+
+    fn play_queue_item(&self, id: &str) -> Result<()> {
+        if let Some(player) = Self::get_player(self) {
+            let id = TrackID::new(id).map_err(Report::msg)?;
+            player.go_to(&id)?;
+        } else {
+            error!("Could not find player");
+        }
+        Ok(())
+    }
+
+    fn set_active_player(&self, bus_name: &str) -> Result<()> {
+        let player_finder = PlayerFinder::new()?;
+
+        let (filter, saved_player, player_list) = {
+            let mut state = lock!(self.state);
+            if !state.contains(bus_name) {
+                error!("Could not find player '{bus_name}'");
+                return Ok(());
+            }
+
+            state.set_current(bus_name, true);
+            (
+                state.filter.clone(),
+                state.saved_player(),
+                state.player_list(),
+            )
+        };
+
+        SavedPlayer::save(&filter, saved_player);
+
+        Self::send_current_update(&player_finder, &self.state, &self.tx);
+        send!(self.tx, player_list);
+
+        Ok(())
+    }
+
     fn subscribe_change(&self) -> broadcast::Receiver<PlayerUpdate> {
         debug!("Creating new subscription");
         let rx = self.tx.subscribe();
 
-        if let Some(player) = self.get_player() {
-            if let Err(err) = Self::send_update(&player, &self.tx) {
-                error!("{err:?}");
-            }
-        } else {
-            let status = Status {
-                playlist_position: 0,
-                playlist_length: 0,
-                state: PlayerState::Stopped,
-                volume_percent: None,
-            };
-            send!(self.tx, PlayerUpdate::Update(Box::new(None), status));
+        match PlayerFinder::new() {
+            Ok(player_finder) => Self::send_current_update(&player_finder, &self.state, &self.tx),
+            Err(err) => error!("{err:?}"),
         }
 
+        let player_list = lock!(self.state).player_list();
+        send!(self.tx, player_list);
+
         rx
     }
 }
 
-pub fn get_client() -> Arc<Client> {
-    CLIENT.clone()
+impl Drop for Client {
+    fn drop(&mut self) {
+        debug!("Stopping MPRIS client");
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Gets the client for `filter`, creating it if there is not one running.
+///
+/// Callers must keep the client for as long as they are subscribed,
+/// as it stops once every reference to it is dropped.
+pub fn get_client(filter: PlayerFilter) -> Arc<Client> {
+    let mut clients = lock!(CLIENTS);
+    clients.retain(|_, client| client.strong_count() > 0);
+
+    if let Some(client) = clients.get(&filter).and_then(Weak::upgrade) {
+        return client;
+    }
+
+    let client = Arc::new(Client::new(filter.clone()));
+    clients.insert(filter, Arc::downgrade(&client));
+    client
 }
 
 impl From<Metadata> for Track {
@@ -323,6 +836,7 @@ impl From<Metadata> for Track {
                 .and_then(|arr| arr.first().map(|val| (*val).to_string())),
             track: value.track_number().map(|track| track as u64),
             cover_path: value.art_url().map(string::ToString::to_string),
+            lyrics_path: None,
         }
     }
 }
@@ -337,6 +851,26 @@ impl From<PlaybackStatus> for PlayerState {
     }
 }
 
+impl From<LoopStatus> for RepeatMode {
+    fn from(value: LoopStatus) -> Self {
+        match value {
+            LoopStatus::None => Self::Off,
+            LoopStatus::Track => Self::Track,
+            LoopStatus::Playlist => Self::Playlist,
+        }
+    }
+}
+
+impl From<RepeatMode> for LoopStatus {
+    fn from(value: RepeatMode) -> Self {
+        match value {
+            RepeatMode::Off => Self::None,
+            RepeatMode::Track => Self::Track,
+            RepeatMode::Playlist => Self::Playlist,
+        }
+    }
+}
+
 fn replace_empty_none(string: String) -> Option<String> {
     if string.is_empty() {
         None
@@ -344,3 +878,86 @@ fn replace_empty_none(string: String) -> Option<String> {
         Some(string)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(bus_name: &str, state: PlayerState) -> PlayerInfo {
+        PlayerInfo {
+            bus_name: bus_name.to_string(),
+            name: bus_name.to_string(),
+            state,
+        }
+    }
+
+    fn state(pinned: &[&str]) -> State {
+        State {
+            filter: PlayerFilter {
+                pinned: pinned.iter().map(ToString::to_string).collect(),
+                blacklist: vec![],
+            },
+            ..State::default()
+        }
+    }
+
+    #[test]
+    fn test_playing_player_takes_over() {
+        let mut state = state(&[]);
+
+        assert!(state.add(player("spotify", PlayerState::Paused)));
+        assert!(!state.add(player("firefox", PlayerState::Paused)));
+        assert!(state.add(player("vlc", PlayerState::Playing)));
+
+        assert_eq!(state.current.as_deref(), Some("vlc"));
+    }
+
+    #[test]
+    fn test_pinned_player_is_not_taken_over() {
+        let mut state = state(&["spotify"]);
+
+        assert!(state.add(player("firefox", PlayerState::Playing)));
+        assert!(state.add(player("spotify", PlayerState::Paused)));
+
+        assert!(!state.can_take_over("firefox", true));
+        assert_eq!(state.current.as_deref(), Some("spotify"));
+    }
+
+    #[test]
+    fn test_selected_player_is_kept_until_removed() {
+        let mut state = state(&["firefox"]);
+
+        state.add(player("spotify", PlayerState::Paused));
+        state.add(player("vlc", PlayerState::Paused));
+        state.set_current("spotify", true);
+
+        assert!(!state.can_take_over("vlc", true));
+        assert!(!state.add(player("firefox", PlayerState::Playing)));
+        assert_eq!(state.current.as_deref(), Some("spotify"));
+
+        assert!(state.remove("spotify"));
+        assert!(!state.selected);
+        assert_eq!(state.current.as_deref(), Some("firefox"));
+    }
+
+    #[test]
+    fn test_restored_player() {
+        let mut state = state(&[]);
+        state.restored = Some(SavedPlayer {
+            bus_name: String::from("spotify"),
+            selected: true,
+        });
+
+        state.add(player("firefox", PlayerState::Playing));
+        assert!(state.add(player("spotify", PlayerState::Paused)));
+
+        assert_eq!(
+            state.saved_player(),
+            Some(SavedPlayer {
+                bus_name: String::from("spotify"),
+                selected: true
+            })
+        );
+        assert!(!state.can_take_over("firefox", true));
+    }
+}
@@ -0,0 +1,129 @@
+//! D-Bus proxies for the subset of the NetworkManager API used by Ironbar.
+//!
+//! See <https://networkmanager.dev/docs/api/latest/spec.html>.
+
+use std::collections::HashMap;
+use zbus::dbus_proxy;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager"
+)]
+trait NetworkManager {
+    fn activate_connection(
+        &self,
+        connection: &ObjectPath<'_>,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    fn add_and_activate_connection(
+        &self,
+        connection: HashMap<&str, HashMap<&str, Value<'_>>>,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> zbus::Result<(OwnedObjectPath, OwnedObjectPath)>;
+
+    fn deactivate_connection(&self, active_connection: &ObjectPath<'_>) -> zbus::Result<()>;
+
+    fn get_devices(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+
+    #[dbus_proxy(property)]
+    fn primary_connection(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[dbus_proxy(property)]
+    fn wireless_enabled(&self) -> zbus::Result<bool>;
+
+    #[dbus_proxy(property)]
+    fn set_wireless_enabled(&self, value: bool) -> zbus::Result<()>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager.Connection.Active",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait ActiveConnection {
+    #[dbus_proxy(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(property, name = "Type")]
+    fn type_(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(property)]
+    fn ip4_config(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[dbus_proxy(property)]
+    fn specific_object(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager.Device",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait Device {
+    #[dbus_proxy(property)]
+    fn device_type(&self) -> zbus::Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn active_connection(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager.Device.Wireless",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait WirelessDevice {
+    fn request_scan(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+
+    #[dbus_proxy(property)]
+    fn access_points(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager.AccessPoint",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait AccessPoint {
+    #[dbus_proxy(property)]
+    fn ssid(&self) -> zbus::Result<Vec<u8>>;
+
+    #[dbus_proxy(property)]
+    fn strength(&self) -> zbus::Result<u8>;
+
+    #[dbus_proxy(property)]
+    fn flags(&self) -> zbus::Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn wpa_flags(&self) -> zbus::Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn rsn_flags(&self) -> zbus::Result<u32>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager.IP4Config",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait Ip4Config {
+    #[dbus_proxy(property)]
+    fn address_data(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager.Settings",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager/Settings"
+)]
+trait Settings {
+    fn list_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager.Settings.Connection",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait SettingsConnection {
+    fn get_settings(&self) -> zbus::Result<HashMap<String, HashMap<String, OwnedValue>>>;
+}
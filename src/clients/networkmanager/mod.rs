@@ -0,0 +1,620 @@
+mod dbus;
+
+use self::dbus::{
+    AccessPointProxy, ActiveConnectionProxy, DeviceProxy, Ip4ConfigProxy, NetworkManagerProxy,
+    SettingsConnectionProxy, SettingsProxy, WirelessDeviceProxy,
+};
+use crate::clients::signals::next_burst;
+use crate::{arc_mut, lock, send};
+use async_once::AsyncOnce;
+use color_eyre::{Report, Result};
+use lazy_static::lazy_static;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::spawn;
+use tokio::sync::broadcast;
+use tracing::{debug, error, trace};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, Value};
+use zbus::{CacheProperties, MatchRule, MessageStream, MessageType, ProxyBuilder, ProxyDefault};
+
+const SERVICE_NAME: &str = "org.freedesktop.NetworkManager";
+const DEVICE_TYPE_WIFI: u32 = 2;
+const AP_FLAGS_PRIVACY: u32 = 0x1;
+
+lazy_static! {
+    static ref CLIENT: AsyncOnce<Client> = AsyncOnce::new(async {
+        Client::new()
+            .await
+            .expect("Failed to connect to NetworkManager")
+    });
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConnectionType {
+    #[default]
+    None,
+    Wired,
+    Wifi,
+    Cellular,
+    Vpn,
+    Other,
+}
+
+impl From<&str> for ConnectionType {
+    fn from(value: &str) -> Self {
+        match value {
+            "802-3-ethernet" => Self::Wired,
+            "802-11-wireless" => Self::Wifi,
+            "gsm" | "cdma" => Self::Cellular,
+            "vpn" | "wireguard" => Self::Vpn,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// A visible Wi-Fi network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessPoint {
+    pub ssid: String,
+    pub strength: u8,
+    /// Whether the network requires authentication.
+    pub secured: bool,
+    /// Whether this is the currently connected network.
+    pub active: bool,
+    /// Whether there is a saved connection for this network.
+    pub known: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NetworkState {
+    pub connection_type: ConnectionType,
+    /// The name of the primary connection.
+    pub name: Option<String>,
+    pub ssid: Option<String>,
+    pub strength: Option<u8>,
+    pub ip4_address: Option<String>,
+    pub wifi_enabled: bool,
+    /// Visible Wi-Fi networks, strongest first.
+    pub access_points: Vec<AccessPoint>,
+}
+
+pub struct Client {
+    connection: zbus::Connection,
+    state: Arc<Mutex<NetworkState>>,
+    tx: broadcast::Sender<NetworkState>,
+    _rx: broadcast::Receiver<NetworkState>,
+}
+
+impl Client {
+    async fn new() -> Result<Self> {
+        let connection = zbus::Connection::system().await?;
+
+        let (tx, rx) = broadcast::channel(8);
+
+        let mut cache = StateCache::load(&connection).await?;
+        let state = arc_mut!(cache.state());
+
+        {
+            let connection = connection.clone();
+            let state = state.clone();
+            let tx = tx.clone();
+
+            spawn(async move {
+                let rule = MatchRule::builder()
+                    .msg_type(MessageType::Signal)
+                    .sender(SERVICE_NAME)?
+                    .interface("org.freedesktop.DBus.Properties")?
+                    .member("PropertiesChanged")?
+                    .path_namespace("/org/freedesktop/NetworkManager")?
+                    .build();
+
+                let mut stream = MessageStream::for_match_rule(rule, &connection, None).await?;
+
+                let change = |message: zbus::Result<Arc<zbus::Message>>| {
+                    message
+                        .ok()
+                        .and_then(|message| message.path().map(OwnedObjectPath::from))
+                        .and_then(Change::from_path)
+                };
+
+                while let Some(messages) = next_burst(&mut stream).await {
+                    let changes = messages
+                        .into_iter()
+                        .filter_map(change)
+                        .collect::<HashSet<_>>();
+
+                    if changes.is_empty() {
+                        continue;
+                    }
+
+                    if let Err(err) = cache.apply(&connection, changes).await {
+                        error!("{:?}", err.wrap_err("Failed to refresh network state"));
+
+                        // a partial refresh may leave the cache inconsistent
+                        match StateCache::load(&connection).await {
+                            Ok(new_cache) => cache = new_cache,
+                            Err(err) => {
+                                error!("{:?}", err.wrap_err("Failed to reload network state"));
+                                continue;
+                            }
+                        }
+                    }
+
+                    let new_state = cache.state();
+
+                    let mut state = lock!(state);
+                    if *state != new_state {
+                        trace!("Network state changed: {new_state:?}");
+                        *state = new_state.clone();
+                        send!(tx, new_state);
+                    }
+                }
+
+                Ok::<(), zbus::Error>(())
+            });
+        }
+
+        Ok(Self {
+            connection,
+            state,
+            tx,
+            _rx: rx,
+        })
+    }
+
+    /// Subscribes to network state changes.
+    ///
+    /// The current state is returned alongside the receiver
+    /// to bring the subscriber up to date.
+    pub fn subscribe(&self) -> (NetworkState, broadcast::Receiver<NetworkState>) {
+        let state = lock!(self.state);
+
+        // subscribe while locked so no changes are missed or duplicated
+        let rx = self.tx.subscribe();
+
+        (state.clone(), rx)
+    }
+
+    /// Enables or disables the Wi-Fi radio.
+    pub async fn set_wifi_enabled(&self, enabled: bool) -> Result<()> {
+        debug!("Setting Wi-Fi enabled: {enabled}");
+
+        let nm = NetworkManagerProxy::new(&self.connection).await?;
+        nm.set_wireless_enabled(enabled).await?;
+        Ok(())
+    }
+
+    /// Asks each Wi-Fi device to scan for networks.
+    pub async fn scan(&self) -> Result<()> {
+        for device in get_wifi_devices(&self.connection).await? {
+            let wireless: WirelessDeviceProxy = build_proxy(&self.connection, device).await?;
+
+            // scans are rate-limited, so an error here is expected
+            if let Err(err) = wireless.request_scan(HashMap::new()).await {
+                trace!("Failed to request scan: {err:?}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Connects to the Wi-Fi network with the given SSID,
+    /// using the saved connection for the network if there is one.
+    pub async fn connect_wifi(&self, ssid: &str) -> Result<()> {
+        debug!("Connecting to Wi-Fi network '{ssid}'");
+
+        let nm = NetworkManagerProxy::new(&self.connection).await?;
+
+        for device in get_wifi_devices(&self.connection).await? {
+            let wireless: WirelessDeviceProxy =
+                build_proxy(&self.connection, device.clone()).await?;
+
+            for ap_path in wireless.access_points().await? {
+                let ap: AccessPointProxy = build_proxy(&self.connection, ap_path.clone()).await?;
+                if String::from_utf8_lossy(&ap.ssid().await?) != ssid {
+                    continue;
+                }
+
+                let saved = get_saved_connections(&self.connection).await?;
+                if let Some(connection) = saved.get(ssid) {
+                    nm.activate_connection(connection, &device, &ap_path)
+                        .await?;
+                } else {
+                    // NetworkManager will prompt any registered secret agent for credentials
+                    nm.add_and_activate_connection(HashMap::new(), &device, &ap_path)
+                        .await?;
+                }
+
+                return Ok(());
+            }
+        }
+
+        Err(Report::msg(format!("Wi-Fi network '{ssid}' not found")))
+    }
+
+    /// Disconnects each Wi-Fi device from its current network.
+    pub async fn disconnect_wifi(&self) -> Result<()> {
+        debug!("Disconnecting from Wi-Fi");
+
+        let nm = NetworkManagerProxy::new(&self.connection).await?;
+
+        for device in get_wifi_devices(&self.connection).await? {
+            let device: DeviceProxy = build_proxy(&self.connection, device).await?;
+            let active_connection = device.active_connection().await?;
+
+            if !is_null_path(&active_connection) {
+                nm.deactivate_connection(&active_connection).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn get_client() -> &'static Client {
+    CLIENT.get().await
+}
+
+/// Builds a proxy for the object at the given path,
+/// without caching properties as the objects are short-lived.
+async fn build_proxy<'a, T>(connection: &zbus::Connection, path: OwnedObjectPath) -> Result<T>
+where
+    T: From<zbus::Proxy<'a>> + ProxyDefault,
+{
+    let proxy = ProxyBuilder::new(connection)
+        .path(path)?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+
+    Ok(proxy)
+}
+
+/// NetworkManager uses `/` in place of a missing object path.
+fn is_null_path(path: &ObjectPath) -> bool {
+    path.as_str() == "/"
+}
+
+/// The parts of the network state which can be refreshed independently.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Change {
+    /// The manager itself, which holds the radio state, primary connection and device list.
+    Manager,
+    /// The primary connection or its IP configuration.
+    Connection,
+    /// The saved connections.
+    Settings,
+    /// The access point list of a device.
+    Device(OwnedObjectPath),
+    AccessPoint(OwnedObjectPath),
+}
+
+impl Change {
+    /// Gets the change for a `PropertiesChanged` signal from the object at `path`.
+    fn from_path(path: OwnedObjectPath) -> Option<Self> {
+        let object = path
+            .as_str()
+            .strip_prefix("/org/freedesktop/NetworkManager")?;
+
+        match object.split('/').nth(1) {
+            None => Some(Self::Manager),
+            Some("ActiveConnection" | "IP4Config") => Some(Self::Connection),
+            Some("Settings") => Some(Self::Settings),
+            Some("Devices") => Some(Self::Device(path)),
+            Some("AccessPoint") => Some(Self::AccessPoint(path)),
+            Some(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PrimaryConnection {
+    connection_type: ConnectionType,
+    name: String,
+    ip4_address: Option<String>,
+    /// The connected access point, for Wi-Fi connections.
+    access_point: Option<OwnedObjectPath>,
+}
+
+#[derive(Debug)]
+struct AccessPointInfo {
+    ssid: String,
+    strength: u8,
+    secured: bool,
+}
+
+/// The NetworkManager objects making up the network state,
+/// so that only objects which change need to be read again.
+#[derive(Debug, Default)]
+struct StateCache {
+    wifi_enabled: bool,
+    primary: Option<PrimaryConnection>,
+    known_ssids: HashSet<String>,
+    /// Access point paths for each Wi-Fi device.
+    devices: HashMap<OwnedObjectPath, Vec<OwnedObjectPath>>,
+    access_points: HashMap<OwnedObjectPath, AccessPointInfo>,
+}
+
+impl StateCache {
+    /// Reads every object.
+    async fn load(connection: &zbus::Connection) -> Result<Self> {
+        let mut cache = Self::default();
+        cache.refresh_settings(connection).await?;
+        cache.refresh_manager(connection).await?;
+        Ok(cache)
+    }
+
+    /// Reads the objects affected by the given changes.
+    async fn apply(
+        &mut self,
+        connection: &zbus::Connection,
+        changes: HashSet<Change>,
+    ) -> Result<()> {
+        if changes.contains(&Change::Settings) {
+            self.refresh_settings(connection).await?;
+        }
+
+        // refreshing the manager also refreshes the connection and any new devices
+        if changes.contains(&Change::Manager) {
+            self.refresh_manager(connection).await?;
+        } else if changes.contains(&Change::Connection) {
+            self.refresh_connection(connection).await?;
+        }
+
+        for change in &changes {
+            if let Change::Device(path) = change {
+                // property changes for wired devices are not relevant
+                if self.devices.contains_key(path) {
+                    self.refresh_device(connection, path.clone()).await?;
+                }
+            }
+        }
+
+        for change in changes {
+            if let Change::AccessPoint(path) = change {
+                if self.access_points.contains_key(&path) {
+                    self.refresh_access_point(connection, path).await;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn refresh_manager(&mut self, connection: &zbus::Connection) -> Result<()> {
+        let nm = NetworkManagerProxy::builder(connection)
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+
+        self.wifi_enabled = nm.wireless_enabled().await?;
+        self.refresh_connection(connection).await?;
+
+        let devices = get_wifi_devices(connection).await?;
+        self.devices.retain(|path, _| devices.contains(path));
+
+        for device in devices {
+            if !self.devices.contains_key(&device) {
+                self.refresh_device(connection, device).await?;
+            }
+        }
+
+        self.remove_stale_access_points();
+        Ok(())
+    }
+
+    async fn refresh_connection(&mut self, connection: &zbus::Connection) -> Result<()> {
+        let nm = NetworkManagerProxy::builder(connection)
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+
+        let primary_connection = nm.primary_connection().await?;
+        if is_null_path(&primary_connection) {
+            self.primary = None;
+            return Ok(());
+        }
+
+        let active: ActiveConnectionProxy = build_proxy(connection, primary_connection).await?;
+
+        let mut primary = PrimaryConnection {
+            connection_type: ConnectionType::from(active.type_().await?.as_str()),
+            name: active.id().await?,
+            ..PrimaryConnection::default()
+        };
+
+        let ip4_config = active.ip4_config().await?;
+        if !is_null_path(&ip4_config) {
+            let ip4_config: Ip4ConfigProxy = build_proxy(connection, ip4_config).await?;
+            primary.ip4_address = ip4_config
+                .address_data()
+                .await?
+                .first()
+                .and_then(|data| data.get("address"))
+                .and_then(|address| address.downcast_ref::<str>())
+                .map(ToString::to_string);
+        }
+
+        if primary.connection_type == ConnectionType::Wifi {
+            let specific_object = active.specific_object().await?;
+            if !is_null_path(&specific_object) {
+                primary.access_point = Some(specific_object);
+            }
+        }
+
+        self.primary = Some(primary);
+        Ok(())
+    }
+
+    async fn refresh_settings(&mut self, connection: &zbus::Connection) -> Result<()> {
+        self.known_ssids = get_saved_connections(connection)
+            .await?
+            .into_keys()
+            .collect();
+
+        Ok(())
+    }
+
+    /// Reads the access point list of a device,
+    /// reading only access points which are not already known.
+    async fn refresh_device(
+        &mut self,
+        connection: &zbus::Connection,
+        device: OwnedObjectPath,
+    ) -> Result<()> {
+        let wireless: WirelessDeviceProxy = build_proxy(connection, device.clone()).await?;
+        let access_points = wireless.access_points().await?;
+
+        for path in &access_points {
+            if !self.access_points.contains_key(path) {
+                self.refresh_access_point(connection, path.clone()).await;
+            }
+        }
+
+        self.devices.insert(device, access_points);
+        self.remove_stale_access_points();
+
+        Ok(())
+    }
+
+    /// Reads an access point.
+    /// Access points can disappear at any time,
+    /// so one which cannot be read is removed rather than treated as an error.
+    async fn refresh_access_point(&mut self, connection: &zbus::Connection, path: OwnedObjectPath) {
+        match read_access_point(connection, path.clone()).await {
+            Ok(info) => {
+                self.access_points.insert(path, info);
+            }
+            Err(err) => {
+                trace!("Failed to read access point '{}': {err:?}", path.as_str());
+                self.access_points.remove(&path);
+            }
+        }
+    }
+
+    fn remove_stale_access_points(&mut self) {
+        let devices = &self.devices;
+        self.access_points
+            .retain(|path, _| devices.values().any(|paths| paths.contains(path)));
+    }
+
+    /// Builds the network state from the cached objects.
+    fn state(&self) -> NetworkState {
+        let mut state = NetworkState {
+            wifi_enabled: self.wifi_enabled,
+            ..NetworkState::default()
+        };
+
+        let active_ap = self.primary.as_ref().and_then(|primary| {
+            state.connection_type = primary.connection_type;
+            state.name = Some(primary.name.clone());
+            state.ip4_address = primary.ip4_address.clone();
+
+            primary.access_point.as_ref()
+        });
+
+        let mut access_points = HashMap::<&str, AccessPoint>::new();
+
+        for (path, info) in self
+            .devices
+            .values()
+            .flatten()
+            .filter_map(|path| self.access_points.get(path).map(|info| (path, info)))
+        {
+            // hidden networks
+            if info.ssid.is_empty() {
+                continue;
+            }
+
+            let active = active_ap == Some(path);
+            if active {
+                state.ssid = Some(info.ssid.clone());
+                state.strength = Some(info.strength);
+            }
+
+            // the same network is often visible through multiple access points
+            let existing = access_points.get(info.ssid.as_str());
+            if active
+                || existing.map_or(true, |existing| {
+                    !existing.active && existing.strength < info.strength
+                })
+            {
+                access_points.insert(
+                    &info.ssid,
+                    AccessPoint {
+                        ssid: info.ssid.clone(),
+                        strength: info.strength,
+                        secured: info.secured,
+                        active,
+                        known: self.known_ssids.contains(&info.ssid),
+                    },
+                );
+            }
+        }
+
+        let mut access_points = access_points.into_values().collect::<Vec<_>>();
+        access_points.sort_by(|a, b| {
+            b.strength
+                .cmp(&a.strength)
+                .then_with(|| a.ssid.cmp(&b.ssid))
+        });
+        state.access_points = access_points;
+
+        state
+    }
+}
+
+async fn read_access_point(
+    connection: &zbus::Connection,
+    path: OwnedObjectPath,
+) -> Result<AccessPointInfo> {
+    let ap: AccessPointProxy = build_proxy(connection, path).await?;
+
+    Ok(AccessPointInfo {
+        ssid: String::from_utf8_lossy(&ap.ssid().await?).to_string(),
+        strength: ap.strength().await?,
+        secured: ap.flags().await? & AP_FLAGS_PRIVACY != 0
+            || ap.wpa_flags().await? != 0
+            || ap.rsn_flags().await? != 0,
+    })
+}
+
+/// Gets the object paths for all Wi-Fi devices.
+async fn get_wifi_devices(connection: &zbus::Connection) -> Result<Vec<OwnedObjectPath>> {
+    let nm = NetworkManagerProxy::new(connection).await?;
+
+    let mut wifi_devices = vec![];
+    for path in nm.get_devices().await? {
+        let device: DeviceProxy = build_proxy(connection, path.clone()).await?;
+        if device.device_type().await? == DEVICE_TYPE_WIFI {
+            wifi_devices.push(path);
+        }
+    }
+
+    Ok(wifi_devices)
+}
+
+/// Gets the saved Wi-Fi connections,
+/// as a map of SSIDs to their connection object paths.
+async fn get_saved_connections(
+    connection: &zbus::Connection,
+) -> Result<HashMap<String, OwnedObjectPath>> {
+    let settings = SettingsProxy::new(connection).await?;
+
+    let mut connections = HashMap::new();
+    for path in settings.list_connections().await? {
+        let saved: SettingsConnectionProxy = build_proxy(connection, path.clone()).await?;
+        let saved_settings = saved.get_settings().await?;
+
+        let ssid = saved_settings
+            .get("802-11-wireless")
+            .and_then(|wireless| wireless.get("ssid"))
+            .and_then(|ssid| Vec::<u8>::try_from(Value::from(ssid.clone())).ok());
+
+        if let Some(ssid) = ssid {
+            connections.insert(String::from_utf8_lossy(&ssid).to_string(), path);
+        }
+    }
+
+    Ok(connections)
+}
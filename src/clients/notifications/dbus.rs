@@ -0,0 +1,152 @@
+//! Server implementation of the `org.freedesktop.Notifications` interface.
+//!
+//! See <https://specifications.freedesktop.org/notification-spec/latest/>.
+
+use super::{CloseReason, Notification, NotificationState, Urgency};
+use crate::{lock, send};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::{debug, error};
+use zbus::zvariant::OwnedValue;
+use zbus::{dbus_interface, SignalContext};
+
+/// Maximum number of notifications to keep in the history.
+/// The oldest notifications are dropped beyond this.
+const MAX_HISTORY: usize = 100;
+
+pub struct NotificationServer {
+    pub state: Arc<Mutex<NotificationState>>,
+    pub tx: broadcast::Sender<NotificationState>,
+    pub next_id: u32,
+}
+
+#[dbus_interface(name = "org.freedesktop.Notifications")]
+impl NotificationServer {
+    fn get_capabilities(&self) -> Vec<&str> {
+        vec!["actions", "body", "icon-static", "persistence"]
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn notify(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        app_name: String,
+        replaces_id: u32,
+        app_icon: String,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        hints: HashMap<String, OwnedValue>,
+        _expire_timeout: i32,
+    ) -> u32 {
+        let id = {
+            let state = lock!(self.state);
+            if replaces_id != 0 && state.notifications.iter().any(|n| n.id == replaces_id) {
+                replaces_id
+            } else {
+                self.next_id += 1;
+                self.next_id
+            }
+        };
+
+        debug!("Received notification {id} from '{app_name}'");
+
+        let urgency = hints
+            .get("urgency")
+            .and_then(|value| value.downcast_ref::<u8>().copied())
+            .map(Urgency::from)
+            .unwrap_or_default();
+
+        let resident = hints
+            .get("resident")
+            .and_then(|value| value.downcast_ref::<bool>().copied())
+            .unwrap_or_default();
+
+        let actions = actions
+            .chunks_exact(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect();
+
+        let dropped = {
+            let mut state = lock!(self.state);
+
+            // notifications are silently added to the history while in do-not-disturb,
+            // unless they are critical.
+            let read = state.dnd && urgency != Urgency::Critical;
+
+            let notification = Notification {
+                id,
+                app_name,
+                app_icon,
+                summary,
+                body,
+                actions,
+                urgency,
+                resident,
+                read,
+            };
+
+            if let Some(existing) = state.notifications.iter_mut().find(|n| n.id == id) {
+                *existing = notification;
+            } else {
+                state.notifications.insert(0, notification);
+            }
+
+            let len = state.notifications.len();
+            let dropped = state.notifications.split_off(MAX_HISTORY.min(len));
+
+            send!(self.tx, state.clone());
+            dropped
+        };
+
+        for notification in dropped {
+            if let Err(err) =
+                Self::notification_closed(&ctxt, notification.id, CloseReason::Undefined as u32)
+                    .await
+            {
+                error!("{err:?}");
+            }
+        }
+
+        id
+    }
+
+    async fn close_notification(&self, #[zbus(signal_context)] ctxt: SignalContext<'_>, id: u32) {
+        let removed = {
+            let mut state = lock!(self.state);
+            let removed = state.remove(id);
+
+            if removed {
+                send!(self.tx, state.clone());
+            }
+
+            removed
+        };
+
+        if removed {
+            if let Err(err) = Self::notification_closed(&ctxt, id, CloseReason::Closed as u32).await
+            {
+                error!("{err:?}");
+            }
+        }
+    }
+
+    fn get_server_information(&self) -> (&str, &str, &str, &str) {
+        ("ironbar", "ironbar", crate::VERSION, "1.2")
+    }
+
+    #[dbus_interface(signal)]
+    pub async fn notification_closed(
+        ctxt: &SignalContext<'_>,
+        id: u32,
+        reason: u32,
+    ) -> zbus::Result<()>;
+
+    #[dbus_interface(signal)]
+    pub async fn action_invoked(
+        ctxt: &SignalContext<'_>,
+        id: u32,
+        action_key: &str,
+    ) -> zbus::Result<()>;
+}
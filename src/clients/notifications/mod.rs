@@ -0,0 +1,310 @@
+mod dbus;
+
+use self::dbus::NotificationServer;
+#[cfg(feature = "ipc")]
+use crate::ironvar::{get_variable_manager, is_truthy};
+use crate::{arc_mut, lock, send};
+#[cfg(feature = "ipc")]
+use crate::{read_lock, write_lock};
+use async_once::AsyncOnce;
+use color_eyre::{Report, Result};
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "ipc")]
+use tokio::spawn;
+use tokio::sync::broadcast;
+use tracing::debug;
+#[cfg(feature = "ipc")]
+use tracing::error;
+use zbus::SignalContext;
+
+const BUS_NAME: &str = "org.freedesktop.Notifications";
+const OBJECT_PATH: &str = "/org/freedesktop/Notifications";
+
+/// Name of the `ironvar` kept in sync with the do-not-disturb state.
+#[cfg(feature = "ipc")]
+const DND_VARIABLE: &str = "dnd";
+
+lazy_static! {
+    static ref CLIENT: AsyncOnce<Result<Client>> = AsyncOnce::new(async { Client::new().await });
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+impl From<u8> for Urgency {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Low,
+            2 => Self::Critical,
+            _ => Self::Normal,
+        }
+    }
+}
+
+/// Reason sent with the `NotificationClosed` signal.
+#[derive(Debug, Clone, Copy)]
+#[repr(u32)]
+enum CloseReason {
+    Dismissed = 2,
+    Closed = 3,
+    Undefined = 4,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub id: u32,
+    pub app_name: String,
+    pub app_icon: String,
+    pub summary: String,
+    pub body: String,
+    /// Action key/label pairs.
+    pub actions: Vec<(String, String)>,
+    pub urgency: Urgency,
+    /// Whether the notification should remain after an action is invoked.
+    pub resident: bool,
+    pub read: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NotificationState {
+    /// Notification history, newest first.
+    pub notifications: Vec<Notification>,
+    pub dnd: bool,
+}
+
+impl NotificationState {
+    pub fn unread_count(&self) -> usize {
+        self.notifications.iter().filter(|n| !n.read).count()
+    }
+
+    /// Removes the notification with the given ID,
+    /// returning whether it existed.
+    fn remove(&mut self, id: u32) -> bool {
+        let len = self.notifications.len();
+        self.notifications.retain(|n| n.id != id);
+        self.notifications.len() != len
+    }
+}
+
+pub struct Client {
+    connection: zbus::Connection,
+    state: Arc<Mutex<NotificationState>>,
+    tx: broadcast::Sender<NotificationState>,
+    _rx: broadcast::Receiver<NotificationState>,
+}
+
+impl Client {
+    async fn new() -> Result<Self> {
+        let (tx, rx) = broadcast::channel(16);
+        let state = arc_mut!(NotificationState::default());
+
+        let server = NotificationServer {
+            state: state.clone(),
+            tx: tx.clone(),
+            next_id: 0,
+        };
+
+        let connection = zbus::ConnectionBuilder::session()?
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, server)?
+            .build()
+            .await
+            .map_err(|err| match err {
+                zbus::Error::NameTaken => Report::msg(
+                    "Failed to start notification server: another notification daemon is running",
+                ),
+                err => Report::new(err).wrap_err("Failed to start notification server"),
+            })?;
+
+        debug!("Acquired notification server name");
+
+        let client = Self {
+            connection,
+            state,
+            tx,
+            _rx: rx,
+        };
+
+        #[cfg(feature = "ipc")]
+        client.sync_dnd_variable();
+
+        Ok(client)
+    }
+
+    /// Keeps the do-not-disturb state in sync with its `ironvar`,
+    /// in both directions.
+    ///
+    /// If the variable already has a value (for example from `ironvar_defaults`),
+    /// that is used as the initial state.
+    #[cfg(feature = "ipc")]
+    fn sync_dnd_variable(&self) {
+        let variable_manager = get_variable_manager();
+
+        let initial = read_lock!(variable_manager).get(DND_VARIABLE);
+        match initial {
+            Some(value) => lock!(self.state).dnd = is_truthy(&value),
+            None => {
+                let res = write_lock!(variable_manager).set(DND_VARIABLE.into(), false.to_string());
+
+                if let Err(err) = res {
+                    error!("{err:?}");
+                }
+            }
+        }
+
+        let mut rx = write_lock!(variable_manager).subscribe(DND_VARIABLE.into());
+        let state = self.state.clone();
+        let tx = self.tx.clone();
+
+        spawn(async move {
+            while let Ok(value) = rx.recv().await {
+                let dnd = value.map(|value| is_truthy(&value)).unwrap_or_default();
+
+                let mut state = lock!(state);
+                if state.dnd != dnd {
+                    state.dnd = dnd;
+                    send!(tx, state.clone());
+                }
+            }
+        });
+    }
+
+    /// Subscribes to notification state changes.
+    ///
+    /// The current state is returned alongside the receiver
+    /// to bring the subscriber up to date.
+    pub fn subscribe(&self) -> (NotificationState, broadcast::Receiver<NotificationState>) {
+        let state = lock!(self.state);
+
+        // subscribe while locked so no changes are missed or duplicated
+        let rx = self.tx.subscribe();
+
+        (state.clone(), rx)
+    }
+
+    /// Marks all notifications as read.
+    pub fn mark_read(&self) {
+        let mut state = lock!(self.state);
+        if state.notifications.iter().any(|n| !n.read) {
+            for notification in &mut state.notifications {
+                notification.read = true;
+            }
+
+            send!(self.tx, state.clone());
+        }
+    }
+
+    pub fn set_dnd(&self, dnd: bool) {
+        debug!("Setting do-not-disturb: {dnd}");
+
+        {
+            let mut state = lock!(self.state);
+            if state.dnd == dnd {
+                return;
+            }
+
+            state.dnd = dnd;
+            send!(self.tx, state.clone());
+        }
+
+        #[cfg(feature = "ipc")]
+        {
+            let variable_manager = get_variable_manager();
+            let res = write_lock!(variable_manager).set(DND_VARIABLE.into(), dnd.to_string());
+
+            if let Err(err) = res {
+                error!("{err:?}");
+            }
+        }
+    }
+
+    /// Removes a notification from the history,
+    /// informing the sending application it was dismissed.
+    pub async fn dismiss(&self, id: u32) -> Result<()> {
+        let removed = {
+            let mut state = lock!(self.state);
+            let removed = state.remove(id);
+
+            if removed {
+                send!(self.tx, state.clone());
+            }
+
+            removed
+        };
+
+        if removed {
+            NotificationServer::notification_closed(
+                &self.signal_context()?,
+                id,
+                CloseReason::Dismissed as u32,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Dismisses all notifications.
+    pub async fn clear(&self) -> Result<()> {
+        let removed = {
+            let mut state = lock!(self.state);
+            let removed = std::mem::take(&mut state.notifications);
+            send!(self.tx, state.clone());
+            removed
+        };
+
+        let ctxt = self.signal_context()?;
+        for notification in removed {
+            NotificationServer::notification_closed(
+                &ctxt,
+                notification.id,
+                CloseReason::Dismissed as u32,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Invokes an action on a notification.
+    /// The notification is dismissed afterwards, unless it is resident.
+    pub async fn invoke_action(&self, id: u32, action_key: &str) -> Result<()> {
+        debug!("Invoking action '{action_key}' on notification {id}");
+
+        let resident = lock!(self.state)
+            .notifications
+            .iter()
+            .find(|n| n.id == id)
+            .map(|n| n.resident);
+
+        let Some(resident) = resident else {
+            return Ok(());
+        };
+
+        NotificationServer::action_invoked(&self.signal_context()?, id, action_key).await?;
+
+        if !resident {
+            self.dismiss(id).await?;
+        }
+
+        Ok(())
+    }
+
+    fn signal_context(&self) -> zbus::Result<SignalContext<'_>> {
+        SignalContext::new(&self.connection, OBJECT_PATH)
+    }
+}
+
+/// Gets the notification server client.
+///
+/// Fails if the server could not be started,
+/// for example because another notification daemon is running.
+pub async fn get_client() -> std::result::Result<&'static Client, &'static Report> {
+    CLIENT.get().await.as_ref()
+}
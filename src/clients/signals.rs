@@ -0,0 +1,44 @@
+//! Helpers for clients which refresh their state from D-Bus signals.
+
+use futures_lite::future::poll_once;
+use futures_lite::{Stream, StreamExt};
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// Time to wait for a burst of signals to settle.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Waits for the next item on the stream,
+/// and then for any burst of items following it to settle.
+///
+/// Changes tend to arrive in bursts,
+/// so this allows state to be refreshed once per burst rather than once per signal.
+///
+/// Returns every item received, or `None` once the stream ends.
+pub async fn next_burst<S>(stream: &mut S) -> Option<Vec<S::Item>>
+where
+    S: Stream + Unpin,
+{
+    let mut items = vec![stream.next().await?];
+
+    sleep(DEBOUNCE).await;
+    while let Some(Some(item)) = poll_once(stream.next()).await {
+        items.push(item);
+    }
+
+    Some(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_lite::stream;
+
+    #[tokio::test]
+    async fn test_next_burst() {
+        let mut stream = stream::iter([1, 2, 3]);
+
+        assert_eq!(next_burst(&mut stream).await, Some(vec![1, 2, 3]));
+        assert_eq!(next_burst(&mut stream).await, None);
+    }
+}
@@ -0,0 +1,23 @@
+//! D-Bus proxy for power-profiles-daemon.
+//!
+//! See <https://gitlab.freedesktop.org/upower/power-profiles-daemon>.
+
+use std::collections::HashMap;
+use zbus::dbus_proxy;
+use zbus::zvariant::OwnedValue;
+
+#[dbus_proxy(
+    interface = "net.hadess.PowerProfiles",
+    default_service = "net.hadess.PowerProfiles",
+    default_path = "/net/hadess/PowerProfiles"
+)]
+trait PowerProfiles {
+    #[dbus_proxy(property)]
+    fn active_profile(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(property)]
+    fn set_active_profile(&self, profile: &str) -> zbus::Result<()>;
+
+    #[dbus_proxy(property)]
+    fn profiles(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+}
@@ -0,0 +1,203 @@
+mod dbus;
+
+pub use self::dbus::PowerProfilesProxy;
+use async_once::AsyncOnce;
+use color_eyre::Result;
+use futures_lite::{Stream, StreamExt};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Arc;
+use upower_dbus::UPowerProxy;
+use zbus::fdo::PropertiesProxy;
+use zbus::names::InterfaceName;
+use zbus::zvariant::OwnedValue;
+use zbus::{MatchRule, MatchRuleBuilder, Message, MessageStream, MessageType};
+
+const SERVICE_NAME: &str = "org.freedesktop.UPower";
+const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+
+const DEVICE_TYPE_LINE_POWER: u32 = 1;
+
+lazy_static! {
+    static ref CONNECTION: AsyncOnce<zbus::Connection> = AsyncOnce::new(async {
+        Box::pin(zbus::Connection::system())
+            .await
+            .expect("failed to create connection to system bus")
+    });
+    static ref DISPLAY_PROXY: AsyncOnce<Arc<PropertiesProxy<'static>>> = AsyncOnce::new(async {
+        let dbus = CONNECTION.get().await;
+
+        let device_proxy = UPowerProxy::new(dbus)
+            .await
+            .expect("failed to create upower proxy");
+
+        let display_device = device_proxy
+            .get_display_device()
+            .await
+            .unwrap_or_else(|_| panic!("failed to get display device for {device_proxy:?}"));
+
+        let path = display_device.path().to_owned();
+
+        let proxy = PropertiesProxy::builder(dbus)
+            .destination(SERVICE_NAME)
+            .expect("failed to set proxy destination address")
+            .path(path)
+            .expect("failed to set proxy path")
+            .cache_properties(zbus::CacheProperties::No)
+            .build()
+            .await
+            .expect("failed to build proxy");
+
+        Arc::new(proxy)
+    });
+}
+
+pub async fn get_display_proxy() -> &'static PropertiesProxy<'static> {
+    DISPLAY_PROXY.get().await
+}
+
+/// A peripheral device with a battery, such as a mouse or headset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Device {
+    pub path: String,
+    pub name: String,
+    pub icon_name: String,
+    pub percentage: f64,
+    pub state: u32,
+}
+
+/// Gets every present battery-powered device
+/// which does not power the system itself.
+pub async fn get_devices() -> Result<Vec<Device>> {
+    let dbus = CONNECTION.get().await;
+    let upower = UPowerProxy::new(dbus).await?;
+
+    let interface = InterfaceName::from_static_str(DEVICE_INTERFACE)?;
+
+    let mut devices = vec![];
+
+    for path in upower.enumerate_devices().await? {
+        let proxy = PropertiesProxy::builder(dbus)
+            .destination(SERVICE_NAME)?
+            .path(path.clone())?
+            .cache_properties(zbus::CacheProperties::No)
+            .build()
+            .await?;
+
+        let properties = proxy.get_all(interface.clone()).await?;
+
+        let get_bool = |name| get_property::<bool>(&properties, name).unwrap_or_default();
+
+        // the device type is read as a number,
+        // as newer types such as headsets are not known to `upower_dbus`.
+        let kind = get_property::<u32>(&properties, "Type").unwrap_or_default();
+
+        if kind == DEVICE_TYPE_LINE_POWER || get_bool("PowerSupply") || !get_bool("IsPresent") {
+            continue;
+        }
+
+        let model = properties
+            .get("Model")
+            .and_then(|value| value.downcast_ref::<str>())
+            .filter(|model| !model.is_empty());
+
+        devices.push(Device {
+            path: path.to_string(),
+            name: model.map_or_else(|| device_type_name(kind).to_string(), ToString::to_string),
+            icon_name: properties
+                .get("IconName")
+                .and_then(|value| value.downcast_ref::<str>())
+                .unwrap_or_default()
+                .to_string(),
+            percentage: get_property::<f64>(&properties, "Percentage").unwrap_or_default(),
+            state: get_property::<u32>(&properties, "State").unwrap_or_default(),
+        });
+    }
+
+    Ok(devices)
+}
+
+/// Gets a stream of signals for devices being added or removed,
+/// or device properties changing.
+pub async fn device_changes() -> Result<impl Stream<Item = zbus::Result<Arc<Message>>> + Unpin> {
+    let dbus = CONNECTION.get().await;
+
+    let device_rule = |member| {
+        MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .sender(SERVICE_NAME)?
+            .interface(SERVICE_NAME)?
+            .member(member)
+            .map(MatchRuleBuilder::build)
+    };
+
+    let properties_rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(SERVICE_NAME)?
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .arg(0, DEVICE_INTERFACE)?
+        .build();
+
+    let added = MessageStream::for_match_rule(device_rule("DeviceAdded")?, dbus, None).await?;
+    let removed = MessageStream::for_match_rule(device_rule("DeviceRemoved")?, dbus, None).await?;
+    let properties = MessageStream::for_match_rule(properties_rule, dbus, None).await?;
+
+    Ok(added.or(removed).or(properties))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PowerProfiles {
+    pub active: String,
+    pub available: Vec<String>,
+}
+
+pub async fn get_power_profiles_proxy() -> Result<PowerProfilesProxy<'static>> {
+    let proxy = PowerProfilesProxy::new(CONNECTION.get().await).await?;
+    Ok(proxy)
+}
+
+/// Gets the active and available power profiles from power-profiles-daemon.
+pub async fn get_power_profiles(proxy: &PowerProfilesProxy<'_>) -> Result<PowerProfiles> {
+    let active = proxy.active_profile().await?;
+
+    let available = proxy
+        .profiles()
+        .await?
+        .iter()
+        .filter_map(|profile| get_property::<&str>(profile, "Profile").map(ToString::to_string))
+        .collect();
+
+    Ok(PowerProfiles { active, available })
+}
+
+fn get_property<'a, T>(properties: &'a HashMap<String, OwnedValue>, name: &str) -> Option<T>
+where
+    T: TryFrom<&'a OwnedValue>,
+{
+    properties
+        .get(name)
+        .and_then(|value| T::try_from(value).ok())
+}
+
+/// Gets a readable name for a UPower device type,
+/// used when the device does not report its model.
+const fn device_type_name(kind: u32) -> &'static str {
+    match kind {
+        2 => "Battery",
+        3 => "UPS",
+        4 => "Monitor",
+        5 => "Mouse",
+        6 => "Keyboard",
+        8 => "Phone",
+        9 => "Media player",
+        10 => "Tablet",
+        12 => "Controller",
+        13 => "Pen",
+        14 => "Touchpad",
+        17 => "Headset",
+        18 => "Speakers",
+        19 => "Headphones",
+        _ => "Device",
+    }
+}
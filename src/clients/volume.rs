@@ -0,0 +1,696 @@
+use crate::{arc_mut, lock, send};
+use color_eyre::{Report, Result};
+use lazy_static::lazy_static;
+use libpulse_binding::callbacks::ListResult;
+use libpulse_binding::context::introspect::{
+    Introspector, ServerInfo, SinkInfo, SinkInputInfo, SourceInfo,
+};
+use libpulse_binding::context::subscribe::{Facility, InterestMaskSet, Operation};
+use libpulse_binding::context::{Context, FlagSet, State};
+use libpulse_binding::mainloop::standard::Mainloop;
+use libpulse_binding::proplist::{properties, Proplist};
+use libpulse_binding::time::MicroSeconds;
+use libpulse_binding::volume::{ChannelVolumes, Volume};
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::spawn_blocking;
+use tracing::{debug, error, info, trace, warn};
+
+/// Maximum time to block on the server connection
+/// before checking for queued commands.
+const COMMAND_INTERVAL_US: u64 = 20_000;
+
+/// Time to wait before attempting to reconnect
+/// after the connection to the server is lost.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    static ref CLIENT: Arc<Client> = Arc::new(Client::new());
+}
+
+/// An audio output or input device.
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub index: u32,
+    pub name: String,
+    pub description: String,
+    pub volume: f64,
+    pub muted: bool,
+    /// Whether this is the default device of its kind.
+    pub active: bool,
+    channels: u8,
+}
+
+/// An application playback stream.
+#[derive(Debug, Clone)]
+pub struct SinkInput {
+    pub index: u32,
+    pub name: String,
+    pub icon_name: Option<String>,
+    pub volume: f64,
+    pub muted: bool,
+    pub can_set_volume: bool,
+    channels: u8,
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    AddSink(Device),
+    UpdateSink(Device),
+    RemoveSink(u32),
+
+    AddSource(Device),
+    UpdateSource(Device),
+    RemoveSource(u32),
+
+    AddInput(SinkInput),
+    UpdateInput(SinkInput),
+    RemoveInput(u32),
+}
+
+#[derive(Debug)]
+enum Command {
+    DefaultSink(String),
+    DefaultSource(String),
+    SinkVolume(u32, f64),
+    SinkMuted(u32, bool),
+    SourceVolume(u32, f64),
+    SourceMuted(u32, bool),
+    InputVolume(u32, f64),
+    InputMuted(u32, bool),
+}
+
+/// Current server state,
+/// mirrored from the server to allow new subscribers to sync.
+#[derive(Debug, Default)]
+struct Data {
+    sinks: Vec<Device>,
+    sources: Vec<Device>,
+    inputs: Vec<SinkInput>,
+    default_sink_name: Option<String>,
+    default_source_name: Option<String>,
+}
+
+/// Client for the PulseAudio server,
+/// which also works with PipeWire via `pipewire-pulse`.
+///
+/// The connection is owned by a dedicated thread,
+/// which listens for server events and processes commands sent from the client.
+pub struct Client {
+    data: Arc<Mutex<Data>>,
+    cmd_tx: Mutex<mpsc::Sender<Command>>,
+    tx: broadcast::Sender<Event>,
+    _rx: broadcast::Receiver<Event>,
+}
+
+impl Client {
+    fn new() -> Self {
+        let (tx, rx) = broadcast::channel(32);
+        let (cmd_tx, cmd_rx) = mpsc::channel();
+
+        let data = arc_mut!(Data::default());
+
+        {
+            let data = data.clone();
+            let tx = tx.clone();
+
+            spawn_blocking(move || loop {
+                if let Err(err) = run(&data, &tx, &cmd_rx) {
+                    error!("{err:?}");
+                }
+
+                warn!(
+                    "Lost connection to audio server, reconnecting in {}s",
+                    RECONNECT_INTERVAL.as_secs()
+                );
+                sleep(RECONNECT_INTERVAL);
+            });
+        }
+
+        Self {
+            data,
+            cmd_tx: Mutex::new(cmd_tx),
+            tx,
+            _rx: rx,
+        }
+    }
+
+    /// Subscribes to server events.
+    ///
+    /// Alongside the receiver, an add event is returned for each known
+    /// sink, source and input to bring the subscriber up to date.
+    pub fn subscribe(&self) -> (Vec<Event>, broadcast::Receiver<Event>) {
+        let data = lock!(self.data);
+
+        // subscribe while locked so no events are missed or duplicated
+        let rx = self.tx.subscribe();
+
+        let events = data
+            .sinks
+            .iter()
+            .cloned()
+            .map(Event::AddSink)
+            .chain(data.sources.iter().cloned().map(Event::AddSource))
+            .chain(data.inputs.iter().cloned().map(Event::AddInput))
+            .collect();
+
+        (events, rx)
+    }
+
+    pub fn set_default_sink(&self, name: String) {
+        self.send_command(Command::DefaultSink(name));
+    }
+
+    pub fn set_default_source(&self, name: String) {
+        self.send_command(Command::DefaultSource(name));
+    }
+
+    pub fn set_sink_volume(&self, index: u32, volume_percent: f64) {
+        self.send_command(Command::SinkVolume(index, volume_percent));
+    }
+
+    pub fn set_sink_muted(&self, index: u32, muted: bool) {
+        self.send_command(Command::SinkMuted(index, muted));
+    }
+
+    pub fn set_source_volume(&self, index: u32, volume_percent: f64) {
+        self.send_command(Command::SourceVolume(index, volume_percent));
+    }
+
+    pub fn set_source_muted(&self, index: u32, muted: bool) {
+        self.send_command(Command::SourceMuted(index, muted));
+    }
+
+    pub fn set_input_volume(&self, index: u32, volume_percent: f64) {
+        self.send_command(Command::InputVolume(index, volume_percent));
+    }
+
+    pub fn set_input_muted(&self, index: u32, muted: bool) {
+        self.send_command(Command::InputMuted(index, muted));
+    }
+
+    fn send_command(&self, command: Command) {
+        trace!("Queueing command: {command:?}");
+        send!(lock!(self.cmd_tx), command);
+    }
+}
+
+pub fn get_client() -> Arc<Client> {
+    CLIENT.clone()
+}
+
+/// Connects to the server and runs the main loop until the connection is lost.
+fn run(
+    data: &Arc<Mutex<Data>>,
+    tx: &broadcast::Sender<Event>,
+    cmd_rx: &mpsc::Receiver<Command>,
+) -> Result<()> {
+    let mut proplist = Proplist::new().ok_or_else(|| Report::msg("Failed to create proplist"))?;
+    proplist
+        .set_str(properties::APPLICATION_NAME, "ironbar")
+        .map_err(|()| Report::msg("Failed to set application name"))?;
+
+    let mut mainloop = Mainloop::new().ok_or_else(|| Report::msg("Failed to create mainloop"))?;
+
+    let context = Context::new_with_proplist(&mainloop, "IronbarContext", &proplist)
+        .ok_or_else(|| Report::msg("Failed to create context"))?;
+    let context = Rc::new(RefCell::new(context));
+
+    {
+        let weak_context = Rc::downgrade(&context);
+        let data = data.clone();
+        let tx = tx.clone();
+
+        context
+            .borrow_mut()
+            .set_state_callback(Some(Box::new(move || {
+                on_state_change(&weak_context, &data, &tx);
+            })));
+    }
+
+    // NOFAIL waits for the server to become available rather than failing
+    context.borrow_mut().connect(None, FlagSet::NOFAIL, None)?;
+
+    let mut introspector = context.borrow().introspect();
+
+    loop {
+        mainloop.prepare(Some(MicroSeconds(COMMAND_INTERVAL_US)))?;
+        mainloop.poll()?;
+        mainloop.dispatch()?;
+
+        match context.borrow().get_state() {
+            State::Failed => return Err(Report::msg("Connection to audio server failed")),
+            State::Terminated => return Err(Report::msg("Connection to audio server terminated")),
+            _ => {}
+        }
+
+        while let Ok(command) = cmd_rx.try_recv() {
+            on_command(&context, &mut introspector, data, command);
+        }
+    }
+}
+
+fn on_state_change(
+    context: &Weak<RefCell<Context>>,
+    data: &Arc<Mutex<Data>>,
+    tx: &broadcast::Sender<Event>,
+) {
+    let Some(context) = context.upgrade() else {
+        return;
+    };
+
+    // the state can change synchronously while connecting,
+    // in which case the context is already borrowed.
+    let Ok(state) = context.try_borrow().map(|context| context.get_state()) else {
+        return;
+    };
+
+    if state != State::Ready {
+        return;
+    }
+
+    info!("Connected to audio server");
+
+    clear_data(data, tx);
+
+    let introspector = context.borrow().introspect();
+
+    {
+        let data = data.clone();
+        let tx = tx.clone();
+        introspector.get_server_info(move |info| on_server_info(info, &data, &tx));
+    }
+
+    {
+        let data = data.clone();
+        let tx = tx.clone();
+        introspector.get_sink_info_list(move |info| on_sink_info(info, &data, &tx));
+    }
+
+    {
+        let data = data.clone();
+        let tx = tx.clone();
+        introspector.get_source_info_list(move |info| on_source_info(info, &data, &tx));
+    }
+
+    {
+        let data = data.clone();
+        let tx = tx.clone();
+        introspector.get_sink_input_info_list(move |info| on_sink_input_info(info, &data, &tx));
+    }
+
+    let weak_context = Rc::downgrade(&context);
+    let data = data.clone();
+    let tx = tx.clone();
+
+    let mut context = context.borrow_mut();
+    context.set_subscribe_callback(Some(Box::new(move |facility, op, index| {
+        on_event(&weak_context, &data, &tx, facility, op, index);
+    })));
+
+    context.subscribe(
+        InterestMaskSet::SERVER
+            | InterestMaskSet::SINK
+            | InterestMaskSet::SOURCE
+            | InterestMaskSet::SINK_INPUT,
+        |success| {
+            if !success {
+                error!("Failed to subscribe to audio server events");
+            }
+        },
+    );
+}
+
+/// Removes all known objects,
+/// sending remove events for each.
+///
+/// Used when (re)connecting to avoid stale state.
+fn clear_data(data: &Arc<Mutex<Data>>, tx: &broadcast::Sender<Event>) {
+    let mut data = lock!(data);
+
+    for sink in data.sinks.drain(..) {
+        send!(tx, Event::RemoveSink(sink.index));
+    }
+    for source in data.sources.drain(..) {
+        send!(tx, Event::RemoveSource(source.index));
+    }
+    for input in data.inputs.drain(..) {
+        send!(tx, Event::RemoveInput(input.index));
+    }
+
+    data.default_sink_name = None;
+    data.default_source_name = None;
+}
+
+fn on_event(
+    context: &Weak<RefCell<Context>>,
+    data: &Arc<Mutex<Data>>,
+    tx: &broadcast::Sender<Event>,
+    facility: Option<Facility>,
+    op: Option<Operation>,
+    index: u32,
+) {
+    let (Some(facility), Some(op), Some(context)) = (facility, op, context.upgrade()) else {
+        return;
+    };
+
+    trace!("Server event: {facility:?}, op: {op:?}, index: {index}");
+
+    let introspector = context.borrow().introspect();
+    let data = data.clone();
+    let tx = tx.clone();
+
+    match (facility, op) {
+        (Facility::Server, _) => {
+            introspector.get_server_info(move |info| on_server_info(info, &data, &tx));
+        }
+        (Facility::Sink, Operation::Removed) => {
+            lock!(data).sinks.retain(|sink| sink.index != index);
+            send!(tx, Event::RemoveSink(index));
+        }
+        (Facility::Sink, _) => {
+            introspector.get_sink_info_by_index(index, move |info| on_sink_info(info, &data, &tx));
+        }
+        (Facility::Source, Operation::Removed) => {
+            lock!(data).sources.retain(|source| source.index != index);
+            send!(tx, Event::RemoveSource(index));
+        }
+        (Facility::Source, _) => {
+            introspector
+                .get_source_info_by_index(index, move |info| on_source_info(info, &data, &tx));
+        }
+        (Facility::SinkInput, Operation::Removed) => {
+            lock!(data).inputs.retain(|input| input.index != index);
+            send!(tx, Event::RemoveInput(index));
+        }
+        (Facility::SinkInput, _) => {
+            introspector
+                .get_sink_input_info(index, move |info| on_sink_input_info(info, &data, &tx));
+        }
+        _ => trace!("Ignoring event for unhandled facility: {facility:?}"),
+    }
+}
+
+fn on_server_info(info: &ServerInfo, data: &Arc<Mutex<Data>>, tx: &broadcast::Sender<Event>) {
+    let mut data = lock!(data);
+
+    data.default_sink_name = info.default_sink_name.as_ref().map(ToString::to_string);
+    data.default_source_name = info.default_source_name.as_ref().map(ToString::to_string);
+
+    let default_sink_name = data.default_sink_name.clone();
+    for sink in &mut data.sinks {
+        let active = Some(&sink.name) == default_sink_name.as_ref();
+        if sink.active != active {
+            debug!("Setting sink '{}' active: {active}", sink.name);
+            sink.active = active;
+            send!(tx, Event::UpdateSink(sink.clone()));
+        }
+    }
+
+    let default_source_name = data.default_source_name.clone();
+    for source in &mut data.sources {
+        let active = Some(&source.name) == default_source_name.as_ref();
+        if source.active != active {
+            debug!("Setting source '{}' active: {active}", source.name);
+            source.active = active;
+            send!(tx, Event::UpdateSource(source.clone()));
+        }
+    }
+}
+
+fn on_sink_info(
+    info: ListResult<&SinkInfo>,
+    data: &Arc<Mutex<Data>>,
+    tx: &broadcast::Sender<Event>,
+) {
+    let ListResult::Item(info) = info else {
+        return;
+    };
+
+    let mut data = lock!(data);
+
+    let name = info
+        .name
+        .as_ref()
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    let sink = Device {
+        index: info.index,
+        active: data.default_sink_name.as_ref() == Some(&name),
+        name,
+        description: info
+            .description
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+        volume: volume_to_percent(&info.volume),
+        muted: info.mute,
+        channels: info.volume.len(),
+    };
+
+    upsert(
+        &mut data.sinks,
+        sink,
+        |sink| sink.index,
+        tx,
+        Event::AddSink,
+        Event::UpdateSink,
+    );
+}
+
+fn on_source_info(
+    info: ListResult<&SourceInfo>,
+    data: &Arc<Mutex<Data>>,
+    tx: &broadcast::Sender<Event>,
+) {
+    let ListResult::Item(info) = info else {
+        return;
+    };
+
+    // monitors are exposed as sources, but are not real inputs
+    if info.monitor_of_sink.is_some() {
+        return;
+    }
+
+    let mut data = lock!(data);
+
+    let name = info
+        .name
+        .as_ref()
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    let source = Device {
+        index: info.index,
+        active: data.default_source_name.as_ref() == Some(&name),
+        name,
+        description: info
+            .description
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+        volume: volume_to_percent(&info.volume),
+        muted: info.mute,
+        channels: info.volume.len(),
+    };
+
+    upsert(
+        &mut data.sources,
+        source,
+        |source| source.index,
+        tx,
+        Event::AddSource,
+        Event::UpdateSource,
+    );
+}
+
+fn on_sink_input_info(
+    info: ListResult<&SinkInputInfo>,
+    data: &Arc<Mutex<Data>>,
+    tx: &broadcast::Sender<Event>,
+) {
+    let ListResult::Item(info) = info else {
+        return;
+    };
+
+    let name = info
+        .proplist
+        .get_str(properties::APPLICATION_NAME)
+        .or_else(|| info.name.as_ref().map(ToString::to_string))
+        .unwrap_or_default();
+
+    let input = SinkInput {
+        index: info.index,
+        name,
+        icon_name: info.proplist.get_str(properties::APPLICATION_ICON_NAME),
+        volume: volume_to_percent(&info.volume),
+        muted: info.mute,
+        can_set_volume: info.has_volume && info.volume_writable,
+        channels: info.volume.len(),
+    };
+
+    let mut data = lock!(data);
+    upsert(
+        &mut data.inputs,
+        input,
+        |input| input.index,
+        tx,
+        Event::AddInput,
+        Event::UpdateInput,
+    );
+}
+
+/// Inserts the item into the list, or replaces the existing item with the same index.
+/// Sends the appropriate add or update event.
+fn upsert<T: Clone>(
+    list: &mut Vec<T>,
+    item: T,
+    index: impl Fn(&T) -> u32,
+    tx: &broadcast::Sender<Event>,
+    add: impl Fn(T) -> Event,
+    update: impl Fn(T) -> Event,
+) {
+    if let Some(existing) = list
+        .iter_mut()
+        .find(|existing| index(existing) == index(&item))
+    {
+        *existing = item.clone();
+        send!(tx, update(item));
+    } else {
+        list.push(item.clone());
+        send!(tx, add(item));
+    }
+}
+
+fn on_command(
+    context: &Rc<RefCell<Context>>,
+    introspector: &mut Introspector,
+    data: &Arc<Mutex<Data>>,
+    command: Command,
+) {
+    debug!("Running command: {command:?}");
+
+    let data = lock!(data);
+
+    let find_channels = |devices: &[Device], index: u32| {
+        devices
+            .iter()
+            .find(|device| device.index == index)
+            .map(|device| device.channels)
+    };
+
+    match command {
+        Command::DefaultSink(name) => {
+            context.borrow_mut().set_default_sink(&name, |_| {});
+        }
+        Command::DefaultSource(name) => {
+            context.borrow_mut().set_default_source(&name, |_| {});
+        }
+        Command::SinkVolume(index, volume) => {
+            if let Some(channels) = find_channels(&data.sinks, index) {
+                introspector.set_sink_volume_by_index(
+                    index,
+                    &percent_to_volume(volume, channels),
+                    None,
+                );
+            }
+        }
+        Command::SinkMuted(index, muted) => {
+            introspector.set_sink_mute_by_index(index, muted, None);
+        }
+        Command::SourceVolume(index, volume) => {
+            if let Some(channels) = find_channels(&data.sources, index) {
+                introspector.set_source_volume_by_index(
+                    index,
+                    &percent_to_volume(volume, channels),
+                    None,
+                );
+            }
+        }
+        Command::SourceMuted(index, muted) => {
+            introspector.set_source_mute_by_index(index, muted, None);
+        }
+        Command::InputVolume(index, volume) => {
+            if let Some(input) = data
+                .inputs
+                .iter()
+                .find(|input| input.index == index && input.can_set_volume)
+            {
+                introspector.set_sink_input_volume(
+                    index,
+                    &percent_to_volume(volume, input.channels),
+                    None,
+                );
+            }
+        }
+        Command::InputMuted(index, muted) => {
+            introspector.set_sink_input_mute(index, muted, None);
+        }
+    }
+}
+
+/// Converts the average volume across all channels
+/// into a percentage of the normal (100%) volume.
+fn volume_to_percent(volume: &ChannelVolumes) -> f64 {
+    let avg = volume.avg().0;
+    let normal = Volume::NORMAL.0 - Volume::MUTED.0;
+
+    (f64::from(avg.saturating_sub(Volume::MUTED.0)) / f64::from(normal) * 100.0).round()
+}
+
+/// Converts a percentage of the normal (100%) volume
+/// into an equal volume across the given number of channels.
+fn percent_to_volume(percent: f64, channels: u8) -> ChannelVolumes {
+    let normal = f64::from(Volume::NORMAL.0 - Volume::MUTED.0);
+    let value = Volume::MUTED.0 + (normal * percent.max(0.0) / 100.0) as u32;
+
+    let mut volume = ChannelVolumes::default();
+    volume.set(channels, Volume(value));
+    volume
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel_volumes(value: u32) -> ChannelVolumes {
+        let mut volume = ChannelVolumes::default();
+        volume.set(2, Volume(value));
+        volume
+    }
+
+    #[test]
+    fn test_volume_to_percent_rounds() {
+        assert_eq!(volume_to_percent(&channel_volumes(Volume::MUTED.0)), 0.0);
+        assert_eq!(volume_to_percent(&channel_volumes(Volume::NORMAL.0)), 100.0);
+
+        // 49.4995% and 49.5010%
+        assert_eq!(volume_to_percent(&channel_volumes(32440)), 49.0);
+        assert_eq!(volume_to_percent(&channel_volumes(32441)), 50.0);
+    }
+
+    #[test]
+    fn test_percent_to_volume_bounds() {
+        let volume = percent_to_volume(100.0, 2);
+        assert_eq!(volume.len(), 2);
+        assert_eq!(volume.avg(), Volume::NORMAL);
+
+        assert_eq!(percent_to_volume(-10.0, 2).avg(), Volume::MUTED);
+
+        // volumes above 100% are amplified rather than capped
+        let volume = percent_to_volume(150.0, 2);
+        assert_eq!(volume.avg().0, Volume::NORMAL.0 + Volume::NORMAL.0 / 2);
+        assert_eq!(volume_to_percent(&volume), 150.0);
+    }
+
+    #[test]
+    fn test_percent_round_trip() {
+        for percent in 0..=150 {
+            let percent = f64::from(percent);
+            assert_eq!(volume_to_percent(&percent_to_volume(percent, 2)), percent);
+        }
+    }
+}
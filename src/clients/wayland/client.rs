@@ -1,6 +1,9 @@
+use super::ext_image_capture::ToplevelCaptureState;
+use super::preview::Preview;
 use super::wlr_foreign_toplevel::handle::ToplevelHandle;
 use super::wlr_foreign_toplevel::manager::ToplevelManagerState;
-use super::wlr_foreign_toplevel::ToplevelEvent;
+use super::wlr_foreign_toplevel::{ToplevelAction, ToplevelEvent};
+use super::wlr_screencopy::ScreencopyState;
 use super::Environment;
 use crate::error::ERR_CHANNEL_RECV;
 use crate::send;
@@ -11,9 +14,10 @@ use smithay_client_toolkit::reexports::calloop::channel::{channel, Event, Sender
 use smithay_client_toolkit::reexports::calloop::EventLoop;
 use smithay_client_toolkit::registry::RegistryState;
 use smithay_client_toolkit::seat::SeatState;
+use smithay_client_toolkit::shm::Shm;
 use std::collections::HashMap;
 use std::sync::mpsc;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, oneshot};
 use tokio::task::spawn_blocking;
 use tracing::{debug, error, trace};
 use wayland_client::globals::registry_queue_init;
@@ -29,6 +33,13 @@ cfg_if! {
     }
 }
 
+cfg_if! {
+    if #[cfg(feature = "workspaces+ext")] {
+        use super::ext_workspace::{WorkspaceManagerState, WorkspaceState};
+        use crate::clients::compositor::{Workspace, WorkspaceId, WorkspaceUpdate};
+    }
+}
+
 #[derive(Debug)]
 pub enum Request {
     /// Sends a request for all the outputs.
@@ -40,6 +51,18 @@ pub enum Request {
     /// Sends a request for all the toplevels.
     /// These are then sent on the `toplevel_init` channel.
     Toplevels,
+    /// Performs a window management action on a toplevel.
+    ToplevelAction {
+        toplevel_id: usize,
+        action: ToplevelAction,
+    },
+    /// Captures a preview of the toplevel.
+    /// The result is sent on the provided channel.
+    CapturePreview {
+        toplevel_id: usize,
+        max_width: i32,
+        tx: oneshot::Sender<Option<Preview>>,
+    },
     /// Sends a request for the current clipboard item.
     /// This is then sent on the `clipboard_init` channel.
     #[cfg(feature = "clipboard")]
@@ -47,6 +70,14 @@ pub enum Request {
     /// Copies the value to the clipboard
     #[cfg(feature = "clipboard")]
     CopyToClipboard(Arc<ClipboardItem>),
+    /// Sends a request for the current workspaces,
+    /// or `None` if the compositor does not support `ext-workspace-v1`.
+    /// This is then sent on the `workspace_init` channel.
+    #[cfg(feature = "workspaces+ext")]
+    Workspaces,
+    /// Requests the workspace with the given ID is activated.
+    #[cfg(feature = "workspaces+ext")]
+    FocusWorkspace(WorkspaceId),
     /// Forces a dispatch, flushing any currently queued events
     Roundtrip,
 }
@@ -59,6 +90,10 @@ pub struct WaylandClient {
     clipboard_tx: broadcast::Sender<Arc<ClipboardItem>>,
     #[cfg(feature = "clipboard")]
     _clipboard_rx: broadcast::Receiver<Arc<ClipboardItem>>,
+    #[cfg(feature = "workspaces+ext")]
+    workspace_tx: broadcast::Sender<WorkspaceUpdate>,
+    #[cfg(feature = "workspaces+ext")]
+    _workspace_rx: broadcast::Receiver<WorkspaceUpdate>,
 
     // Internal channels
     toplevel_init_rx: mpsc::Receiver<HashMap<usize, ToplevelHandle>>,
@@ -66,6 +101,8 @@ pub struct WaylandClient {
     seat_rx: mpsc::Receiver<Vec<WlSeat>>,
     #[cfg(feature = "clipboard")]
     clipboard_init_rx: mpsc::Receiver<Option<Arc<ClipboardItem>>>,
+    #[cfg(feature = "workspaces+ext")]
+    workspace_init_rx: mpsc::Receiver<Option<Vec<Workspace>>>,
 
     request_tx: Sender<Request>,
 }
@@ -89,6 +126,14 @@ impl WaylandClient {
             }
         }
 
+        cfg_if! {
+            if #[cfg(feature = "workspaces+ext")] {
+                let (workspace_tx, workspace_rx) = broadcast::channel(32);
+                let workspace_tx2 = workspace_tx.clone();
+                let (workspace_init_tx, workspace_init_rx) = mpsc::channel();
+            }
+        }
+
         let (ev_tx, ev_rx) = channel::<Request>();
 
         // `queue` is not `Send` so we need to handle everything inside the task
@@ -96,6 +141,8 @@ impl WaylandClient {
             let toplevel_tx = toplevel_tx2;
             #[cfg(feature = "clipboard")]
             let clipboard_tx = clipboard_tx2;
+            #[cfg(feature = "workspaces+ext")]
+            let workspace_tx = workspace_tx2;
 
             let conn =
                 Connection::connect_to_env().expect("Failed to connect to Wayland compositor");
@@ -119,6 +166,7 @@ impl WaylandClient {
 
             let output_delegate = OutputState::new(&globals, &qh);
             let seat_delegate = SeatState::new(&globals, &qh);
+            let shm_delegate = Shm::bind(&globals, &qh).expect("wl_shm is not available");
 
             #[cfg(feature = "clipboard")]
             let data_control_device_manager_delegate =
@@ -128,13 +176,30 @@ impl WaylandClient {
             let foreign_toplevel_manager_delegate = ToplevelManagerState::bind(&globals, &qh)
                 .expect("foreign toplevel manager is not available");
 
+            // optional, as only some compositors support this.
+            // previews are disabled if any of the required globals are missing.
+            let toplevel_capture_delegate = ToplevelCaptureState::bind(&globals, &qh).ok();
+
+            // optional, as only wlroots-based compositors support this.
+            // used for previews where the above is unavailable.
+            let screencopy_delegate = ScreencopyState::bind(&globals, &qh).ok();
+
+            // optional, as only some compositors support this
+            #[cfg(feature = "workspaces+ext")]
+            let workspace_manager_delegate = WorkspaceManagerState::bind(&globals, &qh).ok();
+
             let mut env = Environment {
                 registry_state,
                 output_state: output_delegate,
                 seat_state: seat_delegate,
                 #[cfg(feature = "clipboard")]
                 data_control_device_manager_state: data_control_device_manager_delegate,
+                shm_state: shm_delegate,
                 foreign_toplevel_manager_state: foreign_toplevel_manager_delegate,
+                toplevel_capture_state: toplevel_capture_delegate,
+                screencopy_state: screencopy_delegate,
+                #[cfg(feature = "workspaces+ext")]
+                workspace_manager_state: workspace_manager_delegate,
                 seats: vec![],
                 handles: HashMap::new(),
                 #[cfg(feature = "clipboard")]
@@ -148,6 +213,10 @@ impl WaylandClient {
                 selection_offers: vec![],
                 #[cfg(feature = "clipboard")]
                 copy_paste_sources: vec![],
+                #[cfg(feature = "workspaces+ext")]
+                workspace_state: WorkspaceState::default(),
+                #[cfg(feature = "workspaces+ext")]
+                workspace_tx,
                 loop_handle: event_loop.handle(),
             };
 
@@ -169,6 +238,21 @@ impl WaylandClient {
                             trace!("Receive get toplevels request");
                             send!(toplevel_init_tx, env.handles.clone());
                         }
+                        Event::Msg(Request::ToplevelAction {
+                            toplevel_id,
+                            action,
+                        }) => {
+                            trace!("Receive toplevel action request");
+                            env.toplevel_action(toplevel_id, action);
+                        }
+                        Event::Msg(Request::CapturePreview {
+                            toplevel_id,
+                            max_width,
+                            tx,
+                        }) => {
+                            trace!("Receive capture preview request");
+                            env.capture_preview(toplevel_id, max_width, tx, &qh);
+                        }
                         #[cfg(feature = "clipboard")]
                         Event::Msg(Request::Clipboard) => {
                             trace!("Receive get clipboard requests");
@@ -179,6 +263,19 @@ impl WaylandClient {
                         Event::Msg(Request::CopyToClipboard(value)) => {
                             env.copy_to_clipboard(value, &qh);
                         }
+                        #[cfg(feature = "workspaces+ext")]
+                        Event::Msg(Request::Workspaces) => {
+                            trace!("Receive get workspaces request");
+                            let workspaces = env
+                                .workspace_manager_state
+                                .is_some()
+                                .then(|| env.workspace_state.workspaces().to_vec());
+                            send!(workspace_init_tx, workspaces);
+                        }
+                        #[cfg(feature = "workspaces+ext")]
+                        Event::Msg(Request::FocusWorkspace(id)) => {
+                            env.focus_workspace(&id);
+                        }
                         Event::Closed => panic!("Channel unexpectedly closed"),
                     }
                 })
@@ -207,6 +304,12 @@ impl WaylandClient {
             clipboard_tx,
             #[cfg(feature = "clipboard")]
             _clipboard_rx: clipboard_rx,
+            #[cfg(feature = "workspaces+ext")]
+            workspace_tx,
+            #[cfg(feature = "workspaces+ext")]
+            _workspace_rx: workspace_rx,
+            #[cfg(feature = "workspaces+ext")]
+            workspace_init_rx,
             request_tx: ev_tx,
         }
     }
@@ -226,6 +329,45 @@ impl WaylandClient {
         (rx, data)
     }
 
+    /// Performs a window management action on the toplevel with this ID.
+    pub fn toplevel_action(&self, toplevel_id: usize, action: ToplevelAction) {
+        send!(
+            self.request_tx,
+            Request::ToplevelAction {
+                toplevel_id,
+                action,
+            }
+        );
+    }
+
+    /// Captures a preview of the toplevel with this ID,
+    /// downscaled to fit within `max_width`.
+    ///
+    /// Where `ext-image-copy-capture-v1` is unavailable,
+    /// the whole output is captured using `wlr-screencopy-unstable-v1` instead,
+    /// which is only done while the toplevel is focused.
+    ///
+    /// Resolves to `None` if the capture fails,
+    /// or neither protocol can be used.
+    pub fn capture_preview(
+        &self,
+        toplevel_id: usize,
+        max_width: i32,
+    ) -> oneshot::Receiver<Option<Preview>> {
+        let (tx, rx) = oneshot::channel();
+
+        send!(
+            self.request_tx,
+            Request::CapturePreview {
+                toplevel_id,
+                max_width,
+                tx,
+            }
+        );
+
+        rx
+    }
+
     #[cfg(feature = "clipboard")]
     pub fn subscribe_clipboard(
         &self,
@@ -242,6 +384,33 @@ impl WaylandClient {
         (rx, data)
     }
 
+    /// Gets the current workspaces,
+    /// or `None` if the compositor does not support `ext-workspace-v1`.
+    #[cfg(feature = "workspaces+ext")]
+    pub fn get_workspaces(&self) -> Option<Vec<Workspace>> {
+        trace!("Sending get workspaces request");
+
+        send!(self.request_tx, Request::Workspaces);
+        self.workspace_init_rx.recv().expect(ERR_CHANNEL_RECV)
+    }
+
+    /// Creates a new workspace event receiver.
+    /// The current workspaces are re-sent to all subscribers as an `Init` event.
+    #[cfg(feature = "workspaces+ext")]
+    pub fn subscribe_workspaces(&self) -> broadcast::Receiver<WorkspaceUpdate> {
+        let rx = self.workspace_tx.subscribe();
+
+        let workspaces = self.get_workspaces().unwrap_or_default();
+        send!(self.workspace_tx, WorkspaceUpdate::Init(workspaces));
+
+        rx
+    }
+
+    #[cfg(feature = "workspaces+ext")]
+    pub fn focus_workspace(&self, id: WorkspaceId) {
+        send!(self.request_tx, Request::FocusWorkspace(id));
+    }
+
     /// Force a roundtrip on the wayland connection,
     /// flushing any queued events and immediately receiving any new ones.
     pub fn roundtrip(&self) {
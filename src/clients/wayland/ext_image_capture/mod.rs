@@ -0,0 +1,455 @@
+mod protocol;
+
+use self::protocol::foreign_toplevel_list::client::{
+    ext_foreign_toplevel_handle_v1::{self, ExtForeignToplevelHandleV1},
+    ext_foreign_toplevel_list_v1::{self, ExtForeignToplevelListV1},
+};
+use self::protocol::image_capture_source::client::{
+    ext_foreign_toplevel_image_capture_source_manager_v1::{
+        self, ExtForeignToplevelImageCaptureSourceManagerV1,
+    },
+    ext_image_capture_source_v1::{self, ExtImageCaptureSourceV1},
+};
+use self::protocol::image_copy_capture::client::{
+    ext_image_copy_capture_frame_v1::{self, ExtImageCopyCaptureFrameV1},
+    ext_image_copy_capture_manager_v1::{self, ExtImageCopyCaptureManagerV1, Options},
+    ext_image_copy_capture_session_v1::{self, ExtImageCopyCaptureSessionV1},
+};
+use super::preview::{BufferInfo, Preview};
+use super::{Environment, ToplevelHandle};
+use crate::lock;
+use smithay_client_toolkit::globals::GlobalData;
+use smithay_client_toolkit::shm::raw::RawPool;
+use smithay_client_toolkit::shm::Shm;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+use tracing::{debug, error, trace};
+use wayland_client::globals::{BindError, GlobalList};
+use wayland_client::protocol::wl_buffer::WlBuffer;
+use wayland_client::protocol::wl_shm::Format;
+use wayland_client::{event_created_child, Connection, Dispatch, QueueHandle, WEnum};
+
+/// State for capturing images of individual toplevels.
+///
+/// Toplevels are captured using `ext-image-copy-capture-v1`,
+/// which identifies them by their `ext-foreign-toplevel-list-v1` handle.
+/// As these are separate from the `wlr-foreign-toplevel-management` handles
+/// used everywhere else, the two are matched up by app ID and title,
+/// falling back to the order they were announced in.
+pub struct ToplevelCaptureState {
+    source_manager: ExtForeignToplevelImageCaptureSourceManagerV1,
+    copy_manager: ExtImageCopyCaptureManagerV1,
+    _list: ExtForeignToplevelListV1,
+
+    toplevels: Vec<ExtToplevel>,
+}
+
+/// A toplevel from `ext-foreign-toplevel-list-v1`.
+#[derive(Debug)]
+struct ExtToplevel {
+    handle: ExtForeignToplevelHandleV1,
+    app_id: String,
+    title: String,
+}
+
+impl ToplevelCaptureState {
+    pub fn bind<State>(globals: &GlobalList, qh: &QueueHandle<State>) -> Result<Self, BindError>
+    where
+        State: Dispatch<ExtForeignToplevelListV1, GlobalData>
+            + Dispatch<ExtForeignToplevelImageCaptureSourceManagerV1, GlobalData>
+            + Dispatch<ExtImageCopyCaptureManagerV1, GlobalData>
+            + 'static,
+    {
+        let source_manager = globals.bind(qh, 1..=1, GlobalData)?;
+        let copy_manager = globals.bind(qh, 1..=1, GlobalData)?;
+        let list = globals.bind(qh, 1..=1, GlobalData)?;
+        debug!("Bound to ExtImageCopyCaptureManagerV1 global");
+
+        Ok(Self {
+            source_manager,
+            copy_manager,
+            _list: list,
+            toplevels: vec![],
+        })
+    }
+
+    /// Gets the `ext-foreign-toplevel-list-v1` toplevels
+    /// with the given app ID and title, in the order they were announced.
+    fn matching<'a>(
+        &'a self,
+        app_id: &'a str,
+        title: &'a str,
+    ) -> impl Iterator<Item = &'a ExtForeignToplevelHandleV1> {
+        self.toplevels
+            .iter()
+            .filter(move |toplevel| toplevel.app_id == app_id && toplevel.title == title)
+            .map(|toplevel| &toplevel.handle)
+    }
+}
+
+/// Capture state shared between a session and its frame.
+pub struct CaptureData {
+    inner: Mutex<CaptureInner>,
+}
+
+struct CaptureInner {
+    max_width: i32,
+    tx: Option<oneshot::Sender<Option<Preview>>>,
+
+    source: ExtImageCaptureSourceV1,
+    session: Option<ExtImageCopyCaptureSessionV1>,
+    frame: Option<ExtImageCopyCaptureFrameV1>,
+
+    size: Option<(i32, i32)>,
+    format: Option<Format>,
+    pool: Option<RawPool>,
+    buffer: Option<WlBuffer>,
+}
+
+impl CaptureData {
+    fn new(
+        max_width: i32,
+        tx: oneshot::Sender<Option<Preview>>,
+        source: ExtImageCaptureSourceV1,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            inner: Mutex::new(CaptureInner {
+                max_width,
+                tx: Some(tx),
+                source,
+                session: None,
+                frame: None,
+                size: None,
+                format: None,
+                pool: None,
+                buffer: None,
+            }),
+        })
+    }
+}
+
+impl CaptureInner {
+    fn buffer_info(&self) -> Option<BufferInfo> {
+        let (width, height) = self.size?;
+
+        Some(BufferInfo {
+            format: self.format?,
+            width,
+            height,
+            // all supported formats are 32 bits per pixel
+            stride: width * 4,
+        })
+    }
+
+    /// Creates a shared memory buffer matching the session constraints
+    /// and requests a frame is captured into it.
+    fn capture(
+        &mut self,
+        session: &ExtImageCopyCaptureSessionV1,
+        data: &Arc<CaptureData>,
+        shm: &Shm,
+        qh: &QueueHandle<Environment>,
+    ) {
+        // constraints may be re-sent, but only a single frame is needed
+        if self.frame.is_some() {
+            return;
+        }
+
+        let Some(info) = self.buffer_info() else {
+            error!("Compositor did not offer a supported capture buffer format");
+            self.finish(None);
+            return;
+        };
+
+        let mut pool = match RawPool::new((info.stride * info.height) as usize, shm) {
+            Ok(pool) => pool,
+            Err(err) => {
+                error!("Failed to create capture buffer: {err:?}");
+                self.finish(None);
+                return;
+            }
+        };
+
+        let buffer =
+            pool.create_buffer(0, info.width, info.height, info.stride, info.format, (), qh);
+
+        let frame = session.create_frame(qh, data.clone());
+        frame.attach_buffer(&buffer);
+        frame.damage_buffer(0, 0, info.width, info.height);
+        frame.capture();
+
+        self.frame = Some(frame);
+        self.pool = Some(pool);
+        self.buffer = Some(buffer);
+    }
+
+    /// Downscales the captured frame to fit within `max_width`,
+    /// converting it to RGBA.
+    fn preview(&mut self) -> Option<Preview> {
+        let info = self.buffer_info()?;
+        let data = self.pool.as_mut()?.mmap();
+
+        info.preview(data, self.max_width, false)
+    }
+
+    /// Sends the result to the requester
+    /// and releases the capture resources.
+    fn finish(&mut self, preview: Option<Preview>) {
+        let Some(tx) = self.tx.take() else {
+            return;
+        };
+
+        // the requester may have given up waiting
+        let _ = tx.send(preview);
+
+        if let Some(frame) = self.frame.take() {
+            frame.destroy();
+        }
+
+        if let Some(session) = self.session.take() {
+            session.destroy();
+        }
+
+        if let Some(buffer) = self.buffer.take() {
+            buffer.destroy();
+        }
+
+        self.pool = None;
+        self.source.destroy();
+    }
+}
+
+impl Environment {
+    /// Finds the `ext-foreign-toplevel-list-v1` handle for a toplevel.
+    ///
+    /// Toplevels are matched by app ID and title.
+    /// Where several share both, both protocols announce them
+    /// in the same order, so the nth oldest of each are paired up.
+    pub(super) fn find_capture_toplevel(
+        &self,
+        toplevel_id: usize,
+    ) -> Option<ExtForeignToplevelHandleV1> {
+        let state = self.toplevel_capture_state.as_ref()?;
+        let info = self.handles.get(&toplevel_id)?.info()?;
+
+        let mut siblings = self
+            .handles
+            .values()
+            .filter_map(ToplevelHandle::info)
+            .filter(|other| other.app_id == info.app_id && other.title == info.title)
+            .map(|other| other.id)
+            .collect::<Vec<_>>();
+        siblings.sort_unstable();
+
+        let matches = state
+            .matching(&info.app_id, &info.title)
+            .collect::<Vec<_>>();
+
+        // the lists can briefly disagree while toplevels open, close or change title
+        if matches.len() != siblings.len() {
+            debug!(
+                "Unable to match toplevel '{}' ({}) for capture: {} candidates for {} windows",
+                info.title,
+                info.app_id,
+                matches.len(),
+                siblings.len()
+            );
+            return None;
+        }
+
+        let index = siblings.iter().position(|&id| id == info.id)?;
+        matches.get(index).map(|&handle| handle.clone())
+    }
+
+    /// Captures the toplevel using `ext-image-copy-capture-v1`.
+    pub(super) fn capture_toplevel(
+        &mut self,
+        handle: &ExtForeignToplevelHandleV1,
+        toplevel_id: usize,
+        max_width: i32,
+        tx: oneshot::Sender<Option<Preview>>,
+        qh: &QueueHandle<Self>,
+    ) {
+        let Some(state) = &self.toplevel_capture_state else {
+            let _ = tx.send(None);
+            return;
+        };
+
+        trace!("Capturing toplevel {toplevel_id}");
+
+        let source = state.source_manager.create_source(handle, qh, ());
+        let data = CaptureData::new(max_width, tx, source.clone());
+
+        let session =
+            state
+                .copy_manager
+                .create_session(&source, Options::empty(), qh, data.clone());
+
+        lock!(data.inner).session = Some(session);
+    }
+}
+
+impl Dispatch<ExtForeignToplevelListV1, GlobalData> for Environment {
+    event_created_child!(Environment, ExtForeignToplevelListV1, [
+        0 => (ExtForeignToplevelHandleV1, ())
+    ]);
+
+    fn event(
+        state: &mut Self,
+        _list: &ExtForeignToplevelListV1,
+        event: ext_foreign_toplevel_list_v1::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let ext_foreign_toplevel_list_v1::Event::Toplevel { toplevel } = event {
+            if let Some(state) = &mut state.toplevel_capture_state {
+                state.toplevels.push(ExtToplevel {
+                    handle: toplevel,
+                    app_id: String::new(),
+                    title: String::new(),
+                });
+            }
+        }
+    }
+}
+
+impl Dispatch<ExtForeignToplevelHandleV1, ()> for Environment {
+    fn event(
+        state: &mut Self,
+        handle: &ExtForeignToplevelHandleV1,
+        event: ext_foreign_toplevel_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        use ext_foreign_toplevel_handle_v1::Event;
+
+        let Some(state) = &mut state.toplevel_capture_state else {
+            return;
+        };
+
+        if let Event::Closed = event {
+            state
+                .toplevels
+                .retain(|toplevel| &toplevel.handle != handle);
+            handle.destroy();
+            return;
+        }
+
+        let Some(toplevel) = state
+            .toplevels
+            .iter_mut()
+            .find(|toplevel| &toplevel.handle == handle)
+        else {
+            return;
+        };
+
+        match event {
+            Event::AppId { app_id } => toplevel.app_id = app_id,
+            Event::Title { title } => toplevel.title = title,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtForeignToplevelImageCaptureSourceManagerV1, GlobalData> for Environment {
+    fn event(
+        _state: &mut Self,
+        _manager: &ExtForeignToplevelImageCaptureSourceManagerV1,
+        _event: ext_foreign_toplevel_image_capture_source_manager_v1::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // no events
+    }
+}
+
+impl Dispatch<ExtImageCaptureSourceV1, ()> for Environment {
+    fn event(
+        _state: &mut Self,
+        _source: &ExtImageCaptureSourceV1,
+        _event: ext_image_capture_source_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // no events
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureManagerV1, GlobalData> for Environment {
+    fn event(
+        _state: &mut Self,
+        _manager: &ExtImageCopyCaptureManagerV1,
+        _event: ext_image_copy_capture_manager_v1::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // no events
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureSessionV1, Arc<CaptureData>> for Environment {
+    fn event(
+        state: &mut Self,
+        session: &ExtImageCopyCaptureSessionV1,
+        event: ext_image_copy_capture_session_v1::Event,
+        data: &Arc<CaptureData>,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        use ext_image_copy_capture_session_v1::Event;
+
+        let mut inner = lock!(data.inner);
+
+        match event {
+            Event::BufferSize { width, height } => {
+                inner.size = Some((width as i32, height as i32));
+            }
+            // several formats may be offered, so take the first we can read
+            Event::ShmFormat {
+                format: WEnum::Value(format),
+            } if inner.format.is_none() && BufferInfo::channels(format).is_some() => {
+                inner.format = Some(format);
+            }
+            Event::Done => {
+                inner.capture(session, data, &state.shm_state, qh);
+            }
+            Event::Stopped => {
+                debug!("Capture session stopped before a frame was ready");
+                inner.finish(None);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureFrameV1, Arc<CaptureData>> for Environment {
+    fn event(
+        _state: &mut Self,
+        _frame: &ExtImageCopyCaptureFrameV1,
+        event: ext_image_copy_capture_frame_v1::Event,
+        data: &Arc<CaptureData>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        use ext_image_copy_capture_frame_v1::Event;
+
+        let mut inner = lock!(data.inner);
+
+        match event {
+            Event::Ready => {
+                let preview = inner.preview();
+                inner.finish(preview);
+            }
+            Event::Failed { reason } => {
+                error!("Compositor failed to capture frame: {reason:?}");
+                inner.finish(None);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Generated bindings for the `ext-foreign-toplevel-list-v1`,
+//! `ext-image-capture-source-v1` and `ext-image-copy-capture-v1` protocols,
+//! which are not yet shipped as part of `wayland-protocols`.
+
+#![allow(dead_code, non_camel_case_types, unused_unsafe, unused_variables)]
+#![allow(non_upper_case_globals, non_snake_case, unused_imports)]
+#![allow(missing_docs, clippy::all)]
+
+pub mod foreign_toplevel_list {
+    pub mod client {
+        use wayland_client;
+        use wayland_client::backend as wayland_backend;
+        use wayland_client::protocol::*;
+
+        pub mod __interfaces {
+            use wayland_client::backend as wayland_backend;
+            use wayland_client::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("protocols/ext-foreign-toplevel-list-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_client_code!("protocols/ext-foreign-toplevel-list-v1.xml");
+    }
+}
+
+pub mod image_capture_source {
+    pub mod client {
+        use super::super::foreign_toplevel_list::client::*;
+        use wayland_client;
+        use wayland_client::backend as wayland_backend;
+        use wayland_client::protocol::*;
+
+        pub mod __interfaces {
+            use super::super::super::foreign_toplevel_list::client::__interfaces::*;
+            use wayland_client::backend as wayland_backend;
+            use wayland_client::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("protocols/ext-image-capture-source-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_client_code!("protocols/ext-image-capture-source-v1.xml");
+    }
+}
+
+pub mod image_copy_capture {
+    pub mod client {
+        use super::super::image_capture_source::client::*;
+        use wayland_client;
+        use wayland_client::backend as wayland_backend;
+        use wayland_client::protocol::*;
+
+        pub mod __interfaces {
+            use super::super::super::image_capture_source::client::__interfaces::*;
+            use wayland_client::backend as wayland_backend;
+            use wayland_client::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("protocols/ext-image-copy-capture-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_client_code!("protocols/ext-image-copy-capture-v1.xml");
+    }
+}
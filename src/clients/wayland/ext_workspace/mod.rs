@@ -0,0 +1,395 @@
+mod protocol;
+
+use self::protocol::client::{
+    ext_workspace_group_handle_v1::{self, ExtWorkspaceGroupHandleV1},
+    ext_workspace_handle_v1::{self, ExtWorkspaceHandleV1, State},
+    ext_workspace_manager_v1::{self, ExtWorkspaceManagerV1},
+};
+use super::{Environment, ToplevelHandle};
+use crate::clients::compositor::{Visibility, Workspace, WorkspaceId, WorkspaceUpdate};
+use crate::send;
+use smithay_client_toolkit::globals::GlobalData;
+use tracing::{debug, trace, warn};
+use wayland_client::globals::{BindError, GlobalList};
+use wayland_client::protocol::wl_output::WlOutput;
+use wayland_client::{event_created_child, Connection, Dispatch, Proxy, QueueHandle, WEnum};
+
+pub struct WorkspaceManagerState {
+    manager: ExtWorkspaceManagerV1,
+}
+
+impl WorkspaceManagerState {
+    pub fn bind<State>(globals: &GlobalList, qh: &QueueHandle<State>) -> Result<Self, BindError>
+    where
+        State: Dispatch<ExtWorkspaceManagerV1, GlobalData, State> + 'static,
+    {
+        let manager = globals.bind(qh, 1..=1, GlobalData)?;
+        debug!("Bound to ExtWorkspaceManagerV1 global");
+        Ok(Self { manager })
+    }
+}
+
+#[derive(Debug)]
+struct WorkspaceGroup {
+    handle: ExtWorkspaceGroupHandleV1,
+    outputs: Vec<WlOutput>,
+}
+
+#[derive(Debug)]
+struct ExtWorkspace {
+    handle: ExtWorkspaceHandleV1,
+    name: String,
+    state: State,
+    group: Option<ExtWorkspaceGroupHandleV1>,
+}
+
+impl ExtWorkspace {
+    /// Gets the ID for the workspace, from its handle's protocol ID.
+    ///
+    /// Names are not unique, as each output can have a workspace with the same name,
+    /// so the handle is used instead.
+    fn id(&self) -> WorkspaceId {
+        WorkspaceId(self.handle.id().protocol_id().to_string())
+    }
+}
+
+/// Pending protocol state,
+/// alongside the workspaces last sent to subscribers.
+#[derive(Debug, Default)]
+pub struct WorkspaceState {
+    groups: Vec<WorkspaceGroup>,
+    workspaces: Vec<ExtWorkspace>,
+    published: Vec<Workspace>,
+    /// Name of the output last known to have focus.
+    focused_output: Option<String>,
+}
+
+impl WorkspaceState {
+    pub fn workspaces(&self) -> &[Workspace] {
+        &self.published
+    }
+
+    fn workspace_mut(&mut self, handle: &ExtWorkspaceHandleV1) -> Option<&mut ExtWorkspace> {
+        self.workspaces.iter_mut().find(|ws| &ws.handle == handle)
+    }
+
+    fn group_mut(&mut self, handle: &ExtWorkspaceGroupHandleV1) -> Option<&mut WorkspaceGroup> {
+        self.groups.iter_mut().find(|group| &group.handle == handle)
+    }
+}
+
+impl Environment {
+    /// Requests the compositor activates the workspace with the given ID.
+    pub fn focus_workspace(&self, id: &WorkspaceId) {
+        let Some(manager) = &self.workspace_manager_state else {
+            return;
+        };
+
+        if let Some(workspace) = self
+            .workspace_state
+            .workspaces
+            .iter()
+            .find(|ws| &ws.id() == id)
+        {
+            workspace.handle.activate();
+            manager.manager.commit();
+        } else {
+            warn!("Unable to focus workspace '{}': not found", id.0);
+        }
+    }
+
+    /// Gets the name of the output the workspace is on.
+    fn workspace_monitor(&self, workspace: &ExtWorkspace) -> String {
+        workspace
+            .group
+            .as_ref()
+            .and_then(|handle| {
+                self.workspace_state
+                    .groups
+                    .iter()
+                    .find(|group| &group.handle == handle)
+            })
+            .and_then(|group| group.outputs.first())
+            .and_then(|output| self.output_state.info(output))
+            .and_then(|info| info.name)
+            .unwrap_or_default()
+    }
+
+    /// Gets the name of the output the focused toplevel is on, if any.
+    fn focused_toplevel_output(&self) -> Option<String> {
+        self.handles
+            .values()
+            .find(|handle| handle.info().map_or(false, |info| info.focused))
+            .and_then(ToplevelHandle::output)
+            .and_then(|output| self.output_state.info(&output))
+            .and_then(|info| info.name)
+    }
+
+    /// Works out which output has focus.
+    ///
+    /// The protocol has no concept of focus,
+    /// so a workspace which has just been activated is assumed to be focused,
+    /// as the user most likely switched to it.
+    /// Otherwise, the output with the focused toplevel is used.
+    fn update_focused_output(&mut self) {
+        let published = &self.workspace_state.published;
+
+        let activated = self
+            .workspace_state
+            .workspaces
+            .iter()
+            .filter(|ws| ws.state.contains(State::Active) && !ws.state.contains(State::Hidden))
+            .find(|ws| {
+                !published
+                    .iter()
+                    .any(|old| old.id == ws.id() && old.visibility.is_visible())
+            })
+            .map(|ws| self.workspace_monitor(ws));
+
+        // on startup every workspace is newly active,
+        // so the focused toplevel is a better guess
+        let focused_output = if published.is_empty() {
+            self.focused_toplevel_output().or(activated)
+        } else {
+            activated.or_else(|| self.focused_toplevel_output())
+        };
+
+        if focused_output.is_some() {
+            self.workspace_state.focused_output = focused_output;
+        }
+    }
+
+    /// Builds the current list of workspaces from the protocol state.
+    /// Hidden workspaces are omitted.
+    fn collect_workspaces(&self) -> Vec<Workspace> {
+        let focused_output = self.workspace_state.focused_output.as_deref();
+
+        self.workspace_state
+            .workspaces
+            .iter()
+            .filter(|ws| !ws.state.contains(State::Hidden))
+            .map(|ws| {
+                let monitor = self.workspace_monitor(ws);
+
+                // only the active workspace on the focused output is focused,
+                // with active workspaces on other outputs just visible.
+                let visibility = if !ws.state.contains(State::Active) {
+                    Visibility::Hidden
+                } else if focused_output == Some(monitor.as_str()) {
+                    Visibility::focused()
+                } else {
+                    Visibility::visible()
+                };
+
+                Workspace {
+                    id: ws.id(),
+                    name: ws.name.clone(),
+                    monitor,
+                    visibility,
+                    urgent: ws.state.contains(State::Urgent),
+                    // the protocol does not expose windows
+                    windows: None,
+                }
+            })
+            .collect()
+    }
+
+    /// Re-publishes the workspaces after a toplevel changes,
+    /// as focus may have moved to another output.
+    pub(super) fn refresh_workspace_focus(&mut self) {
+        // wait for the compositor to send the initial state
+        if self.workspace_state.published.is_empty() {
+            return;
+        }
+
+        self.publish_workspaces();
+    }
+
+    /// Diffs the current state against what was last published,
+    /// sending an update for each change.
+    fn publish_workspaces(&mut self) {
+        self.update_focused_output();
+
+        let current = self.collect_workspaces();
+        let previous = std::mem::take(&mut self.workspace_state.published);
+
+        let find_previous = |id: &WorkspaceId| previous.iter().find(|ws| &ws.id == id);
+
+        // whether another workspace became focused,
+        // in which case the change is sent as part of its focus event.
+        let gained_focus = current.iter().any(|ws| {
+            ws.visibility.is_focused()
+                && !find_previous(&ws.id).map_or(false, |old| old.visibility.is_focused())
+        });
+
+        for workspace in &current {
+            let update = match find_previous(&workspace.id) {
+                None => Some(WorkspaceUpdate::Add(workspace.clone())),
+                Some(old) if workspace.visibility.is_focused() && !old.visibility.is_focused() => {
+                    let old = previous
+                        .iter()
+                        .find(|ws| ws.id != workspace.id && ws.visibility.is_focused())
+                        .cloned();
+
+                    Some(WorkspaceUpdate::Focus {
+                        old,
+                        new: workspace.clone(),
+                    })
+                }
+                Some(old) if old.monitor != workspace.monitor => {
+                    Some(WorkspaceUpdate::Move(workspace.clone()))
+                }
+                Some(old) if old.urgent != workspace.urgent || old.name != workspace.name => {
+                    Some(WorkspaceUpdate::Update(workspace.clone()))
+                }
+                Some(old)
+                    if old.visibility.is_focused()
+                        && !workspace.visibility.is_focused()
+                        && !gained_focus =>
+                {
+                    Some(WorkspaceUpdate::Update(workspace.clone()))
+                }
+                Some(old) if old.visibility.is_visible() != workspace.visibility.is_visible() => {
+                    Some(WorkspaceUpdate::Update(workspace.clone()))
+                }
+                Some(_) => None,
+            };
+
+            if let Some(update) = update {
+                trace!("Sending workspace update: {update:?}");
+                send!(self.workspace_tx, update);
+            }
+        }
+
+        for workspace in &previous {
+            if !current.iter().any(|ws| ws.id == workspace.id) {
+                send!(
+                    self.workspace_tx,
+                    WorkspaceUpdate::Remove {
+                        id: workspace.id.clone(),
+                        name: workspace.name.clone(),
+                    }
+                );
+            }
+        }
+
+        self.workspace_state.published = current;
+    }
+}
+
+impl Dispatch<ExtWorkspaceManagerV1, GlobalData> for Environment {
+    event_created_child!(Environment, ExtWorkspaceManagerV1, [
+        0 => (ExtWorkspaceGroupHandleV1, ()),
+        1 => (ExtWorkspaceHandleV1, ())
+    ]);
+
+    fn event(
+        state: &mut Self,
+        _manager: &ExtWorkspaceManagerV1,
+        event: ext_workspace_manager_v1::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_workspace_manager_v1::Event::WorkspaceGroup { workspace_group } => {
+                trace!("Manager received new workspace group");
+                state.workspace_state.groups.push(WorkspaceGroup {
+                    handle: workspace_group,
+                    outputs: vec![],
+                });
+            }
+            ext_workspace_manager_v1::Event::Workspace { workspace } => {
+                trace!("Manager received new workspace");
+                state.workspace_state.workspaces.push(ExtWorkspace {
+                    handle: workspace,
+                    name: String::new(),
+                    state: State::empty(),
+                    group: None,
+                });
+            }
+            ext_workspace_manager_v1::Event::Done => state.publish_workspaces(),
+            ext_workspace_manager_v1::Event::Finished => {
+                warn!("Workspace manager is no longer valid. Workspaces will no longer update.");
+            }
+        }
+    }
+}
+
+impl Dispatch<ExtWorkspaceGroupHandleV1, ()> for Environment {
+    fn event(
+        state: &mut Self,
+        handle: &ExtWorkspaceGroupHandleV1,
+        event: ext_workspace_group_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let workspace_state = &mut state.workspace_state;
+
+        match event {
+            ext_workspace_group_handle_v1::Event::OutputEnter { output } => {
+                if let Some(group) = workspace_state.group_mut(handle) {
+                    group.outputs.push(output);
+                }
+            }
+            ext_workspace_group_handle_v1::Event::OutputLeave { output } => {
+                if let Some(group) = workspace_state.group_mut(handle) {
+                    group.outputs.retain(|o| o != &output);
+                }
+            }
+            ext_workspace_group_handle_v1::Event::WorkspaceEnter { workspace } => {
+                if let Some(workspace) = workspace_state.workspace_mut(&workspace) {
+                    workspace.group = Some(handle.clone());
+                }
+            }
+            ext_workspace_group_handle_v1::Event::WorkspaceLeave { workspace } => {
+                if let Some(workspace) = workspace_state.workspace_mut(&workspace) {
+                    if workspace.group.as_ref() == Some(handle) {
+                        workspace.group = None;
+                    }
+                }
+            }
+            ext_workspace_group_handle_v1::Event::Removed => {
+                workspace_state
+                    .groups
+                    .retain(|group| &group.handle != handle);
+                handle.destroy();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtWorkspaceHandleV1, ()> for Environment {
+    fn event(
+        state: &mut Self,
+        handle: &ExtWorkspaceHandleV1,
+        event: ext_workspace_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let workspace_state = &mut state.workspace_state;
+
+        match event {
+            ext_workspace_handle_v1::Event::Name { name } => {
+                if let Some(workspace) = workspace_state.workspace_mut(handle) {
+                    workspace.name = name;
+                }
+            }
+            ext_workspace_handle_v1::Event::State {
+                state: WEnum::Value(value),
+            } => {
+                if let Some(workspace) = workspace_state.workspace_mut(handle) {
+                    workspace.state = value;
+                }
+            }
+            ext_workspace_handle_v1::Event::Removed => {
+                workspace_state.workspaces.retain(|ws| &ws.handle != handle);
+                handle.destroy();
+            }
+            _ => {}
+        }
+    }
+}
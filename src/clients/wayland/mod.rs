@@ -1,11 +1,16 @@
 mod client;
 
+mod ext_image_capture;
 mod macros;
+mod preview;
 mod wl_output;
 mod wl_seat;
 mod wlr_foreign_toplevel;
+mod wlr_screencopy;
 
+use self::ext_image_capture::ToplevelCaptureState;
 use self::wlr_foreign_toplevel::manager::ToplevelManagerState;
+use self::wlr_screencopy::ScreencopyState;
 use crate::{arc_mut, delegate_foreign_toplevel_handle, delegate_foreign_toplevel_manager};
 use cfg_if::cfg_if;
 use lazy_static::lazy_static;
@@ -13,8 +18,9 @@ use smithay_client_toolkit::output::OutputState;
 use smithay_client_toolkit::reexports::calloop::LoopHandle;
 use smithay_client_toolkit::registry::{ProvidesRegistryState, RegistryState};
 use smithay_client_toolkit::seat::SeatState;
+use smithay_client_toolkit::shm::{Shm, ShmHandler};
 use smithay_client_toolkit::{
-    delegate_output, delegate_registry, delegate_seat, registry_handlers,
+    delegate_output, delegate_registry, delegate_seat, delegate_shm, registry_handlers,
 };
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
@@ -22,7 +28,8 @@ use tokio::sync::broadcast;
 use wayland_client::protocol::wl_seat::WlSeat;
 
 pub use self::client::WaylandClient;
-pub use self::wlr_foreign_toplevel::{ToplevelEvent, ToplevelHandle, ToplevelInfo};
+pub use self::preview::Preview;
+pub use self::wlr_foreign_toplevel::{ToplevelAction, ToplevelEvent, ToplevelHandle, ToplevelInfo};
 
 cfg_if! {
     if #[cfg(feature = "clipboard")] {
@@ -43,13 +50,33 @@ cfg_if! {
     }
 }
 
+cfg_if! {
+    if #[cfg(feature = "workspaces+ext")] {
+        mod ext_workspace;
+
+        use self::ext_workspace::{WorkspaceManagerState, WorkspaceState};
+        use crate::clients::compositor::WorkspaceUpdate;
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "idle_inhibitor")] {
+        pub mod wp_idle_inhibit;
+    }
+}
+
 pub struct Environment {
     pub registry_state: RegistryState,
     pub output_state: OutputState,
     pub seat_state: SeatState,
+    pub shm_state: Shm,
     pub foreign_toplevel_manager_state: ToplevelManagerState,
+    pub toplevel_capture_state: Option<ToplevelCaptureState>,
+    pub screencopy_state: Option<ScreencopyState>,
     #[cfg(feature = "clipboard")]
     pub data_control_device_manager_state: DataControlDeviceManagerState,
+    #[cfg(feature = "workspaces+ext")]
+    pub workspace_manager_state: Option<WorkspaceManagerState>,
     pub loop_handle: LoopHandle<'static, Self>,
 
     pub seats: Vec<WlSeat>,
@@ -60,6 +87,8 @@ pub struct Environment {
     pub selection_offers: Vec<SelectionOfferItem>,
     #[cfg(feature = "clipboard")]
     pub copy_paste_sources: Vec<CopyPasteSource>,
+    #[cfg(feature = "workspaces+ext")]
+    pub workspace_state: WorkspaceState,
 
     pub handles: HashMap<usize, ToplevelHandle>,
     #[cfg(feature = "clipboard")]
@@ -68,12 +97,15 @@ pub struct Environment {
     toplevel_tx: broadcast::Sender<ToplevelEvent>,
     #[cfg(feature = "clipboard")]
     clipboard_tx: broadcast::Sender<Arc<ClipboardItem>>,
+    #[cfg(feature = "workspaces+ext")]
+    workspace_tx: broadcast::Sender<WorkspaceUpdate>,
 }
 
 // Now we need to say we are delegating the responsibility of output related events for our application data
 // type to the requisite delegate.
 delegate_output!(Environment);
 delegate_seat!(Environment);
+delegate_shm!(Environment);
 
 delegate_foreign_toplevel_manager!(Environment);
 delegate_foreign_toplevel_handle!(Environment);
@@ -97,6 +129,12 @@ delegate_registry!(Environment);
 //
 // We also need to indicate which delegates will get told about globals being created. We specify
 // the types of the delegates inside the array.
+impl ShmHandler for Environment {
+    fn shm_state(&mut self) -> &mut Shm {
+        &mut self.shm_state
+    }
+}
+
 impl ProvidesRegistryState for Environment {
     fn registry(&mut self) -> &mut RegistryState {
         &mut self.registry_state
@@ -0,0 +1,183 @@
+use super::Environment;
+use std::fmt::{Debug, Formatter};
+use tokio::sync::oneshot;
+use wayland_client::protocol::wl_buffer::{self, WlBuffer};
+use wayland_client::protocol::wl_shm::Format;
+use wayland_client::{Connection, Dispatch, QueueHandle};
+
+/// A downscaled capture of a toplevel.
+///
+/// Pixels are stored as 8-bit RGBA,
+/// with a row stride of `4 * width`.
+#[derive(Clone)]
+pub struct Preview {
+    pub width: i32,
+    pub height: i32,
+    pub data: Vec<u8>,
+}
+
+impl Debug for Preview {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Preview")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Layout of a shared memory buffer a frame is captured into.
+#[derive(Debug, Clone, Copy)]
+pub struct BufferInfo {
+    pub format: Format,
+    pub width: i32,
+    pub height: i32,
+    pub stride: i32,
+}
+
+impl BufferInfo {
+    /// Gets the byte offsets of the red, green and blue channels,
+    /// and the alpha channel if the format has one.
+    ///
+    /// Formats are little-endian, so are stored in reverse order.
+    pub const fn channels(format: Format) -> Option<([usize; 3], Option<usize>)> {
+        match format {
+            Format::Argb8888 => Some(([2, 1, 0], Some(3))),
+            Format::Xrgb8888 => Some(([2, 1, 0], None)),
+            Format::Abgr8888 => Some(([0, 1, 2], Some(3))),
+            Format::Xbgr8888 => Some(([0, 1, 2], None)),
+            _ => None,
+        }
+    }
+
+    /// Downscales a captured frame to fit within `max_width`,
+    /// converting it to RGBA.
+    ///
+    /// `y_invert` flips the frame vertically.
+    pub fn preview(self, data: &[u8], max_width: i32, y_invert: bool) -> Option<Preview> {
+        let ([r, g, b], a) = Self::channels(self.format)?;
+
+        let scale = (f64::from(max_width) / f64::from(self.width)).min(1.0);
+        let width = ((f64::from(self.width) * scale) as i32).max(1);
+        let height = ((f64::from(self.height) * scale) as i32).max(1);
+
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+
+        for y in 0..height {
+            let mut src_y = (y * self.height / height) as usize;
+            if y_invert {
+                src_y = self.height as usize - 1 - src_y;
+            }
+
+            let row = src_y * self.stride as usize;
+
+            for x in 0..width {
+                let offset = row + (x * self.width / width) as usize * 4;
+                let pixel = data.get(offset..offset + 4)?;
+
+                pixels.extend_from_slice(&[
+                    pixel[r],
+                    pixel[g],
+                    pixel[b],
+                    a.map_or(u8::MAX, |a| pixel[a]),
+                ]);
+            }
+        }
+
+        Some(Preview {
+            width,
+            height,
+            data: pixels,
+        })
+    }
+}
+
+impl Environment {
+    /// Captures the toplevel,
+    /// sending a downscaled preview once the frame is ready.
+    ///
+    /// The toplevel itself is captured using `ext-image-copy-capture-v1` where supported,
+    /// which works regardless of whether it is visible.
+    /// Otherwise, the output it is on is captured using `wlr-screencopy-unstable-v1`,
+    /// which is only done while the toplevel is focused.
+    ///
+    /// `None` is sent if the capture cannot be made.
+    pub fn capture_preview(
+        &mut self,
+        toplevel_id: usize,
+        max_width: i32,
+        tx: oneshot::Sender<Option<Preview>>,
+        qh: &QueueHandle<Self>,
+    ) {
+        match self.find_capture_toplevel(toplevel_id) {
+            Some(handle) => self.capture_toplevel(&handle, toplevel_id, max_width, tx, qh),
+            None => self.capture_output(toplevel_id, max_width, tx, qh),
+        }
+    }
+}
+
+impl Dispatch<WlBuffer, ()> for Environment {
+    fn event(
+        _state: &mut Self,
+        _buffer: &WlBuffer,
+        _event: wl_buffer::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // buffers are destroyed once the frame is ready,
+        // so release events are irrelevant
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_converts_to_rgba() {
+        let info = BufferInfo {
+            format: Format::Xrgb8888,
+            width: 2,
+            height: 1,
+            stride: 8,
+        };
+
+        // BGRX, as the format is little-endian
+        let data = [1, 2, 3, 0, 4, 5, 6, 0];
+        let preview = info.preview(&data, 10, false).expect("format is supported");
+
+        assert_eq!((preview.width, preview.height), (2, 1));
+        assert_eq!(preview.data, [3, 2, 1, 255, 6, 5, 4, 255]);
+    }
+
+    #[test]
+    fn test_preview_downscales_and_inverts() {
+        let info = BufferInfo {
+            format: Format::Abgr8888,
+            width: 2,
+            height: 2,
+            stride: 8,
+        };
+
+        let data = [
+            1, 1, 1, 1, 2, 2, 2, 2, // top row
+            3, 3, 3, 3, 4, 4, 4, 4, // bottom row
+        ];
+        let preview = info.preview(&data, 1, true).expect("format is supported");
+
+        assert_eq!((preview.width, preview.height), (1, 1));
+        assert_eq!(preview.data, [3, 3, 3, 3]);
+    }
+
+    #[test]
+    fn test_preview_unsupported_format() {
+        let info = BufferInfo {
+            format: Format::Rgb565,
+            width: 1,
+            height: 1,
+            stride: 2,
+        };
+
+        assert!(info.preview(&[0, 0], 10, false).is_none());
+    }
+}
@@ -30,10 +30,66 @@ impl ToplevelHandle {
         data.info()
     }
 
+    /// Gets the output the toplevel is currently shown on, if any.
+    pub fn output(&self) -> Option<WlOutput> {
+        let data = self.handle.data::<ToplevelHandleData>()?;
+        lock!(data.inner).output.clone()
+    }
+
+    /// Gets the version of `wlr-foreign-toplevel-management` the handle was bound with.
+    /// Fullscreen requests require v2.
+    pub fn version(&self) -> u32 {
+        self.handle.version()
+    }
+
     pub fn focus(&self, seat: &WlSeat) {
         trace!("Activating handle");
         self.handle.activate(seat);
     }
+
+    /// Requests the toplevel is closed.
+    /// The client may ignore this, for example to show a save prompt.
+    pub fn close(&self) {
+        trace!("Closing handle");
+        self.handle.close();
+    }
+
+    pub fn set_minimized(&self, minimized: bool) {
+        trace!("Setting handle minimized: {minimized}");
+        if minimized {
+            self.handle.set_minimized();
+        } else {
+            self.handle.unset_minimized();
+        }
+    }
+
+    pub fn set_maximized(&self, maximized: bool) {
+        trace!("Setting handle maximized: {maximized}");
+        if maximized {
+            self.handle.set_maximized();
+        } else {
+            self.handle.unset_maximized();
+        }
+    }
+
+    /// Sets whether the toplevel is fullscreen.
+    ///
+    /// The `output` is a hint for which output to fullscreen on,
+    /// and is ignored when leaving fullscreen.
+    /// Requires v2 of the protocol; earlier versions ignore this.
+    pub fn set_fullscreen(&self, fullscreen: bool, output: Option<&WlOutput>) {
+        trace!("Setting handle fullscreen: {fullscreen}");
+
+        if self.handle.version() < 2 {
+            return;
+        }
+
+        if fullscreen {
+            self.handle.set_fullscreen(output);
+        } else {
+            self.handle.unset_fullscreen();
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -62,6 +118,8 @@ pub struct ToplevelInfo {
     pub app_id: String,
     pub title: String,
     pub fullscreen: bool,
+    pub maximized: bool,
+    pub minimized: bool,
     pub focused: bool,
 }
 
@@ -72,6 +130,8 @@ impl Default for ToplevelInfo {
             app_id: String::new(),
             title: String::new(),
             fullscreen: false,
+            maximized: false,
+            minimized: false,
             focused: false,
         }
     }
@@ -108,6 +168,8 @@ where
         conn: &Connection,
         qh: &QueueHandle<D>,
     ) {
+        const STATE_MAXIMIZED: u32 = 0;
+        const STATE_MINIMIZED: u32 = 1;
         const STATE_ACTIVE: u32 = 2;
         const STATE_FULLSCREEN: u32 = 3;
 
@@ -133,8 +195,11 @@ where
                     })
                     .collect::<HashSet<_>>();
 
-                lock!(data.inner).pending_info.focused = state.contains(&STATE_ACTIVE);
-                lock!(data.inner).pending_info.fullscreen = state.contains(&STATE_FULLSCREEN);
+                let mut inner = lock!(data.inner);
+                inner.pending_info.maximized = state.contains(&STATE_MAXIMIZED);
+                inner.pending_info.minimized = state.contains(&STATE_MINIMIZED);
+                inner.pending_info.focused = state.contains(&STATE_ACTIVE);
+                inner.pending_info.fullscreen = state.contains(&STATE_FULLSCREEN);
             }
             Event::OutputEnter { output } => lock!(data.inner).output = Some(output),
             Event::OutputLeave { output: _ } => lock!(data.inner).output = None,
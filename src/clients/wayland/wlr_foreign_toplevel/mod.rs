@@ -17,6 +17,58 @@ pub enum ToplevelEvent {
     Remove(ToplevelHandle),
 }
 
+/// A window management action to perform on a toplevel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToplevelAction {
+    Close,
+    ToggleMinimized,
+    ToggleMaximized,
+    ToggleFullscreen,
+    /// Makes the toplevel fullscreen on the output with the given name.
+    FullscreenOnOutput(String),
+}
+
+impl Environment {
+    /// Performs `action` on the toplevel with the given ID,
+    /// resolving toggles against its current state.
+    pub fn toplevel_action(&self, toplevel_id: usize, action: ToplevelAction) {
+        let Some(handle) = self.handles.get(&toplevel_id) else {
+            error!("Unable to perform {action:?}: toplevel {toplevel_id} does not exist");
+            return;
+        };
+
+        let Some(info) = handle.info() else {
+            error!("Handle is missing information!");
+            return;
+        };
+
+        debug!("Performing {action:?} on toplevel {toplevel_id}");
+
+        match action {
+            ToplevelAction::Close => handle.close(),
+            ToplevelAction::ToggleMinimized => handle.set_minimized(!info.minimized),
+            ToplevelAction::ToggleMaximized => handle.set_maximized(!info.maximized),
+            ToplevelAction::ToggleFullscreen => handle.set_fullscreen(!info.fullscreen, None),
+            ToplevelAction::FullscreenOnOutput(name) => {
+                let output = self.output_state.outputs().find(|output| {
+                    self.output_state
+                        .info(output)
+                        .and_then(|info| info.name)
+                        .as_ref()
+                        == Some(&name)
+                });
+
+                let Some(output) = output else {
+                    error!("Unable to fullscreen toplevel: output '{name}' does not exist");
+                    return;
+                };
+
+                handle.set_fullscreen(true, Some(&output));
+            }
+        }
+    }
+}
+
 impl ToplevelManagerHandler for Environment {
     fn toplevel(
         &mut self,
@@ -57,6 +109,9 @@ impl ToplevelHandleHandler for Environment {
                 trace!("Updating handle: {info:?}");
                 self.handles.insert(info.id, handle.clone());
                 send!(self.toplevel_tx, ToplevelEvent::Update(handle));
+
+                #[cfg(feature = "workspaces+ext")]
+                self.refresh_workspace_focus();
             }
             None => {
                 error!("Handle is missing information!");
@@ -0,0 +1,229 @@
+use super::preview::{BufferInfo, Preview};
+use super::Environment;
+use crate::lock;
+use smithay_client_toolkit::globals::GlobalData;
+use smithay_client_toolkit::shm::raw::RawPool;
+use smithay_client_toolkit::shm::Shm;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+use tracing::{debug, error, trace};
+use wayland_client::globals::{BindError, GlobalList};
+use wayland_client::protocol::wl_buffer::WlBuffer;
+use wayland_client::{Connection, Dispatch, Proxy, QueueHandle, WEnum};
+use wayland_protocols_wlr::screencopy::v1::client::{
+    zwlr_screencopy_frame_v1::{self, Flags, ZwlrScreencopyFrameV1},
+    zwlr_screencopy_manager_v1::{self, ZwlrScreencopyManagerV1},
+};
+
+/// State for capturing images of whole outputs.
+///
+/// This is used as a fallback for toplevel previews
+/// where `ext-image-copy-capture-v1` is unavailable.
+pub struct ScreencopyState {
+    manager: ZwlrScreencopyManagerV1,
+}
+
+impl ScreencopyState {
+    pub fn bind<State>(globals: &GlobalList, qh: &QueueHandle<State>) -> Result<Self, BindError>
+    where
+        State: Dispatch<ZwlrScreencopyManagerV1, GlobalData> + 'static,
+    {
+        let manager = globals.bind(qh, 1..=3, GlobalData)?;
+        debug!("Bound to ZwlrScreencopyManagerV1 global");
+
+        Ok(Self { manager })
+    }
+}
+
+pub struct FrameData {
+    inner: Mutex<FrameDataInner>,
+}
+
+struct FrameDataInner {
+    max_width: i32,
+    tx: Option<oneshot::Sender<Option<Preview>>>,
+    buffer_info: Option<BufferInfo>,
+    y_invert: bool,
+    pool: Option<RawPool>,
+    buffer: Option<WlBuffer>,
+}
+
+impl FrameData {
+    fn new(max_width: i32, tx: oneshot::Sender<Option<Preview>>) -> Self {
+        Self {
+            inner: Mutex::new(FrameDataInner {
+                max_width,
+                tx: Some(tx),
+                buffer_info: None,
+                y_invert: false,
+                pool: None,
+                buffer: None,
+            }),
+        }
+    }
+}
+
+impl FrameDataInner {
+    /// Creates a shared memory buffer matching the chosen format
+    /// and requests the frame is copied into it.
+    fn copy(&mut self, frame: &ZwlrScreencopyFrameV1, shm: &Shm, qh: &QueueHandle<Environment>) {
+        let Some(info) = self.buffer_info else {
+            error!("Compositor did not offer a supported screencopy buffer format");
+            self.finish(frame, None);
+            return;
+        };
+
+        let mut pool = match RawPool::new((info.stride * info.height) as usize, shm) {
+            Ok(pool) => pool,
+            Err(err) => {
+                error!("Failed to create screencopy buffer: {err:?}");
+                self.finish(frame, None);
+                return;
+            }
+        };
+
+        let buffer =
+            pool.create_buffer(0, info.width, info.height, info.stride, info.format, (), qh);
+
+        frame.copy(&buffer);
+
+        self.pool = Some(pool);
+        self.buffer = Some(buffer);
+    }
+
+    /// Downscales the copied frame to fit within `max_width`,
+    /// converting it to RGBA.
+    fn preview(&mut self) -> Option<Preview> {
+        let info = self.buffer_info?;
+        let data = self.pool.as_mut()?.mmap();
+
+        info.preview(data, self.max_width, self.y_invert)
+    }
+
+    /// Sends the result to the requester
+    /// and releases the frame resources.
+    fn finish(&mut self, frame: &ZwlrScreencopyFrameV1, preview: Option<Preview>) {
+        if let Some(tx) = self.tx.take() {
+            // the requester may have given up waiting
+            let _ = tx.send(preview);
+        }
+
+        if let Some(buffer) = self.buffer.take() {
+            buffer.destroy();
+        }
+
+        self.pool = None;
+        frame.destroy();
+    }
+}
+
+impl Environment {
+    /// Captures the output the toplevel is shown on using `wlr-screencopy-unstable-v1`.
+    ///
+    /// The output only shows the toplevel while it is focused,
+    /// so no capture is made otherwise.
+    pub(super) fn capture_output(
+        &mut self,
+        toplevel_id: usize,
+        max_width: i32,
+        tx: oneshot::Sender<Option<Preview>>,
+        qh: &QueueHandle<Self>,
+    ) {
+        let Some(state) = &self.screencopy_state else {
+            debug!(
+                "Unable to capture preview: compositor does not support wlr-screencopy-unstable-v1"
+            );
+            let _ = tx.send(None);
+            return;
+        };
+
+        let Some(handle) = self.handles.get(&toplevel_id) else {
+            let _ = tx.send(None);
+            return;
+        };
+
+        if !handle.info().is_some_and(|info| info.focused) {
+            debug!("Unable to capture preview: toplevel {toplevel_id} is not focused");
+            let _ = tx.send(None);
+            return;
+        }
+
+        let Some(output) = handle.output() else {
+            debug!("Unable to capture preview: toplevel {toplevel_id} is not on an output");
+            let _ = tx.send(None);
+            return;
+        };
+
+        trace!("Capturing output for toplevel {toplevel_id}");
+        state
+            .manager
+            .capture_output(0, &output, qh, FrameData::new(max_width, tx));
+    }
+}
+
+impl Dispatch<ZwlrScreencopyManagerV1, GlobalData> for Environment {
+    fn event(
+        _state: &mut Self,
+        _manager: &ZwlrScreencopyManagerV1,
+        _event: zwlr_screencopy_manager_v1::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // no events
+    }
+}
+
+impl Dispatch<ZwlrScreencopyFrameV1, FrameData> for Environment {
+    fn event(
+        state: &mut Self,
+        frame: &ZwlrScreencopyFrameV1,
+        event: zwlr_screencopy_frame_v1::Event,
+        data: &FrameData,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        use zwlr_screencopy_frame_v1::Event;
+
+        let mut inner = lock!(data.inner);
+
+        match event {
+            Event::Buffer {
+                format: WEnum::Value(format),
+                width,
+                height,
+                stride,
+            } => {
+                let info = BufferInfo {
+                    format,
+                    width: width as i32,
+                    height: height as i32,
+                    stride: stride as i32,
+                };
+
+                // several formats may be offered, so take the first we can read
+                if inner.buffer_info.is_none() && BufferInfo::channels(format).is_some() {
+                    inner.buffer_info = Some(info);
+                }
+
+                // `buffer_done` is only sent from v3
+                if frame.version() < 3 {
+                    inner.copy(frame, &state.shm_state, qh);
+                }
+            }
+            Event::BufferDone => inner.copy(frame, &state.shm_state, qh),
+            Event::Flags {
+                flags: WEnum::Value(flags),
+            } => inner.y_invert = flags.contains(Flags::YInvert),
+            Event::Ready { .. } => {
+                let preview = inner.preview();
+                inner.finish(frame, preview);
+            }
+            Event::Failed => {
+                error!("Compositor failed to copy frame");
+                inner.finish(frame, None);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use color_eyre::{Report, Result};
+use glib::translate::ToGlibPtr;
+use gtk::prelude::*;
+use gtk::{gdk, gio};
+use std::cell::RefCell;
+use std::ffi::c_void;
+use tokio::sync::broadcast;
+use tracing::{debug, error};
+use wayland_client::backend::{Backend, ObjectId};
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::wl_registry::{self, WlRegistry};
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::{Connection, Dispatch, EventQueue, Proxy, QueueHandle};
+use wayland_protocols::wp::idle_inhibit::zv1::client::{
+    zwp_idle_inhibit_manager_v1::{self, ZwpIdleInhibitManagerV1},
+    zwp_idle_inhibitor_v1::{self, ZwpIdleInhibitorV1},
+};
+
+extern "C" {
+    fn gdk_wayland_display_get_wl_display(display: *mut gdk::ffi::GdkDisplay) -> *mut c_void;
+    fn gdk_wayland_window_get_wl_surface(window: *mut gdk::ffi::GdkWindow) -> *mut c_void;
+}
+
+thread_local! {
+    static CLIENT: Option<RefCell<IdleInhibitClient>> = match IdleInhibitClient::new() {
+        Ok(client) => Some(RefCell::new(client)),
+        Err(err) => {
+            error!("{err:?}");
+            None
+        }
+    };
+}
+
+/// Runs `f` against the idle inhibit client.
+///
+/// Inhibitors must be attached to the bar surfaces,
+/// which belong to GTK's Wayland connection,
+/// so this must only be called from the GTK main thread.
+///
+/// Returns `None` if the compositor does not support `idle-inhibit-unstable-v1`.
+pub fn with_client<F, T>(f: F) -> Option<T>
+where
+    F: FnOnce(&mut IdleInhibitClient) -> T,
+{
+    CLIENT.with(|client| client.as_ref().map(|client| f(&mut client.borrow_mut())))
+}
+
+fn application() -> Option<gtk::Application> {
+    gio::Application::default().and_then(|app| app.downcast().ok())
+}
+
+/// Dispatch state for the inhibit protocol objects.
+/// None of them send events.
+struct State;
+
+pub struct IdleInhibitClient {
+    conn: Connection,
+    queue: EventQueue<State>,
+    manager: ZwpIdleInhibitManagerV1,
+
+    /// One inhibitor per mapped bar window.
+    inhibitors: Vec<ZwpIdleInhibitorV1>,
+    inhibited: bool,
+
+    tx: broadcast::Sender<bool>,
+    _rx: broadcast::Receiver<bool>,
+}
+
+impl IdleInhibitClient {
+    fn new() -> Result<Self> {
+        let display = gdk::Display::default()
+            .filter(|display| display.type_().name() == "GdkWaylandDisplay")
+            .ok_or_else(|| Report::msg("Idle inhibitor requires a GDK Wayland display"))?;
+
+        // SAFETY: the display is a `GdkWaylandDisplay`,
+        // which owns the `wl_display` for the rest of the program.
+        let backend = unsafe {
+            let ptr = gdk_wayland_display_get_wl_display(display.to_glib_none().0);
+            Backend::from_foreign_display(ptr.cast())
+        };
+
+        let conn = Connection::from_backend(backend);
+        let (globals, queue) = registry_queue_init::<State>(&conn)?;
+
+        let manager = globals
+            .bind(&queue.handle(), 1..=1, ())
+            .map_err(|_| Report::msg("Compositor does not support idle-inhibit-unstable-v1"))?;
+
+        debug!("Bound to ZwpIdleInhibitManagerV1 global");
+
+        let (tx, rx) = broadcast::channel(8);
+
+        Self::watch_windows();
+
+        Ok(Self {
+            conn,
+            queue,
+            manager,
+            inhibitors: vec![],
+            inhibited: false,
+            tx,
+            _rx: rx,
+        })
+    }
+
+    /// Re-attaches the inhibitors whenever a bar window is mapped,
+    /// as GDK creates a new surface each time.
+    fn watch_windows() {
+        let Some(application) = application() else {
+            return;
+        };
+
+        let on_window = |window: &gtk::Window| {
+            window.connect_map_event(|_, _| {
+                with_client(IdleInhibitClient::refresh);
+                Inhibit(false)
+            });
+        };
+
+        for window in application.windows() {
+            on_window(&window);
+        }
+
+        application.connect_window_added(move |_, window| on_window(window));
+    }
+
+    pub const fn is_inhibited(&self) -> bool {
+        self.inhibited
+    }
+
+    /// Creates a new receiver for inhibit state changes.
+    pub fn subscribe(&self) -> broadcast::Receiver<bool> {
+        self.tx.subscribe()
+    }
+
+    /// Creates or destroys the inhibitors,
+    /// notifying subscribers of the new state.
+    pub fn set_inhibited(&mut self, inhibit: bool) {
+        if inhibit == self.inhibited {
+            return;
+        }
+
+        debug!("Setting idle inhibited: {inhibit}");
+
+        self.inhibited = inhibit;
+        self.refresh();
+
+        // no receivers is fine
+        let _ = self.tx.send(inhibit);
+    }
+
+    /// Replaces the inhibitors with ones attached to the currently mapped bar windows.
+    fn refresh(&mut self) {
+        for inhibitor in self.inhibitors.drain(..) {
+            inhibitor.destroy();
+        }
+
+        if self.inhibited {
+            let qh = self.queue.handle();
+
+            self.inhibitors = Self::surfaces(&self.conn)
+                .iter()
+                .map(|surface| self.manager.create_inhibitor(surface, &qh, ()))
+                .collect();
+        }
+
+        if let Err(err) = self.conn.flush() {
+            error!(
+                "{:?}",
+                Report::new(err).wrap_err("Failed to flush idle inhibitors")
+            );
+        }
+
+        if let Err(err) = self.queue.dispatch_pending(&mut State) {
+            error!(
+                "{:?}",
+                Report::new(err).wrap_err("Failed to dispatch idle inhibit events")
+            );
+        }
+    }
+
+    /// Gets the `wl_surface` of each mapped bar window.
+    ///
+    /// GDK destroys the surface when a window is unmapped,
+    /// so these must be used immediately.
+    fn surfaces(conn: &Connection) -> Vec<WlSurface> {
+        let Some(application) = application() else {
+            return vec![];
+        };
+
+        application
+            .windows()
+            .iter()
+            .filter_map(|window| window.window())
+            .filter(|window| window.is_visible() && window.type_().name() == "GdkWaylandWindow")
+            .filter_map(|window| {
+                // SAFETY: the window is a mapped `GdkWaylandWindow`,
+                // so has a live surface on GTK's connection.
+                let id = unsafe {
+                    let ptr = gdk_wayland_window_get_wl_surface(window.to_glib_none().0);
+                    if ptr.is_null() {
+                        return None;
+                    }
+
+                    ObjectId::from_ptr(WlSurface::interface(), ptr.cast()).ok()?
+                };
+
+                WlSurface::from_id(conn, id).ok()
+            })
+            .collect()
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _state: &mut Self,
+        _registry: &WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // the manager is bound once on startup
+    }
+}
+
+impl Dispatch<ZwpIdleInhibitManagerV1, ()> for State {
+    fn event(
+        _state: &mut Self,
+        _manager: &ZwpIdleInhibitManagerV1,
+        _event: zwp_idle_inhibit_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // no events
+    }
+}
+
+impl Dispatch<ZwpIdleInhibitorV1, ()> for State {
+    fn event(
+        _state: &mut Self,
+        _inhibitor: &ZwpIdleInhibitorV1,
+        _event: zwp_idle_inhibitor_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // no events
+    }
+}
@@ -8,7 +8,7 @@ use tracing::trace;
 
 /// Common configuration options
 /// which can be set on every module.
-#[derive(Debug, Default, Deserialize, Clone)]
+#[derive(Debug, Default, Deserialize, Clone, PartialEq)]
 pub struct CommonConfig {
     pub class: Option<String>,
     pub name: Option<String>,
@@ -28,7 +28,7 @@ pub struct CommonConfig {
     pub tooltip: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum TransitionType {
     None,
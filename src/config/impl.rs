@@ -56,3 +56,20 @@ impl BarPosition {
         }
     }
 }
+
+impl Config {
+    /// Checks whether the bar-level options on both configs match.
+    ///
+    /// Module lists, per-monitor configs and ironvar defaults are ignored,
+    /// so this can be used to check if a bar can be updated in place
+    /// rather than needing to be recreated.
+    pub fn bar_options_eq(&self, other: &Self) -> bool {
+        self.position == other.position
+            && self.anchor_to_edges == other.anchor_to_edges
+            && self.height == other.height
+            && self.margin == other.margin
+            && self.popup_gap == other.popup_gap
+            && self.name == other.name
+            && self.icon_theme == other.icon_theme
+    }
+}
@@ -2,16 +2,28 @@ mod common;
 mod r#impl;
 mod truncate;
 
+#[cfg(feature = "bluetooth")]
+use crate::modules::bluetooth::BluetoothModule;
+#[cfg(feature = "brightness")]
+use crate::modules::brightness::BrightnessModule;
 #[cfg(feature = "clipboard")]
 use crate::modules::clipboard::ClipboardModule;
 #[cfg(feature = "clock")]
 use crate::modules::clock::ClockModule;
 use crate::modules::custom::CustomModule;
 use crate::modules::focused::FocusedModule;
+#[cfg(feature = "idle_inhibitor")]
+use crate::modules::idle_inhibitor::IdleInhibitorModule;
+#[cfg(feature = "keyboard")]
+use crate::modules::keyboard::KeyboardModule;
 use crate::modules::label::LabelModule;
 use crate::modules::launcher::LauncherModule;
 #[cfg(feature = "music")]
 use crate::modules::music::MusicModule;
+#[cfg(feature = "network")]
+use crate::modules::network::NetworkModule;
+#[cfg(feature = "notifications")]
+use crate::modules::notifications::NotificationsModule;
 use crate::modules::script::ScriptModule;
 #[cfg(feature = "sys_info")]
 use crate::modules::sysinfo::SysInfoModule;
@@ -19,6 +31,8 @@ use crate::modules::sysinfo::SysInfoModule;
 use crate::modules::tray::TrayModule;
 #[cfg(feature = "upower")]
 use crate::modules::upower::UpowerModule;
+#[cfg(feature = "volume")]
+use crate::modules::volume::VolumeModule;
 #[cfg(feature = "workspaces")]
 use crate::modules::workspaces::WorkspacesModule;
 use cfg_if::cfg_if;
@@ -28,19 +42,31 @@ use std::collections::HashMap;
 pub use self::common::{CommonConfig, TransitionType};
 pub use self::truncate::{EllipsizeMode, TruncateMode};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ModuleConfig {
+    #[cfg(feature = "bluetooth")]
+    Bluetooth(Box<BluetoothModule>),
+    #[cfg(feature = "brightness")]
+    Brightness(Box<BrightnessModule>),
     #[cfg(feature = "clipboard")]
     Clipboard(Box<ClipboardModule>),
     #[cfg(feature = "clock")]
     Clock(Box<ClockModule>),
     Custom(Box<CustomModule>),
     Focused(Box<FocusedModule>),
+    #[cfg(feature = "idle_inhibitor")]
+    IdleInhibitor(Box<IdleInhibitorModule>),
+    #[cfg(feature = "keyboard")]
+    Keyboard(Box<KeyboardModule>),
     Label(Box<LabelModule>),
     Launcher(Box<LauncherModule>),
     #[cfg(feature = "music")]
     Music(Box<MusicModule>),
+    #[cfg(feature = "network")]
+    Network(Box<NetworkModule>),
+    #[cfg(feature = "notifications")]
+    Notifications(Box<NotificationsModule>),
     Script(Box<ScriptModule>),
     #[cfg(feature = "sys_info")]
     SysInfo(Box<SysInfoModule>),
@@ -48,11 +74,13 @@ pub enum ModuleConfig {
     Tray(Box<TrayModule>),
     #[cfg(feature = "upower")]
     Upower(Box<UpowerModule>),
+    #[cfg(feature = "volume")]
+    Volume(Box<VolumeModule>),
     #[cfg(feature = "workspaces")]
     Workspaces(Box<WorkspacesModule>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum MonitorConfig {
     Single(Config),
     Multiple(Vec<Config>),
@@ -85,7 +113,7 @@ pub struct MarginConfig {
     pub top: i32,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct Config {
     #[serde(default)]
     pub position: BarPosition,
@@ -2,7 +2,7 @@ use gtk::pango::EllipsizeMode as GtkEllipsizeMode;
 use gtk::prelude::*;
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum EllipsizeMode {
     Start,
@@ -20,7 +20,7 @@ impl From<EllipsizeMode> for GtkEllipsizeMode {
     }
 }
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
 #[serde(untagged)]
 pub enum TruncateMode {
     Auto(EllipsizeMode),
@@ -1,13 +1,13 @@
 #[cfg(feature = "ipc")]
-use crate::ironvar::get_variable_manager;
+use crate::ironvar::{get_variable_manager, is_truthy};
+use crate::module_tasks::spawn;
 use crate::script::Script;
 use crate::send;
 use cfg_if::cfg_if;
 use glib::Continue;
 use serde::Deserialize;
-use tokio::spawn;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum DynamicBool {
     /// Either a script or variable, to be determined.
@@ -70,9 +70,3 @@ impl DynamicBool {
         });
     }
 }
-
-/// Check if a string ironvar is 'truthy'
-#[cfg(feature = "ipc")]
-fn is_truthy(string: &str) -> bool {
-    !(string.is_empty() || string == "0" || string == "false")
-}
@@ -1,9 +1,9 @@
 #[cfg(feature = "ipc")]
 use crate::ironvar::get_variable_manager;
+use crate::module_tasks::spawn;
 use crate::script::{OutputStream, Script};
 use crate::{arc_mut, lock, send};
 use gtk::prelude::*;
-use tokio::spawn;
 
 /// A segment of a dynamic string,
 /// containing either a static string
@@ -1,3 +1,4 @@
+use crate::bar::Bar;
 use crate::popup::Popup;
 use std::cell::{RefCell, RefMut};
 use std::collections::HashMap;
@@ -9,16 +10,22 @@ use std::rc::Rc;
 /// that is not otherwise accessible should be placed on here.
 #[derive(Debug)]
 pub struct GlobalState {
+    bars: Vec<Bar>,
     popups: HashMap<Box<str>, Rc<RefCell<Popup>>>,
 }
 
 impl GlobalState {
     pub(crate) fn new() -> Self {
         Self {
+            bars: vec![],
             popups: HashMap::new(),
         }
     }
 
+    pub fn bars_mut(&mut self) -> &mut Vec<Bar> {
+        &mut self.bars
+    }
+
     pub fn popups(&self) -> &HashMap<Box<str>, Rc<RefCell<Popup>>> {
         &self.popups
     }
@@ -1,20 +1,15 @@
 use super::Ipc;
-use crate::ipc::{Command, Response};
+use crate::ipc::{Command, Event, Response};
 use color_eyre::Result;
 use color_eyre::{Help, Report};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::net::UnixStream;
 
 impl Ipc {
     /// Sends a command to the IPC server.
     /// The server response is returned.
     pub async fn send(&self, command: Command) -> Result<Response> {
-        let mut stream = match UnixStream::connect(&self.path).await {
-            Ok(stream) => Ok(stream),
-            Err(err) => Err(Report::new(err)
-                .wrap_err("Failed to connect to Ironbar IPC server")
-                .suggestion("Is Ironbar running?")),
-        }?;
+        let mut stream = self.connect().await?;
 
         let write_buffer = serde_json::to_vec(&command)?;
         stream.write_all(&write_buffer).await?;
@@ -25,4 +20,35 @@ impl Ipc {
         let response = serde_json::from_slice(&read_buffer[..bytes])?;
         Ok(response)
     }
+
+    /// Subscribes to events from the IPC server,
+    /// calling `f` for each event received.
+    ///
+    /// This runs until the server closes the connection.
+    pub async fn subscribe<F>(&self, mut f: F) -> Result<()>
+    where
+        F: FnMut(Event),
+    {
+        let mut stream = self.connect().await?;
+
+        let write_buffer = serde_json::to_vec(&Command::Subscribe)?;
+        stream.write_all(&write_buffer).await?;
+
+        let mut lines = BufReader::new(stream).lines();
+        while let Some(line) = lines.next_line().await? {
+            let event = serde_json::from_str(&line)?;
+            f(event);
+        }
+
+        Ok(())
+    }
+
+    async fn connect(&self) -> Result<UnixStream> {
+        match UnixStream::connect(&self.path).await {
+            Ok(stream) => Ok(stream),
+            Err(err) => Err(Report::new(err)
+                .wrap_err("Failed to connect to Ironbar IPC server")
+                .suggestion("Is Ironbar running?")),
+        }
+    }
 }
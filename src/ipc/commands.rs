@@ -76,4 +76,55 @@ pub enum Command {
         /// The name of the monitor the bar is located on.
         bar_name: String,
     },
+
+    /// Control the countdown timer and stopwatch on clock modules.
+    #[cfg(feature = "clock")]
+    Timer {
+        /// The action to perform.
+        action: TimerAction,
+        /// Countdown duration when starting, such as `90s`, `5m` or `1h30m`.
+        /// Starts a stopwatch if omitted.
+        duration: Option<String>,
+    },
+
+    /// Control the Wayland idle inhibitor,
+    /// which prevents the screen from idling while active.
+    #[cfg(feature = "idle_inhibitor")]
+    IdleInhibit {
+        /// The action to perform.
+        action: IdleInhibitAction,
+    },
+
+    /// Subscribe to `ironvar` changes, popups opening and closing,
+    /// and bars being shown and hidden.
+    /// Events are streamed as newline-delimited JSON until the connection is closed.
+    Subscribe,
+}
+
+#[cfg(feature = "clock")]
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimerAction {
+    /// Start a new countdown or stopwatch, replacing any existing one.
+    Start,
+    /// Pause the running timer.
+    Pause,
+    /// Resume the paused timer.
+    Resume,
+    /// Stop and clear the timer.
+    Reset,
+}
+
+#[cfg(feature = "idle_inhibitor")]
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleInhibitAction {
+    /// Inhibit idle.
+    On,
+    /// Stop inhibiting idle.
+    Off,
+    /// Switch between on and off.
+    Toggle,
+    /// Get whether idle is inhibited.
+    Status,
 }
@@ -0,0 +1,49 @@
+use crate::send;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+lazy_static! {
+    static ref EVENTS: EventBus = EventBus::new();
+}
+
+/// An event streamed to clients of the `subscribe` command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// An `ironvar` was set.
+    Variable { key: Box<str>, value: String },
+
+    /// A bar's popup was opened or closed.
+    Popup {
+        bar_name: String,
+        /// The name of the module the popup belongs to.
+        name: Option<String>,
+        open: bool,
+    },
+
+    /// A bar was shown or hidden.
+    BarVisibility { bar_name: String, visible: bool },
+}
+
+struct EventBus {
+    tx: broadcast::Sender<Event>,
+    _rx: broadcast::Receiver<Event>,
+}
+
+impl EventBus {
+    fn new() -> Self {
+        let (tx, rx) = broadcast::channel(64);
+        Self { tx, _rx: rx }
+    }
+}
+
+/// Broadcasts an event to all IPC subscribers.
+pub fn send_event(event: Event) {
+    send!(EVENTS.tx, event);
+}
+
+/// Subscribes to all events sent after this point.
+pub fn subscribe_events() -> broadcast::Receiver<Event> {
+    EVENTS.tx.subscribe()
+}
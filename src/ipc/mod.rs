@@ -1,5 +1,6 @@
 mod client;
 pub mod commands;
+pub mod events;
 pub mod responses;
 mod server;
 
@@ -10,6 +11,7 @@ use tracing::warn;
 
 use crate::GlobalState;
 pub use commands::Command;
+pub use events::{send_event, Event};
 pub use responses::Response;
 
 #[derive(Debug)]
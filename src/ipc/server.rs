@@ -10,11 +10,13 @@ use gtk::Application;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{UnixListener, UnixStream};
 use tokio::spawn;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::mpsc::{self, Receiver, Sender};
 use tracing::{debug, error, info, warn};
 
 use crate::bridge_channel::BridgeChannel;
-use crate::ipc::{Command, Response};
+use crate::ipc::events::subscribe_events;
+use crate::ipc::{Command, Event, Response};
 use crate::ironvar::get_variable_manager;
 use crate::modules::PopupButton;
 use crate::style::load_css;
@@ -81,21 +83,30 @@ impl Ipc {
     /// Takes an incoming connections,
     /// reads the command message, and sends the response.
     ///
-    /// The connection is closed once the response has been written.
+    /// The connection is closed once the response has been written,
+    /// except for subscriptions, which are handed off to their own task.
     async fn handle_connection(
         mut stream: UnixStream,
         cmd_tx: &Sender<Command>,
         res_rx: &mut Receiver<Response>,
     ) -> Result<()> {
-        let (mut stream_read, mut stream_write) = stream.split();
-
         let mut read_buffer = vec![0; 1024];
-        let bytes = stream_read.read(&mut read_buffer).await?;
+        let bytes = stream.read(&mut read_buffer).await?;
 
         let command = serde_json::from_slice::<Command>(&read_buffer[..bytes])?;
 
         debug!("Received command: {command:?}");
 
+        if matches!(command, Command::Subscribe) {
+            spawn(async move {
+                if let Err(err) = Self::stream_events(stream).await {
+                    debug!("Closed IPC subscription: {err}");
+                }
+            });
+
+            return Ok(());
+        }
+
         send_async!(cmd_tx, command);
         let res = res_rx
             .recv()
@@ -103,12 +114,58 @@ impl Ipc {
             .unwrap_or(Response::Err { message: None });
         let res = serde_json::to_vec(&res)?;
 
-        stream_write.write_all(&res).await?;
-        stream_write.shutdown().await?;
+        stream.write_all(&res).await?;
+        stream.shutdown().await?;
+
+        Ok(())
+    }
+
+    /// Writes events to the stream as newline-delimited JSON,
+    /// starting with the current value of each `ironvar`.
+    ///
+    /// This runs until the client disconnects.
+    async fn stream_events(mut stream: UnixStream) -> Result<()> {
+        // subscribe before taking the snapshot so no changes are missed
+        let mut rx = subscribe_events();
+
+        let snapshot = {
+            let variable_manager = get_variable_manager();
+            let variable_manager = read_lock!(variable_manager);
+
+            variable_manager
+                .values()
+                .map(|(key, value)| Event::Variable {
+                    key: key.into(),
+                    value,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for event in snapshot {
+            Self::write_event(&mut stream, &event).await?;
+        }
+
+        loop {
+            match rx.recv().await {
+                Ok(event) => Self::write_event(&mut stream, &event).await?,
+                Err(RecvError::Lagged(count)) => {
+                    warn!("IPC subscriber lagged behind, skipped {count} events");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
 
         Ok(())
     }
 
+    async fn write_event(stream: &mut UnixStream, event: &Event) -> Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+
+        stream.write_all(&line).await?;
+        Ok(())
+    }
+
     /// Takes an input command, runs it and returns with the appropriate response.
     ///
     /// This runs on the main thread, allowing commands to interact with GTK.
@@ -123,15 +180,15 @@ impl Ipc {
                 Response::Ok
             }
             Command::Reload => {
-                info!("Closing existing bars");
-                let windows = application.windows();
-                for window in windows {
-                    window.close();
-                }
-
-                crate::load_interface(application, global_state);
+                info!("Reloading config");
 
-                Response::Ok
+                match crate::reload_interface(application, global_state) {
+                    Ok(()) => Response::Ok,
+                    Err(err) => {
+                        error!("{:?}", err);
+                        Response::error(&format!("{err}"))
+                    }
+                }
             }
             Command::Set { key, value } => {
                 let variable_manager = get_variable_manager();
@@ -222,7 +279,50 @@ impl Ipc {
                     Response::error("Invalid monitor name")
                 }
             }
+            #[cfg(feature = "clock")]
+            Command::Timer { action, duration } => {
+                use crate::ipc::commands::TimerAction;
+                use crate::modules::clock::{parse_duration, send_timer_command, TimerCommand};
+
+                let command = match (action, duration) {
+                    (TimerAction::Start, None) => TimerCommand::Start(None),
+                    (TimerAction::Start, Some(duration)) => match parse_duration(&duration) {
+                        Some(duration) => TimerCommand::Start(Some(duration)),
+                        None => return Response::error("Invalid duration"),
+                    },
+                    (TimerAction::Pause, _) => TimerCommand::Pause,
+                    (TimerAction::Resume, _) => TimerCommand::Resume,
+                    (TimerAction::Reset, _) => TimerCommand::Reset,
+                };
+
+                send_timer_command(command);
+                Response::Ok
+            }
+            #[cfg(feature = "idle_inhibitor")]
+            Command::IdleInhibit { action } => {
+                use crate::ipc::commands::IdleInhibitAction;
+                use crate::modules::idle_inhibitor;
+
+                let result = match action {
+                    IdleInhibitAction::On => idle_inhibitor::set_inhibited(true).map(|()| None),
+                    IdleInhibitAction::Off => idle_inhibitor::set_inhibited(false).map(|()| None),
+                    IdleInhibitAction::Toggle => idle_inhibitor::toggle().map(|_| None),
+                    IdleInhibitAction::Status => idle_inhibitor::is_inhibited().map(Some),
+                };
+
+                match result {
+                    Some(Some(inhibited)) => Response::OkValue {
+                        value: inhibited.to_string(),
+                    },
+                    Some(None) => Response::Ok,
+                    None => Response::error("Compositor does not support idle-inhibit-unstable-v1"),
+                }
+            }
             Command::Ping => Response::Ok,
+            // handled directly by the connection, as it never completes
+            Command::Subscribe => {
+                Response::error("Subscriptions must be made on their own connection")
+            }
             Command::SetVisible { bar_name, visible } => {
                 let windows = application.windows();
                 let found = windows
@@ -1,5 +1,6 @@
 #![doc = include_str!("../docs/Ironvars.md")]
 
+use crate::ipc::{send_event, Event};
 use crate::{arc_rw, send};
 use color_eyre::{Report, Result};
 use lazy_static::lazy_static;
@@ -31,6 +32,11 @@ impl VariableManager {
     /// creating it if it does not exist.
     pub fn set(&mut self, key: Box<str>, value: String) -> Result<()> {
         if Self::key_is_valid(&key) {
+            send_event(Event::Variable {
+                key: key.clone(),
+                value: value.clone(),
+            });
+
             if let Some(var) = self.variables.get_mut(&key) {
                 var.set(Some(value));
             } else {
@@ -50,6 +56,13 @@ impl VariableManager {
         self.variables.get(key).and_then(IronVar::get)
     }
 
+    /// Gets the current value of every `ironvar` which has one.
+    pub fn values(&self) -> impl Iterator<Item = (&str, String)> {
+        self.variables
+            .iter()
+            .filter_map(|(key, var)| var.get().map(|value| (key.as_ref(), value)))
+    }
+
     /// Subscribes to an `ironvar`, creating it if it does not exist.
     /// Any time the var is set, its value is sent on the channel.
     pub fn subscribe(&mut self, key: Box<str>) -> broadcast::Receiver<Option<String>> {
@@ -105,3 +118,8 @@ impl IronVar {
         rx
     }
 }
+
+/// Check if a string ironvar is 'truthy'
+pub fn is_truthy(string: &str) -> bool {
+    !(string.is_empty() || string == "0" || string == "false")
+}
@@ -3,22 +3,27 @@
 use std::cell::{Cell, RefCell};
 use std::env;
 use std::future::Future;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 use std::rc::Rc;
 use std::sync::mpsc;
+use std::time::Duration;
 
 use cfg_if::cfg_if;
 #[cfg(feature = "cli")]
 use clap::Parser;
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{Result, WrapErr};
 use color_eyre::Report;
 use dirs::config_dir;
-use gtk::gdk::Display;
+use glib::Continue;
+use gtk::gdk::{Display, Monitor};
 use gtk::prelude::*;
 use gtk::Application;
+use notify::{recommended_watcher, Event, EventKind, RecursiveMode, Watcher};
 use tokio::runtime::Handle;
+use tokio::spawn;
 use tokio::task::{block_in_place, spawn_blocking};
+use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 use universal_config::ConfigLoader;
 
@@ -48,6 +53,7 @@ mod ipc;
 mod ironvar;
 mod logging;
 mod macros;
+mod module_tasks;
 mod modules;
 mod popup;
 mod script;
@@ -57,6 +63,9 @@ mod unique_id;
 const GTK_APP_ID: &str = "dev.jstanger.ironbar";
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Time to wait for config file events to settle before reloading.
+const CONFIG_RELOAD_DEBOUNCE_MS: u64 = 250;
+
 #[tokio::main]
 async fn main() {
     let _guard = logging::install_logging();
@@ -77,6 +86,12 @@ async fn run_with_args(global_state: Rc<RefCell<GlobalState>>) {
     let args = cli::Args::parse();
 
     match args.command {
+        Some(ipc::Command::Subscribe) => {
+            let ipc = ipc::Ipc::new(global_state);
+            if let Err(err) = ipc.subscribe(|event| cli::handle_event(&event)).await {
+                error!("{err:?}");
+            }
+        }
         Some(command) => {
             let ipc = ipc::Ipc::new(global_state);
             match ipc.send(command).await {
@@ -113,6 +128,7 @@ fn start_ironbar(global_state: Rc<RefCell<GlobalState>>) {
         }
 
         load_interface(app, &global_state);
+        watch_config(app, &global_state);
 
         let style_path = env::var("IRONBAR_CSS").ok().map_or_else(
             || {
@@ -158,47 +174,225 @@ fn start_ironbar(global_state: Rc<RefCell<GlobalState>>) {
 
 /// Loads the Ironbar config and interface.
 pub fn load_interface(app: &Application, global_state: &Rc<RefCell<GlobalState>>) {
-    let display = Display::default().map_or_else(
+    let display = get_display();
+    let config = load_config();
+
+    if let Err(err) = create_bars(app, &display, &config, global_state) {
+        error!("{:?}", err);
+        exit(ExitCode::CreateBars as i32);
+    }
+
+    debug!("Created bars");
+}
+
+/// Reloads the Ironbar config and applies it to the running interface.
+///
+/// Bars are only recreated if their bar-level options have changed.
+/// Otherwise, only modules whose config has changed are recreated.
+///
+/// If the config cannot be loaded, the running config is kept
+/// and an error is returned.
+/// Bars which fail to be created are skipped and logged.
+pub fn reload_interface(app: &Application, global_state: &Rc<RefCell<GlobalState>>) -> Result<()> {
+    let display =
+        Display::default().ok_or_else(|| Report::msg("Failed to get default GTK display"))?;
+
+    let config = read_config().wrap_err("Failed to reload config, keeping the current config")?;
+    let outputs = get_bar_configs(&display, &config)?;
+
+    let mut old_bars = std::mem::take(global_state.borrow_mut().bars_mut());
+    let mut bars = vec![];
+
+    for (monitor, monitor_name, configs) in outputs {
+        let create = |index, config| {
+            create_bar(app, &monitor, &monitor_name, index, config, global_state)
+                .map_err(|err| {
+                    error!(
+                        "{:?}",
+                        err.wrap_err(format!("Failed to create bar on '{monitor_name}'"))
+                    );
+                })
+                .ok()
+        };
+
+        for (index, config) in configs.into_iter().enumerate() {
+            // bars are matched on their position in the monitor's list,
+            // so each bar is updated from its own config
+            let existing = old_bars
+                .iter()
+                .position(|bar| bar.monitor_name() == monitor_name && bar.index() == index)
+                .map(|position| old_bars.remove(position));
+
+            let bar = match existing {
+                Some(mut bar) => {
+                    if bar.update(app, config.clone()) {
+                        Some(bar)
+                    } else {
+                        info!("Recreating bar on '{}'", monitor_name);
+                        bar.close(global_state);
+                        create(index, config)
+                    }
+                }
+                None => {
+                    info!("Creating bar on '{}'", monitor_name);
+                    create(index, config)
+                }
+            };
+
+            bars.extend(bar);
+        }
+    }
+
+    for bar in old_bars {
+        bar.close(global_state);
+    }
+
+    global_state.borrow_mut().bars_mut().extend(bars);
+    debug!("Reloaded bars");
+
+    Ok(())
+}
+
+/// Gets the default GTK display,
+/// exiting if it is unavailable.
+fn get_display() -> Display {
+    Display::default().map_or_else(
         || {
             let report = Report::msg("Failed to get default GTK display");
             error!("{:?}", report);
             exit(ExitCode::GtkDisplay as i32)
         },
         |display| display,
-    );
+    )
+}
 
-    let mut config = env::var("IRONBAR_CONFIG")
+/// Loads the config from disk,
+/// falling back to the default config if it cannot be loaded.
+fn load_config() -> Config {
+    read_config().unwrap_or_else(|err| {
+        error!("{:?}", err);
+        warn!("Falling back to the default config");
+        info!("If this is your first time using Ironbar, you should create a config in ~/.config/ironbar/");
+        info!("More info here: https://github.com/JakeStanger/ironbar/wiki/configuration-guide");
+
+        Config::default()
+    })
+}
+
+/// Loads the config from disk.
+///
+/// Any ironvar defaults are applied in the process,
+/// to variables which do not already have a value.
+fn read_config() -> Result<Config> {
+    let mut config: Config = env::var("IRONBAR_CONFIG")
         .map_or_else(
             |_| ConfigLoader::new("ironbar").find_and_load(),
             ConfigLoader::load,
         )
-        .unwrap_or_else(|err| {
-            error!("Failed to load config: {}", err);
-            warn!("Falling back to the default config");
-            info!("If this is your first time using Ironbar, you should create a config in ~/.config/ironbar/");
-            info!("More info here: https://github.com/JakeStanger/ironbar/wiki/configuration-guide");
-
-            Config::default()
-        });
+        .map_err(|err| Report::msg(format!("Failed to load config: {err}")))?;
 
     debug!("Loaded config file");
 
     #[cfg(feature = "ipc")]
     if let Some(ironvars) = config.ironvar_defaults.take() {
         let variable_manager = ironvar::get_variable_manager();
+        let mut variable_manager = write_lock!(variable_manager);
+
         for (k, v) in ironvars {
-            if write_lock!(variable_manager).set(k.clone(), v).is_err() {
+            // values set over IPC are kept on reload
+            if variable_manager.get(&k).is_some() {
+                continue;
+            }
+
+            if variable_manager.set(k.clone(), v).is_err() {
                 warn!("Ignoring invalid ironvar: '{k}'");
             }
         }
     }
 
-    if let Err(err) = create_bars(app, &display, &config, global_state) {
-        error!("{:?}", err);
-        exit(ExitCode::CreateBars as i32);
-    }
+    Ok(config)
+}
 
-    debug!("Created bars");
+/// Installs a file watcher on the config,
+/// and reloads the interface when changes are detected.
+///
+/// If `IRONBAR_CONFIG` is set, that file is watched.
+/// Otherwise, any `config.*` file in the config directory is watched.
+///
+/// The parent directory is watched rather than the file itself,
+/// as many editors replace the file when saving.
+fn watch_config(app: &Application, global_state: &Rc<RefCell<GlobalState>>) {
+    let config_path = env::var("IRONBAR_CONFIG").ok().map(PathBuf::from);
+
+    let watch_dir = match &config_path {
+        Some(path) => path.parent().map(Path::to_path_buf),
+        None => config_dir().map(|dir| dir.join("ironbar")),
+    };
+
+    let Some(watch_dir) = watch_dir.filter(|dir| dir.exists()) else {
+        warn!("Failed to locate config dir, config will not be hot-reloaded");
+        return;
+    };
+
+    let (tx, rx) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+    spawn(async move {
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::channel(16);
+
+        let mut watcher = recommended_watcher(move |res: notify::Result<Event>| match res {
+            Ok(event)
+                if matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_))
+                    && event
+                        .paths
+                        .iter()
+                        .any(|path| is_config_file(path, config_path.as_deref())) =>
+            {
+                debug!("{event:?}");
+                // a full channel means a reload is already pending
+                event_tx.try_send(()).ok();
+            }
+            Err(e) => error!("Error occurred when watching config: {:?}", e),
+            _ => {}
+        })
+        .expect("Failed to create config file watcher");
+
+        watcher
+            .watch(&watch_dir, RecursiveMode::NonRecursive)
+            .expect("Failed to start config file watcher");
+        debug!("Installed config file watcher on '{}'", watch_dir.display());
+
+        // editors often write files in several steps,
+        // so wait for the events to settle before reloading
+        while event_rx.recv().await.is_some() {
+            sleep(Duration::from_millis(CONFIG_RELOAD_DEBOUNCE_MS)).await;
+            while event_rx.try_recv().is_ok() {}
+
+            send!(tx, ());
+        }
+
+        // avoid watcher from dropping
+        drop(watcher);
+    });
+
+    let app = app.clone();
+    let global_state = global_state.clone();
+    rx.attach(None, move |()| {
+        info!("Config changed, reloading");
+        if let Err(err) = reload_interface(&app, &global_state) {
+            error!("{:?}", err);
+        }
+        Continue(true)
+    });
+}
+
+/// Checks if the path is to an Ironbar config file.
+///
+/// If an explicit config path is provided, only that file matches.
+fn is_config_file(path: &Path, config_path: Option<&Path>) -> bool {
+    match config_path {
+        Some(config_path) => path.file_name() == config_path.file_name(),
+        None => path.file_stem().map_or(false, |stem| stem == "config"),
+    }
 }
 
 /// Creates each of the bars across each of the (configured) outputs.
@@ -208,6 +402,24 @@ fn create_bars(
     config: &Config,
     global_state: &Rc<RefCell<GlobalState>>,
 ) -> Result<()> {
+    let outputs = get_bar_configs(display, config)?;
+
+    for (monitor, monitor_name, configs) in outputs {
+        for (index, config) in configs.into_iter().enumerate() {
+            info!("Creating bar on '{}'", monitor_name);
+            let bar = create_bar(app, &monitor, &monitor_name, index, config, global_state)?;
+            global_state.borrow_mut().bars_mut().push(bar);
+        }
+    }
+
+    Ok(())
+}
+
+/// Gets the list of bar configs for each of the (configured) outputs.
+fn get_bar_configs(
+    display: &Display,
+    config: &Config,
+) -> Result<Vec<(Monitor, String, Vec<Config>)>> {
     let wl = wayland::get_client();
     let outputs = lock!(wl).get_outputs();
 
@@ -216,6 +428,8 @@ fn create_bars(
 
     let num_monitors = display.n_monitors();
 
+    let mut bar_configs = vec![];
+
     for i in 0..num_monitors {
         let monitor = display
             .monitor(i)
@@ -228,33 +442,19 @@ fn create_bars(
             continue;
         };
 
-        config.monitors.as_ref().map_or_else(
-            || {
-                info!("Creating bar on '{}'", monitor_name);
-                create_bar(app, &monitor, monitor_name, config.clone(), global_state)
+        let configs = match config.monitors.as_ref() {
+            None => vec![config.clone()],
+            Some(monitors) => match monitors.get(monitor_name) {
+                Some(MonitorConfig::Single(config)) => vec![config.clone()],
+                Some(MonitorConfig::Multiple(configs)) => configs.clone(),
+                None => vec![],
             },
-            |config| {
-                let config = config.get(monitor_name);
-                match &config {
-                    Some(MonitorConfig::Single(config)) => {
-                        info!("Creating bar on '{}'", monitor_name);
-                        create_bar(app, &monitor, monitor_name, config.clone(), global_state)
-                    }
-                    Some(MonitorConfig::Multiple(configs)) => {
-                        for config in configs {
-                            info!("Creating bar on '{}'", monitor_name);
-                            create_bar(app, &monitor, monitor_name, config.clone(), global_state)?;
-                        }
+        };
 
-                        Ok(())
-                    }
-                    _ => Ok(()),
-                }
-            },
-        )?;
+        bar_configs.push((monitor, monitor_name.clone(), configs));
     }
 
-    Ok(())
+    Ok(bar_configs)
 }
 
 /// Blocks on a `Future` until it resolves.
@@ -0,0 +1,79 @@
+use crate::lock;
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::task::{AbortHandle, JoinHandle};
+
+tokio::task_local! {
+    /// The tasks of the module which spawned the current task.
+    static TASKS: ModuleTasks;
+}
+
+thread_local! {
+    /// The tasks of the module currently being created on this thread.
+    static CREATING: RefCell<Option<ModuleTasks>> = const { RefCell::new(None) };
+}
+
+/// Tracks the background tasks belonging to a single module instance,
+/// so that they can be stopped when the module is removed.
+///
+/// Any task spawned using [`spawn`] while the module is being created,
+/// or from inside one of its tasks, is added to the module's tasks.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleTasks(Arc<Mutex<Vec<AbortHandle>>>);
+
+impl ModuleTasks {
+    /// Runs `f`, adding any tasks it spawns to this module.
+    pub fn enter<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        let previous = CREATING.with(|creating| creating.replace(Some(self.clone())));
+        let res = f();
+        CREATING.with(|creating| creating.replace(previous));
+
+        res
+    }
+
+    /// Aborts all of the module's tasks.
+    ///
+    /// Tasks are stopped at their next `.await`,
+    /// dropping anything they own such as child processes.
+    pub fn abort(&self) {
+        for handle in lock!(self.0).drain(..) {
+            handle.abort();
+        }
+    }
+
+    fn push(&self, handle: AbortHandle) {
+        let mut handles = lock!(self.0);
+
+        handles.retain(|handle| !handle.is_finished());
+        handles.push(handle);
+    }
+}
+
+/// Spawns a new asynchronous task,
+/// which belongs to the current module if there is one.
+///
+/// This should be used over `tokio::spawn` for any module tasks,
+/// but not for shared clients which outlive a module.
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let tasks = TASKS
+        .try_with(Clone::clone)
+        .ok()
+        .or_else(|| CREATING.with(|creating| creating.borrow().clone()));
+
+    match tasks {
+        Some(tasks) => {
+            let handle = tokio::spawn(TASKS.scope(tasks.clone(), future));
+            tasks.push(handle.abort_handle());
+            handle
+        }
+        None => tokio::spawn(future),
+    }
+}
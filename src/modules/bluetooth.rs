@@ -0,0 +1,375 @@
+use crate::clients::bluez::{self, BluetoothState, Device};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::module_tasks::spawn;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{send_async, try_send};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Button, Label, Orientation, Switch};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tracing::error;
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct BluetoothModule {
+    /// Format string to use for the widget button label.
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// Adapter state icons.
+    #[serde(default)]
+    icons: Icons,
+
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{icon}")
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct Icons {
+    /// Icon to show when the adapter is powered on.
+    #[serde(default = "default_icon_on")]
+    on: String,
+
+    /// Icon to show when the adapter is powered off or missing.
+    #[serde(default = "default_icon_off")]
+    off: String,
+
+    /// Icon to show when at least one device is connected.
+    #[serde(default = "default_icon_connected")]
+    connected: String,
+}
+
+impl Icons {
+    /// Gets the icon for the adapter state.
+    fn state_icon(&self, state: &BluetoothState) -> &str {
+        let powered = state
+            .adapter
+            .as_ref()
+            .map_or(false, |adapter| adapter.powered);
+
+        if !powered {
+            &self.off
+        } else if state.devices.iter().any(|device| device.connected) {
+            &self.connected
+        } else {
+            &self.on
+        }
+    }
+}
+
+impl Default for Icons {
+    fn default() -> Self {
+        Self {
+            on: default_icon_on(),
+            off: default_icon_off(),
+            connected: default_icon_connected(),
+        }
+    }
+}
+
+fn default_icon_on() -> String {
+    String::from("󰂯")
+}
+
+fn default_icon_off() -> String {
+    String::from("󰂲")
+}
+
+fn default_icon_connected() -> String {
+    String::from("󰂱")
+}
+
+#[derive(Debug, Clone)]
+pub enum BluetoothCommand {
+    SetPowered(bool),
+    SetDiscoverable(bool),
+    SetDiscovering(bool),
+    Connect(String),
+    Disconnect(String),
+    Pair(String),
+}
+
+impl Module<Button> for BluetoothModule {
+    type SendMessage = BluetoothState;
+    type ReceiveMessage = BluetoothCommand;
+
+    fn name() -> &'static str {
+        "bluetooth"
+    }
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        tx: Sender<ModuleUpdateEvent<Self::SendMessage>>,
+        mut rx: Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        spawn(async move {
+            let client = bluez::get_client().await;
+            let (state, mut state_rx) = client.subscribe();
+
+            spawn(async move {
+                send_async!(tx, ModuleUpdateEvent::Update(state));
+
+                loop {
+                    match state_rx.recv().await {
+                        Ok(state) => send_async!(tx, ModuleUpdateEvent::Update(state)),
+                        // each update is the full state, so missed updates can be skipped
+                        Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            while let Some(command) = rx.recv().await {
+                let res = match command {
+                    BluetoothCommand::SetPowered(powered) => client.set_powered(powered).await,
+                    BluetoothCommand::SetDiscoverable(discoverable) => {
+                        client.set_discoverable(discoverable).await
+                    }
+                    BluetoothCommand::SetDiscovering(discovering) => {
+                        client.set_discovering(discovering).await
+                    }
+                    BluetoothCommand::Connect(path) => client.connect(&path).await,
+                    BluetoothCommand::Disconnect(path) => client.disconnect(&path).await,
+                    BluetoothCommand::Pair(path) => client.pair(&path).await,
+                };
+
+                if let Err(err) = res {
+                    error!("{err:?}");
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+        let label = Label::builder()
+            .label("")
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+
+        button.add(&label);
+
+        {
+            let tx = context.tx.clone();
+
+            button.connect_clicked(move |button| {
+                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+            });
+        }
+
+        {
+            let format = self.format.clone();
+            let icons = self.icons.clone();
+
+            context
+                .widget_rx
+                .attach(None, move |state: BluetoothState| {
+                    let connected = state
+                        .devices
+                        .iter()
+                        .filter(|device| device.connected)
+                        .collect::<Vec<_>>();
+
+                    let first = connected.first();
+
+                    let label_text = format
+                        .replace("{icon}", icons.state_icon(&state))
+                        .replace("{count}", &connected.len().to_string())
+                        .replace(
+                            "{name}",
+                            first.map(|device| device.name.as_str()).unwrap_or_default(),
+                        )
+                        .replace(
+                            "{battery}",
+                            &first
+                                .and_then(|device| device.battery)
+                                .map(|battery| battery.to_string())
+                                .unwrap_or_default(),
+                        );
+
+                    label.set_markup(&label_text);
+
+                    Continue(true)
+                });
+        }
+
+        let popup = self
+            .into_popup(context.controller_tx, context.popup_rx, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: Sender<Self::ReceiveMessage>,
+        rx: glib::Receiver<Self::SendMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 10);
+
+        let (power_box, power_switch) = create_toggle("power-toggle", "Bluetooth");
+        let (discoverable_box, discoverable_switch) =
+            create_toggle("discoverable-toggle", "Pairing mode");
+        let (scan_box, scan_switch) = create_toggle("scan-toggle", "Scan for devices");
+
+        let power_handler = {
+            let tx = tx.clone();
+            power_switch.connect_state_set(move |_, enabled| {
+                try_send!(tx, BluetoothCommand::SetPowered(enabled));
+                Inhibit(false)
+            })
+        };
+
+        let discoverable_handler = {
+            let tx = tx.clone();
+            discoverable_switch.connect_state_set(move |_, enabled| {
+                try_send!(tx, BluetoothCommand::SetDiscoverable(enabled));
+                Inhibit(false)
+            })
+        };
+
+        let scan_handler = {
+            let tx = tx.clone();
+            scan_switch.connect_state_set(move |_, enabled| {
+                try_send!(tx, BluetoothCommand::SetDiscovering(enabled));
+                Inhibit(false)
+            })
+        };
+
+        let devices = gtk::Box::new(Orientation::Vertical, 0);
+        devices.add_class("devices");
+
+        container.add(&power_box);
+        container.add(&discoverable_box);
+        container.add(&scan_box);
+        container.add(&devices);
+
+        container.show_all();
+
+        rx.attach(None, move |state| {
+            let adapter = state.adapter.as_ref();
+            let powered = adapter.map_or(false, |adapter| adapter.powered);
+
+            // block handlers while syncing the switches
+            // to avoid sending the new state straight back to the adapter.
+            power_switch.block_signal(&power_handler);
+            power_switch.set_active(powered);
+            power_switch.set_sensitive(adapter.is_some());
+            power_switch.unblock_signal(&power_handler);
+
+            discoverable_switch.block_signal(&discoverable_handler);
+            discoverable_switch.set_active(adapter.map_or(false, |adapter| adapter.discoverable));
+            discoverable_switch.unblock_signal(&discoverable_handler);
+            discoverable_box.set_visible(powered);
+
+            scan_switch.block_signal(&scan_handler);
+            scan_switch.set_active(adapter.map_or(false, |adapter| adapter.discovering));
+            scan_switch.unblock_signal(&scan_handler);
+            scan_box.set_visible(powered);
+
+            for child in devices.children() {
+                devices.remove(&child);
+            }
+
+            if powered {
+                for device in &state.devices {
+                    let row = create_device_row(device, &tx);
+                    devices.add(&row);
+                }
+            }
+
+            devices.show_all();
+
+            Continue(true)
+        });
+
+        Some(container)
+    }
+}
+
+/// Creates a labelled switch inside a box with the given class.
+fn create_toggle(class: &str, text: &str) -> (gtk::Box, Switch) {
+    let container = gtk::Box::new(Orientation::Horizontal, 5);
+    container.add_class(class);
+
+    let label = Label::new(Some(text));
+    label.set_hexpand(true);
+    label.set_halign(gtk::Align::Start);
+
+    let switch = Switch::new();
+
+    container.add(&label);
+    container.add(&switch);
+
+    (container, switch)
+}
+
+/// Creates a row for a device,
+/// with a button to pair, connect or disconnect it.
+fn create_device_row(device: &Device, tx: &Sender<BluetoothCommand>) -> gtk::Box {
+    let row = gtk::Box::new(Orientation::Horizontal, 5);
+    row.add_class("device");
+
+    if device.connected {
+        row.add_class("connected");
+    }
+
+    if device.paired {
+        row.add_class("paired");
+    }
+
+    let name = Label::new(Some(&device.name));
+    name.add_class("name");
+    name.set_hexpand(true);
+    name.set_halign(gtk::Align::Start);
+    row.add(&name);
+
+    if let Some(battery) = device.battery {
+        let battery = Label::new(Some(&format!("{battery}%")));
+        battery.add_class("battery");
+        row.add(&battery);
+    }
+
+    let (text, command) = if device.connected {
+        (
+            "Disconnect",
+            BluetoothCommand::Disconnect(device.path.clone()),
+        )
+    } else if device.paired {
+        ("Connect", BluetoothCommand::Connect(device.path.clone()))
+    } else {
+        ("Pair", BluetoothCommand::Pair(device.path.clone()))
+    };
+
+    let button = Button::with_label(text);
+    button.add_class("action");
+
+    let tx = tx.clone();
+    button.connect_clicked(move |_| {
+        try_send!(tx, command.clone());
+    });
+
+    row.add(&button);
+
+    row
+}
@@ -0,0 +1,225 @@
+use crate::clients::brightness::{Brightness, Client, BACKLIGHT_ROOT};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::module_tasks::spawn;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{send_async, try_send};
+use color_eyre::Result;
+use gtk::gdk::{EventMask, ScrollDirection};
+use gtk::prelude::*;
+use gtk::{Button, Label, Orientation, Scale};
+use serde::Deserialize;
+use std::cell::Cell;
+use std::rc::Rc;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tracing::error;
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct BrightnessModule {
+    /// Format string to use for the widget button label.
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// Name of the backlight device to control.
+    /// Defaults to the first device.
+    device: Option<String>,
+
+    /// Percentage to change the brightness by on each scroll.
+    #[serde(default = "default_step")]
+    step: f64,
+
+    /// Lowest percentage which can be set,
+    /// to avoid turning the screen off.
+    #[serde(default)]
+    min: f64,
+
+    /// Icon to show in the label.
+    #[serde(default = "default_icon")]
+    icon: String,
+
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{icon} {percentage}%")
+}
+
+const fn default_step() -> f64 {
+    5.0
+}
+
+fn default_icon() -> String {
+    String::from("󰃠")
+}
+
+impl Module<Button> for BrightnessModule {
+    type SendMessage = Brightness;
+    /// Brightness percentage to set.
+    type ReceiveMessage = f64;
+
+    fn name() -> &'static str {
+        "brightness"
+    }
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        tx: Sender<ModuleUpdateEvent<Self::SendMessage>>,
+        mut rx: Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let client = Client::new(BACKLIGHT_ROOT, self.device.as_deref())?;
+
+        let (change_tx, mut change_rx) = mpsc::channel(1);
+
+        {
+            let client = client.clone();
+            spawn(async move { client.watch(change_tx).await });
+        }
+
+        spawn(async move {
+            while let Some(brightness) = change_rx.recv().await {
+                send_async!(tx, ModuleUpdateEvent::Update(brightness));
+            }
+        });
+
+        spawn(async move {
+            while let Some(percentage) = rx.recv().await {
+                let result = match client.get() {
+                    Ok(brightness) => client.set(brightness.value_for(percentage)).await,
+                    Err(err) => Err(err),
+                };
+
+                if let Err(err) = result {
+                    error!("{:?}", err.wrap_err("Failed to set brightness"));
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+        let label = Label::builder()
+            .label("")
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+
+        button.add(&label);
+
+        let percentage = Rc::new(Cell::new(0.0));
+
+        {
+            let tx = context.tx.clone();
+
+            button.connect_clicked(move |button| {
+                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+            });
+        }
+
+        {
+            let tx = context.controller_tx.clone();
+            let percentage = percentage.clone();
+            let step = self.step;
+            let min = self.min;
+
+            button.add_events(EventMask::SCROLL_MASK);
+            button.connect_scroll_event(move |_, event| {
+                let delta = match event.direction() {
+                    ScrollDirection::Up => step,
+                    ScrollDirection::Down => -step,
+                    ScrollDirection::Smooth if event.delta().1 != 0.0 => {
+                        -event.delta().1.signum() * step
+                    }
+                    _ => return Inhibit(false),
+                };
+
+                let value = (percentage.get() + delta).clamp(min, 100.0);
+                if value != percentage.get() {
+                    percentage.set(value);
+                    try_send!(tx, value);
+                }
+
+                Inhibit(true)
+            });
+        }
+
+        {
+            let format = self.format.clone();
+            let icon = self.icon.clone();
+
+            context.widget_rx.attach(None, move |brightness| {
+                percentage.set(brightness.percentage());
+
+                let label_text = format
+                    .replace("{icon}", &icon)
+                    .replace("{percentage}", &format!("{:.0}", brightness.percentage()))
+                    .replace("{value}", &brightness.value.to_string())
+                    .replace("{max}", &brightness.max.to_string());
+
+                label.set_markup(&label_text);
+                Continue(true)
+            });
+        }
+
+        let popup = self
+            .into_popup(context.controller_tx, context.popup_rx, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: Sender<Self::ReceiveMessage>,
+        rx: glib::Receiver<Self::SendMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 5);
+
+        let slider = Scale::with_range(Orientation::Horizontal, self.min, 100.0, self.step);
+        slider.add_class("slider");
+        slider.set_digits(0);
+        container.add(&slider);
+
+        {
+            let min = self.min;
+
+            // GTK will spam the same value over and over
+            let prev_value = Cell::new(slider.value());
+
+            slider.connect_change_value(move |_, _, val| {
+                // GTK will send values outside min/max range
+                let val = val.clamp(min, 100.0);
+
+                if val != prev_value.get() {
+                    try_send!(tx, val);
+                    prev_value.set(val);
+                }
+
+                Inhibit(false)
+            });
+        }
+
+        rx.attach(None, move |brightness| {
+            slider.set_value(brightness.percentage());
+            Continue(true)
+        });
+
+        container.show_all();
+
+        Some(container)
+    }
+}
@@ -0,0 +1,134 @@
+use chrono::{DateTime, Local, Utc};
+use color_eyre::Result;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Method;
+
+/// Fetches all events between `start` and `end` from a CalDAV calendar collection,
+/// returning the iCalendar data for each.
+pub async fn fetch(
+    url: &str,
+    username: Option<&str>,
+    password: Option<&str>,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+) -> Result<Vec<String>> {
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop>
+    <c:calendar-data/>
+  </d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VEVENT">
+        <c:time-range start="{}" end="{}"/>
+      </c:comp-filter>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#,
+        format_time(start),
+        format_time(end)
+    );
+
+    let mut request = reqwest::Client::new()
+        .request(Method::from_bytes(b"REPORT")?, url)
+        .header("Depth", "1")
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .body(body);
+
+    if let Some(username) = username {
+        request = request.basic_auth(username, password);
+    }
+
+    let response = request.send().await?.error_for_status()?.text().await?;
+
+    Ok(extract_calendars(&response))
+}
+
+/// Formats a time as a UTC iCalendar `DATE-TIME`.
+fn format_time(time: DateTime<Local>) -> String {
+    time.with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// Extracts each iCalendar document from a multistatus response.
+///
+/// Rather than parsing the XML, this looks for the calendar boundaries directly,
+/// which avoids depending on the namespace prefixes used by the server.
+fn extract_calendars(xml: &str) -> Vec<String> {
+    const BEGIN: &str = "BEGIN:VCALENDAR";
+    const END: &str = "END:VCALENDAR";
+
+    let mut calendars = vec![];
+    let mut rest = xml;
+
+    while let Some(start) = rest.find(BEGIN) {
+        let Some(len) = rest[start..].find(END) else {
+            break;
+        };
+
+        let end = start + len + END.len();
+        calendars.push(unescape_xml(&rest[start..end]));
+        rest = &rest[end..];
+    }
+
+    calendars
+}
+
+/// Replaces the XML entities which may appear in calendar data.
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&#xD;", "\r")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_calendars() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:propstat>
+      <d:prop>
+        <cal:calendar-data>BEGIN:VCALENDAR&#13;
+BEGIN:VEVENT&#13;
+SUMMARY:Fish &amp; chips&#13;
+END:VEVENT&#13;
+END:VCALENDAR</cal:calendar-data>
+      </d:prop>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:propstat>
+      <d:prop>
+        <C:calendar-data xmlns:C="urn:ietf:params:xml:ns:caldav"><![CDATA[BEGIN:VCALENDAR
+END:VCALENDAR]]></C:calendar-data>
+      </d:prop>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#;
+
+        let calendars = extract_calendars(xml);
+
+        assert_eq!(
+            calendars,
+            [
+                "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:Fish & chips\r\nEND:VEVENT\r\nEND:VCALENDAR",
+                "BEGIN:VCALENDAR\nEND:VCALENDAR",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_calendars_unterminated() {
+        assert!(extract_calendars("<d:multistatus>BEGIN:VCALENDAR").is_empty());
+    }
+}
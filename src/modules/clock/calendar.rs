@@ -0,0 +1,280 @@
+use super::{ics, ClockUpdate};
+use crate::modules::ModuleUpdateEvent;
+use crate::send_async;
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use color_eyre::{Report, Result};
+use dirs::home_dir;
+use notify::{recommended_watcher, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use tracing::{debug, error};
+use walkdir::WalkDir;
+
+/// Time to wait for file changes to settle before reloading.
+const RELOAD_DEBOUNCE_MS: u64 = 500;
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct CalendarConfig {
+    /// Sources to read events from.
+    #[serde(default)]
+    pub(crate) sources: Vec<CalendarSource>,
+
+    /// Number of days ahead to show upcoming events for.
+    #[serde(default = "default_days")]
+    pub(crate) days: i64,
+
+    /// Maximum number of upcoming events to list.
+    #[serde(default = "default_max_events")]
+    pub(crate) max_events: usize,
+
+    /// Format string for event start times.
+    #[serde(default = "default_format_event")]
+    pub(crate) format: String,
+
+    /// Format string for all-day event dates.
+    #[serde(default = "default_format_all_day")]
+    pub(crate) format_all_day: String,
+
+    /// Time in seconds between refreshing all sources.
+    /// Local sources are additionally refreshed when they change.
+    #[serde(default = "default_refresh_interval")]
+    pub(crate) refresh_interval: u64,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            sources: vec![],
+            days: default_days(),
+            max_events: default_max_events(),
+            format: default_format_event(),
+            format_all_day: default_format_all_day(),
+            refresh_interval: default_refresh_interval(),
+        }
+    }
+}
+
+const fn default_days() -> i64 {
+    7
+}
+
+const fn default_max_events() -> usize {
+    5
+}
+
+fn default_format_event() -> String {
+    String::from("%a %H:%M")
+}
+
+fn default_format_all_day() -> String {
+    String::from("%a")
+}
+
+const fn default_refresh_interval() -> u64 {
+    300
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CalendarSource {
+    /// An `.ics` file, or a directory of them such as a vdir.
+    Local { path: String },
+    /// A CalDAV calendar collection.
+    #[cfg(feature = "http")]
+    Caldav {
+        url: String,
+        username: Option<String>,
+        password: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarEvent {
+    pub summary: String,
+    pub location: Option<String>,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub all_day: bool,
+}
+
+impl CalendarEvent {
+    /// Gets each date the event takes place on.
+    pub fn dates(&self) -> impl Iterator<Item = NaiveDate> {
+        let start = self.start.date_naive();
+
+        // events ending at midnight do not take place on that day
+        let end = if self.end > self.start {
+            (self.end - Duration::nanoseconds(1)).date_naive()
+        } else {
+            start
+        };
+
+        start.iter_days().take_while(move |date| date <= &end)
+    }
+}
+
+impl CalendarConfig {
+    /// Loads events from every source,
+    /// which finish after now and start within the configured number of days.
+    /// Events are sorted by start time.
+    pub async fn load_events(&self) -> Vec<CalendarEvent> {
+        let now = Local::now();
+        let range_end = now + Duration::days(self.days);
+
+        let mut events = vec![];
+
+        for source in &self.sources {
+            match source.load(now, range_end).await {
+                Ok(source_events) => events.extend(source_events),
+                Err(err) => error!("{err:?}"),
+            }
+        }
+
+        events.retain(|event| event.end > now && event.start < range_end);
+        events.sort_by_key(|event| event.start);
+
+        events
+    }
+}
+
+impl CalendarSource {
+    async fn load(
+        &self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> Result<Vec<CalendarEvent>> {
+        match self {
+            Self::Local { path } => Ok(load_local(&expand_path(path), start, end)),
+            #[cfg(feature = "http")]
+            Self::Caldav {
+                url,
+                username,
+                password,
+            } => {
+                let data =
+                    super::caldav::fetch(url, username.as_deref(), password.as_deref(), start, end)
+                        .await?;
+
+                Ok(data
+                    .iter()
+                    .flat_map(|data| ics::parse_events(data, start, end))
+                    .collect())
+            }
+        }
+    }
+}
+
+/// Expands a leading `~` to the user's home directory.
+fn expand_path(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home_dir()) {
+        (Some(path), Some(home)) => home.join(path),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Reads events between `start` and `end` from an `.ics` file,
+/// or every `.ics` file inside a directory.
+fn load_local(path: &Path, start: DateTime<Local>, end: DateTime<Local>) -> Vec<CalendarEvent> {
+    WalkDir::new(path)
+        .follow_links(true)
+        .into_iter()
+        .flatten()
+        .filter(|entry| {
+            entry.file_type().is_file()
+                && entry
+                    .path()
+                    .extension()
+                    .map_or(false, |ext| ext.eq_ignore_ascii_case("ics"))
+        })
+        .filter_map(|entry| match fs::read_to_string(entry.path()) {
+            Ok(data) => Some(ics::parse_events(&data, start, end)),
+            Err(err) => {
+                error!(
+                    "{:?}",
+                    Report::new(err).wrap_err(format!(
+                        "Failed to read calendar file '{}'",
+                        entry.path().display()
+                    ))
+                );
+                None
+            }
+        })
+        .flatten()
+        .collect()
+}
+
+/// Installs a file watcher on each local source,
+/// which sends on `tx` whenever a source changes.
+///
+/// The watcher must be kept alive for events to be sent.
+pub fn watch_sources(
+    sources: &[CalendarSource],
+    tx: mpsc::Sender<()>,
+) -> Option<RecommendedWatcher> {
+    let paths = sources
+        .iter()
+        .filter_map(|source| match source {
+            CalendarSource::Local { path } => Some(expand_path(path)),
+            #[cfg(feature = "http")]
+            CalendarSource::Caldav { .. } => None,
+        })
+        .collect::<Vec<_>>();
+
+    if paths.is_empty() {
+        return None;
+    }
+
+    let mut watcher = recommended_watcher(move |res: notify::Result<Event>| match res {
+        Ok(event)
+            if matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) =>
+        {
+            debug!("{event:?}");
+            // a full channel means a reload is already pending
+            tx.try_send(()).ok();
+        }
+        Err(err) => error!("Error occurred when watching calendar: {err:?}"),
+        _ => {}
+    })
+    .map_err(|err| error!("Failed to create calendar watcher: {err:?}"))
+    .ok()?;
+
+    for path in paths {
+        match watcher.watch(&path, RecursiveMode::Recursive) {
+            Ok(()) => debug!("Installed calendar watcher on '{}'", path.display()),
+            Err(err) => error!("Failed to watch calendar '{}': {err:?}", path.display()),
+        }
+    }
+
+    Some(watcher)
+}
+
+/// Loads events and sends them on `tx`,
+/// refreshing when a local source changes or the refresh interval elapses.
+pub async fn run(config: CalendarConfig, tx: mpsc::Sender<ModuleUpdateEvent<ClockUpdate>>) {
+    let (reload_tx, mut reload_rx) = mpsc::channel(1);
+    let _watcher = watch_sources(&config.sources, reload_tx);
+
+    let interval = std::time::Duration::from_secs(config.refresh_interval);
+
+    loop {
+        let events = config.load_events().await;
+        debug!("Loaded {} calendar events", events.len());
+        send_async!(tx, ModuleUpdateEvent::Update(ClockUpdate::Events(events)));
+
+        tokio::select! {
+            Some(()) = reload_rx.recv() => {
+                // editors often write files in several steps,
+                // so wait for the events to settle before reloading
+                sleep(std::time::Duration::from_millis(RELOAD_DEBOUNCE_MS)).await;
+                while reload_rx.try_recv().is_ok() {}
+            }
+            () = sleep(interval) => {}
+        }
+    }
+}
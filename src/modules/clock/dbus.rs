@@ -0,0 +1,27 @@
+//! D-Bus proxy for sending desktop notifications.
+//!
+//! See <https://specifications.freedesktop.org/notification-spec/latest/>.
+
+use std::collections::HashMap;
+use zbus::dbus_proxy;
+use zbus::zvariant::Value;
+
+#[dbus_proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
@@ -0,0 +1,558 @@
+//! A minimal iCalendar (RFC 5545) parser,
+//! covering only what is required to list events.
+//!
+//! Recurring events are expanded into each occurrence within a range,
+//! taking into account `RRULE`, `RDATE`, `EXDATE` and `RECURRENCE-ID` overrides.
+//! Times with a `TZID` parameter are read in that zone if it is an IANA name,
+//! or as local time otherwise.
+
+use super::calendar::CalendarEvent;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use rrule::{RRule, RRuleSet, Tz, Unvalidated};
+use std::collections::HashSet;
+use tracing::warn;
+
+/// Maximum number of occurrences to expand for a single recurring event.
+const MAX_OCCURRENCES: u16 = 1000;
+
+/// Parses all events in an iCalendar document,
+/// expanding recurring events into each occurrence
+/// which overlaps `range_start` to `range_end`.
+pub fn parse_events(
+    data: &str,
+    range_start: DateTime<Local>,
+    range_end: DateTime<Local>,
+) -> Vec<CalendarEvent> {
+    let mut builders = vec![];
+
+    let mut components = vec![];
+    let mut event = EventBuilder::default();
+
+    for line in unfold(data) {
+        let Some(property) = Property::parse(&line) else {
+            continue;
+        };
+
+        let value = property.value;
+
+        match property.name.as_str() {
+            "BEGIN" => {
+                if value.eq_ignore_ascii_case("VEVENT") {
+                    event = EventBuilder::default();
+                }
+                components.push(value.to_ascii_uppercase());
+            }
+            "END" => {
+                let component = components.pop();
+
+                if component.as_deref() == Some("VEVENT") {
+                    builders.push(std::mem::take(&mut event));
+                }
+            }
+            // ignore properties of nested components, such as alarms
+            _ if components.last().map(String::as_str) != Some("VEVENT") => {}
+            "UID" => event.uid = Some(value.to_string()),
+            "SUMMARY" => event.summary = Some(unescape(value)),
+            "LOCATION" => event.location = Some(unescape(value)),
+            "STATUS" => event.cancelled = value.eq_ignore_ascii_case("CANCELLED"),
+            "DTSTART" => event.start = property.time(),
+            "DTEND" => event.end = property.time(),
+            "DURATION" => event.duration = parse_duration(value),
+            "RRULE" => event.rrules.push(value.to_string()),
+            "RDATE" => event.rdates.extend(property.times()),
+            "EXDATE" => event.exdates.extend(property.times()),
+            "RECURRENCE-ID" => event.recurrence_id = property.time(),
+            _ => {}
+        }
+    }
+
+    // occurrences which are replaced by a separate `RECURRENCE-ID` event,
+    // identified by UID and original start time.
+    let overridden = builders
+        .iter()
+        .filter_map(|event| {
+            let recurrence_id = event.recurrence_id?;
+            Some((event.uid.clone()?, recurrence_id.time.with_timezone(&Utc)))
+        })
+        .collect::<HashSet<_>>();
+
+    builders
+        .into_iter()
+        .flat_map(|event| event.build(range_start, range_end, &overridden))
+        .collect()
+}
+
+/// A `DATE` or `DATE-TIME` value.
+#[derive(Debug, Clone, Copy)]
+struct Time {
+    time: DateTime<Tz>,
+    all_day: bool,
+}
+
+#[derive(Debug, Default)]
+struct EventBuilder {
+    uid: Option<String>,
+    summary: Option<String>,
+    location: Option<String>,
+    cancelled: bool,
+    start: Option<Time>,
+    end: Option<Time>,
+    duration: Option<Duration>,
+    rrules: Vec<String>,
+    rdates: Vec<Time>,
+    exdates: Vec<Time>,
+    recurrence_id: Option<Time>,
+}
+
+impl EventBuilder {
+    /// Creates an event for each occurrence overlapping the range,
+    /// skipping any in `overridden`.
+    fn build(
+        self,
+        range_start: DateTime<Local>,
+        range_end: DateTime<Local>,
+        overridden: &HashSet<(String, DateTime<Utc>)>,
+    ) -> Vec<CalendarEvent> {
+        if self.cancelled {
+            return vec![];
+        }
+
+        let Some(start) = self.start else {
+            warn!("Ignoring event without start time");
+            return vec![];
+        };
+
+        // an all-day event without an end lasts for the day,
+        // otherwise the event ends as it starts.
+        let end = self
+            .end
+            .map(|end| end.time)
+            .or_else(|| self.duration.map(|duration| start.time + duration))
+            .unwrap_or_else(|| {
+                if start.all_day {
+                    start.time + Duration::days(1)
+                } else {
+                    start.time
+                }
+            });
+
+        let length = end - start.time;
+
+        // an override is a single occurrence in its own right
+        let occurrences = if self.recurrence_id.is_some() {
+            vec![start.time]
+        } else {
+            self.occurrences(start, range_start - length, range_end)
+        };
+
+        occurrences
+            .into_iter()
+            .filter(|time| match &self.uid {
+                Some(uid) => !overridden.contains(&(uid.clone(), time.with_timezone(&Utc))),
+                None => true,
+            })
+            .map(|time| {
+                let start_time = time.with_timezone(&Local);
+
+                // keep all-day events on date boundaries,
+                // even if the occurrence is either side of a DST change.
+                let end_time = if start.all_day {
+                    let days = (end.date_naive() - start.time.date_naive()).num_days();
+                    local_midnight(start_time.date_naive() + Duration::days(days))
+                        .unwrap_or(start_time + length)
+                } else {
+                    start_time + length
+                };
+
+                CalendarEvent {
+                    summary: self.summary.clone().unwrap_or_default(),
+                    location: self.location.clone(),
+                    start: start_time,
+                    end: end_time,
+                    all_day: start.all_day,
+                }
+            })
+            .collect()
+    }
+
+    /// Gets the start time of each occurrence between `after` and `before`.
+    ///
+    /// Events without recurrence have a single occurrence.
+    /// If the recurrence cannot be parsed, only the first occurrence is used.
+    fn occurrences(
+        &self,
+        start: Time,
+        after: DateTime<Local>,
+        before: DateTime<Local>,
+    ) -> Vec<DateTime<Tz>> {
+        if self.rrules.is_empty() && self.rdates.is_empty() {
+            return vec![start.time];
+        }
+
+        let tz = start.time.timezone();
+        let mut set = RRuleSet::new(start.time)
+            .after(after.with_timezone(&tz))
+            .before(before.with_timezone(&tz));
+
+        for value in &self.rrules {
+            let rrule = value
+                .parse::<RRule<Unvalidated>>()
+                .and_then(|rrule| rrule.validate(start.time));
+
+            match rrule {
+                Ok(rrule) => set = set.rrule(rrule),
+                Err(err) => {
+                    warn!("Ignoring invalid recurrence rule '{value}': {err}");
+                    return vec![start.time];
+                }
+            }
+        }
+
+        // the start is always the first occurrence,
+        // but is only produced by a rule it matches.
+        if self.rrules.is_empty() {
+            set = set.rdate(start.time);
+        }
+
+        for rdate in &self.rdates {
+            set = set.rdate(rdate.time.with_timezone(&tz));
+        }
+
+        for exdate in &self.exdates {
+            set = set.exdate(exdate.time.with_timezone(&tz));
+        }
+
+        set.all(MAX_OCCURRENCES).dates
+    }
+}
+
+/// A single unfolded content line.
+#[derive(Debug)]
+struct Property<'a> {
+    /// Upper-case property name.
+    name: String,
+    /// Raw parameters, separated by `;`.
+    params: &'a str,
+    value: &'a str,
+}
+
+impl<'a> Property<'a> {
+    /// Splits a content line into its name, parameters and value.
+    fn parse(line: &'a str) -> Option<Self> {
+        // the value starts at the first colon outside of a quoted parameter
+        let mut quoted = false;
+        let split = line.char_indices().find_map(|(i, c)| match c {
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            ':' if !quoted => Some(i),
+            _ => None,
+        })?;
+
+        let (head, value) = (&line[..split], &line[split + 1..]);
+        let (name, params) = head.split_once(';').unwrap_or((head, ""));
+
+        Some(Self {
+            name: name.to_ascii_uppercase(),
+            params,
+            value,
+        })
+    }
+
+    /// Gets the value of a parameter, without quotes.
+    fn param(&self, name: &str) -> Option<&'a str> {
+        self.params.split(';').find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            key.eq_ignore_ascii_case(name)
+                .then(|| value.trim_matches('"'))
+        })
+    }
+
+    /// Parses the value as a single `DATE` or `DATE-TIME`.
+    fn time(&self) -> Option<Time> {
+        parse_time(self.value, self.param("TZID"))
+    }
+
+    /// Parses the value as a comma-separated list of `DATE`, `DATE-TIME` or `PERIOD` values.
+    /// Only the start of each period is kept.
+    fn times(&self) -> Vec<Time> {
+        let tzid = self.param("TZID");
+
+        self.value
+            .split(',')
+            .filter_map(|value| {
+                let value = value.split_once('/').map_or(value, |(start, _)| start);
+                parse_time(value, tzid)
+            })
+            .collect()
+    }
+}
+
+/// Joins continuation lines,
+/// which start with a single space or tab.
+fn unfold(data: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+
+    for line in data.lines() {
+        let line = line.trim_end_matches('\r');
+
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(prev)) => prev.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+/// Parses a `DATE` or `DATE-TIME` value.
+///
+/// `DATE` values and floating times are local,
+/// and `DATE-TIME` values ending in `Z` are UTC.
+/// Otherwise, the time is read in the `TZID` zone,
+/// falling back to local if it is not an IANA name.
+fn parse_time(value: &str, tzid: Option<&str>) -> Option<Time> {
+    // only `DATE-TIME` values contain a time component
+    if !value.contains('T') {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        let time = local_midnight(date)?.with_timezone(&Tz::LOCAL);
+
+        return Some(Time {
+            time,
+            all_day: true,
+        });
+    }
+
+    let time = if let Some(value) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+        Tz::UTC.from_utc_datetime(&time)
+    } else {
+        let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+
+        let tz = tzid.map_or(Tz::LOCAL, |tzid| match tzid.parse::<chrono_tz::Tz>() {
+            Ok(tz) => Tz::Tz(tz),
+            Err(_) => {
+                warn!("Unknown time zone '{tzid}', using local time");
+                Tz::LOCAL
+            }
+        });
+
+        tz.from_local_datetime(&time).earliest()?
+    };
+
+    Some(Time {
+        time,
+        all_day: false,
+    })
+}
+
+/// Gets the start of `date` in local time.
+fn local_midnight(date: NaiveDate) -> Option<DateTime<Local>> {
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+}
+
+/// Parses a `DURATION` value, such as `PT1H30M` or `P1D`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+
+    let value = value.strip_prefix('P')?;
+
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            _ => {
+                let n = number.parse::<i64>().ok()?;
+                number.clear();
+
+                duration = duration
+                    + match c {
+                        'W' => Duration::weeks(n),
+                        'D' => Duration::days(n),
+                        'H' => Duration::hours(n),
+                        'M' => Duration::minutes(n),
+                        'S' => Duration::seconds(n),
+                        _ => return None,
+                    };
+            }
+        }
+    }
+
+    Some(if negative { -duration } else { duration })
+}
+
+/// Removes escaping from a `TEXT` value.
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n' | 'N') => out.push('\n'),
+                Some(c) => out.push(c),
+                None => {}
+            }
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").unwrap())
+    }
+
+    fn parse(data: &str) -> Vec<CalendarEvent> {
+        let start = utc("20240101T000000").with_timezone(&Local);
+        let end = utc("20250101T000000").with_timezone(&Local);
+
+        parse_events(data, start, end)
+    }
+
+    fn starts(events: &[CalendarEvent]) -> Vec<DateTime<Utc>> {
+        events
+            .iter()
+            .map(|event| event.start.with_timezone(&Utc))
+            .collect()
+    }
+
+    #[test]
+    fn test_folding_and_escapes() {
+        let events = parse(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART:20240301T090000Z\r\nSUMMARY:Team\r\n  stand-up\\, daily\r\nLOCATION:Room 1\\nFloor 2\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+        );
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Team stand-up, daily");
+        assert_eq!(events[0].location.as_deref(), Some("Room 1\nFloor 2"));
+    }
+
+    #[test]
+    fn test_date_and_date_time() {
+        let events = parse(
+            "BEGIN:VEVENT\nSUMMARY:Holiday\nDTSTART;VALUE=DATE:20240301\nEND:VEVENT\nBEGIN:VEVENT\nSUMMARY:Meeting\nDTSTART:20240301T090000Z\nDTEND:20240301T100000Z\nEND:VEVENT",
+        );
+
+        assert_eq!(events.len(), 2);
+
+        let holiday = &events[0];
+        assert!(holiday.all_day);
+        assert_eq!(
+            holiday.start.date_naive(),
+            NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+        );
+        assert_eq!(holiday.end - holiday.start, Duration::days(1));
+
+        let meeting = &events[1];
+        assert!(!meeting.all_day);
+        assert_eq!(meeting.start.with_timezone(&Utc), utc("20240301T090000"));
+        assert_eq!(meeting.end.with_timezone(&Utc), utc("20240301T100000"));
+    }
+
+    #[test]
+    fn test_tzid() {
+        let events =
+            parse("BEGIN:VEVENT\nDTSTART;TZID=\"Europe/London\":20240701T090000\nEND:VEVENT");
+
+        assert_eq!(starts(&events), [utc("20240701T080000")]);
+    }
+
+    #[test]
+    fn test_duration() {
+        let events = parse("BEGIN:VEVENT\nDTSTART:20240301T090000Z\nDURATION:PT1H30M\nEND:VEVENT");
+
+        assert_eq!(events[0].end - events[0].start, Duration::minutes(90));
+
+        assert_eq!(parse_duration("P1W"), Some(Duration::weeks(1)));
+        assert_eq!(
+            parse_duration("-P1DT2H"),
+            Some(-(Duration::days(1) + Duration::hours(2)))
+        );
+        assert_eq!(parse_duration("1H"), None);
+    }
+
+    #[test]
+    fn test_cancelled() {
+        let events = parse("BEGIN:VEVENT\nDTSTART:20240301T090000Z\nSTATUS:CANCELLED\nEND:VEVENT");
+
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_ignores_nested_components() {
+        let events = parse(
+            "BEGIN:VEVENT\nSUMMARY:Event\nDTSTART:20240301T090000Z\nBEGIN:VALARM\nSUMMARY:Alarm\nEND:VALARM\nEND:VEVENT",
+        );
+
+        assert_eq!(events[0].summary, "Event");
+    }
+
+    #[test]
+    fn test_recurrence() {
+        let events = parse(
+            "BEGIN:VEVENT\nUID:daily\nDTSTART:20240301T090000Z\nRRULE:FREQ=DAILY;COUNT=4\nRDATE:20240310T090000Z\nEXDATE:20240302T090000Z\nEND:VEVENT",
+        );
+
+        assert_eq!(
+            starts(&events),
+            [
+                utc("20240301T090000"),
+                utc("20240303T090000"),
+                utc("20240304T090000"),
+                utc("20240310T090000"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recurrence_range() {
+        let data = "BEGIN:VEVENT\nDTSTART:20240301T090000Z\nDTEND:20240301T100000Z\nRRULE:FREQ=DAILY\nEND:VEVENT";
+
+        let start = utc("20240305T093000").with_timezone(&Local);
+        let end = utc("20240307T000000").with_timezone(&Local);
+
+        // includes the occurrence in progress at the start of the range
+        assert_eq!(
+            starts(&parse_events(data, start, end)),
+            [utc("20240305T090000"), utc("20240306T090000")]
+        );
+    }
+
+    #[test]
+    fn test_recurrence_override() {
+        let events = parse(
+            "BEGIN:VEVENT\nUID:weekly\nSUMMARY:Weekly\nDTSTART:20240301T090000Z\nRRULE:FREQ=WEEKLY;COUNT=3\nEND:VEVENT\nBEGIN:VEVENT\nUID:weekly\nSUMMARY:Moved\nRECURRENCE-ID:20240308T090000Z\nDTSTART:20240308T140000Z\nEND:VEVENT",
+        );
+
+        let mut events = events
+            .into_iter()
+            .map(|event| (event.start.with_timezone(&Utc), event.summary))
+            .collect::<Vec<_>>();
+        events.sort();
+
+        assert_eq!(
+            events,
+            [
+                (utc("20240301T090000"), String::from("Weekly")),
+                (utc("20240308T140000"), String::from("Moved")),
+                (utc("20240315T090000"), String::from("Weekly")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,511 @@
+#[cfg(feature = "http")]
+mod caldav;
+mod calendar;
+mod dbus;
+mod ics;
+mod timer;
+
+use std::cell::{Cell, RefCell};
+use std::env;
+use std::rc::Rc;
+
+use crate::module_tasks::spawn;
+use chrono::{DateTime, Datelike, Local, Locale};
+use chrono_tz::Tz;
+use color_eyre::Result;
+use glib::Continue;
+use gtk::prelude::*;
+use gtk::{Align, Button, Calendar, Label, Orientation, SpinButton};
+use serde::Deserialize;
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use tracing::error;
+
+use self::calendar::{CalendarConfig, CalendarEvent};
+use self::timer::{format_duration, TimerConfig, TimerStatus};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{send_async, try_send};
+
+pub use self::timer::TimerCommand;
+#[cfg(feature = "ipc")]
+pub use self::timer::{parse_duration, send_command as send_timer_command};
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct ClockModule {
+    /// Date/time format string.
+    /// Default: `%d/%m/%Y %H:%M`
+    ///
+    /// Detail on available tokens can be found here:
+    /// <https://docs.rs/chrono/latest/chrono/format/strftime/index.html>
+    #[serde(default = "default_format")]
+    format: String,
+
+    #[serde(default = "default_popup_format")]
+    format_popup: String,
+
+    #[serde(default = "default_locale")]
+    locale: String,
+
+    /// Calendar event sources and display options for the popup.
+    #[serde(default)]
+    calendar: CalendarConfig,
+
+    /// IANA names of additional time zones to show in the popup,
+    /// such as `Europe/London`.
+    #[serde(default)]
+    time_zones: Vec<String>,
+
+    /// Date/time format string for additional time zones.
+    #[serde(default = "default_time_zone_format")]
+    format_time_zone: String,
+
+    /// Enables the countdown timer and stopwatch.
+    timer: Option<TimerConfig>,
+
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+impl Default for ClockModule {
+    fn default() -> Self {
+        ClockModule {
+            format: default_format(),
+            format_popup: default_popup_format(),
+            locale: default_locale(),
+            calendar: CalendarConfig::default(),
+            time_zones: vec![],
+            format_time_zone: default_time_zone_format(),
+            timer: None,
+            common: Some(CommonConfig::default()),
+        }
+    }
+}
+
+fn default_format() -> String {
+    String::from("%d/%m/%Y %H:%M")
+}
+
+fn default_popup_format() -> String {
+    String::from("%H:%M:%S")
+}
+
+fn default_time_zone_format() -> String {
+    String::from("%H:%M")
+}
+
+fn default_locale() -> String {
+    env::var("LC_TIME")
+        .or_else(|_| env::var("LANG"))
+        .map_or_else(|_| "POSIX".to_string(), strip_tail)
+}
+
+fn strip_tail(string: String) -> String {
+    string
+        .split_once('.')
+        .map(|(head, _)| head.to_string())
+        .unwrap_or(string)
+}
+
+#[derive(Debug, Clone)]
+pub enum ClockUpdate {
+    Time(DateTime<Local>),
+    Events(Vec<CalendarEvent>),
+    Timer(Option<TimerStatus>),
+}
+
+/// How often to update the time.
+const TICK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+impl Module<Button> for ClockModule {
+    type SendMessage = ClockUpdate;
+    type ReceiveMessage = TimerCommand;
+
+    fn name() -> &'static str {
+        "clock"
+    }
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        tx: mpsc::Sender<ModuleUpdateEvent<Self::SendMessage>>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        if !self.calendar.sources.is_empty() {
+            let config = self.calendar.clone();
+            let tx = tx.clone();
+            spawn(calendar::run(config, tx));
+        }
+
+        if let Some(config) = self.timer.clone() {
+            let tx = tx.clone();
+            spawn(async move {
+                let mut subscription = timer::subscribe(config);
+
+                let status = subscription.status;
+                send_async!(tx, ModuleUpdateEvent::Update(ClockUpdate::Timer(status)));
+
+                loop {
+                    match subscription.rx.recv().await {
+                        Ok(status) => {
+                            send_async!(tx, ModuleUpdateEvent::Update(ClockUpdate::Timer(status)));
+                        }
+                        Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+        }
+
+        spawn(async move {
+            loop {
+                let date = Local::now();
+                send_async!(tx, ModuleUpdateEvent::Update(ClockUpdate::Time(date)));
+
+                select! {
+                    Some(command) = rx.recv() => timer::send_command(command),
+                    () = sleep(TICK_INTERVAL) => {}
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+        let container = gtk::Box::new(info.bar_position.get_orientation(), 5);
+        button.add(&container);
+
+        let label = Label::new(None);
+        label.set_angle(info.bar_position.get_angle());
+        container.add(&label);
+
+        let timer_label = Label::new(None);
+        timer_label.add_class("timer");
+        timer_label.set_angle(info.bar_position.get_angle());
+        timer_label.set_no_show_all(true);
+        container.add(&timer_label);
+
+        button.connect_clicked(move |button| {
+            try_send!(
+                context.tx,
+                ModuleUpdateEvent::TogglePopup(button.popup_id())
+            );
+        });
+
+        let format = self.format.clone();
+        let locale = Locale::try_from(self.locale.as_str()).unwrap_or(Locale::POSIX);
+
+        context.widget_rx.attach(None, move |update| {
+            match update {
+                ClockUpdate::Time(date) => {
+                    let date_string = format!("{}", date.format_localized(&format, locale));
+                    label.set_label(&date_string);
+                }
+                ClockUpdate::Timer(Some(status)) => {
+                    timer_label.set_label(&format_duration(status.value));
+                    timer_label.show();
+                }
+                ClockUpdate::Timer(None) => timer_label.hide(),
+                ClockUpdate::Events(_) => {}
+            }
+
+            Continue(true)
+        });
+
+        let popup = self
+            .into_popup(context.controller_tx, context.popup_rx, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: glib::Receiver<Self::SendMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box> {
+        let container = gtk::Box::new(Orientation::Vertical, 0);
+
+        let clock = Label::builder().halign(Align::Center).build();
+        clock.add_class("calendar-clock");
+
+        container.add(&clock);
+
+        let time_zones_container = gtk::Box::new(Orientation::Vertical, 0);
+        time_zones_container.add_class("time-zones");
+
+        let time_zones = self
+            .time_zones
+            .iter()
+            .filter_map(|name| match name.parse::<Tz>() {
+                Ok(tz) => Some(tz),
+                Err(err) => {
+                    error!("Invalid time zone '{name}': {err}");
+                    None
+                }
+            })
+            .map(|tz| {
+                let row = gtk::Box::new(Orientation::Horizontal, 10);
+                row.add_class("time-zone");
+
+                let name = Label::builder()
+                    .label(
+                        tz.name()
+                            .rsplit('/')
+                            .next()
+                            .unwrap_or_default()
+                            .replace('_', " "),
+                    )
+                    .halign(Align::Start)
+                    .hexpand(true)
+                    .build();
+                name.add_class("name");
+                name.set_tooltip_text(Some(tz.name()));
+                row.add(&name);
+
+                let time = Label::new(None);
+                time.add_class("time");
+                row.add(&time);
+
+                time_zones_container.add(&row);
+                (tz, time)
+            })
+            .collect::<Vec<_>>();
+
+        if !time_zones.is_empty() {
+            container.add(&time_zones_container);
+        }
+
+        let calendar = Calendar::new();
+        calendar.add_class("calendar");
+        container.add(&calendar);
+
+        let events_container = gtk::Box::new(Orientation::Vertical, 0);
+        events_container.add_class("events");
+        container.add(&events_container);
+
+        let timer = self.timer.as_ref().map(|_| TimerWidget::new(&tx));
+        if let Some(timer) = &timer {
+            container.add(&timer.container);
+        }
+
+        let events = Rc::new(RefCell::new(Vec::<CalendarEvent>::new()));
+
+        {
+            let events = events.clone();
+            calendar.connect_month_changed(move |calendar| {
+                mark_days(calendar, &events.borrow());
+            });
+        }
+
+        let format = self.format_popup;
+        let format_time_zone = self.format_time_zone;
+        let locale = Locale::try_from(self.locale.as_str()).unwrap_or(Locale::POSIX);
+        let calendar_config = self.calendar;
+
+        rx.attach(None, move |update| {
+            match update {
+                ClockUpdate::Time(date) => {
+                    let date_string = format!("{}", date.format_localized(&format, locale));
+                    clock.set_label(&date_string);
+
+                    for (tz, label) in &time_zones {
+                        let date = date.with_timezone(tz);
+                        let date_string =
+                            format!("{}", date.format_localized(&format_time_zone, locale));
+                        label.set_label(&date_string);
+                    }
+                }
+                ClockUpdate::Timer(status) => {
+                    if let Some(timer) = &timer {
+                        timer.update(status);
+                    }
+                }
+                ClockUpdate::Events(new_events) => {
+                    mark_days(&calendar, &new_events);
+
+                    for child in events_container.children() {
+                        events_container.remove(&child);
+                    }
+
+                    if new_events.is_empty() {
+                        let label = Label::new(Some("No upcoming events"));
+                        label.add_class("empty");
+                        events_container.add(&label);
+                    }
+
+                    for event in new_events.iter().take(calendar_config.max_events) {
+                        events_container.add(&create_event_row(event, &calendar_config, locale));
+                    }
+
+                    events_container.show_all();
+                    events.replace(new_events);
+                }
+            }
+
+            Continue(true)
+        });
+
+        container.show_all();
+
+        Some(container)
+    }
+}
+
+/// Popup controls for the countdown timer and stopwatch.
+struct TimerWidget {
+    container: gtk::Box,
+    value: Label,
+    minutes: SpinButton,
+    toggle: Button,
+    status: Rc<Cell<Option<TimerStatus>>>,
+}
+
+impl TimerWidget {
+    fn new(tx: &mpsc::Sender<TimerCommand>) -> Self {
+        let container = gtk::Box::new(Orientation::Horizontal, 5);
+        container.add_class("timer");
+
+        let value = Label::new(Some(&format_duration(std::time::Duration::ZERO)));
+        value.add_class("value");
+        value.set_hexpand(true);
+        container.add(&value);
+
+        let minutes = SpinButton::with_range(0.0, 1440.0, 1.0);
+        minutes.add_class("minutes");
+        minutes.set_tooltip_text(Some("Countdown minutes. Leave at 0 for a stopwatch."));
+        container.add(&minutes);
+
+        let toggle = Button::with_label("Start");
+        toggle.add_class("toggle");
+        container.add(&toggle);
+
+        let reset = Button::with_label("Reset");
+        reset.add_class("reset");
+        container.add(&reset);
+
+        let status = Rc::new(Cell::new(None::<TimerStatus>));
+
+        {
+            let tx = tx.clone();
+            let status = status.clone();
+            let minutes = minutes.clone();
+
+            toggle.connect_clicked(move |_| {
+                let command = match status.get() {
+                    Some(status) if status.running => TimerCommand::Pause,
+                    Some(_) => TimerCommand::Resume,
+                    None => {
+                        let minutes = minutes.value_as_int().unsigned_abs();
+                        let duration = (minutes > 0)
+                            .then(|| std::time::Duration::from_secs(u64::from(minutes) * 60));
+
+                        TimerCommand::Start(duration)
+                    }
+                };
+
+                try_send!(tx, command);
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            reset.connect_clicked(move |_| {
+                try_send!(tx, TimerCommand::Reset);
+            });
+        }
+
+        Self {
+            container,
+            value,
+            minutes,
+            toggle,
+            status,
+        }
+    }
+
+    fn update(&self, status: Option<TimerStatus>) {
+        self.status.set(status);
+
+        let value = status.map_or(std::time::Duration::ZERO, |status| status.value);
+        self.value.set_label(&format_duration(value));
+
+        self.toggle.set_label(match status {
+            Some(status) if status.running => "Pause",
+            Some(_) => "Resume",
+            None => "Start",
+        });
+
+        self.minutes.set_visible(status.is_none());
+
+        let style = self.container.style_context();
+        if status.map_or(false, |status| status.running) {
+            style.add_class("running");
+        } else {
+            style.remove_class("running");
+        }
+    }
+}
+
+/// Marks each day in the calendar's current month
+/// which has at least one event.
+fn mark_days(calendar: &Calendar, events: &[CalendarEvent]) {
+    calendar.clear_marks();
+
+    // GTK months are zero-indexed
+    let (year, month, _) = calendar.date();
+
+    events
+        .iter()
+        .flat_map(CalendarEvent::dates)
+        .filter(|date| date.year() == year as i32 && date.month0() == month)
+        .for_each(|date| calendar.mark_day(date.day()));
+}
+
+/// Creates a row showing an upcoming event's start time and summary.
+fn create_event_row(event: &CalendarEvent, config: &CalendarConfig, locale: Locale) -> gtk::Box {
+    let row = gtk::Box::new(Orientation::Horizontal, 10);
+    row.add_class("event");
+
+    if event.all_day {
+        row.add_class("all-day");
+    }
+
+    let format = if event.all_day {
+        &config.format_all_day
+    } else {
+        &config.format
+    };
+
+    let time = Label::new(Some(
+        &event.start.format_localized(format, locale).to_string(),
+    ));
+    time.add_class("time");
+    row.add(&time);
+
+    let summary = Label::builder()
+        .label(&event.summary)
+        .halign(Align::Start)
+        .hexpand(true)
+        .build();
+    summary.add_class("summary");
+    row.add(&summary);
+
+    if let Some(location) = &event.location {
+        row.set_tooltip_text(Some(location));
+    }
+
+    row
+}
@@ -0,0 +1,311 @@
+use super::dbus::NotificationsProxy;
+use crate::script::{Script, ScriptInput};
+use crate::unique_id::get_unique_usize;
+use crate::{lock, send};
+use color_eyre::Result;
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::spawn;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+use tracing::error;
+
+lazy_static! {
+    static ref CLIENT: Client = Client::new();
+}
+
+/// How often to check the timer for changes.
+const TICK_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct TimerConfig {
+    /// Whether to send a desktop notification when a countdown finishes.
+    #[serde(default = "crate::config::default_true")]
+    notify: bool,
+
+    /// Script to run when a countdown finishes.
+    on_finish: Option<ScriptInput>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerCommand {
+    /// Starts a countdown for the given duration,
+    /// or a stopwatch if no duration is given.
+    /// Any existing timer is replaced.
+    Start(Option<Duration>),
+    Pause,
+    Resume,
+    Reset,
+}
+
+/// The timer shared by every clock module.
+///
+/// A single timer is kept so that the finish actions
+/// run once, rather than once per clock.
+struct Client {
+    state: Mutex<ClientState>,
+    tx: broadcast::Sender<Option<TimerStatus>>,
+}
+
+#[derive(Debug, Default)]
+struct ClientState {
+    timer: Option<Timer>,
+    status: Option<TimerStatus>,
+    /// Config of each subscribed clock, by subscription ID.
+    configs: Vec<(usize, TimerConfig)>,
+}
+
+impl Client {
+    fn new() -> Self {
+        let (tx, _) = broadcast::channel(16);
+
+        spawn(async {
+            loop {
+                sleep(TICK_INTERVAL).await;
+                CLIENT.update();
+            }
+        });
+
+        Self {
+            state: Mutex::default(),
+            tx,
+        }
+    }
+
+    /// Runs the finish actions if the timer has finished,
+    /// and notifies subscribers if its status has changed.
+    fn update(&self) {
+        let mut state = lock!(self.state);
+
+        if state.timer.as_ref().map_or(false, Timer::is_finished) {
+            state.timer = None;
+
+            let configs = state
+                .configs
+                .iter()
+                .map(|(_, config)| config)
+                .collect::<Vec<_>>();
+
+            finish(&configs);
+        }
+
+        let status = state.timer.as_ref().map(Timer::status);
+        if status != state.status {
+            state.status = status;
+            send!(self.tx, status);
+        }
+    }
+}
+
+/// Sends a command to the shared timer.
+pub fn send_command(command: TimerCommand) {
+    apply_command(&mut lock!(CLIENT.state).timer, command);
+    CLIENT.update();
+}
+
+/// Subscribes to timer status changes,
+/// registering the clock's config so that its actions run when a countdown finishes.
+///
+/// The config is unregistered when the subscription is dropped.
+pub fn subscribe(config: TimerConfig) -> Subscription {
+    let id = get_unique_usize();
+
+    let mut state = lock!(CLIENT.state);
+    state.configs.push((id, config));
+
+    Subscription {
+        id,
+        status: state.status,
+        rx: CLIENT.tx.subscribe(),
+    }
+}
+
+pub struct Subscription {
+    id: usize,
+    /// Timer status at the time of subscribing.
+    pub status: Option<TimerStatus>,
+    pub rx: broadcast::Receiver<Option<TimerStatus>>,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        lock!(CLIENT.state).configs.retain(|(id, _)| *id != self.id);
+    }
+}
+
+/// A countdown timer or stopwatch.
+#[derive(Debug, Clone, Copy)]
+pub struct Timer {
+    /// Countdown length, or `None` for a stopwatch.
+    duration: Option<Duration>,
+    /// Time elapsed before the timer was last resumed.
+    elapsed: Duration,
+    /// When the timer was last resumed, if it is running.
+    resumed: Option<Instant>,
+}
+
+/// A snapshot of a timer's state for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerStatus {
+    /// Time remaining for countdowns, or time elapsed for stopwatches.
+    pub value: Duration,
+    pub running: bool,
+    pub countdown: bool,
+}
+
+impl Timer {
+    fn new(duration: Option<Duration>) -> Self {
+        Self {
+            duration,
+            elapsed: Duration::ZERO,
+            resumed: Some(Instant::now()),
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.elapsed
+            + self
+                .resumed
+                .map_or(Duration::ZERO, |resumed| resumed.elapsed())
+    }
+
+    fn pause(&mut self) {
+        self.elapsed = self.elapsed();
+        self.resumed = None;
+    }
+
+    fn resume(&mut self) {
+        self.resumed.get_or_insert_with(Instant::now);
+    }
+
+    fn is_finished(&self) -> bool {
+        self.duration
+            .map_or(false, |duration| self.elapsed() >= duration)
+    }
+
+    fn status(&self) -> TimerStatus {
+        let elapsed = self.elapsed();
+
+        // values are shown in whole seconds.
+        // countdowns round up so that they reach zero as they finish.
+        TimerStatus {
+            value: self.duration.map_or_else(
+                || Duration::from_secs(elapsed.as_secs()),
+                |duration| {
+                    let remaining = duration.saturating_sub(elapsed);
+                    Duration::from_secs(
+                        remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0),
+                    )
+                },
+            ),
+            running: self.resumed.is_some(),
+            countdown: self.duration.is_some(),
+        }
+    }
+}
+
+/// Applies a command to the current timer.
+fn apply_command(timer: &mut Option<Timer>, command: TimerCommand) {
+    match command {
+        TimerCommand::Start(duration) => *timer = Some(Timer::new(duration)),
+        TimerCommand::Pause => {
+            if let Some(timer) = timer {
+                timer.pause();
+            }
+        }
+        TimerCommand::Resume => {
+            if let Some(timer) = timer {
+                timer.resume();
+            }
+        }
+        TimerCommand::Reset => *timer = None,
+    }
+}
+
+/// Runs the actions for a finished countdown.
+///
+/// Where several clocks have a timer, a single notification is sent
+/// and each distinct script is run once.
+fn finish(configs: &[&TimerConfig]) {
+    if configs.iter().any(|config| config.notify) {
+        spawn(async {
+            if let Err(err) = send_notification().await {
+                error!("Failed to send timer notification: {err:?}");
+            }
+        });
+    }
+
+    let mut scripts: Vec<&ScriptInput> = vec![];
+    for script in configs
+        .iter()
+        .filter_map(|config| config.on_finish.as_ref())
+    {
+        if !scripts.contains(&script) {
+            scripts.push(script);
+        }
+    }
+
+    for script in scripts {
+        Script::from(script.clone()).run_as_oneshot(None);
+    }
+}
+
+async fn send_notification() -> Result<()> {
+    let connection = zbus::Connection::session().await?;
+    let proxy = NotificationsProxy::new(&connection).await?;
+
+    proxy
+        .notify(
+            "Ironbar",
+            0,
+            "alarm-symbolic",
+            "Timer finished",
+            "",
+            &[],
+            HashMap::new(),
+            -1,
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Parses a duration such as `90`, `90s`, `5m` or `1h30m`.
+/// Numbers without a unit are treated as seconds.
+#[cfg(feature = "ipc")]
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let mut total = 0;
+    let mut number = String::new();
+
+    for c in value.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let multiplier = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+
+        total += number.parse::<u64>().ok()? * multiplier;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        total += number.parse::<u64>().ok()?;
+    }
+
+    (total > 0).then(|| Duration::from_secs(total))
+}
+
+/// Formats a duration as `HH:MM:SS`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
@@ -5,7 +5,7 @@ use gtk::prelude::*;
 use gtk::Orientation;
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct BoxWidget {
     name: Option<String>,
     class: Option<String>,
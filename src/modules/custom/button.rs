@@ -8,7 +8,7 @@ use crate::{build, try_send};
 
 use super::{CustomWidget, CustomWidgetContext, ExecEvent};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct ButtonWidget {
     name: Option<String>,
     class: Option<String>,
@@ -8,7 +8,7 @@ use crate::image::ImageProvider;
 
 use super::{CustomWidget, CustomWidgetContext};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct ImageWidget {
     name: Option<String>,
     class: Option<String>,
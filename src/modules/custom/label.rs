@@ -7,7 +7,7 @@ use crate::dynamic_value::dynamic_string;
 
 use super::{CustomWidget, CustomWidgetContext};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct LabelWidget {
     name: Option<String>,
     class: Option<String>,
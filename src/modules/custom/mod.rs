@@ -10,6 +10,7 @@ use self::label::LabelWidget;
 use self::r#box::BoxWidget;
 use self::slider::SliderWidget;
 use crate::config::CommonConfig;
+use crate::module_tasks::spawn;
 use crate::modules::custom::button::ButtonWidget;
 use crate::modules::custom::progress::ProgressWidget;
 use crate::modules::{
@@ -23,11 +24,10 @@ use gtk::{Button, IconTheme, Orientation};
 use serde::Deserialize;
 use std::cell::RefCell;
 use std::rc::Rc;
-use tokio::spawn;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tracing::{debug, error};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct CustomModule {
     /// Widgets to add to the bar container
     bar: Vec<WidgetConfig>,
@@ -38,7 +38,7 @@ pub struct CustomModule {
     pub common: Option<CommonConfig>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct WidgetConfig {
     #[serde(flatten)]
     widget: Widget,
@@ -46,7 +46,7 @@ pub struct WidgetConfig {
     common: CommonConfig,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Widget {
     Box(BoxWidget),
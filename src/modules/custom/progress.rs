@@ -1,7 +1,7 @@
+use crate::module_tasks::spawn;
 use gtk::prelude::*;
 use gtk::ProgressBar;
 use serde::Deserialize;
-use tokio::spawn;
 use tracing::error;
 
 use crate::dynamic_value::dynamic_string;
@@ -11,7 +11,7 @@ use crate::{build, send};
 
 use super::{try_get_orientation, CustomWidget, CustomWidgetContext};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct ProgressWidget {
     name: Option<String>,
     class: Option<String>,
@@ -1,10 +1,10 @@
 use std::cell::Cell;
 use std::ops::Neg;
 
+use crate::module_tasks::spawn;
 use gtk::prelude::*;
 use gtk::Scale;
 use serde::Deserialize;
-use tokio::spawn;
 use tracing::error;
 
 use crate::modules::custom::set_length;
@@ -13,7 +13,7 @@ use crate::{build, send, try_send};
 
 use super::{try_get_orientation, CustomWidget, CustomWidgetContext, ExecEvent};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct SliderWidget {
     name: Option<String>,
     class: Option<String>,
@@ -1,19 +1,23 @@
-use crate::clients::wayland::{self, ToplevelEvent};
+use crate::clients::wayland::{self, ToplevelAction, ToplevelEvent, ToplevelHandle};
 use crate::config::{CommonConfig, TruncateMode};
 use crate::gtk_helpers::IronbarGtkExt;
 use crate::image::ImageProvider;
-use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::module_tasks::spawn;
+use crate::modules::{
+    window_menu, Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext,
+};
 use crate::{lock, send_async, try_send};
 use color_eyre::Result;
 use glib::Continue;
 use gtk::prelude::*;
-use gtk::Label;
+use gtk::{EventBox, Label, Menu};
 use serde::Deserialize;
-use tokio::spawn;
+use std::cell::RefCell;
+use std::rc::Rc;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tracing::debug;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct FocusedModule {
     /// Whether to show icon on the bar.
     #[serde(default = "crate::config::default_true")]
@@ -48,9 +52,11 @@ const fn default_icon_size() -> i32 {
     32
 }
 
-impl Module<gtk::Box> for FocusedModule {
-    type SendMessage = (String, String);
-    type ReceiveMessage = ();
+impl Module<EventBox> for FocusedModule {
+    /// The focused window, or `None` once it has closed.
+    type SendMessage = Option<ToplevelHandle>;
+    /// Action to perform on the window with the given ID.
+    type ReceiveMessage = (usize, ToplevelAction);
 
     fn name() -> &'static str {
         "focused"
@@ -60,7 +66,7 @@ impl Module<gtk::Box> for FocusedModule {
         &self,
         _info: &ModuleInfo,
         tx: Sender<ModuleUpdateEvent<Self::SendMessage>>,
-        _rx: Receiver<Self::ReceiveMessage>,
+        mut rx: Receiver<Self::ReceiveMessage>,
     ) -> Result<()> {
         spawn(async move {
             let (mut wlrx, handles) = {
@@ -69,34 +75,51 @@ impl Module<gtk::Box> for FocusedModule {
                 wl.subscribe_toplevels()
             };
 
-            let focused = handles.values().find_map(|handle| {
-                handle
-                    .info()
-                    .and_then(|info| if info.focused { Some(info) } else { None })
-            });
+            // ID of the window currently shown
+            let mut focused_id = None;
+
+            let focused = handles
+                .values()
+                .find(|handle| handle.info().map_or(false, |info| info.focused));
 
             if let Some(focused) = focused {
-                try_send!(
-                    tx,
-                    ModuleUpdateEvent::Update((focused.title.clone(), focused.app_id))
-                );
+                focused_id = focused.info().map(|info| info.id);
+                try_send!(tx, ModuleUpdateEvent::Update(Some(focused.clone())));
             };
 
             while let Ok(event) = wlrx.recv().await {
-                if let ToplevelEvent::Update(handle) = event {
-                    let info = handle.info().unwrap_or_default();
-
-                    if info.focused {
-                        debug!("Changing focus");
-                        send_async!(
-                            tx,
-                            ModuleUpdateEvent::Update((info.title.clone(), info.app_id.clone()))
-                        );
+                match event {
+                    ToplevelEvent::Update(handle) => {
+                        let info = handle.info().unwrap_or_default();
+
+                        if info.focused {
+                            debug!("Changing focus");
+                            focused_id = Some(info.id);
+                            send_async!(tx, ModuleUpdateEvent::Update(Some(handle)));
+                        }
+                    }
+                    ToplevelEvent::Remove(handle) => {
+                        let id = handle.info().map(|info| info.id);
+
+                        if id.is_some() && id == focused_id {
+                            debug!("Focused window closed");
+                            focused_id = None;
+                            send_async!(tx, ModuleUpdateEvent::Update(None));
+                        }
                     }
+                    ToplevelEvent::New(_) => {}
                 }
             }
         });
 
+        // listen to ui events
+        spawn(async move {
+            while let Some((id, action)) = rx.recv().await {
+                let wl = wayland::get_client();
+                lock!(wl).toplevel_action(id, action);
+            }
+        });
+
         Ok(())
     }
 
@@ -104,10 +127,13 @@ impl Module<gtk::Box> for FocusedModule {
         self,
         context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
         info: &ModuleInfo,
-    ) -> Result<ModuleParts<gtk::Box>> {
+    ) -> Result<ModuleParts<EventBox>> {
         let icon_theme = info.icon_theme;
 
+        let event_box = EventBox::new();
+
         let container = gtk::Box::new(info.bar_position.get_orientation(), 5);
+        event_box.add(&container);
 
         let icon = gtk::Image::new();
         if self.show_icon {
@@ -124,11 +150,65 @@ impl Module<gtk::Box> for FocusedModule {
 
         container.add(&label);
 
+        // handle for the currently displayed window
+        let focused = Rc::new(RefCell::new(None::<ToplevelHandle>));
+
+        {
+            let focused = focused.clone();
+            let tx = context.controller_tx.clone();
+
+            let menu = Menu::new();
+            menu.add_class("window-menu");
+
+            event_box.connect_button_press_event(move |_, event| {
+                const BUTTON_RIGHT: u32 = 3;
+
+                if event.button() != BUTTON_RIGHT {
+                    return Inhibit(false);
+                }
+
+                let Some(handle) = &*focused.borrow() else {
+                    return Inhibit(false);
+                };
+
+                let Some(info) = handle.info() else {
+                    return Inhibit(false);
+                };
+
+                let id = info.id;
+                let tx = tx.clone();
+
+                window_menu::clear(&menu);
+                window_menu::populate(
+                    &menu,
+                    &info,
+                    handle.version(),
+                    &window_menu::output_names(),
+                    move |action| {
+                        try_send!(tx, (id, action));
+                    },
+                );
+
+                menu.show_all();
+                menu.popup_at_pointer(Some(event));
+
+                Inhibit(true)
+            });
+        }
+
         {
             let icon_theme = icon_theme.clone();
-            context.widget_rx.attach(None, move |(name, id)| {
+            context.widget_rx.attach(None, move |handle| {
+                let Some(info) = handle.as_ref().and_then(ToplevelHandle::info) else {
+                    icon.hide();
+                    label.set_label("");
+                    focused.replace(None);
+
+                    return Continue(true);
+                };
+
                 if self.show_icon {
-                    match ImageProvider::parse(&id, &icon_theme, true, self.icon_size)
+                    match ImageProvider::parse(&info.app_id, &icon_theme, true, self.icon_size)
                         .map(|image| image.load_into_image(icon.clone()))
                     {
                         Some(Ok(_)) => icon.show(),
@@ -137,15 +217,17 @@ impl Module<gtk::Box> for FocusedModule {
                 }
 
                 if self.show_title {
-                    label.set_label(&name);
+                    label.set_label(&info.title);
                 }
 
+                focused.replace(handle);
+
                 Continue(true)
             });
         }
 
         Ok(ModuleParts {
-            widget: container,
+            widget: event_box,
             popup: None,
         })
     }
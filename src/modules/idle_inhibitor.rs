@@ -0,0 +1,207 @@
+use crate::clients::wayland::wp_idle_inhibit;
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+#[cfg(feature = "ipc")]
+use crate::lock;
+use crate::module_tasks::spawn;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::send_async;
+use color_eyre::Result;
+use glib::Continue;
+use gtk::prelude::*;
+use gtk::{Button, Label};
+#[cfg(feature = "ipc")]
+use lazy_static::lazy_static;
+use serde::Deserialize;
+#[cfg(feature = "ipc")]
+use std::sync::Mutex;
+use tokio::sync::mpsc::{Receiver, Sender};
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct IdleInhibitorModule {
+    /// Icon to show while idle is inhibited.
+    #[serde(default = "default_icon_on")]
+    icon_on: String,
+
+    /// Icon to show while idle is not inhibited.
+    #[serde(default = "default_icon_off")]
+    icon_off: String,
+
+    /// Name of an ironvar to set to `true` while idle is inhibited,
+    /// and `false` otherwise.
+    variable: Option<Box<str>>,
+
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_icon_on() -> String {
+    String::from("󰅶")
+}
+
+fn default_icon_off() -> String {
+    String::from("󰾪")
+}
+
+#[cfg(feature = "ipc")]
+lazy_static! {
+    /// Names of the ironvars configured by live module instances.
+    /// A name appears once for each instance using it.
+    static ref VARIABLES: Mutex<Vec<Box<str>>> = Mutex::new(vec![]);
+}
+
+/// Keeps an ironvar registered for as long as it is alive.
+///
+/// This is owned by the module's task,
+/// so the variable is unregistered once the module is removed.
+#[cfg(feature = "ipc")]
+struct VariableRegistration(Box<str>);
+
+#[cfg(feature = "ipc")]
+impl VariableRegistration {
+    fn new(variable: Box<str>) -> Self {
+        lock!(VARIABLES).push(variable.clone());
+        Self(variable)
+    }
+}
+
+#[cfg(feature = "ipc")]
+impl Drop for VariableRegistration {
+    fn drop(&mut self) {
+        let mut variables = lock!(VARIABLES);
+        if let Some(index) = variables.iter().position(|variable| *variable == self.0) {
+            variables.remove(index);
+        }
+    }
+}
+
+/// Gets whether idle is currently inhibited.
+///
+/// Returns `None` if the compositor does not support the idle-inhibit protocol.
+/// This must be called from the GTK main thread.
+pub fn is_inhibited() -> Option<bool> {
+    wp_idle_inhibit::with_client(|client| client.is_inhibited())
+}
+
+/// Sets whether idle is inhibited,
+/// updating any configured ironvars.
+///
+/// Returns `None` if the compositor does not support the idle-inhibit protocol.
+/// This must be called from the GTK main thread.
+pub fn set_inhibited(inhibit: bool) -> Option<()> {
+    wp_idle_inhibit::with_client(|client| client.set_inhibited(inhibit))?;
+
+    #[cfg(feature = "ipc")]
+    for variable in lock!(VARIABLES).iter() {
+        set_variable(variable, inhibit);
+    }
+
+    Some(())
+}
+
+/// Toggles the idle inhibitor,
+/// returning the new state.
+///
+/// Returns `None` if the compositor does not support the idle-inhibit protocol.
+/// This must be called from the GTK main thread.
+pub fn toggle() -> Option<bool> {
+    let inhibit = !is_inhibited()?;
+    set_inhibited(inhibit)?;
+    Some(inhibit)
+}
+
+#[cfg(feature = "ipc")]
+fn set_variable(variable: &str, inhibited: bool) {
+    use crate::ironvar::get_variable_manager;
+    use crate::write_lock;
+    use tracing::error;
+
+    let variable_manager = get_variable_manager();
+    let result = write_lock!(variable_manager).set(variable.into(), inhibited.to_string());
+    if let Err(err) = result {
+        error!("Failed to set ironvar '{variable}': {err:?}");
+    }
+}
+
+impl Module<Button> for IdleInhibitorModule {
+    /// Whether idle is inhibited.
+    type SendMessage = bool;
+    type ReceiveMessage = ();
+
+    fn name() -> &'static str {
+        "idle_inhibitor"
+    }
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        tx: Sender<ModuleUpdateEvent<Self::SendMessage>>,
+        _rx: Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let Some((mut rx, inhibited)) =
+            wp_idle_inhibit::with_client(|client| (client.subscribe(), client.is_inhibited()))
+        else {
+            return Ok(());
+        };
+
+        #[cfg(feature = "ipc")]
+        let registration = self.variable.clone().map(|variable| {
+            set_variable(&variable, inhibited);
+            VariableRegistration::new(variable)
+        });
+
+        // listen to inhibitor changes
+        spawn(async move {
+            #[cfg(feature = "ipc")]
+            let _registration = registration;
+
+            send_async!(tx, ModuleUpdateEvent::Update(inhibited));
+
+            while let Ok(inhibited) = rx.recv().await {
+                send_async!(tx, ModuleUpdateEvent::Update(inhibited));
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+        let label = Label::builder()
+            .label(&self.icon_off)
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+
+        button.add(&label);
+
+        button.connect_clicked(|_| {
+            toggle();
+        });
+
+        {
+            let button = button.clone();
+
+            context.widget_rx.attach(None, move |inhibited| {
+                if inhibited {
+                    label.set_markup(&self.icon_on);
+                    button.add_class("active");
+                } else {
+                    label.set_markup(&self.icon_off);
+                    button.style_context().remove_class("active");
+                }
+
+                Continue(true)
+            });
+        }
+
+        Ok(ModuleParts {
+            widget: button,
+            popup: None,
+        })
+    }
+}
@@ -0,0 +1,271 @@
+use crate::clients::compositor::{Compositor, KeyboardLayoutUpdate};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::module_tasks::spawn;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::{send_async, try_send};
+use color_eyre::{Report, Result};
+use glib::Continue;
+use gtk::prelude::*;
+use gtk::{Button, Label};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::time::Duration;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::time::sleep;
+use tracing::error;
+
+/// Sysfs directory containing keyboard LED devices.
+const LEDS_PATH: &str = "/sys/class/leds";
+
+/// How often to check the lock key LEDs.
+///
+/// LED brightness attributes do not produce change notifications,
+/// and reading keyboard event devices instead would expose every keystroke,
+/// so the LEDs are polled.
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct KeyboardModule {
+    /// Whether to show the active layout.
+    #[serde(default = "crate::config::default_true")]
+    show_layout: bool,
+
+    /// Whether to show the caps lock indicator.
+    #[serde(default = "crate::config::default_true")]
+    show_caps: bool,
+
+    /// Whether to show the num lock indicator.
+    #[serde(default = "crate::config::default_true")]
+    show_num: bool,
+
+    /// Lock key indicator icons.
+    #[serde(default)]
+    icons: Icons,
+
+    /// Map of layout names to custom labels.
+    /// Layouts use their full name if not present in the map.
+    #[serde(default)]
+    layout_map: HashMap<String, String>,
+
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct Icons {
+    /// Icon to show when caps lock is on.
+    #[serde(default = "default_icon_caps_on")]
+    caps_on: String,
+
+    /// Icon to show when caps lock is off.
+    #[serde(default)]
+    caps_off: String,
+
+    /// Icon to show when num lock is on.
+    #[serde(default = "default_icon_num_on")]
+    num_on: String,
+
+    /// Icon to show when num lock is off.
+    #[serde(default)]
+    num_off: String,
+}
+
+impl Default for Icons {
+    fn default() -> Self {
+        Self {
+            caps_on: default_icon_caps_on(),
+            caps_off: String::new(),
+            num_on: default_icon_num_on(),
+            num_off: String::new(),
+        }
+    }
+}
+
+fn default_icon_caps_on() -> String {
+    String::from("󰪛")
+}
+
+fn default_icon_num_on() -> String {
+    String::from("󰎠")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LockState {
+    caps: bool,
+    num: bool,
+}
+
+impl LockState {
+    /// Reads the current lock key state from the keyboard LEDs.
+    fn read() -> Self {
+        Self {
+            caps: read_led("::capslock"),
+            num: read_led("::numlock"),
+        }
+    }
+}
+
+/// Checks whether any LED device with the given name suffix is lit.
+fn read_led(suffix: &str) -> bool {
+    let Ok(entries) = fs::read_dir(LEDS_PATH) else {
+        return false;
+    };
+
+    entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(suffix))
+        .any(|entry| {
+            fs::read_to_string(entry.path().join("brightness"))
+                .map_or(false, |brightness| brightness.trim() != "0")
+        })
+}
+
+#[derive(Debug, Clone)]
+pub enum KeyboardUpdate {
+    Layout(KeyboardLayoutUpdate),
+    Locks(LockState),
+}
+
+impl Module<gtk::Box> for KeyboardModule {
+    type SendMessage = KeyboardUpdate;
+    type ReceiveMessage = ();
+
+    fn name() -> &'static str {
+        "keyboard"
+    }
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        tx: Sender<ModuleUpdateEvent<Self::SendMessage>>,
+        mut rx: Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        if self.show_layout {
+            let mut layout_rx = Compositor::get_keyboard_layout_client()?.subscribe();
+
+            {
+                let tx = tx.clone();
+                spawn(async move {
+                    while let Ok(layout) = layout_rx.recv().await {
+                        send_async!(
+                            tx,
+                            ModuleUpdateEvent::Update(KeyboardUpdate::Layout(layout))
+                        );
+                    }
+                });
+            }
+
+            // Switch layout on click
+            spawn(async move {
+                while let Some(()) = rx.recv().await {
+                    let client = Compositor::get_keyboard_layout_client()?;
+                    if let Err(err) = client.set_next_active() {
+                        error!("Failed to switch keyboard layout: {err:?}");
+                    }
+                }
+
+                Ok::<(), Report>(())
+            });
+        }
+
+        if self.show_caps || self.show_num {
+            spawn(async move {
+                let mut prev = None;
+
+                loop {
+                    let state = LockState::read();
+
+                    if prev != Some(state) {
+                        let update = KeyboardUpdate::Locks(state);
+                        send_async!(tx, ModuleUpdateEvent::Update(update));
+                        prev = Some(state);
+                    }
+
+                    sleep(LOCK_POLL_INTERVAL).await;
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<gtk::Box>> {
+        let container = gtk::Box::new(info.bar_position.get_orientation(), 5);
+
+        let button = Button::new();
+        button.add_class("layout");
+
+        let layout_label = Label::new(None);
+        button.add(&layout_label);
+
+        if self.show_layout {
+            container.add(&button);
+        }
+
+        let caps = Label::new(None);
+        caps.add_class("caps");
+
+        let num = Label::new(None);
+        num.add_class("num");
+
+        if self.show_caps {
+            container.add(&caps);
+        }
+
+        if self.show_num {
+            container.add(&num);
+        }
+
+        {
+            let tx = context.controller_tx.clone();
+            button.connect_clicked(move |_| {
+                try_send!(tx, ());
+            });
+        }
+
+        {
+            let icons = self.icons;
+            let layout_map = self.layout_map;
+
+            context.widget_rx.attach(None, move |update| {
+                match update {
+                    KeyboardUpdate::Layout(KeyboardLayoutUpdate(layout)) => {
+                        let label = layout_map.get(&layout).unwrap_or(&layout);
+                        layout_label.set_label(label);
+                    }
+                    KeyboardUpdate::Locks(state) => {
+                        set_lock_label(&caps, state.caps, &icons.caps_on, &icons.caps_off);
+                        set_lock_label(&num, state.num, &icons.num_on, &icons.num_off);
+                    }
+                }
+
+                Continue(true)
+            });
+        }
+
+        Ok(ModuleParts {
+            widget: container,
+            popup: None,
+        })
+    }
+}
+
+/// Updates a lock key indicator,
+/// hiding it when it has no icon for the current state.
+fn set_lock_label(label: &Label, enabled: bool, icon_on: &str, icon_off: &str) {
+    let icon = if enabled { icon_on } else { icon_off };
+    label.set_label(icon);
+    label.set_visible(!icon.is_empty());
+
+    if enabled {
+        label.add_class("enabled");
+    } else {
+        label.style_context().remove_class("enabled");
+    }
+}
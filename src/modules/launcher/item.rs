@@ -1,15 +1,18 @@
 use super::open_state::OpenState;
-use crate::clients::wayland::ToplevelHandle;
+use crate::clients::wayland::{ToplevelHandle, ToplevelInfo};
 use crate::config::BarPosition;
 use crate::gtk_helpers::IronbarGtkExt;
 use crate::image::ImageProvider;
 use crate::modules::launcher::{ItemEvent, LauncherUpdate};
-use crate::modules::ModuleUpdateEvent;
+use crate::modules::{window_menu, ModuleUpdateEvent};
 use crate::{read_lock, try_send};
 use color_eyre::{Report, Result};
+use gtk::gdk::{DragAction, ModifierType};
 use gtk::prelude::*;
-use gtk::{Button, IconTheme};
+use gtk::{Button, CheckMenuItem, DestDefaults, IconTheme, Menu, TargetEntry, TargetFlags};
 use indexmap::IndexMap;
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::rc::Rc;
 use std::sync::RwLock;
 use tokio::sync::mpsc::Sender;
@@ -18,6 +21,9 @@ use wayland_client::protocol::wl_seat::WlSeat;
 
 #[derive(Debug, Clone)]
 pub struct Item {
+    /// Unique key for the item.
+    /// This is the app ID, unless windows are not grouped.
+    pub key: String,
     pub app_id: String,
     pub favorite: bool,
     pub open_state: OpenState,
@@ -28,6 +34,7 @@ pub struct Item {
 impl Item {
     pub fn new(app_id: String, open_state: OpenState, favorite: bool) -> Self {
         Self {
+            key: app_id.clone(),
             app_id,
             favorite,
             open_state,
@@ -113,6 +120,7 @@ impl TryFrom<ToplevelHandle> for Item {
         windows.insert(info.id, window);
 
         Ok(Self {
+            key: app_id.clone(),
             app_id,
             favorite: false,
             open_state,
@@ -152,15 +160,34 @@ impl Window {
     pub fn focus(&self, seat: &WlSeat) {
         self.handle.focus(seat);
     }
+
+    /// Gets the latest info for the window,
+    /// rather than that from when it was created.
+    pub fn info(&self) -> Option<ToplevelInfo> {
+        self.handle.info()
+    }
+
+    pub fn version(&self) -> u32 {
+        self.handle.version()
+    }
 }
 
 pub struct MenuState {
-    pub num_windows: usize,
+    pub windows: IndexMap<usize, Window>,
 }
 
+/// Set of pinned app IDs,
+/// shared between all buttons in a launcher.
+pub type PinnedApps = Rc<RefCell<HashSet<String>>>;
+
+/// Drag-and-drop target for reordering items.
+/// The dragged item's key is sent as text.
+const DRAG_TARGET: &str = "ironbar/launcher-item";
+
 pub struct ItemButton {
     pub button: Button,
-    pub persistent: bool,
+    pub key: String,
+    pub app_id: String,
     pub show_names: bool,
     pub menu_state: Rc<RwLock<MenuState>>,
 }
@@ -178,6 +205,7 @@ impl ItemButton {
         appearance: AppearanceOptions,
         icon_theme: &IconTheme,
         bar_position: BarPosition,
+        pinned: &PinnedApps,
         tx: &Sender<ModuleUpdateEvent<LauncherUpdate>>,
         controller_tx: &Sender<ItemEvent>,
     ) -> Self {
@@ -217,13 +245,14 @@ impl ItemButton {
         }
 
         {
+            let key = item.key.clone();
             let app_id = item.app_id.clone();
             let tx = controller_tx.clone();
             button.connect_clicked(move |button| {
                 // lazy check :| TODO: Improve this
                 let style_context = button.style_context();
                 if style_context.has_class("open") {
-                    try_send!(tx, ItemEvent::FocusItem(app_id.clone()));
+                    try_send!(tx, ItemEvent::FocusItem(key.clone()));
                 } else {
                     try_send!(tx, ItemEvent::OpenItem(app_id.clone()));
                 }
@@ -231,23 +260,82 @@ impl ItemButton {
         }
 
         let menu_state = Rc::new(RwLock::new(MenuState {
-            num_windows: item.windows.len(),
+            windows: item.windows.clone(),
         }));
 
         {
             let app_id = item.app_id.clone();
+            let pinned = pinned.clone();
             let tx = tx.clone();
+            let controller_tx = controller_tx.clone();
+            let menu_state = menu_state.clone();
+
+            let menu = Menu::new();
+            menu.add_class("window-menu");
+
+            button.connect_button_press_event(move |_, event| {
+                const BUTTON_RIGHT: u32 = 3;
+
+                if event.button() != BUTTON_RIGHT {
+                    return Inhibit(false);
+                }
+
+                try_send!(tx, ModuleUpdateEvent::ClosePopup);
+
+                let is_pinned = pinned.borrow().contains(&app_id);
+                let menu_state = read_lock!(menu_state);
+
+                Self::populate_menu(
+                    &menu,
+                    &menu_state.windows,
+                    &app_id,
+                    is_pinned,
+                    &controller_tx,
+                );
+                menu.popup_at_pointer(Some(event));
+
+                Inhibit(true)
+            });
+        }
+
+        {
+            let targets = [TargetEntry::new(DRAG_TARGET, TargetFlags::SAME_APP, 0)];
+            button.drag_source_set(ModifierType::BUTTON1_MASK, &targets, DragAction::MOVE);
+            button.drag_dest_set(DestDefaults::ALL, &targets, DragAction::MOVE);
+
+            let key = item.key.clone();
+            button.connect_drag_data_get(move |_, _, data, _, _| {
+                data.set_text(&key);
+            });
+
+            let key = item.key.clone();
+            let tx = controller_tx.clone();
+            button.connect_drag_data_received(move |_, _, _, _, data, _, _| {
+                if let Some(source) = data.text() {
+                    if source != key {
+                        try_send!(tx, ItemEvent::Reorder(source.to_string(), key.clone()));
+                    }
+                }
+            });
+        }
+
+        {
+            let key = item.key.clone();
+            let tx = tx.clone();
+            let controller_tx = controller_tx.clone();
             let menu_state = menu_state.clone();
 
             button.connect_enter_notify_event(move |button, _| {
                 let menu_state = read_lock!(menu_state);
 
-                if menu_state.num_windows > 1 {
+                if menu_state.windows.len() > 1 {
                     try_send!(
                         tx,
-                        ModuleUpdateEvent::Update(LauncherUpdate::Hover(app_id.clone(),))
+                        ModuleUpdateEvent::Update(LauncherUpdate::Hover(key.clone(),))
                     );
 
+                    try_send!(controller_tx, ItemEvent::CapturePreviews(key.clone()));
+
                     try_send!(
                         tx,
                         ModuleUpdateEvent::OpenPopupAt(
@@ -291,12 +379,72 @@ impl ItemButton {
 
         Self {
             button,
-            persistent: item.favorite,
+            key: item.key.clone(),
+            app_id: item.app_id.clone(),
             show_names: appearance.show_names,
             menu_state,
         }
     }
 
+    /// Fills the context menu with actions for the item's windows,
+    /// and an option to pin or unpin the app.
+    ///
+    /// With a single window the actions are added directly,
+    /// otherwise each window gets its own submenu.
+    fn populate_menu(
+        menu: &Menu,
+        windows: &IndexMap<usize, Window>,
+        app_id: &str,
+        pinned: bool,
+        controller_tx: &Sender<ItemEvent>,
+    ) {
+        window_menu::clear(menu);
+
+        let outputs = window_menu::output_names();
+
+        let windows = windows
+            .values()
+            .filter_map(|win| win.info().map(|info| (info, win.version())))
+            .collect::<Vec<_>>();
+
+        let on_action = |id| {
+            let tx = controller_tx.clone();
+            move |action| try_send!(tx, ItemEvent::WindowAction(id, action))
+        };
+
+        if let [(info, version)] = windows.as_slice() {
+            window_menu::populate(menu, info, *version, &outputs, on_action(info.id));
+        } else {
+            for (info, version) in &windows {
+                let submenu = Menu::new();
+                window_menu::populate(&submenu, info, *version, &outputs, on_action(info.id));
+
+                let item = gtk::MenuItem::with_label(&info.title);
+                item.set_submenu(Some(&submenu));
+                menu.add(&item);
+            }
+        }
+
+        if !windows.is_empty() {
+            menu.add(&gtk::SeparatorMenuItem::new());
+        }
+
+        let pin_item = CheckMenuItem::with_label("Pinned");
+        pin_item.set_active(pinned);
+
+        {
+            let app_id = app_id.to_string();
+            let tx = controller_tx.clone();
+            pin_item.connect_activate(move |_| {
+                try_send!(tx, ItemEvent::SetPinned(app_id.clone(), !pinned));
+            });
+        }
+
+        menu.add(&pin_item);
+
+        menu.show_all();
+    }
+
     pub fn set_open(&self, open: bool) {
         self.update_class("open", open);
 
@@ -309,6 +457,10 @@ impl ItemButton {
         self.update_class("focused", focused);
     }
 
+    pub fn set_pinned(&self, pinned: bool) {
+        self.update_class("favorite", pinned);
+    }
+
     /// Adds or removes a class to the button based on `toggle`.
     fn update_class(&self, class: &str, toggle: bool) {
         let style_context = self.button.style_context();
@@ -1,11 +1,15 @@
 mod item;
 mod open_state;
+mod state;
 
-use self::item::{Item, ItemButton, Window};
+use self::item::{Item, ItemButton, PinnedApps, Window};
 use self::open_state::OpenState;
-use crate::clients::wayland::{self, ToplevelEvent};
+use self::state::LauncherState;
+use crate::clients::wayland::{self, Preview, ToplevelAction, ToplevelEvent, ToplevelInfo};
 use crate::config::CommonConfig;
 use crate::desktop_file::find_desktop_file;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::module_tasks::spawn;
 use crate::modules::launcher::item::AppearanceOptions;
 use crate::modules::{
     Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, WidgetContext,
@@ -13,20 +17,28 @@ use crate::modules::{
 use crate::{arc_mut, lock, send_async, try_send, write_lock};
 use color_eyre::{Help, Report};
 use glib::Continue;
+use gtk::gdk_pixbuf::{Colorspace, Pixbuf};
 use gtk::prelude::*;
-use gtk::{Button, Orientation};
+use gtk::{Button, Label, Orientation};
 use indexmap::IndexMap;
 use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::process::{Command, Stdio};
+use std::rc::Rc;
 use std::sync::Arc;
-use tokio::spawn;
+use std::time::Duration;
 use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::time::sleep;
 use tracing::{debug, error, trace};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct LauncherModule {
     /// List of app IDs (or classes) to always show regardless of open state,
     /// in the order specified.
+    ///
+    /// Once apps are pinned, unpinned or reordered from the bar,
+    /// the saved state is used instead.
     favorites: Option<Vec<String>>,
     /// Whether to show application names on the bar.
     #[serde(default = "crate::config::default_false")]
@@ -38,30 +50,83 @@ pub struct LauncherModule {
     #[serde(default = "default_icon_size")]
     icon_size: i32,
 
+    /// Whether to show window previews in the popup.
+    /// Requires the compositor to support
+    /// `ext-image-copy-capture-v1` and `ext-foreign-toplevel-list-v1`.
+    #[serde(default = "crate::config::default_false")]
+    show_previews: bool,
+
+    /// How to group windows into items.
+    #[serde(default)]
+    grouping: Grouping,
+
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
 }
 
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Grouping {
+    /// One item per app, with its windows shown in a popup.
+    #[default]
+    AppId,
+    /// One item per window.
+    Window,
+}
+
+impl Grouping {
+    /// Gets the key of the item a new window should be added to.
+    ///
+    /// When not grouping, the first window of an app
+    /// takes over its pinned item, if it has one.
+    fn item_key(self, items: &IndexMap<String, Item>, info: &ToplevelInfo) -> String {
+        match self {
+            Self::AppId => info.app_id.clone(),
+            Self::Window => match items.get(&info.app_id) {
+                Some(item) if !item.windows.is_empty() => format!("{}:{}", info.app_id, info.id),
+                _ => info.app_id.clone(),
+            },
+        }
+    }
+}
+
 const fn default_icon_size() -> i32 {
     32
 }
 
+/// Width of the popup, and therefore the maximum width of its contents.
+const POPUP_WIDTH: i32 = 250;
+
+/// Width to scale window previews down to.
+const PREVIEW_WIDTH: i32 = POPUP_WIDTH - 30;
+
+/// Time to wait after a window is focused before capturing its preview,
+/// allowing it to finish redrawing.
+const PREVIEW_DELAY: Duration = Duration::from_millis(500);
+
 #[derive(Debug, Clone)]
 pub enum LauncherUpdate {
     /// Adds item
     AddItem(Item),
-    /// Adds window to item with `app_id`
+    /// Adds window to item with `key`
     AddWindow(String, Window),
-    /// Removes item with `app_id`
+    /// Removes item with `key`,
+    /// or marks it as closed if the app is pinned.
     RemoveItem(String),
-    /// Removes window from item with `app_id`.
+    /// Removes window from item with `key`.
     RemoveWindow(String, usize),
-    /// Sets title for `app_id`
+    /// Sets title for `key`
     Title(String, usize, String),
-    /// Marks the item with `app_id` as focused or not focused
+    /// Marks the item with `key` as focused or not focused
     Focus(String, bool),
-    /// Declares the item with `app_id` has been hovered over
+    /// Declares the item with `key` has been hovered over
     Hover(String),
+    /// Sets the preview for window on item with `key`
+    Preview(String, usize, Arc<Preview>),
+    /// Marks the app with `app_id` as pinned or unpinned
+    Pin(String, bool),
+    /// Moves the item with the first `key` to the position of the second
+    Reorder(String, String),
 }
 
 #[derive(Debug)]
@@ -69,6 +134,14 @@ pub enum ItemEvent {
     FocusItem(String),
     FocusWindow(usize),
     OpenItem(String),
+    /// Performs a window management action on the window with `id`
+    WindowAction(usize, ToplevelAction),
+    /// Pins or unpins the app with `app_id`
+    SetPinned(String, bool),
+    /// Moves the item with the first `key` to the position of the second
+    Reorder(String, String),
+    /// Captures previews of each window on the item with `key`
+    CapturePreviews(String),
 }
 
 enum ItemOrWindow {
@@ -81,6 +154,109 @@ enum ItemOrWindowId {
     Window,
 }
 
+/// Captures a preview of the window with `id` after `delay`,
+/// allowing it time to redraw.
+///
+/// The preview is left unchanged if the compositor cannot capture the window.
+fn capture_preview(
+    id: usize,
+    key: String,
+    delay: Duration,
+    tx: Sender<ModuleUpdateEvent<LauncherUpdate>>,
+) {
+    spawn(async move {
+        sleep(delay).await;
+
+        let rx = {
+            let wl = wayland::get_client();
+            let wl = lock!(wl);
+            wl.capture_preview(id, PREVIEW_WIDTH)
+        };
+
+        if let Ok(Some(preview)) = rx.await {
+            send_async!(
+                tx,
+                ModuleUpdateEvent::Update(LauncherUpdate::Preview(key, id, Arc::new(preview)))
+            );
+        }
+    });
+}
+
+/// Finds the item containing the window with `id`,
+/// returning it alongside its key.
+fn find_window_item(items: &mut IndexMap<String, Item>, id: usize) -> Option<(String, &mut Item)> {
+    items
+        .iter_mut()
+        .find(|(_, item)| item.windows.contains_key(&id))
+        .map(|(key, item)| (key.clone(), item))
+}
+
+/// Gets the app IDs of the pinned items, in display order.
+fn pinned_order(items: &IndexMap<String, Item>) -> Vec<String> {
+    items
+        .values()
+        .filter(|item| item.favorite)
+        .map(|item| item.app_id.clone())
+        .collect()
+}
+
+/// Pins or unpins the app with `app_id`,
+/// returning the updates required to reflect this on the bar.
+///
+/// Only the item keyed by the app ID is pinned,
+/// so a closed item is added if none exists.
+fn set_pinned(
+    items: &mut IndexMap<String, Item>,
+    app_id: &str,
+    pinned: bool,
+) -> Vec<LauncherUpdate> {
+    let mut updates = vec![LauncherUpdate::Pin(app_id.to_string(), pinned)];
+
+    match items.get_mut(app_id) {
+        Some(item) => {
+            item.favorite = pinned;
+
+            if !pinned && item.windows.is_empty() {
+                items.remove(app_id);
+                updates.push(LauncherUpdate::RemoveItem(app_id.to_string()));
+            }
+        }
+        None if pinned => {
+            let item = Item::new(app_id.to_string(), OpenState::Closed, true);
+            items.insert(app_id.to_string(), item.clone());
+            updates.push(LauncherUpdate::AddItem(item));
+        }
+        None => {}
+    }
+
+    updates
+}
+
+/// Launches the app with `app_id` using its desktop file.
+fn launch(app_id: &str) {
+    find_desktop_file(app_id).map_or_else(
+        || error!("Could not find desktop file for {}", app_id),
+        |file| {
+            if let Err(err) = Command::new("gtk-launch")
+                .arg(
+                    file.file_name()
+                        .expect("File segment missing from path to desktop file"),
+                )
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+            {
+                error!(
+                    "{:?}",
+                    Report::new(err)
+                        .wrap_err("Failed to run gtk-launch command.")
+                        .suggestion("Perhaps the desktop file is invalid?")
+                );
+            }
+        },
+    );
+}
+
 impl Module<gtk::Box> for LauncherModule {
     type SendMessage = LauncherUpdate;
     type ReceiveMessage = ItemEvent;
@@ -95,22 +271,23 @@ impl Module<gtk::Box> for LauncherModule {
         tx: Sender<ModuleUpdateEvent<Self::SendMessage>>,
         mut rx: Receiver<Self::ReceiveMessage>,
     ) -> crate::Result<()> {
-        let items = self
-            .favorites
-            .as_ref()
-            .map_or_else(IndexMap::new, |favorites| {
-                favorites
-                    .iter()
-                    .map(|app_id| {
-                        (
-                            app_id.to_string(),
-                            Item::new(app_id.to_string(), OpenState::Closed, true),
-                        )
-                    })
-                    .collect::<IndexMap<_, _>>()
-            });
+        let pinned = match LauncherState::load() {
+            Ok(Some(state)) => state.pinned,
+            Ok(None) => self.favorites.clone().unwrap_or_default(),
+            Err(err) => {
+                error!("{:?}", err.wrap_err("Failed to load launcher state"));
+                self.favorites.clone().unwrap_or_default()
+            }
+        };
+
+        let items = pinned
+            .into_iter()
+            .map(|app_id| (app_id.clone(), Item::new(app_id, OpenState::Closed, true)))
+            .collect::<IndexMap<_, _>>();
 
         let items = arc_mut!(items);
+        let show_previews = self.show_previews;
+        let grouping = self.grouping;
 
         let items2 = Arc::clone(&items);
         let tx2 = tx.clone();
@@ -118,6 +295,10 @@ impl Module<gtk::Box> for LauncherModule {
             let items = items2;
             let tx = tx2;
 
+            // previews are refreshed when a window gains focus,
+            // so track the focused window to ignore other updates
+            let mut focused_window = None;
+
             let (mut wlrx, handles) = {
                 let wl = wayland::get_client();
                 let wl = lock!(wl);
@@ -128,13 +309,17 @@ impl Module<gtk::Box> for LauncherModule {
                 let Some(info) = handle.info() else { continue };
 
                 let mut items = lock!(items);
-                let item = items.get_mut(&info.app_id);
+                let key = grouping.item_key(&items, &info);
+
+                let item = items.get_mut(&key);
                 match item {
                     Some(item) => {
                         item.merge_toplevel(handle.clone())?;
                     }
                     None => {
-                        items.insert(info.app_id.clone(), Item::try_from(handle.clone())?);
+                        let mut item = Item::try_from(handle.clone())?;
+                        item.key = key.clone();
+                        items.insert(key, item);
                     }
                 }
             }
@@ -159,66 +344,85 @@ impl Module<gtk::Box> for LauncherModule {
                     ToplevelEvent::New(handle) => {
                         let Some(info) = handle.info() else { continue };
 
-                        let new_item = {
+                        let (key, new_item) = {
                             let mut items = lock!(items);
-                            let item = items.get_mut(&info.app_id);
-                            match item {
+                            let key = grouping.item_key(&items, &info);
+
+                            let item = items.get_mut(&key);
+                            let new_item = match item {
                                 None => {
-                                    let item: Item = handle.try_into()?;
+                                    let mut item: Item = handle.clone().try_into()?;
+                                    item.key = key.clone();
 
-                                    items.insert(info.app_id.clone(), item.clone());
+                                    items.insert(key.clone(), item.clone());
 
                                     ItemOrWindow::Item(item)
                                 }
                                 Some(item) => {
-                                    let window = item.merge_toplevel(handle)?;
+                                    let window = item.merge_toplevel(handle.clone())?;
                                     ItemOrWindow::Window(window)
                                 }
-                            }
+                            };
+
+                            (key, new_item)
                         };
 
+                        if show_previews && info.focused {
+                            focused_window = Some(info.id);
+                            capture_preview(info.id, key.clone(), PREVIEW_DELAY, tx.clone());
+                        }
+
                         match new_item {
                             ItemOrWindow::Item(item) => {
                                 send_update(LauncherUpdate::AddItem(item)).await
                             }
                             ItemOrWindow::Window(window) => {
-                                send_update(LauncherUpdate::AddWindow(info.app_id.clone(), window))
-                                    .await
+                                send_update(LauncherUpdate::AddWindow(key, window)).await
                             }
                         }?;
                     }
                     ToplevelEvent::Update(handle) => {
                         let Some(info) = handle.info() else { continue };
 
-                        if let Some(item) = lock!(items).get_mut(&info.app_id) {
+                        let key = {
+                            let mut items = lock!(items);
+                            let Some((key, item)) = find_window_item(&mut items, info.id) else {
+                                continue;
+                            };
+
                             item.set_window_focused(info.id, info.focused);
                             item.set_window_name(info.id, info.title.clone());
+
+                            key
+                        };
+
+                        if show_previews && info.focused && focused_window != Some(info.id) {
+                            focused_window = Some(info.id);
+                            capture_preview(info.id, key.clone(), PREVIEW_DELAY, tx.clone());
                         }
 
-                        send_update(LauncherUpdate::Focus(info.app_id.clone(), info.focused))
+                        send_update(LauncherUpdate::Focus(key.clone(), info.focused)).await?;
+                        send_update(LauncherUpdate::Title(key, info.id, info.title.clone()))
                             .await?;
-                        send_update(LauncherUpdate::Title(
-                            info.app_id.clone(),
-                            info.id,
-                            info.title.clone(),
-                        ))
-                        .await?;
                     }
                     ToplevelEvent::Remove(handle) => {
                         let Some(info) = handle.info() else { continue };
 
                         let remove_item = {
                             let mut items = lock!(items);
-                            let item = items.get_mut(&info.app_id);
-                            match item {
-                                Some(item) => {
+                            match find_window_item(&mut items, info.id) {
+                                Some((key, item)) => {
                                     item.unmerge_toplevel(&handle);
 
                                     if item.windows.is_empty() {
-                                        items.remove(&info.app_id);
-                                        Some(ItemOrWindowId::Item)
+                                        // pinned items stay in place, closed
+                                        if !item.favorite {
+                                            items.remove(&key);
+                                        }
+
+                                        Some((key, ItemOrWindowId::Item))
                                     } else {
-                                        Some(ItemOrWindowId::Window)
+                                        Some((key, ItemOrWindowId::Window))
                                     }
                                 }
                                 None => None,
@@ -226,16 +430,11 @@ impl Module<gtk::Box> for LauncherModule {
                         };
 
                         match remove_item {
-                            Some(ItemOrWindowId::Item) => {
-                                send_update(LauncherUpdate::RemoveItem(info.app_id.clone()))
-                                    .await?;
+                            Some((key, ItemOrWindowId::Item)) => {
+                                send_update(LauncherUpdate::RemoveItem(key)).await?;
                             }
-                            Some(ItemOrWindowId::Window) => {
-                                send_update(LauncherUpdate::RemoveWindow(
-                                    info.app_id.clone(),
-                                    info.id,
-                                ))
-                                .await?;
+                            Some((key, ItemOrWindowId::Window)) => {
+                                send_update(LauncherUpdate::RemoveWindow(key, info.id)).await?;
                             }
                             None => {}
                         };
@@ -249,62 +448,94 @@ impl Module<gtk::Box> for LauncherModule {
         // listen to ui events
         spawn(async move {
             while let Some(event) = rx.recv().await {
-                if let ItemEvent::OpenItem(app_id) = event {
-                    find_desktop_file(&app_id).map_or_else(
-                        || error!("Could not find desktop file for {}", app_id),
-                        |file| {
-                            if let Err(err) = Command::new("gtk-launch")
-                                .arg(
-                                    file.file_name()
-                                        .expect("File segment missing from path to desktop file"),
-                                )
-                                .stdout(Stdio::null())
-                                .stderr(Stdio::null())
-                                .spawn()
-                            {
-                                error!(
-                                    "{:?}",
-                                    Report::new(err)
-                                        .wrap_err("Failed to run gtk-launch command.")
-                                        .suggestion("Perhaps the desktop file is invalid?")
-                                );
-                            }
-                        },
-                    );
-                } else {
-                    send_async!(tx, ModuleUpdateEvent::ClosePopup);
-
-                    let wl = wayland::get_client();
-                    let items = lock!(items);
-
-                    let id = match event {
-                        ItemEvent::FocusItem(app_id) => items.get(&app_id).and_then(|item| {
-                            item.windows
-                                .iter()
-                                .find(|(_, win)| !win.open_state.is_focused())
-                                .or_else(|| item.windows.first())
-                                .map(|(_, win)| win.id)
-                        }),
-                        ItemEvent::FocusWindow(id) => Some(id),
-                        ItemEvent::OpenItem(_) => unreachable!(),
-                    };
-
-                    if let Some(id) = id {
-                        if let Some(window) =
-                            items.iter().find_map(|(_, item)| item.windows.get(&id))
-                        {
-                            debug!("Focusing window {id}: {}", window.name);
+                match event {
+                    ItemEvent::OpenItem(app_id) => launch(&app_id),
+                    ItemEvent::WindowAction(id, action) => {
+                        let wl = wayland::get_client();
+                        lock!(wl).toplevel_action(id, action);
+                    }
+                    ItemEvent::SetPinned(app_id, pinned) => {
+                        let (updates, order) = {
+                            let mut items = lock!(items);
+                            let updates = set_pinned(&mut items, &app_id, pinned);
+                            (updates, pinned_order(&items))
+                        };
+
+                        for update in updates {
+                            send_async!(tx, ModuleUpdateEvent::Update(update));
+                        }
+
+                        LauncherState::update(&order, |state| state.set_pinned(&app_id, pinned));
+                    }
+                    ItemEvent::Reorder(source, target) => {
+                        let order = {
+                            let mut items = lock!(items);
+
+                            let (Some(from), Some(to)) =
+                                (items.get_index_of(&source), items.get_index_of(&target))
+                            else {
+                                continue;
+                            };
+
+                            items.move_index(from, to);
 
-                            let seat = lock!(wl)
-                                .get_seats()
-                                .pop()
-                                .expect("Failed to get Wayland seat");
-                            window.focus(&seat);
+                            pinned_order(&items)
+                        };
+
+                        send_async!(
+                            tx,
+                            ModuleUpdateEvent::Update(LauncherUpdate::Reorder(source, target))
+                        );
+
+                        LauncherState::update(&order, |state| state.sort_pinned(&order));
+                    }
+                    ItemEvent::CapturePreviews(key) if show_previews => {
+                        let ids = lock!(items)
+                            .get(&key)
+                            .map(|item| item.windows.keys().copied().collect::<Vec<_>>())
+                            .unwrap_or_default();
+
+                        // windows are already drawn, so there is no need to wait
+                        for id in ids {
+                            capture_preview(id, key.clone(), Duration::ZERO, tx.clone());
                         }
                     }
+                    ItemEvent::CapturePreviews(_) => {}
+                    ItemEvent::FocusItem(_) | ItemEvent::FocusWindow(_) => {
+                        send_async!(tx, ModuleUpdateEvent::ClosePopup);
+
+                        let wl = wayland::get_client();
+                        let items = lock!(items);
+
+                        let id = match event {
+                            ItemEvent::FocusItem(key) => items.get(&key).and_then(|item| {
+                                item.windows
+                                    .iter()
+                                    .find(|(_, win)| !win.open_state.is_focused())
+                                    .or_else(|| item.windows.first())
+                                    .map(|(_, win)| win.id)
+                            }),
+                            ItemEvent::FocusWindow(id) => Some(id),
+                            _ => unreachable!(),
+                        };
+
+                        if let Some(id) = id {
+                            if let Some(window) =
+                                items.iter().find_map(|(_, item)| item.windows.get(&id))
+                            {
+                                debug!("Focusing window {id}: {}", window.name);
 
-                    // roundtrip to immediately send activate event
-                    lock!(wl).roundtrip();
+                                let seat = lock!(wl)
+                                    .get_seats()
+                                    .pop()
+                                    .expect("Failed to get Wayland seat");
+                                window.focus(&seat);
+                            }
+                        }
+
+                        // roundtrip to immediately send activate event
+                        lock!(wl).roundtrip();
+                    }
                 }
             }
         });
@@ -337,79 +568,116 @@ impl Module<gtk::Box> for LauncherModule {
             let bar_position = info.bar_position;
 
             let mut buttons = IndexMap::<String, ItemButton>::new();
+            let pinned: PinnedApps = Rc::new(RefCell::new(HashSet::new()));
 
             context.widget_rx.attach(None, move |event| {
                 match event {
                     LauncherUpdate::AddItem(item) => {
-                        debug!("Adding item with id {}", item.app_id);
+                        debug!("Adding item with key {}", item.key);
+
+                        if item.favorite {
+                            pinned.borrow_mut().insert(item.app_id.clone());
+                        }
 
-                        if let Some(button) = buttons.get(&item.app_id) {
+                        if let Some(button) = buttons.get(&item.key) {
                             button.set_open(true);
+                            write_lock!(button.menu_state).windows = item.windows;
                         } else {
                             let button = ItemButton::new(
                                 &item,
                                 appearance_options,
                                 &icon_theme,
                                 bar_position,
+                                &pinned,
                                 &context.tx,
                                 &controller_tx,
                             );
 
                             container.add(&button.button);
-                            buttons.insert(item.app_id, button);
+                            buttons.insert(item.key, button);
                         }
                     }
-                    LauncherUpdate::AddWindow(app_id, win) => {
-                        if let Some(button) = buttons.get(&app_id) {
+                    LauncherUpdate::AddWindow(key, win) => {
+                        if let Some(button) = buttons.get(&key) {
                             button.set_open(true);
                             button.set_focused(win.open_state.is_focused());
 
                             let mut menu_state = write_lock!(button.menu_state);
-                            menu_state.num_windows += 1;
+                            menu_state.windows.insert(win.id, win);
                         }
                     }
-                    LauncherUpdate::RemoveItem(app_id) => {
-                        debug!("Removing item with id {}", app_id);
+                    LauncherUpdate::RemoveItem(key) => {
+                        debug!("Removing item with key {}", key);
 
-                        if let Some(button) = buttons.get(&app_id) {
-                            if button.persistent {
+                        if let Some(button) = buttons.get(&key) {
+                            // only the item keyed by the app ID is kept for pinned apps
+                            let persistent =
+                                button.key == button.app_id && pinned.borrow().contains(&key);
+
+                            if persistent {
                                 button.set_open(false);
+                                write_lock!(button.menu_state).windows.clear();
                                 if button.show_names {
-                                    button.button.set_label(&app_id);
+                                    button.button.set_label(&key);
                                 }
                             } else {
                                 container.remove(&button.button);
-                                buttons.remove(&app_id);
+                                buttons.remove(&key);
                             }
                         }
                     }
-                    LauncherUpdate::RemoveWindow(app_id, win_id) => {
-                        debug!("Removing window {win_id} with id {app_id}");
+                    LauncherUpdate::RemoveWindow(key, win_id) => {
+                        debug!("Removing window {win_id} with key {key}");
 
-                        if let Some(button) = buttons.get(&app_id) {
+                        if let Some(button) = buttons.get(&key) {
                             button.set_focused(false);
 
                             let mut menu_state = write_lock!(button.menu_state);
-                            menu_state.num_windows -= 1;
+                            menu_state.windows.remove(&win_id);
                         }
                     }
-                    LauncherUpdate::Focus(app_id, focus) => {
-                        debug!("Changing focus to {} on item with id {}", focus, app_id);
+                    LauncherUpdate::Focus(key, focus) => {
+                        debug!("Changing focus to {} on item with key {}", focus, key);
 
-                        if let Some(button) = buttons.get(&app_id) {
+                        if let Some(button) = buttons.get(&key) {
                             button.set_focused(focus);
                         }
                     }
-                    LauncherUpdate::Title(app_id, _, name) => {
-                        debug!("Updating title for item with id {}: {:?}", app_id, name);
+                    LauncherUpdate::Title(key, _, name) => {
+                        debug!("Updating title for item with key {}: {:?}", key, name);
 
                         if show_names {
-                            if let Some(button) = buttons.get(&app_id) {
+                            if let Some(button) = buttons.get(&key) {
                                 button.button.set_label(&name);
                             }
                         }
                     }
-                    LauncherUpdate::Hover(_) => {}
+                    LauncherUpdate::Pin(app_id, is_pinned) => {
+                        debug!("Setting pinned to {is_pinned} for app {app_id}");
+
+                        if is_pinned {
+                            pinned.borrow_mut().insert(app_id.clone());
+                        } else {
+                            pinned.borrow_mut().remove(&app_id);
+                        }
+
+                        if let Some(button) = buttons.get(&app_id) {
+                            button.set_pinned(is_pinned);
+                        }
+                    }
+                    LauncherUpdate::Reorder(source, target) => {
+                        debug!("Moving item with key {source} to position of {target}");
+
+                        if let (Some(from), Some(to)) =
+                            (buttons.get_index_of(&source), buttons.get_index_of(&target))
+                        {
+                            let position = container.child_position(&buttons[to].button);
+                            container.reorder_child(&buttons[from].button, position);
+
+                            buttons.move_index(from, to);
+                        }
+                    }
+                    LauncherUpdate::Hover(_) | LauncherUpdate::Preview(..) => {}
                 };
 
                 Continue(true)
@@ -432,102 +700,81 @@ impl Module<gtk::Box> for LauncherModule {
         rx: glib::Receiver<Self::SendMessage>,
         _info: &ModuleInfo,
     ) -> Option<gtk::Box> {
-        const MAX_WIDTH: i32 = 250;
-
         let container = gtk::Box::new(Orientation::Vertical, 0);
 
         // we need some content to force the container to have a size
         let placeholder = Button::with_label("PLACEHOLDER");
-        placeholder.set_width_request(MAX_WIDTH);
+        placeholder.set_width_request(POPUP_WIDTH);
         container.add(&placeholder);
 
-        let mut buttons = IndexMap::<String, IndexMap<usize, Button>>::new();
+        let mut buttons = IndexMap::<String, IndexMap<usize, WindowButton>>::new();
 
         {
             let container = container.clone();
             rx.attach(None, move |event| {
                 match event {
                     LauncherUpdate::AddItem(item) => {
-                        let app_id = item.app_id.clone();
-                        trace!("Adding item with id '{app_id}' to the popup: {item:?}");
+                        let key = item.key.clone();
+                        trace!("Adding item with key '{key}' to the popup: {item:?}");
 
                         let window_buttons = item
                             .windows
                             .into_iter()
-                            .map(|(_, win)| {
-                                let button = Button::builder()
-                                    .label(clamp(&win.name))
-                                    .height_request(40)
-                                    .build();
-
-                                {
-                                    let tx = controller_tx.clone();
-                                    button.connect_clicked(move |_| {
-                                        try_send!(tx, ItemEvent::FocusWindow(win.id));
-                                    });
-                                }
-
-                                (win.id, button)
-                            })
+                            .map(|(_, win)| (win.id, WindowButton::new(&win, &controller_tx)))
                             .collect();
 
-                        buttons.insert(app_id, window_buttons);
+                        buttons.insert(key, window_buttons);
                     }
-                    LauncherUpdate::AddWindow(app_id, win) => {
+                    LauncherUpdate::AddWindow(key, win) => {
                         debug!(
-                            "Adding new window to popup for '{app_id}': '{}' ({})",
+                            "Adding new window to popup for '{key}': '{}' ({})",
                             win.name, win.id
                         );
 
-                        if let Some(buttons) = buttons.get_mut(&app_id) {
-                            let button = Button::builder()
-                                .height_request(40)
-                                .label(clamp(&win.name))
-                                .build();
-
-                            {
-                                let tx = controller_tx.clone();
-                                button.connect_clicked(move |_button| {
-                                    try_send!(tx, ItemEvent::FocusWindow(win.id));
-                                });
-                            }
-
-                            buttons.insert(win.id, button);
+                        if let Some(buttons) = buttons.get_mut(&key) {
+                            buttons.insert(win.id, WindowButton::new(&win, &controller_tx));
                         }
                     }
-                    LauncherUpdate::RemoveWindow(app_id, win_id) => {
-                        debug!("Removing window from popup for '{app_id}': {win_id}");
+                    LauncherUpdate::RemoveWindow(key, win_id) => {
+                        debug!("Removing window from popup for '{key}': {win_id}");
 
-                        if let Some(buttons) = buttons.get_mut(&app_id) {
+                        if let Some(buttons) = buttons.get_mut(&key) {
                             buttons.remove(&win_id);
                         }
                     }
-                    LauncherUpdate::Title(app_id, win_id, title) => {
-                        debug!(
-                            "Updating window title on popup for '{app_id}'/{win_id} to '{title}'"
-                        );
+                    LauncherUpdate::Title(key, win_id, title) => {
+                        debug!("Updating window title on popup for '{key}'/{win_id} to '{title}'");
+
+                        if let Some(buttons) = buttons.get_mut(&key) {
+                            if let Some(button) = buttons.get(&win_id) {
+                                button.set_title(&title);
+                            }
+                        }
+                    }
+                    LauncherUpdate::Preview(key, win_id, preview) => {
+                        trace!("Updating window preview on popup for '{key}'/{win_id}");
 
-                        if let Some(buttons) = buttons.get_mut(&app_id) {
+                        if let Some(buttons) = buttons.get(&key) {
                             if let Some(button) = buttons.get(&win_id) {
-                                button.set_label(&title);
+                                button.set_preview(&preview);
                             }
                         }
                     }
-                    LauncherUpdate::Hover(app_id) => {
+                    LauncherUpdate::Hover(key) => {
                         // empty current buttons
                         for child in container.children() {
                             container.remove(&child);
                         }
 
                         // add app's buttons
-                        if let Some(buttons) = buttons.get(&app_id) {
+                        if let Some(buttons) = buttons.get(&key) {
                             for (_, button) in buttons {
-                                button.style_context().add_class("popup-item");
-                                container.add(button);
+                                button.button.style_context().add_class("popup-item");
+                                container.add(&button.button);
                             }
 
                             container.show_all();
-                            container.set_width_request(MAX_WIDTH);
+                            container.set_width_request(POPUP_WIDTH);
                         }
                     }
                     _ => {}
@@ -541,6 +788,69 @@ impl Module<gtk::Box> for LauncherModule {
     }
 }
 
+/// A button in the popup for a single window,
+/// which focuses the window when clicked.
+struct WindowButton {
+    button: Button,
+    label: Label,
+    preview: gtk::Image,
+}
+
+impl WindowButton {
+    fn new(win: &Window, tx: &Sender<ItemEvent>) -> Self {
+        let button = Button::builder().height_request(40).build();
+
+        let container = gtk::Box::new(Orientation::Vertical, 5);
+
+        let preview = gtk::Image::new();
+        preview.add_class("preview");
+        // only shown once a preview has been captured
+        preview.set_no_show_all(true);
+        container.add(&preview);
+
+        let label = Label::new(Some(&clamp(&win.name)));
+        container.add(&label);
+
+        button.add(&container);
+
+        {
+            let tx = tx.clone();
+            let id = win.id;
+
+            button.connect_clicked(move |_| {
+                try_send!(tx, ItemEvent::FocusWindow(id));
+            });
+        }
+
+        Self {
+            button,
+            label,
+            preview,
+        }
+    }
+
+    fn set_title(&self, title: &str) {
+        self.label.set_label(&clamp(title));
+    }
+
+    fn set_preview(&self, preview: &Preview) {
+        const BITS_PER_SAMPLE: i32 = 8;
+
+        let pixbuf = Pixbuf::from_bytes(
+            &glib::Bytes::from(&preview.data),
+            Colorspace::Rgb,
+            true,
+            BITS_PER_SAMPLE,
+            preview.width,
+            preview.height,
+            preview.width * 4,
+        );
+
+        self.preview.set_from_pixbuf(Some(&pixbuf));
+        self.preview.show();
+    }
+}
+
 /// Clamps a string at 24 characters.
 ///
 /// This is a hacky number derived from
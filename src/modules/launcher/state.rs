@@ -0,0 +1,175 @@
+use color_eyre::{Report, Result};
+use dirs::data_dir;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, error};
+
+/// Launcher state persisted between runs.
+///
+/// This is shared between all launcher instances.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LauncherState {
+    /// App IDs pinned to the launcher, in display order.
+    pub pinned: Vec<String>,
+}
+
+impl LauncherState {
+    /// Gets the path to the state file,
+    /// alongside the log file in the user's data directory.
+    fn path() -> Result<PathBuf> {
+        Ok(data_dir()
+            .unwrap_or(env::current_dir()?)
+            .join("ironbar")
+            .join("launcher.json"))
+    }
+
+    /// Loads the state from disk.
+    ///
+    /// Returns `None` if the state file does not exist yet.
+    pub fn load() -> Result<Option<Self>> {
+        Self::load_from(&Self::path()?)
+    }
+
+    /// Applies `f` to the latest state on disk and writes the result back.
+    ///
+    /// The state is re-read first so that changes made by other
+    /// launcher instances are not lost.
+    /// If there is no saved state yet, it is seeded with `pinned`,
+    /// which should be the launcher's current pinned apps.
+    pub fn update<F>(pinned: &[String], f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        match Self::path() {
+            Ok(path) => Self::update_at(&path, pinned, f),
+            Err(err) => error!("{:?}", err.wrap_err("Failed to get launcher state path")),
+        }
+    }
+
+    fn load_from(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        debug!("Loading launcher state from {}", path.display());
+
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(Report::new)
+    }
+
+    fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        debug!("Saving launcher state to {}", path.display());
+
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(path, contents)?;
+
+        Ok(())
+    }
+
+    fn update_at<F>(path: &Path, pinned: &[String], f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let seed = || Self {
+            pinned: pinned.to_vec(),
+        };
+
+        let mut state = match Self::load_from(path) {
+            Ok(state) => state.unwrap_or_else(seed),
+            Err(err) => {
+                error!("{:?}", err.wrap_err("Failed to load launcher state"));
+                seed()
+            }
+        };
+
+        f(&mut state);
+
+        if let Err(err) = state.save_to(path) {
+            error!("{:?}", err.wrap_err("Failed to save launcher state"));
+        }
+    }
+
+    /// Sets whether `app_id` is pinned.
+    /// Newly pinned apps are added to the end.
+    pub fn set_pinned(&mut self, app_id: &str, pinned: bool) {
+        if pinned {
+            if !self.pinned.iter().any(|id| id == app_id) {
+                self.pinned.push(app_id.to_string());
+            }
+        } else {
+            self.pinned.retain(|id| id != app_id);
+        }
+    }
+
+    /// Sorts the pinned apps to match their relative order in `order`.
+    /// Apps not in `order` keep their position after those which are.
+    pub fn sort_pinned(&mut self, order: &[String]) {
+        self.pinned.sort_by_key(|app_id| {
+            order
+                .iter()
+                .position(|id| id == app_id)
+                .unwrap_or(usize::MAX)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(pinned: &[&str]) -> LauncherState {
+        LauncherState {
+            pinned: pinned.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn test_set_pinned() {
+        let mut state = state(&["firefox"]);
+
+        state.set_pinned("kitty", true);
+        state.set_pinned("firefox", true);
+        assert_eq!(state.pinned, ["firefox", "kitty"]);
+
+        state.set_pinned("firefox", false);
+        assert_eq!(state.pinned, ["kitty"]);
+    }
+
+    #[test]
+    fn test_sort_pinned() {
+        let mut state = state(&["firefox", "kitty", "discord", "steam"]);
+
+        let order = ["discord", "code", "firefox"].map(String::from);
+        state.sort_pinned(&order);
+
+        assert_eq!(state.pinned, ["discord", "firefox", "kitty", "steam"]);
+    }
+
+    #[test]
+    fn test_update_seeds_from_pinned() {
+        let dir = env::temp_dir().join(format!("ironbar-launcher-test-{}", std::process::id()));
+        let path = dir.join("launcher.json");
+        let _ = fs::remove_file(&path);
+
+        let favorites = ["firefox", "discord"].map(String::from);
+        let mut pinned = favorites.to_vec();
+        pinned.push(String::from("kitty"));
+
+        LauncherState::update_at(&path, &pinned, |state| state.set_pinned("kitty", true));
+
+        let state = LauncherState::load_from(&path)
+            .expect("state should load")
+            .expect("state file should exist");
+        assert_eq!(state.pinned, ["firefox", "discord", "kitty"]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
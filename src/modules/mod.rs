@@ -15,6 +15,10 @@ use crate::gtk_helpers::{IronbarGtkExt, WidgetGeometry};
 use crate::popup::Popup;
 use crate::send;
 
+#[cfg(feature = "bluetooth")]
+pub mod bluetooth;
+#[cfg(feature = "brightness")]
+pub mod brightness;
 #[cfg(feature = "clipboard")]
 pub mod clipboard;
 /// Displays the current date and time.
@@ -27,10 +31,18 @@ pub mod clipboard;
 pub mod clock;
 pub mod custom;
 pub mod focused;
+#[cfg(feature = "idle_inhibitor")]
+pub mod idle_inhibitor;
+#[cfg(feature = "keyboard")]
+pub mod keyboard;
 pub mod label;
 pub mod launcher;
 #[cfg(feature = "music")]
 pub mod music;
+#[cfg(feature = "network")]
+pub mod network;
+#[cfg(feature = "notifications")]
+pub mod notifications;
 pub mod script;
 #[cfg(feature = "sys_info")]
 pub mod sysinfo;
@@ -38,6 +50,11 @@ pub mod sysinfo;
 pub mod tray;
 #[cfg(feature = "upower")]
 pub mod upower;
+#[cfg(feature = "volume")]
+pub mod volume;
+/// Shared context menu for window management actions,
+/// used by window-based modules.
+pub mod window_menu;
 #[cfg(feature = "workspaces")]
 pub mod workspaces;
 
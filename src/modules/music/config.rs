@@ -3,7 +3,7 @@ use dirs::{audio_dir, home_dir};
 use serde::Deserialize;
 use std::path::PathBuf;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct Icons {
     /// Icon to display when playing.
     #[serde(default = "default_icon_play")]
@@ -21,6 +21,20 @@ pub struct Icons {
     #[serde(default = "default_icon_next")]
     pub(crate) next: String,
 
+    /// Icon to display for shuffle button.
+    #[serde(default = "default_icon_shuffle")]
+    pub(crate) shuffle: String,
+
+    /// Icon to display for repeat button,
+    /// when repeat is off or repeating the queue.
+    #[serde(default = "default_icon_repeat")]
+    pub(crate) repeat: String,
+
+    /// Icon to display for repeat button,
+    /// when repeating the current track.
+    #[serde(default = "default_icon_repeat_track")]
+    pub(crate) repeat_track: String,
+
     /// Icon to display under volume slider
     #[serde(default = "default_icon_volume")]
     pub(crate) volume: String,
@@ -45,6 +59,9 @@ impl Default for Icons {
             play: default_icon_play(),
             prev: default_icon_prev(),
             next: default_icon_next(),
+            shuffle: default_icon_shuffle(),
+            repeat: default_icon_repeat(),
+            repeat_track: default_icon_repeat_track(),
             volume: default_icon_volume(),
             track: default_icon_track(),
             album: default_icon_album(),
@@ -53,11 +70,13 @@ impl Default for Icons {
     }
 }
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum PlayerType {
     Mpd,
     Mpris,
+    #[cfg(feature = "music+mock")]
+    Mock,
 }
 
 impl Default for PlayerType {
@@ -66,7 +85,7 @@ impl Default for PlayerType {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct MusicModule {
     /// Type of player to connect to
     #[serde(default)]
@@ -97,6 +116,34 @@ pub struct MusicModule {
     #[serde(default = "default_cover_image_size")]
     pub(crate) cover_image_size: i32,
 
+    /// Maximum number of queued tracks to show in the popup,
+    /// starting from the current track.
+    #[serde(default = "default_queue_length")]
+    pub(crate) queue_length: usize,
+
+    /// Whether to show synced lyrics in the popup, when available.
+    #[serde(default = "crate::config::default_true")]
+    pub(crate) show_lyrics: bool,
+
+    // -- MPRIS --
+    /// Players to prefer, most preferred first.
+    /// Matched against the D-Bus name, without the `org.mpris.MediaPlayer2.` prefix.
+    #[serde(default)]
+    pub(crate) pinned: Vec<String>,
+    /// Players to ignore.
+    /// Matched the same way as `pinned`.
+    #[serde(default)]
+    pub(crate) blacklist: Vec<String>,
+
+    // -- Mock --
+    /// JSON file of events to play back.
+    #[cfg(feature = "music+mock")]
+    pub(crate) mock_file: Option<PathBuf>,
+    /// Long-running script which writes one JSON event per line.
+    /// Takes precedence over `mock_file`.
+    #[cfg(feature = "music+mock")]
+    pub(crate) mock_script: Option<String>,
+
     // -- Common --
     pub(crate) truncate: Option<TruncateMode>,
 
@@ -128,6 +175,18 @@ fn default_icon_next() -> String {
     String::from("󰒭")
 }
 
+fn default_icon_shuffle() -> String {
+    String::from("󰒟")
+}
+
+fn default_icon_repeat() -> String {
+    String::from("󰑖")
+}
+
+fn default_icon_repeat_track() -> String {
+    String::from("󰑘")
+}
+
 fn default_icon_volume() -> String {
     String::from("󰕾")
 }
@@ -155,3 +214,7 @@ const fn default_icon_size() -> i32 {
 const fn default_cover_image_size() -> i32 {
     128
 }
+
+const fn default_queue_length() -> usize {
+    20
+}
@@ -1,19 +1,25 @@
+use std::cell::Cell;
 use std::path::PathBuf;
+use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
+use crate::module_tasks::spawn;
 use color_eyre::Result;
 use glib::{Continue, PropertySet};
 use gtk::prelude::*;
-use gtk::{Button, IconTheme, Label, Orientation, Scale};
+use gtk::{Button, IconTheme, Label, Orientation, PolicyType, Scale, ScrolledWindow};
 use regex::Regex;
-use tokio::spawn;
 use tokio::sync::mpsc::{Receiver, Sender};
-use tracing::error;
+use tracing::{debug, error};
 
+use crate::clients::music::lyrics::Lyrics;
+#[cfg(feature = "music+mock")]
+use crate::clients::music::mock::MockSource;
 use crate::clients::music::{
-    self, MusicClient, PlayerState, PlayerUpdate, ProgressTick, Status, Track,
+    self, MusicClient, PlayerFilter, PlayerInfo, PlayerState, PlayerUpdate, ProgressTick,
+    QueueItem, RepeatMode, Status, Track,
 };
 use crate::gtk_helpers::IronbarGtkExt;
 use crate::image::{new_icon_button, new_icon_label, ImageProvider};
@@ -36,16 +42,27 @@ pub enum PlayerCommand {
     Next,
     Volume(u8),
     Seek(Duration),
+    Shuffle(bool),
+    Repeat(RepeatMode),
+    /// Jumps to the queue item with the given ID.
+    PlayQueueItem(String),
+    /// Switches to the player with the given bus name.
+    SetPlayer(String),
 }
 
 /// Formats a duration given in seconds
-/// in hh:mm format
+/// in mm:ss format, or h:mm:ss if it is over an hour long
 fn format_time(duration: Duration) -> String {
     let time = duration.as_secs();
+    let hours = time / 3600;
     let minutes = (time / 60) % 60;
     let seconds = time % 60;
 
-    format!("{minutes:0>2}:{seconds:0>2}")
+    if hours > 0 {
+        format!("{hours}:{minutes:0>2}:{seconds:0>2}")
+    } else {
+        format!("{minutes:0>2}:{seconds:0>2}")
+    }
 }
 
 /// Extracts the formatting tokens from a formatting string
@@ -59,6 +76,11 @@ fn get_tokens(re: &Regex, format_string: &str) -> Vec<String> {
 pub enum ControllerEvent {
     Update(Option<SongUpdate>),
     UpdateProgress(ProgressTick),
+    Queue(Vec<QueueItem>),
+    /// Synced lyrics for the current track, if there are any.
+    Lyrics(Option<Lyrics>),
+    /// Available players, and the bus name of the active player.
+    Players(Vec<PlayerInfo>, Option<String>),
 }
 
 #[derive(Clone, Debug)]
@@ -68,18 +90,101 @@ pub struct SongUpdate {
     display_string: String,
 }
 
-async fn get_client(
+/// Options used to get the client for the configured player type.
+#[derive(Clone, Debug)]
+struct ClientOptions {
     player_type: PlayerType,
-    host: &str,
+    host: String,
     music_dir: PathBuf,
-) -> Box<Arc<dyn MusicClient>> {
-    match player_type {
-        PlayerType::Mpd => music::get_client(music::ClientType::Mpd { host, music_dir }),
-        PlayerType::Mpris => music::get_client(music::ClientType::Mpris {}),
+    filter: PlayerFilter,
+    #[cfg(feature = "music+mock")]
+    mock_source: Option<MockSource>,
+}
+
+async fn get_client(options: &ClientOptions) -> Box<Arc<dyn MusicClient>> {
+    match options.player_type {
+        PlayerType::Mpd => music::get_client(music::ClientType::Mpd {
+            host: &options.host,
+            music_dir: options.music_dir.clone(),
+        }),
+        PlayerType::Mpris => music::get_client(music::ClientType::Mpris(options.filter.clone())),
+        #[cfg(feature = "music+mock")]
+        PlayerType::Mock => music::get_client(music::ClientType::Mock(
+            options
+                .mock_source
+                .clone()
+                .expect("mock source should be validated"),
+        )),
     }
     .await
 }
 
+/// Converts an update from the client into an event for the widgets,
+/// rendering the format string for new tracks.
+///
+/// Returns `None` when the client disconnects.
+fn to_controller_event(
+    update: PlayerUpdate,
+    format: &str,
+    tokens: &Vec<String>,
+) -> Option<ControllerEvent> {
+    let event = match update {
+        PlayerUpdate::Update(track, status) => {
+            ControllerEvent::Update((*track).map(|track| SongUpdate {
+                display_string: replace_tokens(format, tokens, &track),
+                song: track,
+                status,
+            }))
+        }
+        PlayerUpdate::ProgressTick(progress_tick) => ControllerEvent::UpdateProgress(progress_tick),
+        PlayerUpdate::Queue(queue) => ControllerEvent::Queue(queue),
+        PlayerUpdate::PlayerList { players, active } => ControllerEvent::Players(players, active),
+        PlayerUpdate::Disconnect => return None,
+    };
+
+    Some(event)
+}
+
+/// Loads the lyrics file for a track,
+/// returning `None` if it does not exist or contains no synced lyrics.
+async fn load_lyrics(path: Option<PathBuf>) -> Option<Lyrics> {
+    let path = path?;
+
+    Lyrics::load(&path)
+        .await
+        .map_err(|err| debug!("No lyrics loaded from {}: {err}", path.display()))
+        .ok()
+        .filter(|lyrics| !lyrics.is_empty())
+}
+
+impl MusicModule {
+    fn client_options(&self) -> Result<ClientOptions> {
+        #[cfg(feature = "music+mock")]
+        let mock_source = match (&self.mock_script, &self.mock_file) {
+            (Some(script), _) => Some(MockSource::Script(script.clone())),
+            (None, Some(file)) => Some(MockSource::File(file.clone())),
+            (None, None) if self.player_type == PlayerType::Mock => {
+                return Err(color_eyre::Report::msg(
+                    "The mock player requires `mock_file` or `mock_script` to be set",
+                ))
+            }
+            (None, None) => None,
+        };
+
+        Ok(ClientOptions {
+            player_type: self.player_type,
+            host: self.host.clone(),
+            music_dir: self.music_dir.clone(),
+            filter: PlayerFilter {
+                pinned: self.pinned.clone(),
+                blacklist: self.blacklist.clone(),
+            },
+            #[cfg(feature = "music+mock")]
+            mock_source,
+        })
+    }
+}
+
 impl Module<Button> for MusicModule {
     type SendMessage = ControllerEvent;
     type ReceiveMessage = PlayerCommand;
@@ -99,51 +204,49 @@ impl Module<Button> for MusicModule {
         let re = Regex::new(r"\{([\w-]+)}")?;
         let tokens = get_tokens(&re, self.format.as_str());
 
+        let options = self.client_options()?;
+        let show_lyrics = self.show_lyrics;
+
         // receive player updates
         {
-            let player_type = self.player_type;
-            let host = self.host.clone();
-            let music_dir = self.music_dir.clone();
+            let options = options.clone();
 
             spawn(async move {
                 loop {
-                    let mut rx = {
-                        let client = get_client(player_type, &host, music_dir.clone()).await;
-                        client.subscribe_change()
-                    };
+                    // the client is held while subscribed,
+                    // as MPRIS clients stop once they are dropped
+                    let client = get_client(&options).await;
+                    let mut rx = client.subscribe_change();
+
+                    let mut prev_lyrics_path = None;
 
                     while let Ok(update) = rx.recv().await {
-                        match update {
-                            PlayerUpdate::Update(track, status) => match *track {
-                                Some(track) => {
-                                    let display_string =
-                                        replace_tokens(format.as_str(), &tokens, &track);
-
-                                    let update = SongUpdate {
-                                        song: track,
-                                        status,
-                                        display_string,
-                                    };
-
-                                    send_async!(
-                                        tx,
-                                        ModuleUpdateEvent::Update(ControllerEvent::Update(Some(
-                                            update
-                                        )))
-                                    );
-                                }
-                                None => send_async!(
-                                    tx,
-                                    ModuleUpdateEvent::Update(ControllerEvent::Update(None))
-                                ),
-                            },
-                            PlayerUpdate::ProgressTick(progress_tick) => send_async!(
-                                tx,
-                                ModuleUpdateEvent::Update(ControllerEvent::UpdateProgress(
-                                    progress_tick
-                                ))
+                        let lyrics_path = match &update {
+                            PlayerUpdate::Update(track, _) if show_lyrics => Some(
+                                track
+                                    .as_ref()
+                                    .as_ref()
+                                    .and_then(|track| track.lyrics_path.clone()),
                             ),
-                            PlayerUpdate::Disconnect => break,
+                            _ => None,
+                        };
+
+                        match to_controller_event(update, &format, &tokens) {
+                            Some(event) => send_async!(tx, ModuleUpdateEvent::Update(event)),
+                            None => break,
+                        }
+
+                        // only reload lyrics when the track changes
+                        if let Some(lyrics_path) = lyrics_path {
+                            if lyrics_path != prev_lyrics_path {
+                                prev_lyrics_path = lyrics_path.clone();
+
+                                let lyrics = load_lyrics(lyrics_path).await;
+                                send_async!(
+                                    tx,
+                                    ModuleUpdateEvent::Update(ControllerEvent::Lyrics(lyrics))
+                                );
+                            }
                         }
                     }
                 }
@@ -152,13 +255,9 @@ impl Module<Button> for MusicModule {
 
         // listen to ui events
         {
-            let player_type = self.player_type;
-            let host = self.host.clone();
-            let music_dir = self.music_dir.clone();
-
             spawn(async move {
                 while let Some(event) = rx.recv().await {
-                    let client = get_client(player_type, &host, music_dir.clone()).await;
+                    let client = get_client(&options).await;
                     let res = match event {
                         PlayerCommand::Previous => client.prev(),
                         PlayerCommand::Play => client.play(),
@@ -166,6 +265,10 @@ impl Module<Button> for MusicModule {
                         PlayerCommand::Next => client.next(),
                         PlayerCommand::Volume(vol) => client.set_volume_percent(vol),
                         PlayerCommand::Seek(duration) => client.seek(duration),
+                        PlayerCommand::Shuffle(shuffle) => client.set_shuffle(shuffle),
+                        PlayerCommand::Repeat(mode) => client.set_repeat(mode),
+                        PlayerCommand::PlayQueueItem(id) => client.play_queue_item(&id),
+                        PlayerCommand::SetPlayer(bus_name) => client.set_active_player(&bus_name),
                     };
 
                     if let Err(err) = res {
@@ -269,6 +372,13 @@ impl Module<Button> for MusicModule {
         let icon_theme = info.icon_theme;
 
         let container = gtk::Box::new(Orientation::Vertical, 10);
+
+        // only shown when there is more than one player to choose from
+        let players_box = gtk::Box::new(Orientation::Horizontal, 5);
+        players_box.add_class("players");
+        players_box.set_no_show_all(true);
+        container.add(&players_box);
+
         let main_container = gtk::Box::new(Orientation::Horizontal, 10);
 
         let album_image = gtk::Image::builder()
@@ -307,10 +417,23 @@ impl Module<Button> for MusicModule {
         let btn_next = new_icon_button(&icons.next, icon_theme, self.icon_size);
         btn_next.add_class("btn-next");
 
+        let btn_shuffle = new_icon_button(&icons.shuffle, icon_theme, self.icon_size);
+        btn_shuffle.add_class("btn-shuffle");
+
+        let btn_repeat = new_icon_button(&icons.repeat, icon_theme, self.icon_size);
+        btn_repeat.add_class("btn-repeat");
+
+        let btn_repeat_track = new_icon_button(&icons.repeat_track, icon_theme, self.icon_size);
+        btn_repeat_track.add_class("btn-repeat");
+        btn_repeat_track.add_class("track");
+
+        controls_box.add(&btn_shuffle);
         controls_box.add(&btn_prev);
         controls_box.add(&btn_play);
         controls_box.add(&btn_pause);
         controls_box.add(&btn_next);
+        controls_box.add(&btn_repeat);
+        controls_box.add(&btn_repeat_track);
 
         info_box.add(&controls_box);
 
@@ -352,6 +475,27 @@ impl Module<Button> for MusicModule {
             try_send!(tx_next, PlayerCommand::Next);
         });
 
+        let tx_player = tx.clone();
+
+        let shuffle = Rc::new(Cell::new(false));
+        let repeat = Rc::new(Cell::new(RepeatMode::Off));
+
+        {
+            let tx = tx.clone();
+            let shuffle = shuffle.clone();
+            btn_shuffle.connect_clicked(move |_| {
+                try_send!(tx, PlayerCommand::Shuffle(!shuffle.get()));
+            });
+        }
+
+        for button in [&btn_repeat, &btn_repeat_track] {
+            let tx = tx.clone();
+            let repeat = repeat.clone();
+            button.connect_clicked(move |_| {
+                try_send!(tx, PlayerCommand::Repeat(repeat.get().next()));
+            });
+        }
+
         let tx_vol = tx.clone();
         volume_slider.connect_change_value(move |_, _, val| {
             try_send!(tx_vol, PlayerCommand::Volume(val as u8));
@@ -375,6 +519,26 @@ impl Module<Button> for MusicModule {
         progress_box.add(&progress_label);
         container.add(&progress_box);
 
+        let lyrics_label = Label::new(None);
+        lyrics_label.add_class("lyrics");
+        lyrics_label.set_line_wrap(true);
+        lyrics_label.set_no_show_all(true);
+        container.add(&lyrics_label);
+
+        let queue_box = gtk::Box::new(Orientation::Vertical, 0);
+        queue_box.add_class("queue");
+
+        let queue_scroll = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .min_content_height(100)
+            .max_content_height(250)
+            .propagate_natural_height(true)
+            .build();
+        queue_scroll.add(&queue_box);
+        container.add(&queue_scroll);
+
+        let tx_queue = tx.clone();
+
         let drag_lock = Arc::new(AtomicBool::new(false));
         {
             let drag_lock = drag_lock.clone();
@@ -396,12 +560,17 @@ impl Module<Button> for MusicModule {
         }
 
         container.show_all();
+        queue_scroll.hide();
 
         {
             let icon_theme = icon_theme.clone();
             let image_size = self.cover_image_size;
 
+            let queue_length = self.queue_length;
+
             let mut prev_cover = None;
+            let mut lyrics: Option<Lyrics> = None;
+
             rx.attach(None, move |event| {
                 match event {
                     ControllerEvent::Update(Some(update)) => {
@@ -459,6 +628,27 @@ impl Module<Button> for MusicModule {
                         btn_prev.set_sensitive(enable_prev);
                         btn_next.set_sensitive(enable_next);
 
+                        if let Some(value) = update.status.shuffle {
+                            shuffle.set(value);
+                            btn_shuffle.show();
+                            set_active(&btn_shuffle, value);
+                        } else {
+                            btn_shuffle.hide();
+                        }
+
+                        if let Some(mode) = update.status.repeat {
+                            repeat.set(mode);
+
+                            btn_repeat.set_visible(mode != RepeatMode::Track);
+                            btn_repeat_track.set_visible(mode == RepeatMode::Track);
+
+                            set_active(&btn_repeat, mode == RepeatMode::Playlist);
+                            set_active(&btn_repeat_track, true);
+                        } else {
+                            btn_repeat.hide();
+                            btn_repeat_track.hide();
+                        }
+
                         if let Some(volume) = update.status.volume_percent {
                             volume_slider.set_value(volume as f64);
                             volume_box.show();
@@ -466,6 +656,25 @@ impl Module<Button> for MusicModule {
                             volume_box.hide();
                         }
                     }
+                    ControllerEvent::Players(players, active) => {
+                        for child in players_box.children() {
+                            players_box.remove(&child);
+                        }
+
+                        if players.len() > 1 {
+                            for player in players {
+                                let is_active = active.as_ref() == Some(&player.bus_name);
+
+                                let button = create_player_button(player, is_active, &tx_player);
+                                players_box.add(&button);
+                                button.show();
+                            }
+
+                            players_box.show();
+                        } else {
+                            players_box.hide();
+                        }
+                    }
                     ControllerEvent::UpdateProgress(progress_tick)
                         if !drag_lock.load(Ordering::Relaxed) =>
                     {
@@ -484,6 +693,62 @@ impl Module<Button> for MusicModule {
                         } else {
                             progress_box.hide();
                         }
+
+                        if let (Some(lyrics), Some(elapsed)) = (&lyrics, progress_tick.elapsed) {
+                            let line = lyrics.line_at(elapsed).unwrap_or_default();
+                            if lyrics_label.label() != line {
+                                lyrics_label.set_label(line);
+                            }
+                        }
+                    }
+                    ControllerEvent::Lyrics(new_lyrics) => {
+                        lyrics = new_lyrics;
+
+                        lyrics_label.set_label("");
+                        lyrics_label.set_visible(lyrics.is_some());
+                    }
+                    ControllerEvent::Queue(queue) => {
+                        for child in queue_box.children() {
+                            queue_box.remove(&child);
+                        }
+
+                        let start = queue.iter().position(|item| item.current).unwrap_or(0);
+
+                        let items = queue
+                            .into_iter()
+                            .skip(start)
+                            .take(queue_length)
+                            .collect::<Vec<_>>();
+
+                        for item in &items {
+                            let button = Button::new();
+                            button.add_class("queue-item");
+
+                            if item.current {
+                                button.add_class("current");
+                            }
+
+                            let label = Label::new(Some(&match (&item.title, &item.artist) {
+                                (Some(title), Some(artist)) if !artist.is_empty() => {
+                                    format!("{title} - {artist}")
+                                }
+                                (Some(title), _) => title.clone(),
+                                (None, _) => item.id.clone(),
+                            }));
+                            label.set_halign(gtk::Align::Start);
+                            button.add(&label);
+
+                            let tx = tx_queue.clone();
+                            let id = item.id.clone();
+                            button.connect_clicked(move |_| {
+                                try_send!(tx, PlayerCommand::PlayQueueItem(id.clone()));
+                            });
+
+                            queue_box.add(&button);
+                        }
+
+                        queue_box.show_all();
+                        queue_scroll.set_visible(!items.is_empty());
                     }
                     _ => {}
                 };
@@ -496,6 +761,32 @@ impl Module<Button> for MusicModule {
     }
 }
 
+fn set_active(button: &Button, active: bool) {
+    if active {
+        button.add_class("active");
+    } else {
+        button.style_context().remove_class("active");
+    }
+}
+
+/// Creates the popup button used to switch to a player.
+fn create_player_button(player: PlayerInfo, active: bool, tx: &Sender<PlayerCommand>) -> Button {
+    let button = Button::with_label(&player.name);
+    button.add_class("player");
+    button.set_tooltip_text(Some(&player.bus_name));
+
+    if active {
+        button.add_class("active");
+    }
+
+    let tx = tx.clone();
+    button.connect_clicked(move |_| {
+        try_send!(tx, PlayerCommand::SetPlayer(player.bus_name.clone()));
+    });
+
+    button
+}
+
 fn update_popup_metadata_label(text: Option<String>, label: &IconLabel) {
     match text {
         Some(value) => {
@@ -557,3 +848,96 @@ impl IconLabel {
         Self { label, container }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(format: &str) -> Vec<String> {
+        let re = Regex::new(r"\{([\w-]+)}").expect("valid regex");
+        get_tokens(&re, format)
+    }
+
+    fn status() -> Status {
+        Status {
+            state: PlayerState::Playing,
+            volume_percent: Some(50),
+            playlist_position: 1,
+            playlist_length: 3,
+            shuffle: None,
+            repeat: None,
+        }
+    }
+
+    fn track() -> Track {
+        Track {
+            title: Some("Song".to_string()),
+            artist: Some("Artist".to_string()),
+            track: Some(4),
+            ..Track::default()
+        }
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(Duration::from_secs(0)), "00:00");
+        assert_eq!(format_time(Duration::from_secs(65)), "01:05");
+        assert_eq!(format_time(Duration::from_millis(59_999)), "00:59");
+    }
+
+    #[test]
+    fn test_format_time_hours() {
+        assert_eq!(format_time(Duration::from_secs(3600 + 125)), "1:02:05");
+        assert_eq!(format_time(Duration::from_secs(36_000)), "10:00:00");
+    }
+
+    #[test]
+    fn test_get_tokens() {
+        assert_eq!(
+            tokens("{title} / {artist} ({track})"),
+            vec!["title", "artist", "track"]
+        );
+    }
+
+    #[test]
+    fn test_get_tokens_none() {
+        assert!(tokens("no tokens {here").is_empty());
+    }
+
+    #[test]
+    fn test_replace_tokens() {
+        const FORMAT: &str = "{track}. {title} / {artist} [{album}] {unknown}";
+        let text = replace_tokens(FORMAT, &tokens(FORMAT), &track());
+
+        // missing values are empty, and unknown tokens are replaced with their name
+        assert_eq!(text, "4. Song / Artist [] unknown");
+    }
+
+    #[test]
+    fn test_update_to_label() {
+        const FORMAT: &str = "{title} / {artist}";
+        let update = PlayerUpdate::Update(Box::new(Some(track())), status());
+
+        let event = to_controller_event(update, FORMAT, &tokens(FORMAT));
+
+        assert!(matches!(
+            event,
+            Some(ControllerEvent::Update(Some(SongUpdate { display_string, status, .. })))
+                if display_string == "Song / Artist" && status.playlist_position == 1
+        ));
+    }
+
+    #[test]
+    fn test_empty_update() {
+        let update = PlayerUpdate::Update(Box::new(None), status());
+        let event = to_controller_event(update, "{title}", &tokens("{title}"));
+
+        assert!(matches!(event, Some(ControllerEvent::Update(None))));
+    }
+
+    #[test]
+    fn test_disconnect() {
+        let event = to_controller_event(PlayerUpdate::Disconnect, "", &vec![]);
+        assert!(event.is_none());
+    }
+}
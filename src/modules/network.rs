@@ -0,0 +1,370 @@
+use crate::clients::networkmanager::{self, AccessPoint, ConnectionType, NetworkState};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::module_tasks::spawn;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{send_async, try_send};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Button, Label, Orientation, Switch};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tracing::error;
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct NetworkModule {
+    /// Format string to use for the widget button label.
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// Connection state icons.
+    #[serde(default)]
+    icons: Icons,
+
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{icon} {name}")
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct Icons {
+    /// Icon to show for wired and other connections.
+    #[serde(default = "default_icon_wired")]
+    wired: String,
+
+    /// Icon to show for Wi-Fi connections with a strong signal.
+    #[serde(default = "default_icon_wifi_high")]
+    wifi_high: String,
+
+    /// Icon to show for Wi-Fi connections with a medium signal.
+    #[serde(default = "default_icon_wifi_medium")]
+    wifi_medium: String,
+
+    /// Icon to show for Wi-Fi connections with a weak signal.
+    #[serde(default = "default_icon_wifi_low")]
+    wifi_low: String,
+
+    /// Icon to show for mobile broadband connections.
+    #[serde(default = "default_icon_cellular")]
+    cellular: String,
+
+    /// Icon to show for VPN connections.
+    #[serde(default = "default_icon_vpn")]
+    vpn: String,
+
+    /// Icon to show when there is no connection.
+    #[serde(default = "default_icon_disconnected")]
+    disconnected: String,
+}
+
+impl Icons {
+    /// Gets the icon for the connection type and signal strength.
+    fn connection_icon(&self, state: &NetworkState) -> &str {
+        match state.connection_type {
+            ConnectionType::None => &self.disconnected,
+            ConnectionType::Wired | ConnectionType::Other => &self.wired,
+            ConnectionType::Cellular => &self.cellular,
+            ConnectionType::Vpn => &self.vpn,
+            ConnectionType::Wifi => match state.strength.unwrap_or_default() {
+                0..=33 => &self.wifi_low,
+                34..=66 => &self.wifi_medium,
+                _ => &self.wifi_high,
+            },
+        }
+    }
+}
+
+impl Default for Icons {
+    fn default() -> Self {
+        Self {
+            wired: default_icon_wired(),
+            wifi_high: default_icon_wifi_high(),
+            wifi_medium: default_icon_wifi_medium(),
+            wifi_low: default_icon_wifi_low(),
+            cellular: default_icon_cellular(),
+            vpn: default_icon_vpn(),
+            disconnected: default_icon_disconnected(),
+        }
+    }
+}
+
+fn default_icon_wired() -> String {
+    String::from("󰈀")
+}
+
+fn default_icon_wifi_high() -> String {
+    String::from("󰤨")
+}
+
+fn default_icon_wifi_medium() -> String {
+    String::from("󰤥")
+}
+
+fn default_icon_wifi_low() -> String {
+    String::from("󰤟")
+}
+
+fn default_icon_cellular() -> String {
+    String::from("󰣺")
+}
+
+fn default_icon_vpn() -> String {
+    String::from("󰖂")
+}
+
+fn default_icon_disconnected() -> String {
+    String::from("󰤮")
+}
+
+#[derive(Debug, Clone)]
+pub enum NetworkCommand {
+    SetWifiEnabled(bool),
+    Connect(String),
+    Disconnect,
+    Scan,
+}
+
+impl Module<Button> for NetworkModule {
+    type SendMessage = NetworkState;
+    type ReceiveMessage = NetworkCommand;
+
+    fn name() -> &'static str {
+        "network"
+    }
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        tx: Sender<ModuleUpdateEvent<Self::SendMessage>>,
+        mut rx: Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        spawn(async move {
+            let client = networkmanager::get_client().await;
+            let (state, mut state_rx) = client.subscribe();
+
+            spawn(async move {
+                send_async!(tx, ModuleUpdateEvent::Update(state));
+
+                loop {
+                    match state_rx.recv().await {
+                        Ok(state) => send_async!(tx, ModuleUpdateEvent::Update(state)),
+                        // each update is the full state, so missed updates can be skipped
+                        Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            while let Some(command) = rx.recv().await {
+                let res = match command {
+                    NetworkCommand::SetWifiEnabled(enabled) => {
+                        client.set_wifi_enabled(enabled).await
+                    }
+                    NetworkCommand::Connect(ssid) => client.connect_wifi(&ssid).await,
+                    NetworkCommand::Disconnect => client.disconnect_wifi().await,
+                    NetworkCommand::Scan => client.scan().await,
+                };
+
+                if let Err(err) = res {
+                    error!("{err:?}");
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+        let label = Label::builder()
+            .label("")
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+
+        button.add(&label);
+
+        {
+            let tx = context.tx.clone();
+            let controller_tx = context.controller_tx.clone();
+
+            button.connect_clicked(move |button| {
+                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+                try_send!(controller_tx, NetworkCommand::Scan);
+            });
+        }
+
+        {
+            let format = self.format.clone();
+            let icons = self.icons.clone();
+
+            context.widget_rx.attach(None, move |state: NetworkState| {
+                let label_text = format
+                    .replace("{icon}", icons.connection_icon(&state))
+                    .replace("{type}", connection_type_name(state.connection_type))
+                    .replace("{name}", state.name.as_deref().unwrap_or_default())
+                    .replace("{ssid}", state.ssid.as_deref().unwrap_or_default())
+                    .replace(
+                        "{strength}",
+                        &state.strength.map(|s| s.to_string()).unwrap_or_default(),
+                    )
+                    .replace("{ip}", state.ip4_address.as_deref().unwrap_or_default());
+
+                label.set_markup(&label_text);
+
+                Continue(true)
+            });
+        }
+
+        let popup = self
+            .into_popup(context.controller_tx, context.popup_rx, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: Sender<Self::ReceiveMessage>,
+        rx: glib::Receiver<Self::SendMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 10);
+
+        let details = Label::new(None);
+        details.add_class("details");
+
+        let wifi_box = gtk::Box::new(Orientation::Horizontal, 5);
+        wifi_box.add_class("wifi-toggle");
+
+        let wifi_label = Label::new(Some("Wi-Fi"));
+        wifi_label.set_hexpand(true);
+        wifi_label.set_halign(gtk::Align::Start);
+
+        let wifi_switch = Switch::new();
+
+        wifi_box.add(&wifi_label);
+        wifi_box.add(&wifi_switch);
+
+        let wifi_handler = {
+            let tx = tx.clone();
+            wifi_switch.connect_state_set(move |_, enabled| {
+                try_send!(tx, NetworkCommand::SetWifiEnabled(enabled));
+                Inhibit(false)
+            })
+        };
+
+        let networks = gtk::Box::new(Orientation::Vertical, 0);
+        networks.add_class("networks");
+
+        container.add(&details);
+        container.add(&wifi_box);
+        container.add(&networks);
+
+        container.show_all();
+
+        rx.attach(None, move |state| {
+            let details_text = match (&state.name, &state.ip4_address) {
+                (Some(name), Some(ip)) => format!("{name}\n{ip}"),
+                (Some(name), None) => name.clone(),
+                _ => String::from("Disconnected"),
+            };
+            details.set_label(&details_text);
+
+            // block the handler so that updating the switch
+            // does not send the state back to NetworkManager
+            wifi_switch.block_signal(&wifi_handler);
+            wifi_switch.set_active(state.wifi_enabled);
+            wifi_switch.unblock_signal(&wifi_handler);
+
+            for child in networks.children() {
+                networks.remove(&child);
+            }
+
+            if state.wifi_enabled {
+                for access_point in state.access_points {
+                    let button = create_network_button(&access_point, &tx);
+                    networks.add(&button);
+                }
+            }
+
+            networks.show_all();
+
+            Continue(true)
+        });
+
+        Some(container)
+    }
+}
+
+/// Creates a button for a Wi-Fi network,
+/// which connects to the network or disconnects from it when clicked.
+fn create_network_button(access_point: &AccessPoint, tx: &Sender<NetworkCommand>) -> Button {
+    let button = Button::new();
+    button.add_class("network");
+
+    if access_point.active {
+        button.add_class("active");
+    }
+
+    if access_point.known {
+        button.add_class("known");
+    }
+
+    let content = gtk::Box::new(Orientation::Horizontal, 5);
+
+    let ssid = Label::new(Some(&access_point.ssid));
+    ssid.add_class("ssid");
+    ssid.set_hexpand(true);
+    ssid.set_halign(gtk::Align::Start);
+    content.add(&ssid);
+
+    if access_point.secured {
+        let secured = Label::new(Some("󰌾"));
+        secured.add_class("secured");
+        content.add(&secured);
+    }
+
+    let strength = Label::new(Some(&format!("{}%", access_point.strength)));
+    strength.add_class("strength");
+    content.add(&strength);
+
+    button.add(&content);
+
+    let tx = tx.clone();
+    let command = if access_point.active {
+        NetworkCommand::Disconnect
+    } else {
+        NetworkCommand::Connect(access_point.ssid.clone())
+    };
+
+    button.connect_clicked(move |_| {
+        try_send!(tx, command.clone());
+    });
+
+    button
+}
+
+const fn connection_type_name(connection_type: ConnectionType) -> &'static str {
+    match connection_type {
+        ConnectionType::None => "none",
+        ConnectionType::Wired => "wired",
+        ConnectionType::Wifi => "wifi",
+        ConnectionType::Cellular => "cellular",
+        ConnectionType::Vpn => "vpn",
+        ConnectionType::Other => "other",
+    }
+}
@@ -0,0 +1,420 @@
+use crate::clients::notifications::{self, Notification, NotificationState, Urgency};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::ImageProvider;
+use crate::module_tasks::spawn;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{send_async, try_send};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Button, IconTheme, Label, Orientation, PolicyType, ScrolledWindow, Switch};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tracing::error;
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct NotificationsModule {
+    /// Format string to use for the widget button label.
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// Notification state icons.
+    #[serde(default)]
+    icons: Icons,
+
+    /// Size to render application icons in the popup at.
+    #[serde(default = "default_icon_size")]
+    icon_size: i32,
+
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{icon} {count}")
+}
+
+const fn default_icon_size() -> i32 {
+    32
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct Icons {
+    /// Icon to show when there are no unread notifications.
+    #[serde(default = "default_icon_none")]
+    none: String,
+
+    /// Icon to show when there are unread notifications.
+    #[serde(default = "default_icon_unread")]
+    unread: String,
+
+    /// Icon to show while do-not-disturb is enabled.
+    #[serde(default = "default_icon_dnd")]
+    dnd: String,
+}
+
+impl Icons {
+    fn state_icon(&self, state: &NotificationState) -> &str {
+        if state.dnd {
+            &self.dnd
+        } else if state.unread_count() > 0 {
+            &self.unread
+        } else {
+            &self.none
+        }
+    }
+}
+
+impl Default for Icons {
+    fn default() -> Self {
+        Self {
+            none: default_icon_none(),
+            unread: default_icon_unread(),
+            dnd: default_icon_dnd(),
+        }
+    }
+}
+
+fn default_icon_none() -> String {
+    String::from("󰂚")
+}
+
+fn default_icon_unread() -> String {
+    String::from("󰂞")
+}
+
+fn default_icon_dnd() -> String {
+    String::from("󰂛")
+}
+
+#[derive(Debug, Clone)]
+pub enum NotificationsCommand {
+    MarkRead,
+    SetDnd(bool),
+    Dismiss(u32),
+    InvokeAction(u32, String),
+    Clear,
+}
+
+impl Module<Button> for NotificationsModule {
+    type SendMessage = NotificationState;
+    type ReceiveMessage = NotificationsCommand;
+
+    fn name() -> &'static str {
+        "notifications"
+    }
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        tx: Sender<ModuleUpdateEvent<Self::SendMessage>>,
+        mut rx: Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        spawn(async move {
+            let client = match notifications::get_client().await {
+                Ok(client) => client,
+                Err(err) => {
+                    error!("{err:?}");
+                    return;
+                }
+            };
+            let (state, mut state_rx) = client.subscribe();
+
+            spawn(async move {
+                send_async!(tx, ModuleUpdateEvent::Update(state));
+
+                loop {
+                    match state_rx.recv().await {
+                        Ok(state) => send_async!(tx, ModuleUpdateEvent::Update(state)),
+                        // each update is the full state, so missed updates can be skipped
+                        Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            while let Some(command) = rx.recv().await {
+                let res = match command {
+                    NotificationsCommand::MarkRead => {
+                        client.mark_read();
+                        Ok(())
+                    }
+                    NotificationsCommand::SetDnd(dnd) => {
+                        client.set_dnd(dnd);
+                        Ok(())
+                    }
+                    NotificationsCommand::Dismiss(id) => client.dismiss(id).await,
+                    NotificationsCommand::InvokeAction(id, key) => {
+                        client.invoke_action(id, &key).await
+                    }
+                    NotificationsCommand::Clear => client.clear().await,
+                };
+
+                if let Err(err) = res {
+                    error!("{err:?}");
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+        let label = Label::builder()
+            .label("")
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+
+        button.add(&label);
+
+        {
+            let tx = context.tx.clone();
+            let controller_tx = context.controller_tx.clone();
+
+            button.connect_clicked(move |button| {
+                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+                try_send!(controller_tx, NotificationsCommand::MarkRead);
+            });
+        }
+
+        {
+            let button = button.clone();
+            let format = self.format.clone();
+            let icons = self.icons.clone();
+
+            context.widget_rx.attach(None, move |state| {
+                let unread = state.unread_count();
+
+                let label_text = format
+                    .replace("{icon}", icons.state_icon(&state))
+                    .replace("{count}", &unread.to_string())
+                    .replace("{total}", &state.notifications.len().to_string());
+
+                label.set_markup(&label_text);
+
+                let style = button.style_context();
+                if unread > 0 {
+                    style.add_class("unread");
+                } else {
+                    style.remove_class("unread");
+                }
+
+                if state.dnd {
+                    style.add_class("dnd");
+                } else {
+                    style.remove_class("dnd");
+                }
+
+                Continue(true)
+            });
+        }
+
+        let popup = self
+            .into_popup(context.controller_tx, context.popup_rx, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: Sender<Self::ReceiveMessage>,
+        rx: glib::Receiver<Self::SendMessage>,
+        info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 10);
+
+        let header = gtk::Box::new(Orientation::Horizontal, 5);
+        header.add_class("header");
+
+        let dnd_label = Label::new(Some("Do not disturb"));
+        dnd_label.set_hexpand(true);
+        dnd_label.set_halign(gtk::Align::Start);
+
+        let dnd_switch = Switch::new();
+        dnd_switch.add_class("dnd-toggle");
+
+        let clear_button = Button::with_label("Clear all");
+        clear_button.add_class("clear");
+
+        header.add(&dnd_label);
+        header.add(&dnd_switch);
+        header.add(&clear_button);
+
+        let dnd_handler = {
+            let tx = tx.clone();
+            dnd_switch.connect_state_set(move |_, enabled| {
+                try_send!(tx, NotificationsCommand::SetDnd(enabled));
+                Inhibit(false)
+            })
+        };
+
+        {
+            let tx = tx.clone();
+            clear_button.connect_clicked(move |_| {
+                try_send!(tx, NotificationsCommand::Clear);
+            });
+        }
+
+        let scrolled = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .propagate_natural_height(true)
+            .max_content_height(500)
+            .build();
+
+        let list = gtk::Box::new(Orientation::Vertical, 5);
+        list.add_class("notifications");
+        scrolled.add(&list);
+
+        container.add(&header);
+        container.add(&scrolled);
+
+        container.show_all();
+
+        let icon_theme = info.icon_theme.clone();
+        let icon_size = self.icon_size;
+
+        rx.attach(None, move |state| {
+            dnd_switch.block_signal(&dnd_handler);
+            dnd_switch.set_active(state.dnd);
+            dnd_switch.unblock_signal(&dnd_handler);
+
+            clear_button.set_sensitive(!state.notifications.is_empty());
+
+            for child in list.children() {
+                list.remove(&child);
+            }
+
+            if state.notifications.is_empty() {
+                let empty = Label::new(Some("No notifications"));
+                empty.add_class("empty");
+                list.add(&empty);
+            }
+
+            for notification in &state.notifications {
+                let row = create_notification_row(notification, &icon_theme, icon_size, &tx);
+                list.add(&row);
+            }
+
+            list.show_all();
+
+            Continue(true)
+        });
+
+        Some(container)
+    }
+}
+
+/// Creates the popup row for a single notification,
+/// with its action and dismiss buttons.
+fn create_notification_row(
+    notification: &Notification,
+    icon_theme: &IconTheme,
+    icon_size: i32,
+    tx: &Sender<NotificationsCommand>,
+) -> gtk::Box {
+    let row = gtk::Box::new(Orientation::Horizontal, 10);
+    row.add_class("notification");
+    row.add_class(match notification.urgency {
+        Urgency::Low => "low",
+        Urgency::Normal => "normal",
+        Urgency::Critical => "critical",
+    });
+
+    if !notification.app_icon.is_empty() {
+        let input = if ImageProvider::is_definitely_image_input(&notification.app_icon) {
+            notification.app_icon.clone()
+        } else {
+            format!("icon:{}", notification.app_icon)
+        };
+
+        let icon = gtk::Image::new();
+        icon.add_class("icon");
+
+        if let Some(provider) = ImageProvider::parse(&input, icon_theme, false, icon_size) {
+            if let Err(err) = provider.load_into_image(icon.clone()) {
+                error!("{err:?}");
+            }
+        }
+
+        icon.set_valign(gtk::Align::Start);
+        row.add(&icon);
+    }
+
+    let content = gtk::Box::new(Orientation::Vertical, 2);
+    content.set_hexpand(true);
+
+    let app_name = Label::new(Some(&notification.app_name));
+    app_name.add_class("app-name");
+    app_name.set_halign(gtk::Align::Start);
+    content.add(&app_name);
+
+    let summary = Label::new(Some(&notification.summary));
+    summary.add_class("summary");
+    summary.set_halign(gtk::Align::Start);
+    summary.set_line_wrap(true);
+    summary.set_xalign(0.0);
+    content.add(&summary);
+
+    if !notification.body.is_empty() {
+        let body = Label::new(Some(&notification.body));
+        body.add_class("body");
+        body.set_halign(gtk::Align::Start);
+        body.set_line_wrap(true);
+        body.set_xalign(0.0);
+        content.add(&body);
+    }
+
+    if !notification.actions.is_empty() {
+        let actions = gtk::Box::new(Orientation::Horizontal, 5);
+        actions.add_class("actions");
+
+        for (key, label) in &notification.actions {
+            let label = if label.is_empty() { key } else { label };
+
+            let button = Button::with_label(label);
+            button.add_class("action");
+
+            let tx = tx.clone();
+            let id = notification.id;
+            let key = key.clone();
+            button.connect_clicked(move |_| {
+                try_send!(tx, NotificationsCommand::InvokeAction(id, key.clone()));
+            });
+
+            actions.add(&button);
+        }
+
+        content.add(&actions);
+    }
+
+    row.add(&content);
+
+    let dismiss = Button::with_label("󰅖");
+    dismiss.add_class("dismiss");
+    dismiss.set_valign(gtk::Align::Start);
+
+    {
+        let tx = tx.clone();
+        let id = notification.id;
+        dismiss.connect_clicked(move |_| {
+            try_send!(tx, NotificationsCommand::Dismiss(id));
+        });
+    }
+
+    row.add(&dismiss);
+
+    row
+}
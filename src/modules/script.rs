@@ -1,16 +1,17 @@
 use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::module_tasks::spawn;
 use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
-use crate::script::{OutputStream, Script, ScriptMode};
+use crate::script::{OutputStream, Script, ScriptFormat, ScriptMode, ScriptOutput};
 use crate::try_send;
 use color_eyre::{Help, Report, Result};
 use gtk::prelude::*;
-use gtk::Label;
+use gtk::{Label, ProgressBar, Widget};
 use serde::Deserialize;
-use tokio::spawn;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tracing::error;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct ScriptModule {
     /// Path to script to execute.
     cmd: String,
@@ -20,6 +21,13 @@ pub struct ScriptModule {
     /// Time in milliseconds between executions.
     #[serde(default = "default_interval")]
     interval: u64,
+    /// How the script output is interpreted.
+    #[serde(default)]
+    format: ScriptFormat,
+    /// Whether to show a progress bar,
+    /// driven by the `percentage` key of JSON output.
+    #[serde(default)]
+    progress_bar: bool,
 
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
@@ -45,8 +53,8 @@ impl From<&ScriptModule> for Script {
     }
 }
 
-impl Module<Label> for ScriptModule {
-    type SendMessage = String;
+impl Module<Widget> for ScriptModule {
+    type SendMessage = ScriptOutput;
     type ReceiveMessage = ();
 
     fn name() -> &'static str {
@@ -60,20 +68,26 @@ impl Module<Label> for ScriptModule {
         _rx: Receiver<Self::ReceiveMessage>,
     ) -> Result<()> {
         let script: Script = self.into();
+        let format = self.format;
 
         spawn(async move {
             script.run(None, move |out, _| match out {
-               OutputStream::Stdout(stdout) => {
-                   try_send!(tx, ModuleUpdateEvent::Update(stdout));
-               },
-               OutputStream::Stderr(stderr) => {
-                   error!("{:?}", Report::msg(stderr)
-                                    .wrap_err("Watched script error:")
-                                    .suggestion("Check the path to your script")
-                                    .suggestion("Check the script for errors")
-                                    .suggestion("If you expect the script to write to stderr, consider redirecting its output to /dev/null to suppress these messages"));
-               }
-           }).await;
+                OutputStream::Stdout(stdout) => match format.parse(stdout) {
+                    Ok(output) => try_send!(tx, ModuleUpdateEvent::Update(output)),
+                    Err(err) => {
+                        error!("{:?}", err
+                                         .wrap_err("Failed to parse script output:")
+                                         .suggestion("Check the script writes one JSON object per line"));
+                    }
+                },
+                OutputStream::Stderr(stderr) => {
+                    error!("{:?}", Report::msg(stderr)
+                                     .wrap_err("Watched script error:")
+                                     .suggestion("Check the path to your script")
+                                     .suggestion("Check the script for errors")
+                                     .suggestion("If you expect the script to write to stderr, consider redirecting its output to /dev/null to suppress these messages"));
+                }
+            }).await;
         });
 
         Ok(())
@@ -83,20 +97,66 @@ impl Module<Label> for ScriptModule {
         self,
         context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
         info: &ModuleInfo,
-    ) -> Result<ModuleParts<Label>> {
+    ) -> Result<ModuleParts<Widget>> {
         let label = Label::builder().use_markup(true).build();
         label.set_angle(info.bar_position.get_angle());
 
+        // the label stays the module widget unless a progress bar is wanted,
+        // so existing styles keep applying to it.
+        let progress = self.progress_bar.then(|| {
+            let container = gtk::Box::new(info.bar_position.get_orientation(), 5);
+            container.add(&label);
+
+            let progress = ProgressBar::new();
+            progress.set_no_show_all(true);
+            container.add(&progress);
+
+            (container, progress)
+        });
+
         {
             let label = label.clone();
-            context.widget_rx.attach(None, move |s| {
-                label.set_markup(s.as_str());
+            let progress = progress.as_ref().map(|(_, progress)| progress.clone());
+            let mut classes: Vec<String> = vec![];
+
+            context.widget_rx.attach(None, move |output| {
+                label.set_markup(&output.text);
+
+                // the tooltip from the common options is set on the module's container,
+                // so clearing the label's tooltip shows that again.
+                label.set_tooltip_markup(output.tooltip.as_deref());
+
+                if let Some(progress) = &progress {
+                    match output.percentage {
+                        Some(percentage) => {
+                            progress.set_fraction((percentage / 100.0).clamp(0.0, 1.0));
+                            progress.show();
+                        }
+                        None => progress.hide(),
+                    }
+                }
+
+                let style = label.style_context();
+                for class in &classes {
+                    style.remove_class(class);
+                }
+
+                classes = output.class.into_vec();
+                for class in &classes {
+                    label.add_class(class);
+                }
+
                 Continue(true)
             });
         }
 
+        let widget = match progress {
+            Some((container, _)) => container.upcast(),
+            None => label.upcast(),
+        };
+
         Ok(ModuleParts {
-            widget: label,
+            widget,
             popup: None,
         })
     }
@@ -0,0 +1,196 @@
+use super::token::{resolve, Value};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// A label's formatting string,
+/// optionally with thresholds for styling the label.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum LabelFormat {
+    Plain(String),
+    Detailed(DetailedFormat),
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct DetailedFormat {
+    /// Formatting string including tokens.
+    format: String,
+
+    /// Token the thresholds are checked against, without braces.
+    /// Defaults to the first token in the format.
+    token: Option<String>,
+
+    /// Value at which the `warning` class is added.
+    warning_at: Option<f64>,
+
+    /// Value at which the `critical` class is added.
+    critical_at: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Warning,
+    Critical,
+}
+
+impl Level {
+    pub const fn class(self) -> &'static str {
+        match self {
+            Self::Warning => "warning",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+impl LabelFormat {
+    pub fn format(&self) -> &str {
+        match self {
+            Self::Plain(format) => format,
+            Self::Detailed(config) => &config.format,
+        }
+    }
+
+    /// Replaces each token in the format with its value.
+    /// Unknown tokens are left as-is.
+    pub fn render(&self, re: &Regex, values: &HashMap<String, Value>) -> String {
+        re.replace_all(self.format(), |caps: &Captures| {
+            resolve(&caps[1], values).map_or_else(|| caps[0].to_string(), |value| value.text)
+        })
+        .to_string()
+    }
+
+    /// Gets the threshold reached by the token value, if any.
+    ///
+    /// If `critical_at` is lower than `warning_at`,
+    /// thresholds are reached when the value falls to them instead.
+    pub fn level(&self, re: &Regex, values: &HashMap<String, Value>) -> Option<Level> {
+        let Self::Detailed(config) = self else {
+            return None;
+        };
+
+        let token = config.token.as_deref().or_else(|| {
+            re.captures(&config.format)
+                .and_then(|caps| caps.get(1))
+                .map(|token| token.as_str())
+        })?;
+
+        let value = resolve(token, values)?.number;
+
+        let descending = matches!(
+            (config.warning_at, config.critical_at),
+            (Some(warning), Some(critical)) if critical < warning
+        );
+
+        let reached = |threshold: Option<f64>| {
+            threshold.map_or(false, |threshold| {
+                if descending {
+                    value <= threshold
+                } else {
+                    value >= threshold
+                }
+            })
+        };
+
+        if reached(config.critical_at) {
+            Some(Level::Critical)
+        } else if reached(config.warning_at) {
+            Some(Level::Warning)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn re() -> Regex {
+        Regex::new(r"\{([^}]+)}").expect("valid regex")
+    }
+
+    fn values(percent: f64, free: f64) -> HashMap<String, Value> {
+        HashMap::from([
+            ("cpu_percent".to_string(), Value::Percent(percent)),
+            ("memory_free".to_string(), Value::Bytes((free * 1e9) as u64)),
+        ])
+    }
+
+    fn detailed(
+        format: &str,
+        token: Option<&str>,
+        warning_at: Option<f64>,
+        critical_at: Option<f64>,
+    ) -> LabelFormat {
+        LabelFormat::Detailed(DetailedFormat {
+            format: format.to_string(),
+            token: token.map(ToString::to_string),
+            warning_at,
+            critical_at,
+        })
+    }
+
+    #[test]
+    fn test_plain_has_no_level() {
+        let format = LabelFormat::Plain("{cpu_percent}%".to_string());
+        assert_eq!(format.level(&re(), &values(100.0, 0.0)), None);
+    }
+
+    #[test]
+    fn test_ascending_thresholds() {
+        let format = detailed("{cpu_percent}%", None, Some(50.0), Some(90.0));
+
+        assert_eq!(format.level(&re(), &values(49.0, 0.0)), None);
+        assert_eq!(
+            format.level(&re(), &values(50.0, 0.0)),
+            Some(Level::Warning)
+        );
+        assert_eq!(
+            format.level(&re(), &values(95.0, 0.0)),
+            Some(Level::Critical)
+        );
+    }
+
+    #[test]
+    fn test_descending_thresholds() {
+        let format = detailed("{memory_free:GB}", None, Some(4.0), Some(1.0));
+
+        assert_eq!(format.level(&re(), &values(0.0, 8.0)), None);
+        assert_eq!(format.level(&re(), &values(0.0, 3.0)), Some(Level::Warning));
+        assert_eq!(
+            format.level(&re(), &values(0.0, 0.5)),
+            Some(Level::Critical)
+        );
+    }
+
+    #[test]
+    fn test_single_threshold() {
+        let format = detailed("{cpu_percent}%", None, None, Some(90.0));
+
+        assert_eq!(format.level(&re(), &values(80.0, 0.0)), None);
+        assert_eq!(
+            format.level(&re(), &values(90.0, 0.0)),
+            Some(Level::Critical)
+        );
+    }
+
+    #[test]
+    fn test_token_override() {
+        let format = detailed(
+            "{cpu_percent}% {memory_free:GB}",
+            Some("memory_free:MB"),
+            Some(5000.0),
+            None,
+        );
+
+        assert_eq!(format.level(&re(), &values(0.0, 6.0)), Some(Level::Warning));
+        assert_eq!(format.level(&re(), &values(100.0, 4.0)), None);
+    }
+
+    #[test]
+    fn test_unknown_token_has_no_level() {
+        let format = detailed("{swap_used}", None, Some(0.0), None);
+        assert_eq!(format.level(&re(), &values(0.0, 0.0)), None);
+    }
+}
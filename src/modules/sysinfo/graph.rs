@@ -0,0 +1,209 @@
+use super::RefreshType;
+use crate::gtk_helpers::IronbarGtkExt;
+use gtk::prelude::*;
+use gtk::{cairo, DrawingArea, StateFlags};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+use tracing::error;
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct GraphConfig {
+    /// Formatting token to graph, without braces.
+    /// The token value must be numeric.
+    pub(crate) token: String,
+
+    /// Number of samples to keep.
+    #[serde(default = "default_samples")]
+    pub(crate) samples: usize,
+
+    /// Value at the top of the graph.
+    /// Scales to the largest sample if not set.
+    pub(crate) max: Option<f64>,
+
+    /// Width of the graph on the bar, in pixels.
+    #[serde(default = "default_width")]
+    pub(crate) width: i32,
+
+    /// Title shown above the graph in the popup.
+    /// Defaults to the token.
+    pub(crate) label: Option<String>,
+}
+
+const fn default_samples() -> usize {
+    60
+}
+
+const fn default_width() -> i32 {
+    60
+}
+
+/// Size of graphs in the popup, in pixels.
+const POPUP_GRAPH_SIZE: (i32, i32) = (300, 100);
+
+impl GraphConfig {
+    /// Gets the refresh which updates this graph's token.
+    pub fn refresh_type(&self) -> Option<RefreshType> {
+        RefreshType::for_token(&self.token)
+    }
+}
+
+/// Ring buffers of samples for each graph,
+/// keyed by the graph's index in the config.
+///
+/// Graphs are kept separate even if they share a token,
+/// as each may keep a different number of samples.
+#[derive(Debug, Default)]
+pub struct History {
+    samples: HashMap<usize, VecDeque<f64>>,
+}
+
+impl History {
+    /// Adds a sample for the graph at `index`,
+    /// dropping the oldest if the buffer is full.
+    ///
+    /// Returns a copy of the samples, oldest first.
+    pub fn push(&mut self, index: usize, graph: &GraphConfig, value: f64) -> Vec<f64> {
+        let samples = self
+            .samples
+            .entry(index)
+            .or_insert_with(|| VecDeque::with_capacity(graph.samples));
+
+        if samples.len() >= graph.samples {
+            samples.pop_front();
+        }
+
+        samples.push_back(value);
+        samples.iter().copied().collect()
+    }
+}
+
+/// A sparkline graph drawn from a shared set of samples.
+#[derive(Clone)]
+pub struct Graph {
+    pub area: DrawingArea,
+    samples: Rc<RefCell<Vec<f64>>>,
+    /// Every area drawing these samples,
+    /// including this one.
+    areas: Rc<RefCell<Vec<DrawingArea>>>,
+}
+
+impl Graph {
+    /// Creates a graph for the bar.
+    pub fn new(config: &GraphConfig) -> Self {
+        let graph = Self::with_samples(config, Rc::default(), Rc::default());
+        graph.area.set_width_request(config.width);
+        graph
+    }
+
+    /// Creates a larger graph for the popup,
+    /// sharing samples with an existing graph.
+    pub fn popup(&self, config: &GraphConfig) -> Self {
+        let graph = Self::with_samples(config, self.samples.clone(), self.areas.clone());
+        graph
+            .area
+            .set_size_request(POPUP_GRAPH_SIZE.0, POPUP_GRAPH_SIZE.1);
+        graph
+    }
+
+    fn with_samples(
+        config: &GraphConfig,
+        samples: Rc<RefCell<Vec<f64>>>,
+        areas: Rc<RefCell<Vec<DrawingArea>>>,
+    ) -> Self {
+        let area = DrawingArea::new();
+        area.add_class("graph");
+
+        {
+            let samples = samples.clone();
+            let capacity = config.samples;
+            let max = config.max;
+
+            area.connect_draw(move |area, cr| {
+                if let Err(err) = draw(area, cr, &samples.borrow(), capacity, max) {
+                    error!("Failed to draw graph: {err:?}");
+                }
+
+                Inhibit(false)
+            });
+        }
+
+        areas.borrow_mut().push(area.clone());
+
+        Self {
+            area,
+            samples,
+            areas,
+        }
+    }
+
+    /// Replaces the samples and redraws every area sharing them.
+    pub fn set_samples(&self, samples: Vec<f64>) {
+        self.samples.replace(samples);
+
+        for area in self.areas.borrow().iter() {
+            area.queue_draw();
+        }
+    }
+}
+
+/// Draws a filled line graph, with the newest sample on the right.
+///
+/// The CSS `background-color` is drawn behind the graph,
+/// and the CSS `color` is used for the line and fill.
+fn draw(
+    area: &DrawingArea,
+    cr: &cairo::Context,
+    samples: &[f64],
+    capacity: usize,
+    max: Option<f64>,
+) -> Result<(), cairo::Error> {
+    let width = f64::from(area.allocated_width());
+    let height = f64::from(area.allocated_height());
+
+    let style = area.style_context();
+    gtk::render_background(&style, cr, 0.0, 0.0, width, height);
+
+    if samples.is_empty() {
+        return Ok(());
+    }
+
+    let max = max
+        .unwrap_or_else(|| samples.iter().copied().fold(0.0, f64::max))
+        .max(f64::EPSILON);
+
+    let step = width / capacity.saturating_sub(1).max(1) as f64;
+    let offset = width - (samples.len() - 1) as f64 * step;
+
+    let point = |i: usize, value: f64| {
+        let y = height - (value / max).clamp(0.0, 1.0) * height;
+        (offset + i as f64 * step, y)
+    };
+
+    cr.move_to(offset, height);
+    for (i, &value) in samples.iter().enumerate() {
+        let (x, y) = point(i, value);
+        cr.line_to(x, y);
+    }
+    cr.line_to(width, height);
+    cr.close_path();
+
+    let color = style.color(StateFlags::NORMAL);
+    cr.set_source_rgba(
+        color.red(),
+        color.green(),
+        color.blue(),
+        color.alpha() * 0.3,
+    );
+    cr.fill()?;
+
+    for (i, &value) in samples.iter().enumerate() {
+        let (x, y) = point(i, value);
+        cr.line_to(x, y);
+    }
+
+    cr.set_source_rgba(color.red(), color.green(), color.blue(), color.alpha());
+    cr.set_line_width(1.5);
+    cr.stroke()
+}
@@ -0,0 +1,630 @@
+mod format;
+mod graph;
+mod processes;
+mod token;
+
+use self::format::{LabelFormat, Level};
+use self::graph::{Graph, GraphConfig, History};
+use self::processes::{ProcessId, ProcessInfo, ProcessList, ProcessesConfig};
+use self::token::Value;
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::module_tasks::spawn;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{send_async, try_send};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Button, Label, Orientation};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use sysinfo::{ComponentExt, CpuExt, DiskExt, NetworkExt, RefreshKind, System, SystemExt};
+use tokio::select;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::time::sleep;
+use tracing::warn;
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct SysInfoModule {
+    /// List of formatting strings.
+    format: Vec<LabelFormat>,
+    /// Number of seconds between refresh
+    #[serde(default = "Interval::default")]
+    interval: Interval,
+    /// History graphs for numeric tokens.
+    #[serde(default)]
+    graphs: Vec<GraphConfig>,
+    /// Button and popup listing the top processes.
+    processes: Option<ProcessesConfig>,
+
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+#[derive(Debug, Deserialize, Copy, Clone, PartialEq)]
+pub struct Intervals {
+    #[serde(default = "default_interval")]
+    memory: u64,
+    #[serde(default = "default_interval")]
+    cpu: u64,
+    #[serde(default = "default_interval")]
+    temps: u64,
+    #[serde(default = "default_interval")]
+    disks: u64,
+    #[serde(default = "default_interval")]
+    networks: u64,
+    #[serde(default = "default_interval")]
+    system: u64,
+    #[serde(default = "default_interval")]
+    processes: u64,
+}
+
+#[derive(Debug, Deserialize, Copy, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Interval {
+    All(u64),
+    Individual(Intervals),
+}
+
+impl Default for Interval {
+    fn default() -> Self {
+        Self::All(default_interval())
+    }
+}
+
+impl Interval {
+    const fn memory(self) -> u64 {
+        match self {
+            Self::All(n) => n,
+            Self::Individual(intervals) => intervals.memory,
+        }
+    }
+
+    const fn cpu(self) -> u64 {
+        match self {
+            Self::All(n) => n,
+            Self::Individual(intervals) => intervals.cpu,
+        }
+    }
+
+    const fn temps(self) -> u64 {
+        match self {
+            Self::All(n) => n,
+            Self::Individual(intervals) => intervals.temps,
+        }
+    }
+
+    const fn disks(self) -> u64 {
+        match self {
+            Self::All(n) => n,
+            Self::Individual(intervals) => intervals.disks,
+        }
+    }
+
+    const fn networks(self) -> u64 {
+        match self {
+            Self::All(n) => n,
+            Self::Individual(intervals) => intervals.networks,
+        }
+    }
+
+    const fn system(self) -> u64 {
+        match self {
+            Self::All(n) => n,
+            Self::Individual(intervals) => intervals.system,
+        }
+    }
+
+    const fn processes(self) -> u64 {
+        match self {
+            Self::All(n) => n,
+            Self::Individual(intervals) => intervals.processes,
+        }
+    }
+}
+
+const fn default_interval() -> u64 {
+    5
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshType {
+    Memory,
+    Cpu,
+    Temps,
+    Disks,
+    Network,
+    System,
+    Processes,
+}
+
+impl RefreshType {
+    /// Gets the refresh which updates the given token.
+    fn for_token(token: &str) -> Option<Self> {
+        let name = token.split([':', '@']).next().unwrap_or(token);
+
+        match name {
+            "memory_free" | "memory_used" | "memory_total" | "memory_percent" | "swap_free"
+            | "swap_used" | "swap_total" | "swap_percent" => Some(Self::Memory),
+            "cpu_percent" | "cpu_frequency" | "cpu_count" => Some(Self::Cpu),
+            "temp_c" | "temp_f" => Some(Self::Temps),
+            "disk_free" | "disk_used" | "disk_total" | "disk_percent" => Some(Self::Disks),
+            "net_down" | "net_up" => Some(Self::Network),
+            "load_average" | "uptime" => Some(Self::System),
+            "process_count" => Some(Self::Processes),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SysInfoUpdate {
+    /// The latest value of every token.
+    Tokens(HashMap<String, Value>),
+    /// The samples for the graph at `index`, oldest first.
+    History { index: usize, samples: Vec<f64> },
+    /// The top processes, if a process list is configured.
+    Processes(Vec<ProcessInfo>),
+}
+
+#[derive(Debug, Clone)]
+pub enum SysInfoCommand {
+    /// Kills the process, if it is still running.
+    Kill(ProcessId),
+}
+
+impl Module<gtk::Box> for SysInfoModule {
+    type SendMessage = SysInfoUpdate;
+    type ReceiveMessage = SysInfoCommand;
+
+    fn name() -> &'static str {
+        "sysinfo"
+    }
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        tx: Sender<ModuleUpdateEvent<Self::SendMessage>>,
+        mut rx: Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let interval = self.interval;
+        let graphs = self.graphs.clone();
+        let processes = self.processes.clone();
+
+        for graph in &graphs {
+            if graph.refresh_type().is_none() {
+                warn!("Unable to graph unknown token '{}'", graph.token);
+            }
+        }
+
+        let refresh_kind = RefreshKind::everything()
+            .without_processes()
+            .without_users_list();
+
+        let mut sys = System::new_with_specifics(refresh_kind);
+        sys.refresh_components_list();
+        sys.refresh_disks_list();
+        sys.refresh_networks_list();
+
+        let (refresh_tx, mut refresh_rx) = mpsc::channel(16);
+
+        macro_rules! spawn_refresh {
+            ($refresh_type:expr, $func:ident) => {{
+                let tx = refresh_tx.clone();
+                spawn(async move {
+                    loop {
+                        send_async!(tx, $refresh_type);
+                        sleep(Duration::from_secs(interval.$func())).await;
+                    }
+                });
+            }};
+        }
+
+        spawn_refresh!(RefreshType::Memory, memory);
+        spawn_refresh!(RefreshType::Cpu, cpu);
+        spawn_refresh!(RefreshType::Temps, temps);
+        spawn_refresh!(RefreshType::Disks, disks);
+        spawn_refresh!(RefreshType::Network, networks);
+        spawn_refresh!(RefreshType::System, system);
+
+        // listing processes is expensive, so only do it when needed
+        if self.uses_token("process_count") || processes.is_some() {
+            spawn_refresh!(RefreshType::Processes, processes);
+        }
+
+        spawn(async move {
+            let mut format_info = HashMap::new();
+            let mut history = History::default();
+
+            loop {
+                let refresh = select! {
+                    Some(refresh) = refresh_rx.recv() => refresh,
+                    Some(SysInfoCommand::Kill(id)) = rx.recv() => {
+                        processes::kill(&mut sys, &id);
+                        RefreshType::Processes
+                    },
+                    else => break,
+                };
+
+                match refresh {
+                    RefreshType::Memory => refresh_memory_tokens(&mut format_info, &mut sys),
+                    RefreshType::Cpu => refresh_cpu_tokens(&mut format_info, &mut sys),
+                    RefreshType::Temps => refresh_temp_tokens(&mut format_info, &mut sys),
+                    RefreshType::Disks => refresh_disk_tokens(&mut format_info, &mut sys),
+                    RefreshType::Network => {
+                        refresh_network_tokens(&mut format_info, &mut sys, interval.networks());
+                    }
+                    RefreshType::System => refresh_system_tokens(&mut format_info, &sys),
+                    RefreshType::Processes => {
+                        refresh_process_tokens(&mut format_info, &mut sys);
+
+                        if let Some(processes) = &processes {
+                            send_async!(
+                                tx,
+                                ModuleUpdateEvent::Update(SysInfoUpdate::Processes(
+                                    processes.top_processes(&sys)
+                                ))
+                            );
+                        }
+                    }
+                };
+
+                for (index, graph) in graphs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, graph)| graph.refresh_type() == Some(refresh))
+                {
+                    let value =
+                        token::resolve(&graph.token, &format_info).map(|value| value.number);
+
+                    if let Some(value) = value {
+                        let samples = history.push(index, graph, value);
+                        send_async!(
+                            tx,
+                            ModuleUpdateEvent::Update(SysInfoUpdate::History { index, samples })
+                        );
+                    }
+                }
+
+                send_async!(
+                    tx,
+                    ModuleUpdateEvent::Update(SysInfoUpdate::Tokens(format_info.clone()))
+                );
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<gtk::Box>> {
+        let re = Regex::new(r"\{([^}]+)}")?;
+
+        let container = gtk::Box::new(info.bar_position.get_orientation(), 10);
+
+        let mut labels = Vec::new();
+
+        for format in &self.format {
+            let label = Label::builder()
+                .label(format.format())
+                .use_markup(true)
+                .build();
+
+            label.add_class("item");
+            label.set_angle(info.bar_position.get_angle());
+
+            container.add(&label);
+            labels.push(label);
+        }
+
+        let graphs = self
+            .graphs
+            .iter()
+            .map(|config| {
+                let graph = Graph::new(config);
+
+                let button = Button::new();
+                button.add_class("graph-button");
+                button.add(&graph.area);
+                container.add(&button);
+
+                let tx = context.tx.clone();
+                button.connect_clicked(move |button| {
+                    try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+                });
+
+                (graph, button)
+            })
+            .collect::<Vec<_>>();
+
+        let processes_button = self.processes.as_ref().map(|config| {
+            let button = Button::new();
+            button.add_class("processes");
+
+            let label = Label::new(Some(&config.icon));
+            label.set_angle(info.bar_position.get_angle());
+            button.add(&label);
+            container.add(&button);
+
+            let tx = context.tx.clone();
+            button.connect_clicked(move |button| {
+                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+            });
+
+            button
+        });
+
+        let mut process_list = self.processes.as_ref().map(|_| ProcessList::new());
+
+        let popup_buttons = graphs
+            .iter()
+            .map(|(_, button)| button)
+            .chain(processes_button.as_ref())
+            .collect();
+
+        let popup = self
+            .create_popup(&graphs, process_list.as_ref().map(|list| &list.container))
+            .into_popup_parts(popup_buttons);
+
+        {
+            let formats = self.format;
+            context.widget_rx.attach(None, move |update| {
+                let info = match update {
+                    SysInfoUpdate::Tokens(info) => info,
+                    SysInfoUpdate::History { index, samples } => {
+                        if let Some((graph, _)) = graphs.get(index) {
+                            graph.set_samples(samples);
+                        }
+
+                        return Continue(true);
+                    }
+                    SysInfoUpdate::Processes(top) => {
+                        if let Some(list) = &mut process_list {
+                            list.update(&top, &context.controller_tx);
+                        }
+
+                        return Continue(true);
+                    }
+                };
+
+                for (format, label) in formats.iter().zip(labels.clone()) {
+                    label.set_markup(&format.render(&re, &info));
+
+                    let level = format.level(&re, &info);
+                    for other in [Level::Warning, Level::Critical] {
+                        if Some(other) == level {
+                            label.add_class(other.class());
+                        } else {
+                            label.style_context().remove_class(other.class());
+                        }
+                    }
+                }
+
+                Continue(true)
+            });
+        }
+
+        Ok(ModuleParts::new(container, popup))
+    }
+}
+
+impl SysInfoModule {
+    /// Checks whether any format string or graph uses the token.
+    fn uses_token(&self, token: &str) -> bool {
+        let placeholder = format!("{{{token}");
+
+        self.format
+            .iter()
+            .any(|format| format.format().contains(&placeholder))
+            || self.graphs.iter().any(|graph| graph.token == token)
+    }
+
+    /// Creates a popup containing a larger version of each graph,
+    /// which shares its samples with the graph on the bar,
+    /// followed by the process list.
+    ///
+    /// There is no popup if there are no graphs or process list.
+    fn create_popup(
+        &self,
+        graphs: &[(Graph, Button)],
+        process_list: Option<&gtk::Box>,
+    ) -> Option<gtk::Box> {
+        if graphs.is_empty() && process_list.is_none() {
+            return None;
+        }
+
+        let container = gtk::Box::new(Orientation::Vertical, 10);
+
+        for (config, (graph, _)) in self.graphs.iter().zip(graphs) {
+            let title = Label::new(Some(config.label.as_ref().unwrap_or(&config.token)));
+            title.add_class("title");
+            container.add(&title);
+
+            container.add(&graph.popup(config).area);
+        }
+
+        if let Some(process_list) = process_list {
+            let title = Label::new(Some("Processes"));
+            title.add_class("title");
+            container.add(&title);
+
+            container.add(process_list);
+        }
+
+        container.show_all();
+        Some(container)
+    }
+}
+
+fn refresh_memory_tokens(format_info: &mut HashMap<String, Value>, sys: &mut System) {
+    sys.refresh_memory();
+
+    let total_memory = sys.total_memory();
+    let available_memory = sys.available_memory();
+
+    let actual_used_memory = total_memory - available_memory;
+    let memory_percent = actual_used_memory as f64 / total_memory as f64 * 100.0;
+
+    format_info.insert(String::from("memory_free"), Value::Bytes(available_memory));
+    format_info.insert(
+        String::from("memory_used"),
+        Value::Bytes(actual_used_memory),
+    );
+    format_info.insert(String::from("memory_total"), Value::Bytes(total_memory));
+    format_info.insert(
+        String::from("memory_percent"),
+        Value::Percent(memory_percent),
+    );
+
+    let used_swap = sys.used_swap();
+    let total_swap = sys.total_swap();
+
+    format_info.insert(String::from("swap_free"), Value::Bytes(sys.free_swap()));
+    format_info.insert(String::from("swap_used"), Value::Bytes(used_swap));
+    format_info.insert(String::from("swap_total"), Value::Bytes(total_swap));
+    format_info.insert(
+        String::from("swap_percent"),
+        Value::Percent(used_swap as f64 / total_swap as f64 * 100.0),
+    );
+}
+
+fn refresh_cpu_tokens(format_info: &mut HashMap<String, Value>, sys: &mut System) {
+    sys.refresh_cpu();
+
+    let cpu_info = sys.global_cpu_info();
+    let cpu_percent = cpu_info.cpu_usage();
+
+    format_info.insert(
+        String::from("cpu_percent"),
+        Value::Percent(f64::from(cpu_percent)),
+    );
+
+    let cpus = sys.cpus();
+    format_info.insert(
+        String::from("cpu_count"),
+        Value::Number {
+            value: cpus.len() as f64,
+            precision: 0,
+        },
+    );
+
+    let total_frequency = cpus.iter().map(CpuExt::frequency).sum::<u64>();
+    format_info.insert(
+        String::from("cpu_frequency"),
+        Value::Frequency(total_frequency as f64 / cpus.len().max(1) as f64),
+    );
+
+    for (i, cpu) in cpus.iter().enumerate() {
+        format_info.insert(
+            format!("cpu_percent@{i}"),
+            Value::Percent(f64::from(cpu.cpu_usage())),
+        );
+
+        format_info.insert(
+            format!("cpu_frequency@{i}"),
+            Value::Frequency(cpu.frequency() as f64),
+        );
+    }
+}
+
+fn refresh_temp_tokens(format_info: &mut HashMap<String, Value>, sys: &mut System) {
+    sys.refresh_components();
+
+    let components = sys.components();
+    for component in components {
+        let key = component.label().replace(' ', "-");
+        let temp = component.temperature();
+
+        format_info.insert(format!("temp_c:{key}"), Value::Temperature(f64::from(temp)));
+        format_info.insert(
+            format!("temp_f:{key}"),
+            Value::Temperature(f64::from(c_to_f(temp))),
+        );
+    }
+}
+
+fn refresh_disk_tokens(format_info: &mut HashMap<String, Value>, sys: &mut System) {
+    sys.refresh_disks();
+
+    for disk in sys.disks() {
+        // replace braces to avoid conflict with regex
+        let key = disk
+            .mount_point()
+            .to_str()
+            .map(|s| s.replace(['{', '}'], ""));
+
+        if let Some(key) = key {
+            let total = disk.total_space();
+            let available = disk.available_space();
+            let used = total - available;
+
+            format_info.insert(format!("disk_free:{key}"), Value::Bytes(available));
+            format_info.insert(format!("disk_used:{key}"), Value::Bytes(used));
+            format_info.insert(format!("disk_total:{key}"), Value::Bytes(total));
+            format_info.insert(
+                format!("disk_percent:{key}"),
+                Value::Percent(used as f64 / total as f64 * 100.0),
+            );
+        }
+    }
+}
+
+fn refresh_network_tokens(
+    format_info: &mut HashMap<String, Value>,
+    sys: &mut System,
+    interval: u64,
+) {
+    sys.refresh_networks();
+
+    for (iface, network) in sys.networks() {
+        format_info.insert(
+            format!("net_down:{iface}"),
+            Value::Rate(network.received() as f64 / interval as f64),
+        );
+
+        format_info.insert(
+            format!("net_up:{iface}"),
+            Value::Rate(network.transmitted() as f64 / interval as f64),
+        );
+    }
+}
+
+fn refresh_system_tokens(format_info: &mut HashMap<String, Value>, sys: &System) {
+    // no refresh required for these tokens
+
+    let load_average = |value| Value::Number {
+        value,
+        precision: 2,
+    };
+
+    let load = sys.load_average();
+    format_info.insert(String::from("load_average:1"), load_average(load.one));
+    format_info.insert(String::from("load_average:5"), load_average(load.five));
+    format_info.insert(String::from("load_average:15"), load_average(load.fifteen));
+
+    format_info.insert(String::from("uptime"), Value::Duration(sys.uptime()));
+}
+
+fn refresh_process_tokens(format_info: &mut HashMap<String, Value>, sys: &mut System) {
+    sys.refresh_processes();
+
+    format_info.insert(
+        String::from("process_count"),
+        Value::Number {
+            value: sys.processes().len() as f64,
+            precision: 0,
+        },
+    );
+}
+
+/// Converts celsius to fahrenheit.
+fn c_to_f(c: f32) -> f32 {
+    c * 9.0 / 5.0 + 32.0
+}
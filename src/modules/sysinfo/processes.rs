@@ -0,0 +1,235 @@
+use super::SysInfoCommand;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::try_send;
+use gtk::prelude::*;
+use gtk::{Align, Button, Label, Orientation};
+use serde::Deserialize;
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
+use sysinfo::{Pid, PidExt, ProcessExt, Signal, System, SystemExt};
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, warn};
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct ProcessesConfig {
+    /// Number of processes to list.
+    #[serde(default = "default_count")]
+    pub(crate) count: usize,
+
+    /// Resource to sort processes by.
+    #[serde(default)]
+    pub(crate) sort: ProcessSort,
+
+    /// Icon to show on the button which opens the popup.
+    #[serde(default = "default_icon")]
+    pub(crate) icon: String,
+}
+
+const fn default_count() -> usize {
+    5
+}
+
+fn default_icon() -> String {
+    String::from("󰍛")
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessSort {
+    #[default]
+    Cpu,
+    Memory,
+}
+
+/// Identifies a process.
+///
+/// PIDs are reused once a process exits,
+/// so the name and start time are included to tell processes apart.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProcessId {
+    pub pid: u32,
+    pub name: String,
+    /// Time the process started, in seconds since the epoch.
+    pub start_time: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessInfo {
+    pub id: ProcessId,
+    /// CPU usage percentage, where 100% is a single core.
+    pub cpu: f32,
+    /// Memory usage in bytes.
+    pub memory: u64,
+}
+
+impl ProcessesConfig {
+    /// Gets the processes using the most of the configured resource.
+    pub fn top_processes(&self, sys: &System) -> Vec<ProcessInfo> {
+        let mut processes = sys
+            .processes()
+            .iter()
+            .map(|(pid, process)| ProcessInfo {
+                id: ProcessId {
+                    pid: pid.as_u32(),
+                    name: process.name().to_string(),
+                    start_time: process.start_time(),
+                },
+                cpu: process.cpu_usage(),
+                memory: process.memory(),
+            })
+            .collect::<Vec<_>>();
+
+        match self.sort {
+            ProcessSort::Cpu => {
+                processes.sort_by(|a, b| b.cpu.partial_cmp(&a.cpu).unwrap_or(Ordering::Equal))
+            }
+            ProcessSort::Memory => processes.sort_by_key(|process| Reverse(process.memory)),
+        }
+
+        processes.truncate(self.count);
+        processes
+    }
+}
+
+/// Asks the process to terminate,
+/// falling back to killing it if `SIGTERM` is unsupported.
+///
+/// The process is refreshed first, and is only signalled
+/// if it is still the same process that was listed.
+pub fn kill(sys: &mut System, id: &ProcessId) {
+    let pid = Pid::from_u32(id.pid);
+
+    let process = if sys.refresh_process(pid) {
+        sys.process(pid)
+    } else {
+        None
+    };
+
+    let Some(process) = process else {
+        warn!("Unable to kill process {}: not found", id.pid);
+        return;
+    };
+
+    if process.name() != id.name || process.start_time() != id.start_time {
+        warn!(
+            "Unable to kill process {} ({}): PID now belongs to another process",
+            id.pid, id.name
+        );
+        return;
+    }
+
+    debug!("Killing process {} ({})", id.pid, id.name);
+
+    let killed = process
+        .kill_with(Signal::Term)
+        .unwrap_or_else(|| process.kill());
+
+    if !killed {
+        warn!("Failed to kill process {}", id.pid);
+    }
+}
+
+/// Popup list of the top processes.
+///
+/// Rows are kept between updates and only their values are changed,
+/// so that the list does not flicker or lose hover state every interval.
+pub struct ProcessList {
+    pub container: gtk::Box,
+    rows: HashMap<ProcessId, ProcessRow>,
+}
+
+impl ProcessList {
+    pub fn new() -> Self {
+        let container = gtk::Box::new(Orientation::Vertical, 5);
+        container.add_class("processes");
+
+        Self {
+            container,
+            rows: HashMap::new(),
+        }
+    }
+
+    /// Updates the list to show `top`, in order.
+    /// Rows for processes no longer listed are removed,
+    /// and rows are created for newly listed processes.
+    pub fn update(&mut self, top: &[ProcessInfo], tx: &Sender<SysInfoCommand>) {
+        let container = &self.container;
+
+        self.rows.retain(|id, row| {
+            let keep = top.iter().any(|process| &process.id == id);
+            if !keep {
+                container.remove(&row.container);
+            }
+            keep
+        });
+
+        for (index, process) in top.iter().enumerate() {
+            let row = self.rows.entry(process.id.clone()).or_insert_with(|| {
+                let row = ProcessRow::new(&process.id, tx);
+                container.add(&row.container);
+                row
+            });
+
+            row.update(process);
+            container.reorder_child(&row.container, index as i32);
+        }
+
+        container.show_all();
+    }
+}
+
+/// Popup row for a process,
+/// with a button to kill it.
+struct ProcessRow {
+    container: gtk::Box,
+    cpu: Label,
+    memory: Label,
+}
+
+impl ProcessRow {
+    fn new(id: &ProcessId, tx: &Sender<SysInfoCommand>) -> Self {
+        let container = gtk::Box::new(Orientation::Horizontal, 10);
+        container.add_class("process");
+
+        let name = Label::builder()
+            .label(&id.name)
+            .halign(Align::Start)
+            .hexpand(true)
+            .build();
+        name.add_class("name");
+        name.set_tooltip_text(Some(&format!("PID {}", id.pid)));
+        container.add(&name);
+
+        let cpu = Label::new(None);
+        cpu.add_class("cpu");
+        container.add(&cpu);
+
+        let memory = Label::new(None);
+        memory.add_class("memory");
+        container.add(&memory);
+
+        let kill = Button::with_label("Kill");
+        kill.add_class("kill");
+        container.add(&kill);
+
+        {
+            let tx = tx.clone();
+            let id = id.clone();
+            kill.connect_clicked(move |_| {
+                try_send!(tx, SysInfoCommand::Kill(id.clone()));
+            });
+        }
+
+        Self {
+            container,
+            cpu,
+            memory,
+        }
+    }
+
+    fn update(&self, process: &ProcessInfo) {
+        self.cpu.set_label(&format!("{:.0}%", process.cpu));
+        self.memory
+            .set_label(&format!("{:.0} MB", process.memory as f64 / 1_000_000.0));
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+/// The latest value of a formatting token.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    /// A size in bytes.
+    Bytes(u64),
+    /// A transfer rate in bytes per second.
+    Rate(f64),
+    /// A frequency in megahertz.
+    Frequency(f64),
+    Percent(f64),
+    Temperature(f64),
+    /// A duration in seconds, shown as `HH:mm`.
+    Duration(u64),
+    /// A unitless number, with its default precision.
+    Number {
+        value: f64,
+        precision: usize,
+    },
+}
+
+/// A unit which a value can be displayed in.
+#[derive(Debug)]
+struct Unit {
+    symbol: &'static str,
+    /// Number of base units in this unit.
+    factor: f64,
+    /// Whether the unit can be picked by `auto`.
+    auto: bool,
+}
+
+impl Unit {
+    const fn new(symbol: &'static str, factor: f64, auto: bool) -> Self {
+        Self {
+            symbol,
+            factor,
+            auto,
+        }
+    }
+}
+
+const BYTE_UNITS: &[Unit] = &[
+    Unit::new("B", 1.0, true),
+    Unit::new("KB", 1e3, true),
+    Unit::new("MB", 1e6, true),
+    Unit::new("GB", 1e9, true),
+    Unit::new("TB", 1e12, true),
+    Unit::new("KiB", 1024.0, false),
+    Unit::new("MiB", 1_048_576.0, false),
+    Unit::new("GiB", 1_073_741_824.0, false),
+    Unit::new("TiB", 1_099_511_627_776.0, false),
+];
+
+const RATE_UNITS: &[Unit] = &[
+    Unit::new("bps", 0.125, true),
+    Unit::new("Kbps", 125.0, true),
+    Unit::new("Mbps", 125e3, true),
+    Unit::new("Gbps", 125e6, true),
+    Unit::new("B/s", 1.0, false),
+    Unit::new("KB/s", 1e3, false),
+    Unit::new("MB/s", 1e6, false),
+    Unit::new("GB/s", 1e9, false),
+    Unit::new("KiB/s", 1024.0, false),
+    Unit::new("MiB/s", 1_048_576.0, false),
+    Unit::new("GiB/s", 1_073_741_824.0, false),
+];
+
+const FREQUENCY_UNITS: &[Unit] = &[Unit::new("MHz", 1.0, true), Unit::new("GHz", 1e3, true)];
+
+/// A token value formatted according to its format spec.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolved {
+    pub text: String,
+    /// The value in the unit it is displayed in.
+    pub number: f64,
+}
+
+impl Value {
+    /// Gets the units the value can be displayed in,
+    /// and the default unit.
+    fn units(self) -> Option<(&'static [Unit], &'static Unit)> {
+        match self {
+            Self::Bytes(_) => Some((BYTE_UNITS, &BYTE_UNITS[3])),
+            Self::Rate(_) => Some((RATE_UNITS, &RATE_UNITS[2])),
+            Self::Frequency(_) => Some((FREQUENCY_UNITS, &FREQUENCY_UNITS[0])),
+            _ => None,
+        }
+    }
+
+    /// Gets the value in its base unit.
+    fn base(self) -> f64 {
+        match self {
+            Self::Bytes(value) | Self::Duration(value) => value as f64,
+            Self::Rate(value)
+            | Self::Frequency(value)
+            | Self::Percent(value)
+            | Self::Temperature(value)
+            | Self::Number { value, .. } => value,
+        }
+    }
+
+    /// Formats the value.
+    ///
+    /// When no precision is given, the output matches
+    /// the token's default format.
+    fn format(self, unit: Option<&Unit>, precision: Option<usize>) -> Resolved {
+        let number = unit.map_or_else(|| self.base(), |unit| self.base() / unit.factor);
+
+        let text = match (self, precision) {
+            (Self::Duration(secs), _) => {
+                format!("{:0>2}:{:0>2}", secs / 3600, (secs % 3600) / 60)
+            }
+            (_, Some(precision)) => format!("{number:.precision$}"),
+            (Self::Bytes(_), None) => format!("{}", number.trunc()),
+            (Self::Rate(_) | Self::Percent(_), None) => format!("{number:0>2.0}"),
+            (Self::Frequency(_) | Self::Temperature(_), None) => format!("{number:.0}"),
+            (Self::Number { precision, .. }, None) => format!("{number:.precision$}"),
+        };
+
+        Resolved { text, number }
+    }
+}
+
+/// Resolves the contents of a placeholder, such as `memory_used:MiB:.1`,
+/// against the current token values.
+///
+/// The token name is followed by optional `:` separated parts,
+/// which may be a unit, `auto` or a precision such as `.2`.
+/// `auto` picks the largest unit with a value of at least 1,
+/// and appends the unit symbol.
+///
+/// Returns `None` if the token does not exist
+/// or the spec is invalid for its value.
+pub fn resolve(spec: &str, values: &HashMap<String, Value>) -> Option<Resolved> {
+    let parts = spec.split(':').collect::<Vec<_>>();
+
+    // token names can contain `:`, so find the longest matching name
+    let (name_len, value) = (1..=parts.len())
+        .rev()
+        .find_map(|i| values.get(&parts[..i].join(":")).map(|value| (i, *value)))?;
+
+    let units = value.units();
+
+    let mut unit = units.map(|(_, default)| default);
+    let mut auto = false;
+    let mut precision = None;
+
+    for part in &parts[name_len..] {
+        if let Some(digits) = part.strip_prefix('.') {
+            precision = Some(digits.parse().ok()?);
+        } else if *part == "auto" {
+            let (units, _) = units?;
+            let base = value.base();
+
+            unit = Some(
+                units
+                    .iter()
+                    .rfind(|unit| unit.auto && base >= unit.factor)
+                    .unwrap_or(&units[0]),
+            );
+            auto = true;
+        } else {
+            let (units, _) = units?;
+            unit = Some(units.iter().find(|unit| unit.symbol == *part)?);
+        }
+    }
+
+    if auto && precision.is_none() {
+        precision = Some(1);
+    }
+
+    let mut resolved = value.format(unit, precision);
+
+    if let Some(unit) = unit.filter(|_| auto) {
+        resolved.text = format!("{} {}", resolved.text, unit.symbol);
+    }
+
+    Some(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> HashMap<String, Value> {
+        HashMap::from([
+            ("memory_used".to_string(), Value::Bytes(1_500_000_000)),
+            ("memory_percent".to_string(), Value::Percent(42.6)),
+            ("disk_free".to_string(), Value::Bytes(1)),
+            ("disk_free:/".to_string(), Value::Bytes(2_000_000_000)),
+            (
+                "disk_free:/mnt:data".to_string(),
+                Value::Bytes(3_000_000_000),
+            ),
+            ("net_down:eth0".to_string(), Value::Rate(2_500_000.0)),
+            ("uptime".to_string(), Value::Duration(3 * 3600 + 25 * 60)),
+        ])
+    }
+
+    fn text(spec: &str) -> Option<String> {
+        resolve(spec, &values()).map(|resolved| resolved.text)
+    }
+
+    #[test]
+    fn test_default_format() {
+        assert_eq!(text("memory_used").as_deref(), Some("1"));
+        assert_eq!(text("memory_percent").as_deref(), Some("43"));
+        assert_eq!(text("net_down:eth0").as_deref(), Some("20"));
+        assert_eq!(text("uptime").as_deref(), Some("03:25"));
+    }
+
+    #[test]
+    fn test_longest_name() {
+        assert_eq!(text("disk_free:/").as_deref(), Some("2"));
+        assert_eq!(text("disk_free:/mnt:data").as_deref(), Some("3"));
+        assert_eq!(text("disk_free:/mnt:data:MB").as_deref(), Some("3000"));
+        assert_eq!(text("disk_free:B").as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn test_unit() {
+        assert_eq!(text("memory_used:MB").as_deref(), Some("1500"));
+        assert_eq!(text("memory_used:GiB:.2").as_deref(), Some("1.40"));
+        assert_eq!(text("memory_used:Mbps"), None);
+        assert_eq!(text("memory_percent:MB"), None);
+    }
+
+    #[test]
+    fn test_auto() {
+        assert_eq!(text("memory_used:auto").as_deref(), Some("1.5 GB"));
+        assert_eq!(text("memory_used:auto:.0").as_deref(), Some("2 GB"));
+        assert_eq!(text("disk_free:auto").as_deref(), Some("1.0 B"));
+        assert_eq!(text("net_down:eth0:auto").as_deref(), Some("20.0 Mbps"));
+        assert_eq!(text("memory_percent:auto"), None);
+    }
+
+    #[test]
+    fn test_precision() {
+        assert_eq!(text("memory_percent:.2").as_deref(), Some("42.60"));
+        assert_eq!(text("memory_used:.1").as_deref(), Some("1.5"));
+        assert_eq!(text("memory_used:.x"), None);
+    }
+
+    #[test]
+    fn test_number() {
+        let resolved = resolve("memory_used:MB", &values()).expect("resolves");
+        assert!((resolved.number - 1500.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_unknown_token() {
+        assert_eq!(text("swap_used"), None);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::clients::system_tray::get_tray_event_client;
 use crate::config::CommonConfig;
+use crate::module_tasks::spawn;
 use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
 use crate::{await_sync, try_send};
 use color_eyre::Result;
@@ -14,11 +15,10 @@ use std::collections::HashMap;
 use system_tray::message::menu::{MenuItem as MenuItemInfo, MenuType};
 use system_tray::message::tray::StatusNotifierItem;
 use system_tray::message::{NotifierItemCommand, NotifierItemMessage};
-use tokio::spawn;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::{Receiver, Sender};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct TrayModule {
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
@@ -173,7 +173,9 @@ impl Module<MenuBar> for TrayModule {
         context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
         _info: &ModuleInfo,
     ) -> Result<ModuleParts<MenuBar>> {
-        let container = MenuBar::builder().pack_direction(gtk::PackDirection::Ttb).build();
+        let container = MenuBar::builder()
+            .pack_direction(gtk::PackDirection::Ttb)
+            .build();
 
         {
             let container = container.clone();
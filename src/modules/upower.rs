@@ -1,14 +1,19 @@
+use crate::module_tasks::spawn;
 use color_eyre::Result;
 use futures_lite::stream::StreamExt;
 use gtk::{prelude::*, Button};
 use gtk::{Label, Orientation};
 use serde::Deserialize;
-use tokio::spawn;
 use tokio::sync::mpsc::{Receiver, Sender};
+use tracing::debug;
 use upower_dbus::BatteryState;
 use zbus;
 
-use crate::clients::upower::get_display_proxy;
+use crate::clients::signals::next_burst;
+use crate::clients::upower::{
+    device_changes, get_devices, get_display_proxy, get_power_profiles, get_power_profiles_proxy,
+    Device, PowerProfiles,
+};
 use crate::config::CommonConfig;
 use crate::gtk_helpers::IronbarGtkExt;
 use crate::image::ImageProvider;
@@ -16,13 +21,14 @@ use crate::modules::PopupButton;
 use crate::modules::{
     Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, WidgetContext,
 };
+use crate::script::{Script, ScriptInput};
 use crate::{await_sync, error, send_async, try_send};
 
 const DAY: i64 = 24 * 60 * 60;
 const HOUR: i64 = 60 * 60;
 const MINUTE: i64 = 60;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct UpowerModule {
     #[serde(default = "default_format")]
     format: String,
@@ -30,6 +36,18 @@ pub struct UpowerModule {
     #[serde(default = "default_icon_size")]
     icon_size: i32,
 
+    /// Whether to show power-profiles-daemon profiles in the popup.
+    #[serde(default = "crate::config::default_true")]
+    show_profiles: bool,
+
+    /// Whether to show the batteries of peripheral devices in the popup.
+    #[serde(default = "crate::config::default_true")]
+    show_devices: bool,
+
+    /// Actions to run when the battery runs low.
+    #[serde(default)]
+    low_battery: Vec<LowBatteryConfig>,
+
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
 }
@@ -42,6 +60,61 @@ const fn default_icon_size() -> i32 {
     24
 }
 
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct LowBatteryConfig {
+    /// Percentage at or below which the battery is considered low.
+    percentage: f64,
+
+    /// Script to run when the battery becomes low.
+    on_low: Option<ScriptInput>,
+
+    /// Name of an ironvar to set to `true` while the battery is low,
+    /// and `false` otherwise.
+    variable: Option<Box<str>>,
+}
+
+impl LowBatteryConfig {
+    fn is_low(&self, properties: &UpowerProperties) -> bool {
+        let discharging = matches!(
+            u32_to_battery_state(properties.state),
+            Ok(BatteryState::Discharging | BatteryState::PendingDischarge | BatteryState::Empty)
+        );
+
+        discharging && properties.percentage <= self.percentage
+    }
+
+    /// Runs the configured actions if the battery has become low,
+    /// and updates the ironvar if it has changed.
+    fn check(&self, properties: &UpowerProperties, was_low: &mut Option<bool>) {
+        let low = self.is_low(properties);
+        if *was_low == Some(low) {
+            return;
+        }
+
+        *was_low = Some(low);
+
+        if low {
+            debug!("Battery low: {}%", properties.percentage);
+
+            if let Some(script) = &self.on_low {
+                Script::from(script.clone()).run_as_oneshot(None);
+            }
+        }
+
+        #[cfg(feature = "ipc")]
+        if let Some(variable) = &self.variable {
+            use crate::ironvar::get_variable_manager;
+            use crate::write_lock;
+
+            let variable_manager = get_variable_manager();
+            let result = write_lock!(variable_manager).set(variable.clone(), low.to_string());
+            if let Err(err) = result {
+                error!("Failed to set ironvar '{variable}': {err:?}");
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct UpowerProperties {
     percentage: f64,
@@ -51,9 +124,21 @@ pub struct UpowerProperties {
     time_to_empty: i64,
 }
 
+#[derive(Clone, Debug)]
+pub enum UpowerUpdate {
+    Properties(UpowerProperties),
+    Devices(Vec<Device>),
+    Profiles(PowerProfiles),
+}
+
+#[derive(Clone, Debug)]
+pub enum UpowerCommand {
+    SetProfile(String),
+}
+
 impl Module<gtk::Button> for UpowerModule {
-    type SendMessage = UpowerProperties;
-    type ReceiveMessage = ();
+    type SendMessage = UpowerUpdate;
+    type ReceiveMessage = UpowerCommand;
 
     fn name() -> &'static str {
         "upower"
@@ -63,8 +148,18 @@ impl Module<gtk::Button> for UpowerModule {
         &self,
         _info: &ModuleInfo,
         tx: Sender<ModuleUpdateEvent<Self::SendMessage>>,
-        _rx: Receiver<Self::ReceiveMessage>,
+        rx: Receiver<Self::ReceiveMessage>,
     ) -> Result<()> {
+        if self.show_devices {
+            spawn_devices_controller(tx.clone());
+        }
+
+        if self.show_profiles {
+            spawn_profiles_controller(tx.clone(), rx);
+        }
+
+        let low_battery = self.low_battery.clone();
+
         spawn(async move {
             // await_sync due to strange "higher-ranked lifetime error"
             let display_proxy = await_sync(async move { get_display_proxy().await });
@@ -100,7 +195,19 @@ impl Module<gtk::Button> for UpowerModule {
                 time_to_empty,
             };
 
-            send_async!(tx, ModuleUpdateEvent::Update(properties.clone()));
+            let mut was_low = vec![None; low_battery.len()];
+            let check_low_battery =
+                |properties: &UpowerProperties, was_low: &mut [Option<bool>]| {
+                    for (config, was_low) in low_battery.iter().zip(was_low) {
+                        config.check(properties, was_low);
+                    }
+                };
+
+            check_low_battery(&properties, &mut was_low);
+            send_async!(
+                tx,
+                ModuleUpdateEvent::Update(UpowerUpdate::Properties(properties.clone()))
+            );
 
             while let Some(signal) = prop_changed_stream.next().await {
                 let args = signal.args().expect("Invalid signal arguments");
@@ -140,7 +247,11 @@ impl Module<gtk::Button> for UpowerModule {
                     }
                 }
 
-                send_async!(tx, ModuleUpdateEvent::Update(properties.clone()));
+                check_low_battery(&properties, &mut was_low);
+                send_async!(
+                    tx,
+                    ModuleUpdateEvent::Update(UpowerUpdate::Properties(properties.clone()))
+                );
             }
 
             Result::<()>::Ok(())
@@ -184,16 +295,18 @@ impl Module<gtk::Button> for UpowerModule {
         label.set_angle(info.bar_position.get_angle());
         let format = self.format.clone();
 
-        context
-            .widget_rx
-            .attach(None, move |properties: UpowerProperties| {
-                let format = format.replace("{percentage}", &properties.percentage.to_string());
-                let icon_name = String::from("icon:") + &properties.icon_name;
-                ImageProvider::parse(&icon_name, &icon_theme, false, self.icon_size)
-                    .map(|provider| provider.load_into_image(icon.clone()));
-                label.set_markup(format.as_ref());
-                Continue(true)
-            });
+        context.widget_rx.attach(None, move |update| {
+            let UpowerUpdate::Properties(properties) = update else {
+                return Continue(true);
+            };
+
+            let format = format.replace("{percentage}", &properties.percentage.to_string());
+            let icon_name = String::from("icon:") + &properties.icon_name;
+            ImageProvider::parse(&icon_name, &icon_theme, false, self.icon_size)
+                .map(|provider| provider.load_into_image(icon.clone()));
+            label.set_markup(format.as_ref());
+            Continue(true)
+        });
 
         let popup = self
             .into_popup(context.controller_tx, context.popup_rx, info)
@@ -204,22 +317,96 @@ impl Module<gtk::Button> for UpowerModule {
 
     fn into_popup(
         self,
-        _tx: Sender<Self::ReceiveMessage>,
+        tx: Sender<Self::ReceiveMessage>,
         rx: glib::Receiver<Self::SendMessage>,
-        _info: &ModuleInfo,
+        info: &ModuleInfo,
     ) -> Option<gtk::Box>
     where
         Self: Sized,
     {
         let container = gtk::Box::builder()
-            .orientation(Orientation::Horizontal)
+            .orientation(Orientation::Vertical)
+            .spacing(5)
             .build();
 
         let label = Label::new(None);
         label.add_class("upower-details");
         container.add(&label);
 
-        rx.attach(None, move |properties| {
+        let profiles = gtk::Box::new(Orientation::Horizontal, 5);
+        profiles.add_class("profiles");
+        container.add(&profiles);
+
+        let devices = gtk::Box::new(Orientation::Vertical, 5);
+        devices.add_class("devices");
+        container.add(&devices);
+
+        let icon_theme = info.icon_theme.clone();
+        let icon_size = self.icon_size;
+
+        rx.attach(None, move |update| {
+            let properties = match update {
+                UpowerUpdate::Properties(properties) => properties,
+                UpowerUpdate::Profiles(power_profiles) => {
+                    for child in profiles.children() {
+                        profiles.remove(&child);
+                    }
+
+                    for profile in power_profiles.available {
+                        let button = Button::with_label(&profile_display_name(&profile));
+                        button.add_class("profile");
+
+                        if profile == power_profiles.active {
+                            button.add_class("active");
+                        }
+
+                        let tx = tx.clone();
+                        button.connect_clicked(move |_| {
+                            try_send!(tx, UpowerCommand::SetProfile(profile.clone()));
+                        });
+
+                        profiles.add(&button);
+                    }
+
+                    profiles.show_all();
+                    return Continue(true);
+                }
+                UpowerUpdate::Devices(new_devices) => {
+                    for child in devices.children() {
+                        devices.remove(&child);
+                    }
+
+                    for device in new_devices {
+                        let row = gtk::Box::new(Orientation::Horizontal, 5);
+                        row.add_class("device");
+
+                        let icon = gtk::Image::new();
+                        icon.add_class("icon");
+                        ImageProvider::parse(
+                            &format!("icon:{}", device.icon_name),
+                            &icon_theme,
+                            false,
+                            icon_size,
+                        )
+                        .map(|provider| provider.load_into_image(icon.clone()));
+                        row.add(&icon);
+
+                        let name = Label::new(Some(&device.name));
+                        name.add_class("name");
+                        row.add(&name);
+
+                        let percentage = Label::new(Some(&format!("{:.0}%", device.percentage)));
+                        percentage.add_class("percentage");
+                        row.add(&percentage);
+
+                        devices.add(&row);
+                    }
+
+                    devices.show_all();
+                    return Continue(true);
+                }
+            };
+
             let state = u32_to_battery_state(properties.state);
             let format = match state {
                 Ok(BatteryState::Charging | BatteryState::PendingCharge) => {
@@ -255,6 +442,92 @@ impl Module<gtk::Button> for UpowerModule {
     }
 }
 
+/// Refreshes the peripheral device list whenever UPower reports a change.
+fn spawn_devices_controller(tx: Sender<ModuleUpdateEvent<UpowerUpdate>>) {
+    spawn(async move {
+        let mut stream = device_changes().await?;
+
+        loop {
+            match get_devices().await {
+                Ok(devices) => {
+                    send_async!(
+                        tx,
+                        ModuleUpdateEvent::Update(UpowerUpdate::Devices(devices))
+                    );
+                }
+                Err(err) => error!("{:?}", err.wrap_err("Failed to get upower devices")),
+            }
+
+            if next_burst(&mut stream).await.is_none() {
+                break;
+            }
+        }
+
+        Result::<()>::Ok(())
+    });
+}
+
+/// Watches the active power profile, and switches profile when requested from the popup.
+fn spawn_profiles_controller(
+    tx: Sender<ModuleUpdateEvent<UpowerUpdate>>,
+    mut rx: Receiver<UpowerCommand>,
+) {
+    spawn(async move {
+        let proxy = get_power_profiles_proxy().await?;
+
+        {
+            let proxy = proxy.clone();
+            spawn(async move {
+                while let Some(UpowerCommand::SetProfile(profile)) = rx.recv().await {
+                    debug!("Setting power profile: {profile}");
+
+                    if let Err(err) = proxy.set_active_profile(&profile).await {
+                        error!("Failed to set power profile: {err:?}");
+                    }
+                }
+            });
+        }
+
+        let mut stream = proxy.receive_active_profile_changed().await;
+
+        loop {
+            match get_power_profiles(&proxy).await {
+                Ok(profiles) => {
+                    send_async!(
+                        tx,
+                        ModuleUpdateEvent::Update(UpowerUpdate::Profiles(profiles))
+                    );
+                }
+                // power-profiles-daemon is optional
+                Err(err) => {
+                    debug!("Failed to get power profiles: {err:?}");
+                    break;
+                }
+            }
+
+            if stream.next().await.is_none() {
+                break;
+            }
+        }
+
+        Result::<()>::Ok(())
+    });
+}
+
+/// Converts a profile name such as `power-saver` into `Power Saver`.
+fn profile_display_name(profile: &str) -> String {
+    profile
+        .split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first.to_uppercase().chain(chars).collect()
+            })
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 fn seconds_to_string(seconds: i64) -> String {
     let mut time_string = String::new();
     let days = seconds / (DAY);
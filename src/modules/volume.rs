@@ -0,0 +1,497 @@
+use crate::clients::volume::{self, Device, Event, SinkInput};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::module_tasks::spawn;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{send_async, try_send};
+use color_eyre::Result;
+use glib::SignalHandlerId;
+use gtk::prelude::*;
+use gtk::{Button, ComboBoxText, Label, Orientation, Scale, ToggleButton};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tracing::warn;
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct VolumeModule {
+    /// Format string to use for the widget button label.
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// Maximum value to allow volume sliders to reach.
+    /// Pulse supports volumes above 100%.
+    #[serde(default = "default_max_volume")]
+    max_volume: f64,
+
+    /// Volume state icons.
+    #[serde(default)]
+    icons: Icons,
+
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{icon} {percentage}%")
+}
+
+const fn default_max_volume() -> f64 {
+    100.0
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct Icons {
+    /// Icon to show for high volume levels.
+    #[serde(default = "default_icon_volume_high")]
+    volume_high: String,
+
+    /// Icon to show for medium volume levels.
+    #[serde(default = "default_icon_volume_medium")]
+    volume_medium: String,
+
+    /// Icon to show for low volume levels.
+    #[serde(default = "default_icon_volume_low")]
+    volume_low: String,
+
+    /// Icon to show for muted outputs.
+    #[serde(default = "default_icon_muted")]
+    muted: String,
+}
+
+impl Icons {
+    /// Gets the icon for the device's volume level and mute state.
+    fn volume_icon(&self, volume_percent: f64, muted: bool) -> &str {
+        match volume_percent.round() as u32 {
+            _ if muted => &self.muted,
+            0..=33 => &self.volume_low,
+            34..=66 => &self.volume_medium,
+            _ => &self.volume_high,
+        }
+    }
+}
+
+impl Default for Icons {
+    fn default() -> Self {
+        Self {
+            volume_high: default_icon_volume_high(),
+            volume_medium: default_icon_volume_medium(),
+            volume_low: default_icon_volume_low(),
+            muted: default_icon_muted(),
+        }
+    }
+}
+
+fn default_icon_volume_high() -> String {
+    String::from("󰕾")
+}
+
+fn default_icon_volume_medium() -> String {
+    String::from("󰖀")
+}
+
+fn default_icon_volume_low() -> String {
+    String::from("󰕿")
+}
+
+fn default_icon_muted() -> String {
+    String::from("󰝟")
+}
+
+#[derive(Debug, Clone)]
+pub enum Update {
+    SinkChange(String),
+    SinkVolume(u32, f64),
+    SinkMute(u32, bool),
+
+    SourceChange(String),
+    SourceVolume(u32, f64),
+    SourceMute(u32, bool),
+
+    InputVolume(u32, f64),
+    InputMute(u32, bool),
+}
+
+impl Module<Button> for VolumeModule {
+    type SendMessage = Event;
+    type ReceiveMessage = Update;
+
+    fn name() -> &'static str {
+        "volume"
+    }
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        tx: Sender<ModuleUpdateEvent<Self::SendMessage>>,
+        mut rx: Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let client = volume::get_client();
+
+        {
+            let client = client.clone();
+
+            spawn(async move {
+                let (events, mut event_rx) = client.subscribe();
+
+                for event in events {
+                    send_async!(tx, ModuleUpdateEvent::Update(event));
+                }
+
+                loop {
+                    match event_rx.recv().await {
+                        Ok(event) => send_async!(tx, ModuleUpdateEvent::Update(event)),
+                        Err(RecvError::Lagged(count)) => {
+                            warn!("Volume module missed {count} events");
+                        }
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+        }
+
+        spawn(async move {
+            while let Some(update) = rx.recv().await {
+                match update {
+                    Update::SinkChange(name) => client.set_default_sink(name),
+                    Update::SinkVolume(index, volume) => client.set_sink_volume(index, volume),
+                    Update::SinkMute(index, muted) => client.set_sink_muted(index, muted),
+                    Update::SourceChange(name) => client.set_default_source(name),
+                    Update::SourceVolume(index, volume) => {
+                        client.set_source_volume(index, volume);
+                    }
+                    Update::SourceMute(index, muted) => client.set_source_muted(index, muted),
+                    Update::InputVolume(index, volume) => client.set_input_volume(index, volume),
+                    Update::InputMute(index, muted) => client.set_input_muted(index, muted),
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+        let label = Label::builder()
+            .label("")
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+
+        button.add(&label);
+
+        {
+            let tx = context.tx.clone();
+
+            button.connect_clicked(move |button| {
+                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+            });
+        }
+
+        {
+            let format = self.format.clone();
+            let icons = self.icons.clone();
+
+            context.widget_rx.attach(None, move |event| {
+                if let Event::AddSink(sink) | Event::UpdateSink(sink) = event {
+                    if sink.active {
+                        let label_text = format
+                            .replace("{icon}", icons.volume_icon(sink.volume, sink.muted))
+                            .replace("{percentage}", &sink.volume.to_string())
+                            .replace("{name}", &sink.description);
+
+                        label.set_markup(&label_text);
+                    }
+                }
+
+                Continue(true)
+            });
+        }
+
+        let popup = self
+            .into_popup(context.controller_tx, context.popup_rx, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: Sender<Self::ReceiveMessage>,
+        rx: glib::Receiver<Self::SendMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 10);
+
+        let sinks = DeviceControls::new(
+            "sinks",
+            self.max_volume,
+            &tx,
+            Update::SinkChange,
+            Update::SinkVolume,
+            Update::SinkMute,
+        );
+
+        let sources = DeviceControls::new(
+            "sources",
+            self.max_volume,
+            &tx,
+            Update::SourceChange,
+            Update::SourceVolume,
+            Update::SourceMute,
+        );
+
+        let inputs_container = gtk::Box::new(Orientation::Vertical, 5);
+        inputs_container.add_class("apps-box");
+
+        container.add(&sinks.container);
+        container.add(&sources.container);
+        container.add(&inputs_container);
+
+        container.show_all();
+
+        let mut inputs = HashMap::<u32, InputUi>::new();
+
+        rx.attach(None, move |event| {
+            match event {
+                Event::AddSink(sink) | Event::UpdateSink(sink) => sinks.update(sink),
+                Event::RemoveSink(index) => sinks.remove(index),
+                Event::AddSource(source) | Event::UpdateSource(source) => sources.update(source),
+                Event::RemoveSource(index) => sources.remove(index),
+                Event::AddInput(input) | Event::UpdateInput(input) => {
+                    if let Some(ui) = inputs.get(&input.index) {
+                        ui.update(&input);
+                    } else {
+                        let ui = InputUi::new(&input, self.max_volume, &tx);
+                        inputs_container.add(&ui.container);
+                        inputs.insert(input.index, ui);
+                    }
+                }
+                Event::RemoveInput(index) => {
+                    if let Some(ui) = inputs.remove(&index) {
+                        inputs_container.remove(&ui.container);
+                    }
+                }
+            }
+
+            Continue(true)
+        });
+
+        Some(container)
+    }
+}
+
+/// Controls for selecting the default device of one kind,
+/// and setting its volume and mute state.
+struct DeviceControls {
+    container: gtk::Box,
+    selector: ComboBoxText,
+    slider: Scale,
+    btn_mute: ToggleButton,
+
+    /// Known devices, in the same order as the selector entries.
+    devices: Rc<RefCell<Vec<Device>>>,
+}
+
+impl DeviceControls {
+    fn new(
+        class: &str,
+        max_volume: f64,
+        tx: &Sender<Update>,
+        set_default: fn(String) -> Update,
+        set_volume: fn(u32, f64) -> Update,
+        set_muted: fn(u32, bool) -> Update,
+    ) -> Self {
+        let container = gtk::Box::new(Orientation::Vertical, 5);
+        container.add_class("device-box");
+        container.add_class(class);
+
+        let selector = ComboBoxText::new();
+        selector.add_class("device-selector");
+
+        let slider = Scale::with_range(Orientation::Horizontal, 0.0, max_volume, 5.0);
+        slider.add_class("slider");
+
+        let btn_mute = ToggleButton::new();
+        btn_mute.add_class("btn-mute");
+
+        container.add(&selector);
+        container.add(&slider);
+        container.add(&btn_mute);
+
+        let devices = Rc::new(RefCell::new(Vec::<Device>::new()));
+
+        {
+            let tx = tx.clone();
+            selector.connect_changed(move |selector| {
+                if let Some(name) = selector.active_id() {
+                    try_send!(tx, set_default(name.to_string()));
+                }
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            let devices = devices.clone();
+            slider.connect_change_value(move |_, _, val| {
+                if let Some(device) = devices.borrow().iter().find(|device| device.active) {
+                    try_send!(tx, set_volume(device.index, val));
+                }
+                Inhibit(false)
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            let devices = devices.clone();
+            btn_mute.connect_toggled(move |btn| {
+                if let Some(device) = devices.borrow().iter().find(|device| device.active) {
+                    if device.muted != btn.is_active() {
+                        try_send!(tx, set_muted(device.index, btn.is_active()));
+                    }
+                }
+            });
+        }
+
+        Self {
+            container,
+            selector,
+            slider,
+            btn_mute,
+            devices,
+        }
+    }
+
+    /// Adds the device if it is new,
+    /// and updates the controls if it is the active device.
+    fn update(&self, device: Device) {
+        let existing = self
+            .devices
+            .borrow()
+            .iter()
+            .position(|existing| existing.index == device.index);
+
+        match existing {
+            Some(position) => self.devices.borrow_mut()[position] = device.clone(),
+            None => {
+                self.selector
+                    .append(Some(&device.name), &device.description);
+                self.devices.borrow_mut().push(device.clone());
+            }
+        }
+
+        if device.active {
+            self.selector.set_active_id(Some(&device.name));
+            self.slider.set_value(device.volume);
+            self.btn_mute.set_active(device.muted);
+            self.btn_mute
+                .set_label(if device.muted { "Unmute" } else { "Mute" });
+        }
+    }
+
+    fn remove(&self, index: u32) {
+        let position = self
+            .devices
+            .borrow()
+            .iter()
+            .position(|device| device.index == index);
+
+        if let Some(position) = position {
+            self.devices.borrow_mut().remove(position);
+            ComboBoxTextExt::remove(&self.selector, position as i32);
+        }
+    }
+}
+
+/// Controls for a single application's playback stream.
+struct InputUi {
+    container: gtk::Box,
+    slider: Scale,
+    btn_mute: ToggleButton,
+    mute_handler: SignalHandlerId,
+}
+
+impl InputUi {
+    fn new(input: &SinkInput, max_volume: f64, tx: &Sender<Update>) -> Self {
+        let container = gtk::Box::new(Orientation::Vertical, 0);
+        container.add_class("app-box");
+
+        let title_box = gtk::Box::new(Orientation::Horizontal, 5);
+        title_box.add_class("title-box");
+
+        if let Some(icon_name) = &input.icon_name {
+            let icon = gtk::Image::from_icon_name(Some(icon_name), gtk::IconSize::Button);
+            icon.add_class("icon");
+            title_box.add(&icon);
+        }
+
+        let label = Label::new(Some(&input.name));
+        label.add_class("title");
+        title_box.add(&label);
+
+        let slider = Scale::with_range(Orientation::Horizontal, 0.0, max_volume, 5.0);
+        slider.add_class("slider");
+        slider.set_sensitive(input.can_set_volume);
+
+        let btn_mute = ToggleButton::new();
+        btn_mute.add_class("btn-mute");
+
+        container.add(&title_box);
+        container.add(&slider);
+        container.add(&btn_mute);
+
+        {
+            let tx = tx.clone();
+            let index = input.index;
+            slider.connect_change_value(move |_, _, val| {
+                try_send!(tx, Update::InputVolume(index, val));
+                Inhibit(false)
+            });
+        }
+
+        let mute_handler = {
+            let tx = tx.clone();
+            let index = input.index;
+            btn_mute.connect_toggled(move |btn| {
+                try_send!(tx, Update::InputMute(index, btn.is_active()));
+            })
+        };
+
+        let ui = Self {
+            container,
+            slider,
+            btn_mute,
+            mute_handler,
+        };
+
+        ui.update(input);
+        ui.container.show_all();
+
+        ui
+    }
+
+    fn update(&self, input: &SinkInput) {
+        self.slider.set_value(input.volume);
+
+        // block the handler so that updating the button
+        // does not send the mute state back to the server
+        self.btn_mute.block_signal(&self.mute_handler);
+        self.btn_mute.set_active(input.muted);
+        self.btn_mute.unblock_signal(&self.mute_handler);
+
+        self.btn_mute
+            .set_label(if input.muted { "Unmute" } else { "Mute" });
+    }
+}
@@ -0,0 +1,85 @@
+use crate::clients::wayland::{self, ToplevelAction, ToplevelInfo};
+use crate::lock;
+use gtk::prelude::*;
+use gtk::{CheckMenuItem, Menu, MenuItem, SeparatorMenuItem};
+
+/// Version of `wlr-foreign-toplevel-management`
+/// which added the fullscreen requests.
+const FULLSCREEN_VERSION: u32 = 2;
+
+/// Gets the names of all current outputs,
+/// for use as `FullscreenOnOutput` targets.
+pub fn output_names() -> Vec<String> {
+    let wl = wayland::get_client();
+    let outputs = lock!(wl).get_outputs();
+
+    outputs
+        .into_iter()
+        .filter_map(|output| output.name)
+        .collect()
+}
+
+/// Removes all items from the menu,
+/// so that it can be re-populated.
+pub fn clear(menu: &Menu) {
+    for child in menu.children() {
+        menu.remove(&child);
+    }
+}
+
+/// Adds window management items for the toplevel to the menu.
+///
+/// Toggle items reflect the toplevel's state when the menu is built.
+/// Fullscreen items are insensitive if the handle `version` does not support them.
+/// `on_action` is called with the chosen action.
+pub fn populate<F>(menu: &Menu, info: &ToplevelInfo, version: u32, outputs: &[String], on_action: F)
+where
+    F: Fn(ToplevelAction) + Clone + 'static,
+{
+    let can_fullscreen = version >= FULLSCREEN_VERSION;
+
+    let add_toggle = |label: &str, active: bool, action: ToplevelAction| {
+        let item = CheckMenuItem::with_label(label);
+        item.set_active(active);
+
+        let on_action = on_action.clone();
+        item.connect_activate(move |_| on_action(action.clone()));
+
+        menu.add(&item);
+        item
+    };
+
+    add_toggle("Minimized", info.minimized, ToplevelAction::ToggleMinimized);
+    add_toggle("Maximized", info.maximized, ToplevelAction::ToggleMaximized);
+    add_toggle(
+        "Fullscreen",
+        info.fullscreen,
+        ToplevelAction::ToggleFullscreen,
+    )
+    .set_sensitive(can_fullscreen);
+
+    let output_item = MenuItem::with_label("Fullscreen on output");
+    output_item.set_sensitive(can_fullscreen && !outputs.is_empty());
+
+    let outputs_menu = Menu::new();
+    for name in outputs {
+        let item = MenuItem::with_label(name);
+
+        let on_action = on_action.clone();
+        let name = name.clone();
+        item.connect_activate(move |_| {
+            on_action(ToplevelAction::FullscreenOnOutput(name.clone()));
+        });
+
+        outputs_menu.add(&item);
+    }
+
+    output_item.set_submenu(Some(&outputs_menu));
+    menu.add(&output_item);
+
+    menu.add(&SeparatorMenuItem::new());
+
+    let close_item = MenuItem::with_label("Close");
+    close_item.connect_activate(move |_| on_action(ToplevelAction::Close));
+    menu.add(&close_item);
+}
@@ -1,17 +1,24 @@
-use crate::clients::compositor::{Compositor, Visibility, Workspace, WorkspaceUpdate, WorkspaceId};
-use crate::config::CommonConfig;
-use crate::image::new_icon_button;
-use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::clients::compositor::{
+    Compositor, ScratchpadWindow, Workspace, WorkspaceId, WorkspaceUpdate, WorkspaceWindow,
+};
+use crate::config::{BarPosition, CommonConfig};
+use crate::desktop_file::get_desktop_icon_name;
+use crate::gtk_helpers::{IronbarGtkExt, WidgetGeometry};
+use crate::image::ImageProvider;
+use crate::module_tasks::spawn;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
 use crate::{send_async, try_send};
 use color_eyre::{Report, Result};
+use gtk::gdk::{NotifyType, BUTTON_SECONDARY};
 use gtk::{prelude::*, Label};
-use gtk::{Button, IconTheme};
+use gtk::{Button, EventBox, IconTheme, Orientation};
 use serde::Deserialize;
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
-use tokio::spawn;
 use tokio::sync::mpsc::{Receiver, Sender};
-use tracing::trace;
+use tracing::{error, trace};
 
 #[derive(Debug, Deserialize, Clone, Copy, Eq, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -29,7 +36,7 @@ impl Default for SortOrder {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum Favorites {
     ByMonitor(HashMap<String, Vec<String>>),
@@ -42,7 +49,19 @@ impl Default for Favorites {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Default, Deserialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowPopupTrigger {
+    /// Never show the window popup.
+    Off,
+    /// Show the window popup while hovering a workspace button.
+    Hover,
+    /// Show the window popup when right-clicking a workspace button.
+    #[default]
+    RightClick,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct WorkspacesModule {
     /// Map of actual workspace names to custom names.
     name_map: Option<HashMap<String, String>>,
@@ -65,6 +84,18 @@ pub struct WorkspacesModule {
     #[serde(default = "default_icon_size")]
     icon_size: i32,
 
+    /// Whether to show a button which opens a popup listing scratchpad windows.
+    #[serde(default = "crate::config::default_false")]
+    scratchpad: bool,
+
+    /// Label for the scratchpad button.
+    #[serde(default = "default_scratchpad_icon")]
+    scratchpad_icon: String,
+
+    /// How to open the popup listing the windows on a workspace.
+    #[serde(default)]
+    window_popup: WindowPopupTrigger,
+
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
 }
@@ -73,15 +104,59 @@ const fn default_icon_size() -> i32 {
     32
 }
 
+fn default_scratchpad_icon() -> String {
+    String::from("󰖯")
+}
+
+#[derive(Debug, Clone)]
+pub enum WorkspaceMessage {
+    Update(WorkspaceUpdate),
+    /// The current scratchpad windows, sent when requested.
+    Scratchpad(Vec<ScratchpadWindow>),
+    /// The windows on a workspace, sent when requested.
+    Windows(Vec<WorkspaceWindow>),
+}
+
+#[derive(Debug, Clone)]
+pub enum WorkspaceCommand {
+    /// Focuses the workspace with this ID and name.
+    Focus(WorkspaceId, String),
+    /// Requests the current scratchpad windows.
+    ListScratchpad,
+    /// Shows the scratchpad window with this ID.
+    ShowScratchpad(String),
+    /// Requests the windows on the workspace with this ID,
+    /// opening the popup at the button geometry.
+    ListWindows(WorkspaceId, WidgetGeometry),
+    /// Focuses the window with this ID.
+    FocusWindow(String),
+    /// Closes the popup.
+    ///
+    /// This goes through the controller so that it cannot overtake
+    /// a popup being opened by an earlier `ListWindows`.
+    ClosePopup,
+}
+
+/// Everything a workspace button needs
+/// to open the window popup.
+#[derive(Clone)]
+struct WindowPopupContext {
+    trigger: WindowPopupTrigger,
+    bar_position: BarPosition,
+}
+
 /// Creates a button from a workspace
 fn create_button(
-    name: &str,
-    visibility: Visibility,
+    workspace: &Workspace,
     name_map: &HashMap<String, String>,
     icon_theme: &IconTheme,
     icon_size: i32,
-    tx: &Sender<String>,
+    tx: &Sender<WorkspaceCommand>,
+    popup: &WindowPopupContext,
 ) -> Button {
+    let name = workspace.name.as_str();
+    let visibility = workspace.visibility;
+
     let button = Button::new();
     let label = Label::builder()
         .use_markup(true)
@@ -103,17 +178,88 @@ fn create_button(
         style_context.add_class("focused");
     }
 
+    if workspace.urgent {
+        style_context.add_class("urgent");
+    }
+
+    if workspace.windows == Some(0) {
+        style_context.add_class("empty");
+    }
+
     {
         let tx = tx.clone();
+        let id = workspace.id.clone();
         let name = name.to_string();
         button.connect_clicked(move |_item| {
-            try_send!(tx, name.clone());
+            try_send!(tx, WorkspaceCommand::Focus(id.clone(), name.clone()));
         });
     }
 
+    connect_window_popup(&button, workspace.id.clone(), tx, popup);
+
     button
 }
 
+/// Opens the window popup when the button is hovered or right-clicked,
+/// depending on the configured trigger.
+fn connect_window_popup(
+    button: &Button,
+    id: WorkspaceId,
+    tx: &Sender<WorkspaceCommand>,
+    popup: &WindowPopupContext,
+) {
+    let orientation = popup.bar_position.get_orientation();
+
+    match popup.trigger {
+        WindowPopupTrigger::Off => {}
+        WindowPopupTrigger::Hover => {
+            {
+                let tx = tx.clone();
+                button.connect_enter_notify_event(move |button, _| {
+                    let geometry = button.geometry(orientation);
+                    try_send!(tx, WorkspaceCommand::ListWindows(id.clone(), geometry));
+                    Inhibit(false)
+                });
+            }
+
+            let tx = tx.clone();
+            let bar_position = popup.bar_position;
+
+            // only close when moving away from the popup
+            button.connect_leave_notify_event(move |button, ev| {
+                const THRESHOLD: f64 = 5.0;
+
+                let alloc = button.allocation();
+                let (x, y) = ev.position();
+
+                let close = match bar_position {
+                    BarPosition::Top => y + THRESHOLD < alloc.height() as f64,
+                    BarPosition::Bottom => y > THRESHOLD,
+                    BarPosition::Left => x + THRESHOLD < alloc.width() as f64,
+                    BarPosition::Right => x > THRESHOLD,
+                };
+
+                if close {
+                    try_send!(tx, WorkspaceCommand::ClosePopup);
+                }
+
+                Inhibit(false)
+            });
+        }
+        WindowPopupTrigger::RightClick => {
+            let tx = tx.clone();
+            button.connect_button_release_event(move |button, ev| {
+                if ev.button() == BUTTON_SECONDARY {
+                    let geometry = button.geometry(orientation);
+                    try_send!(tx, WorkspaceCommand::ListWindows(id.clone(), geometry));
+                }
+
+                Inhibit(false)
+            });
+        }
+    }
+}
+
 fn reorder_workspaces(container: &gtk::Box) {
     let mut buttons = container
         .children()
@@ -144,8 +290,8 @@ impl WorkspacesModule {
 }
 
 impl Module<gtk::Box> for WorkspacesModule {
-    type SendMessage = WorkspaceUpdate;
-    type ReceiveMessage = String;
+    type SendMessage = WorkspaceMessage;
+    type ReceiveMessage = WorkspaceCommand;
 
     fn name() -> &'static str {
         "workspaces"
@@ -158,28 +304,89 @@ impl Module<gtk::Box> for WorkspacesModule {
         mut rx: Receiver<Self::ReceiveMessage>,
     ) -> Result<()> {
         // Subscribe & send events
-        spawn(async move {
-            let mut srx = {
-                let client =
-                    Compositor::get_workspace_client().expect("Failed to get workspace client");
-                client.subscribe_workspace_change()
-            };
+        {
+            let tx = tx.clone();
+            spawn(async move {
+                let mut srx = {
+                    let client =
+                        Compositor::get_workspace_client().expect("Failed to get workspace client");
+                    client.subscribe_workspace_change()
+                };
 
-            trace!("Set up Sway workspace subscription");
+                trace!("Set up Sway workspace subscription");
 
-            while let Ok(payload) = srx.recv().await {
-                send_async!(tx, ModuleUpdateEvent::Update(payload));
-            }
-        });
+                while let Ok(payload) = srx.recv().await {
+                    send_async!(
+                        tx,
+                        ModuleUpdateEvent::Update(WorkspaceMessage::Update(payload))
+                    );
+                }
+            });
+        }
 
         // Change workspace focus
         spawn(async move {
             trace!("Setting up UI event handler");
 
-            while let Some(name) = rx.recv().await {
-                let client =
-                    Compositor::get_workspace_client().expect("Failed to get workspace client");
-                client.focus(name)?;
+            while let Some(command) = rx.recv().await {
+                // the client cannot be held across await points
+                let events = {
+                    let client =
+                        Compositor::get_workspace_client().expect("Failed to get workspace client");
+
+                    match command {
+                        WorkspaceCommand::Focus(id, name) => {
+                            if let Err(err) = client.focus_by_id(&id, name) {
+                                error!("{err:?}");
+                            }
+
+                            vec![]
+                        }
+                        WorkspaceCommand::ListScratchpad => match client.scratchpad_windows() {
+                            Ok(windows) => vec![ModuleUpdateEvent::Update(
+                                WorkspaceMessage::Scratchpad(windows),
+                            )],
+                            Err(err) => {
+                                error!("{err:?}");
+                                vec![]
+                            }
+                        },
+                        WorkspaceCommand::ShowScratchpad(id) => {
+                            if let Err(err) = client.show_scratchpad_window(id) {
+                                error!("{err:?}");
+                            }
+
+                            vec![ModuleUpdateEvent::ClosePopup]
+                        }
+                        WorkspaceCommand::ListWindows(id, geometry) => {
+                            match client.workspace_windows(&id) {
+                                Ok(windows) if windows.is_empty() => {
+                                    vec![ModuleUpdateEvent::ClosePopup]
+                                }
+                                Ok(windows) => vec![
+                                    ModuleUpdateEvent::Update(WorkspaceMessage::Windows(windows)),
+                                    ModuleUpdateEvent::OpenPopupAt(geometry),
+                                ],
+                                Err(err) => {
+                                    error!("{err:?}");
+                                    vec![]
+                                }
+                            }
+                        }
+                        WorkspaceCommand::FocusWindow(id) => {
+                            if let Err(err) = client.focus_window(id) {
+                                error!("{err:?}");
+                            }
+
+                            vec![ModuleUpdateEvent::ClosePopup]
+                        }
+                        WorkspaceCommand::ClosePopup => vec![ModuleUpdateEvent::ClosePopup],
+                    }
+                };
+
+                for event in events {
+                    send_async!(tx, event);
+                }
             }
 
             Ok::<(), Report>(())
@@ -201,6 +408,33 @@ impl Module<gtk::Box> for WorkspacesModule {
 
         let mut button_map: HashMap<WorkspaceId, Button> = HashMap::new();
 
+        let scratchpad_button = self.scratchpad.then(|| {
+            let button = Button::with_label(&self.scratchpad_icon);
+            button.add_class("scratchpad");
+
+            let tx = context.tx.clone();
+            let controller_tx = context.controller_tx.clone();
+            button.connect_clicked(move |button| {
+                try_send!(controller_tx, WorkspaceCommand::ListScratchpad);
+                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+            });
+
+            button
+        });
+
+        let popup = if self.scratchpad || self.window_popup != WindowPopupTrigger::Off {
+            self.clone()
+                .into_popup(context.controller_tx.clone(), context.popup_rx, info)
+                .into_popup_parts(scratchpad_button.iter().collect())
+        } else {
+            None
+        };
+
+        let popup_context = WindowPopupContext {
+            trigger: self.window_popup,
+            bar_position: info.bar_position,
+        };
+
         {
             let container = container.clone();
             let output_name = info.output_name.to_string();
@@ -211,7 +445,11 @@ impl Module<gtk::Box> for WorkspacesModule {
             // since it fires for every workspace subscriber
             let mut has_initialized = false;
 
-            context.widget_rx.attach(None, move |event| {
+            context.widget_rx.attach(None, move |message| {
+                let WorkspaceMessage::Update(event) = message else {
+                    return Continue(true);
+                };
+
                 match event {
                     WorkspaceUpdate::Init(workspaces) => {
                         if !has_initialized {
@@ -219,24 +457,24 @@ impl Module<gtk::Box> for WorkspacesModule {
 
                             let mut added = HashSet::new();
 
-                            let mut add_workspace = |id: &WorkspaceId, name: &str, visibility: Visibility| {
+                            let mut add_workspace = |workspace: &Workspace| {
                                 let item = create_button(
-                                    name,
-                                    visibility,
+                                    workspace,
                                     &name_map,
                                     &icon_theme,
                                     icon_size,
                                     &context.controller_tx,
+                                    &popup_context,
                                 );
 
                                 container.add(&item);
-                                button_map.insert(id.clone(), item);
+                                button_map.insert(workspace.id.clone(), item);
                             };
 
                             // add workspaces from client
                             for workspace in &workspaces {
                                 if self.show_workspace_check(&output_name, workspace) {
-                                    add_workspace(&workspace.id, &workspace.name, workspace.visibility);
+                                    add_workspace(workspace);
                                     added.insert(workspace.name.to_string());
                                 }
                             }
@@ -285,22 +523,23 @@ impl Module<gtk::Box> for WorkspacesModule {
 
                             style.add_class("visible");
                             style.add_class("focused");
+                            style.remove_class("urgent");
                         }
                     }
                     WorkspaceUpdate::Update(workspace) => {
-                        let old = button_map.get(&workspace.id);
+                        let old = button_map.remove(&workspace.id);
                         if let Some(item) = old {
-                            container.remove(item);
+                            container.remove(&item);
                         }
 
-                        if self.all_monitors || workspace.monitor == output_name {
+                        if self.show_workspace_check(&output_name, &workspace) {
                             let item = create_button(
-                                &workspace.name,
-                                workspace.visibility,
+                                &workspace,
                                 &name_map,
                                 &icon_theme,
                                 icon_size,
                                 &context.controller_tx,
+                                &popup_context,
                             );
 
                             container.add(&item);
@@ -325,14 +564,13 @@ impl Module<gtk::Box> for WorkspacesModule {
                                 btn.style_context().remove_class("inactive");
                             }
                         } else if self.show_workspace_check(&output_name, &workspace) {
-                            let name = workspace.name;
                             let item = create_button(
-                                &name,
-                                workspace.visibility,
+                                &workspace,
                                 &name_map,
                                 &icon_theme,
                                 icon_size,
                                 &context.controller_tx,
+                                &popup_context,
                             );
 
                             container.add(&item);
@@ -350,14 +588,13 @@ impl Module<gtk::Box> for WorkspacesModule {
                     WorkspaceUpdate::Move(workspace) => {
                         if !self.hidden.contains(&workspace.name) && !self.all_monitors {
                             if workspace.monitor == output_name {
-                                let name = workspace.name;
                                 let item = create_button(
-                                    &name,
-                                    workspace.visibility,
+                                    &workspace,
                                     &name_map,
                                     &icon_theme,
                                     icon_size,
                                     &context.controller_tx,
+                                    &popup_context,
                                 );
 
                                 container.add(&item);
@@ -368,7 +605,7 @@ impl Module<gtk::Box> for WorkspacesModule {
 
                                 item.show();
 
-                                if !name.is_empty() {
+                                if !workspace.name.is_empty() {
                                     button_map.insert(workspace.id, item);
                                 }
                             } else if let Some(item) = button_map.get(&workspace.id) {
@@ -376,13 +613,13 @@ impl Module<gtk::Box> for WorkspacesModule {
                             }
                         }
                     }
-                    WorkspaceUpdate::Remove{name} => {
+                    WorkspaceUpdate::Remove { id, name } => {
                         // NOTE: Workspace remove is unsupported
-                        // TODO: This is super cursed, we're removing workspaces by
-                        // name here, but the button map contains IDs* However,
-                        // by the time a workspace is removed, it is empty and so
+                        // TODO: This is super cursed, Hyprland only gives us the
+                        // name of a removed workspace, and so uses it as the ID here.
+                        // However, by the time a workspace is removed, it is empty and so
                         // its name matches its id
-                        let button = button_map.get(&WorkspaceId(name.clone()));
+                        let button = button_map.get(&id);
                         if let Some(item) = button {
                             if fav_names.contains(&name) {
                                 item.style_context().add_class("inactive");
@@ -397,9 +634,179 @@ impl Module<gtk::Box> for WorkspacesModule {
             });
         }
 
-        Ok(ModuleParts {
-            widget: container,
-            popup: None,
-        })
+        let widget = if let Some(button) = &scratchpad_button {
+            let wrapper = gtk::Box::new(info.bar_position.get_orientation(), 0);
+            wrapper.add(&container);
+            wrapper.add(button);
+            wrapper
+        } else {
+            container
+        };
+
+        Ok(ModuleParts::new(widget, popup))
     }
+
+    fn into_popup(
+        self,
+        tx: Sender<Self::ReceiveMessage>,
+        rx: glib::Receiver<Self::SendMessage>,
+        info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 5);
+
+        // boxes do not receive crossing events,
+        // so the rows are wrapped to detect the pointer leaving the popup
+        let event_box = EventBox::new();
+        container.add(&event_box);
+
+        let rows = gtk::Box::new(Orientation::Vertical, 5);
+        event_box.add(&rows);
+
+        if self.window_popup == WindowPopupTrigger::Hover {
+            let container = container.clone();
+            let tx = tx.clone();
+
+            event_box.connect_leave_notify_event(move |_, ev| {
+                // moving onto a row also triggers this event
+                if ev.detail() != NotifyType::Inferior
+                    && container.style_context().has_class("workspace-windows")
+                {
+                    try_send!(tx, WorkspaceCommand::ClosePopup);
+                }
+
+                Inhibit(false)
+            });
+        }
+
+        {
+            let container = container.clone();
+            let icon_theme = info.icon_theme.clone();
+            let icon_size = self.icon_size;
+
+            rx.attach(None, move |message| {
+                match message {
+                    WorkspaceMessage::Scratchpad(windows) => {
+                        reset_popup(&container, &rows, "scratchpad-windows");
+
+                        if windows.is_empty() {
+                            let label = Label::new(Some("No scratchpad windows"));
+                            label.add_class("empty");
+                            rows.add(&label);
+                        }
+
+                        for window in windows {
+                            rows.add(&create_scratchpad_row(window, &tx));
+                        }
+
+                        container.show_all();
+                    }
+                    WorkspaceMessage::Windows(windows) => {
+                        reset_popup(&container, &rows, "workspace-windows");
+
+                        for window in windows {
+                            rows.add(&create_window_row(window, &icon_theme, icon_size, &tx));
+                        }
+
+                        container.show_all();
+                    }
+                    WorkspaceMessage::Update(_) => {}
+                }
+
+                Continue(true)
+            });
+        }
+
+        Some(container)
+    }
+}
+
+/// Removes the popup rows and sets the container class.
+///
+/// The same popup is shared between the scratchpad and workspace windows,
+/// so the class depends on which is being shown.
+fn reset_popup(container: &gtk::Box, rows: &gtk::Box, class: &str) {
+    for child in rows.children() {
+        rows.remove(&child);
+    }
+
+    let style_context = container.style_context();
+    style_context.remove_class("scratchpad-windows");
+    style_context.remove_class("workspace-windows");
+    style_context.add_class(class);
+}
+
+/// Creates a popup button for a scratchpad window,
+/// which shows the window when clicked.
+fn create_scratchpad_row(window: ScratchpadWindow, tx: &Sender<WorkspaceCommand>) -> Button {
+    let button = Button::new();
+    button.add_class("window");
+
+    let row = gtk::Box::new(Orientation::Horizontal, 10);
+
+    let app_id = Label::new(Some(&window.app_id));
+    app_id.add_class("app-id");
+    row.add(&app_id);
+
+    let title = Label::new(Some(&window.title));
+    title.add_class("title");
+    row.add(&title);
+
+    button.add(&row);
+
+    {
+        let tx = tx.clone();
+        button.connect_clicked(move |_| {
+            try_send!(tx, WorkspaceCommand::ShowScratchpad(window.id.clone()));
+        });
+    }
+
+    button
+}
+
+/// Creates a popup button for a window on a workspace,
+/// which focuses the window when clicked.
+fn create_window_row(
+    window: WorkspaceWindow,
+    icon_theme: &IconTheme,
+    icon_size: i32,
+    tx: &Sender<WorkspaceCommand>,
+) -> Button {
+    let button = Button::new();
+    button.add_class("window");
+
+    if window.focused {
+        button.add_class("focused");
+    }
+
+    let row = gtk::Box::new(Orientation::Horizontal, 10);
+
+    let icon = gtk::Image::new();
+    icon.add_class("icon");
+
+    let input = get_desktop_icon_name(&window.app_id).unwrap_or_else(|| window.app_id.clone());
+    if let Some(provider) = ImageProvider::parse(&input, icon_theme, true, icon_size) {
+        if let Err(err) = provider.load_into_image(icon.clone()) {
+            error!("{err:?}");
+        }
+    }
+
+    row.add(&icon);
+
+    let title = Label::new(Some(&window.title));
+    title.add_class("title");
+    row.add(&title);
+
+    button.add(&row);
+
+    {
+        let tx = tx.clone();
+        button.connect_clicked(move |_| {
+            try_send!(tx, WorkspaceCommand::FocusWindow(window.id.clone()));
+        });
+    }
+
+    button
 }
@@ -7,9 +7,15 @@ use tracing::debug;
 
 use crate::config::BarPosition;
 use crate::gtk_helpers::{IronbarGtkExt, WidgetGeometry};
+#[cfg(feature = "ipc")]
+use crate::ipc::{send_event, Event};
 use crate::modules::{ModuleInfo, ModulePopupParts, PopupButton};
 use crate::unique_id::get_unique_usize;
 
+/// Data tag on the popup window holding the name of the module
+/// whose content was last shown.
+pub const POPUP_NAME_TAG: &str = "popup-name";
+
 #[derive(Debug, Clone)]
 pub struct Popup {
     pub window: ApplicationWindow,
@@ -17,13 +23,15 @@ pub struct Popup {
     monitor: Monitor,
     pos: BarPosition,
     current_widget: Option<usize>,
+    #[cfg_attr(not(feature = "ipc"), allow(dead_code))]
+    bar_name: String,
 }
 
 impl Popup {
     /// Creates a new popup window.
     /// This includes setting up gtk-layer-shell
     /// and an empty `gtk::Box` container.
-    pub fn new(module_info: &ModuleInfo, gap: i32) -> Self {
+    pub fn new(module_info: &ModuleInfo, bar_name: &str, gap: i32) -> Self {
         let pos = module_info.bar_position;
         let orientation = pos.get_orientation();
 
@@ -78,6 +86,8 @@ impl Popup {
             pos == BarPosition::Right,
         );
 
+        #[cfg(feature = "ipc")]
+        let name = bar_name.to_string();
         win.connect_leave_notify_event(move |win, ev| {
             const THRESHOLD: f64 = 3.0;
 
@@ -103,6 +113,8 @@ impl Popup {
 
             if hide {
                 win.hide();
+                #[cfg(feature = "ipc")]
+                send_popup_event(win, &name, false);
             }
 
             Inhibit(false)
@@ -114,6 +126,7 @@ impl Popup {
             monitor: module_info.monitor.clone(),
             pos,
             current_widget: None,
+            bar_name: bar_name.to_string(),
         }
     }
 
@@ -128,16 +141,31 @@ impl Popup {
         self.cache.insert(key, (name, content));
     }
 
+    /// Removes the popup content registered against the key,
+    /// hiding the popup if it is currently showing that content.
+    pub fn unregister_content(&mut self, key: usize) {
+        debug!("Unregistered popup content for #{}", key);
+
+        if self.current_widget == Some(key) {
+            self.hide();
+        }
+
+        self.cache.remove(&key);
+    }
+
     pub fn show(&mut self, widget_id: usize, button_id: usize) {
         self.clear_window();
 
-        if let Some((_name, content)) = self.cache.get(&widget_id) {
+        if let Some((name, content)) = self.cache.get(&widget_id) {
             self.current_widget = Some(widget_id);
 
             content.container.style_context().add_class("popup");
             self.window.add(&content.container);
+            self.window.set_tag(POPUP_NAME_TAG, name.clone());
 
             self.window.show();
+            #[cfg(feature = "ipc")]
+            send_popup_event(&self.window, &self.bar_name, true);
 
             let button = content
                 .buttons
@@ -155,11 +183,15 @@ impl Popup {
     pub fn show_at(&self, widget_id: usize, geometry: WidgetGeometry) {
         self.clear_window();
 
-        if let Some((_name, content)) = self.cache.get(&widget_id) {
+        if let Some((name, content)) = self.cache.get(&widget_id) {
             content.container.style_context().add_class("popup");
             self.window.add(&content.container);
+            self.window.set_tag(POPUP_NAME_TAG, name.clone());
 
             self.window.show();
+            #[cfg(feature = "ipc")]
+            send_popup_event(&self.window, &self.bar_name, true);
+
             self.set_pos(geometry);
         }
     }
@@ -174,7 +206,12 @@ impl Popup {
     /// Hides the popover
     pub fn hide(&mut self) {
         self.current_widget = None;
-        self.window.hide();
+
+        if self.window.is_visible() {
+            self.window.hide();
+            #[cfg(feature = "ipc")]
+            send_popup_event(&self.window, &self.bar_name, false);
+        }
     }
 
     /// Checks if the popup is currently visible
@@ -226,3 +263,16 @@ impl Popup {
         gtk_layer_shell::set_margin(&self.window, edge, offset as i32);
     }
 }
+
+/// Sends an IPC event for the popup opening or closing.
+///
+/// This is sent on every `show` call rather than on visibility changes,
+/// so that switching content while the popup is open is reported.
+#[cfg(feature = "ipc")]
+fn send_popup_event(win: &ApplicationWindow, bar_name: &str, open: bool) {
+    send_event(Event::Popup {
+        bar_name: bar_name.to_string(),
+        name: win.get_tag::<String>(POPUP_NAME_TAG).cloned(),
+        open,
+    });
+}
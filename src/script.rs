@@ -1,3 +1,4 @@
+use crate::module_tasks::spawn;
 use crate::send_async;
 use color_eyre::eyre::WrapErr;
 use color_eyre::{Report, Result};
@@ -7,12 +8,12 @@ use std::fmt::{Display, Formatter};
 use std::process::Stdio;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
+use tokio::select;
 use tokio::sync::mpsc;
 use tokio::time::sleep;
-use tokio::{select, spawn};
 use tracing::{debug, error, trace, warn};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum ScriptInput {
     String(String),
@@ -74,7 +75,77 @@ impl ScriptMode {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptFormat {
+    /// Output is used as the label markup.
+    #[default]
+    Plain,
+    /// Each line of output is a JSON object.
+    /// See [`ScriptOutput`].
+    Json,
+}
+
+/// A single structured output from a script,
+/// compatible with Waybar's custom module format.
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
+pub struct ScriptOutput {
+    #[serde(default)]
+    pub text: String,
+    pub tooltip: Option<String>,
+    #[serde(default)]
+    pub class: Classes,
+    /// Value between `0` and `100`.
+    pub percentage: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Classes {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Default for Classes {
+    fn default() -> Self {
+        Self::Multiple(vec![])
+    }
+}
+
+impl Classes {
+    pub fn into_vec(self) -> Vec<String> {
+        match self {
+            Self::Single(class) => vec![class],
+            Self::Multiple(classes) => classes,
+        }
+    }
+}
+
+impl ScriptFormat {
+    /// Parses a chunk of script output.
+    ///
+    /// In JSON mode, the last non-empty line is used
+    /// as polled scripts may write several lines at once.
+    pub fn parse(self, output: String) -> Result<ScriptOutput> {
+        match self {
+            Self::Plain => Ok(ScriptOutput {
+                text: output,
+                ..ScriptOutput::default()
+            }),
+            Self::Json => {
+                let line = output
+                    .lines()
+                    .rev()
+                    .find(|line| !line.trim().is_empty())
+                    .unwrap_or_default();
+
+                serde_json::from_str(line).map_err(Report::new)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct Script {
     #[serde(default = "ScriptMode::default")]
     pub(crate) mode: ScriptMode,
@@ -236,6 +307,8 @@ impl Script {
 
         let output = Command::new("/bin/sh")
             .args(&args_list)
+            // stop the script if its module is removed
+            .kill_on_drop(true)
             .output()
             .await
             .wrap_err("Failed to get script output")?;
@@ -270,6 +343,7 @@ impl Script {
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .stdin(Stdio::null())
+            .kill_on_drop(true)
             .spawn()?;
 
         debug!("Spawned a long-running process for '{}'", self.cmd);
@@ -410,4 +484,56 @@ mod tests {
         assert_eq!(script.interval, interval);
         assert_eq!(script.mode, mode);
     }
+
+    #[test]
+    fn test_format_plain() {
+        let output = ScriptFormat::Plain
+            .parse(String::from("{\"text\": \"hello\"}"))
+            .expect("plain output should always parse");
+
+        assert_eq!(output.text, "{\"text\": \"hello\"}");
+        assert_eq!(output.tooltip, None);
+        assert_eq!(output.percentage, None);
+        assert!(output.class.into_vec().is_empty());
+    }
+
+    #[test]
+    fn test_format_json() {
+        let output = ScriptFormat::Json
+            .parse(String::from(
+                r#"{"text": "50%", "tooltip": "Battery", "class": ["battery", "charging"]}"#,
+            ))
+            .expect("output should be valid JSON");
+
+        assert_eq!(output.text, "50%");
+        assert_eq!(output.tooltip.as_deref(), Some("Battery"));
+        assert_eq!(output.class.into_vec(), ["battery", "charging"]);
+    }
+
+    #[test]
+    fn test_format_json_single_class() {
+        let output = ScriptFormat::Json
+            .parse(String::from(r#"{"class": "warning", "percentage": 10}"#))
+            .expect("output should be valid JSON");
+
+        assert_eq!(output.text, "");
+        assert_eq!(output.tooltip, None);
+        assert_eq!(output.percentage, Some(10.0));
+        assert_eq!(output.class.into_vec(), ["warning"]);
+    }
+
+    #[test]
+    fn test_format_json_uses_last_line() {
+        let output = ScriptFormat::Json
+            .parse(String::from("{\"text\": \"old\"}\n{\"text\": \"new\"}\n\n"))
+            .expect("output should be valid JSON");
+
+        assert_eq!(output.text, "new");
+    }
+
+    #[test]
+    fn test_format_json_invalid() {
+        assert!(ScriptFormat::Json.parse(String::from("not json")).is_err());
+        assert!(ScriptFormat::Json.parse(String::new()).is_err());
+    }
 }